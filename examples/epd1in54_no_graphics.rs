@@ -1,7 +1,11 @@
 #![deny(warnings)]
 
 use embedded_hal::prelude::*;
-use epd_waveshare::{epd1in54::Epd1in54, prelude::*};
+use epd_waveshare::{
+    epd1in54::{Epd1in54, HEIGHT, WIDTH},
+    prelude::*,
+    region::Region,
+};
 use linux_embedded_hal::{
     spidev::{self, SpidevOptions},
     sysfs_gpio::Direction,
@@ -12,7 +16,7 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Configure SPI
     // SPI settings are from eink-waveshare-rs documenation
     let mut spi = Spidev::open("/dev/spidev0.0")?;
@@ -70,7 +74,10 @@ fn main() -> Result<(), std::io::Error> {
     let number_of_runs = 1;
     for i in 0..number_of_runs {
         let offset = i * 8 % 150;
-        epd.update_partial_frame(&mut spi, &small_buffer, 25 + offset, 25 + offset, 16, 16)?;
+        let window =
+            PartialWindow::new(Region::new(24 + offset, 24 + offset, 16, 16), WIDTH, HEIGHT)
+                .expect("window on the display");
+        epd.update_partial_window(&mut spi, &small_buffer, window)?;
         epd.display_frame(&mut spi, &mut delay)?;
     }
 
@@ -80,13 +87,19 @@ fn main() -> Result<(), std::io::Error> {
 
     // Draw some squares
     let small_buffer = [Color::Black.get_byte_value(); 3200]; //160x160
-    epd.update_partial_frame(&mut spi, &small_buffer, 20, 20, 160, 160)?;
+    let window = PartialWindow::new(Region::new(16, 20, 160, 160), WIDTH, HEIGHT)
+        .expect("window on the display");
+    epd.update_partial_window(&mut spi, &small_buffer, window)?;
 
     let small_buffer = [Color::White.get_byte_value(); 800]; //80x80
-    epd.update_partial_frame(&mut spi, &small_buffer, 60, 60, 80, 80)?;
+    let window = PartialWindow::new(Region::new(56, 60, 80, 80), WIDTH, HEIGHT)
+        .expect("window on the display");
+    epd.update_partial_window(&mut spi, &small_buffer, window)?;
 
     let small_buffer = [Color::Black.get_byte_value(); 8]; //8x8
-    epd.update_partial_frame(&mut spi, &small_buffer, 96, 96, 8, 8)?;
+    let window = PartialWindow::new(Region::new(96, 96, 8, 8), WIDTH, HEIGHT)
+        .expect("window on the display");
+    epd.update_partial_window(&mut spi, &small_buffer, window)?;
 
     // Display updated frame
     epd.display_frame(&mut spi, &mut delay)?;
@@ -34,7 +34,7 @@ use linux_embedded_hal::{
 //
 // after finishing, put the display to sleep
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     let busy = Pin::new(24); // GPIO 24, board J-18
     busy.export().expect("busy export");
     while !busy.is_exported() {}
@@ -94,7 +94,7 @@ fn main() -> Result<(), std::io::Error> {
     // the bw-buffer of this tri-color screen
 
     epd2in13
-        .update_and_display_frame(&mut spi, &display.bw_buffer(), &mut delay)
+        .update_and_display_frame(&mut spi, display.bw_buffer(), &mut delay)
         .expect("display frame new graphics");
 
     println!("First frame done. Waiting 5s");
@@ -151,7 +151,8 @@ fn main() -> Result<(), std::io::Error> {
     epd2in13.display_frame(&mut spi, &mut delay)?;
 
     println!("Finished tests - going to sleep");
-    epd2in13.sleep(&mut spi, &mut delay)
+    epd2in13.sleep(&mut spi, &mut delay)?;
+    Ok(())
 }
 
 fn draw_text(display: &mut Display2in13bc, text: &str, x: i32, y: i32) {
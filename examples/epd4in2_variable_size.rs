@@ -1,5 +1,4 @@
 #![deny(warnings)]
-#![deny(warnings)]
 
 use embedded_graphics::{
     mono_font::MonoTextStyleBuilder,
@@ -13,6 +12,7 @@ use epd_waveshare::{
     epd4in2::{self, Epd4in2},
     graphics::{Display, DisplayRotation, VarDisplay},
     prelude::*,
+    region::Region,
 };
 use linux_embedded_hal::{
     spidev::{self, SpidevOptions},
@@ -24,7 +24,7 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Configure SPI
     // Settings are taken from
     let mut spi = Spidev::open("/dev/spidev0.0").expect("spidev directory");
@@ -67,7 +67,13 @@ fn main() -> Result<(), std::io::Error> {
 
     println!("Test all the rotations");
 
-    let (x, y, width, height) = (50, 50, 250, 250);
+    let (x, y, width, height) = (48, 50, 256, 250);
+    let window = PartialWindow::new(
+        Region::new(x, y, width, height),
+        epd4in2::WIDTH,
+        epd4in2::HEIGHT,
+    )
+    .expect("window on the display");
 
     let mut buffer = [epd4in2::DEFAULT_BACKGROUND_COLOR.get_byte_value(); 62500]; //250*250
     let mut display = VarDisplay::new(width, height, &mut buffer);
@@ -84,7 +90,7 @@ fn main() -> Result<(), std::io::Error> {
     draw_text(&mut display, "Rotate 270!", 5, 50);
 
     epd4in2
-        .update_partial_frame(&mut spi, &display.buffer(), x, y, width, height)
+        .update_partial_window(&mut spi, display.buffer(), window)
         .unwrap();
     epd4in2
         .display_frame(&mut spi, &mut delay)
@@ -140,7 +146,7 @@ fn main() -> Result<(), std::io::Error> {
         draw_text(&mut display, "  Hello World! ", 5 + i * 12, 50);
 
         epd4in2
-            .update_partial_frame(&mut spi, &display.buffer(), x, y, width, height)
+            .update_partial_window(&mut spi, display.buffer(), window)
             .unwrap();
         epd4in2
             .display_frame(&mut spi, &mut delay)
@@ -150,7 +156,8 @@ fn main() -> Result<(), std::io::Error> {
     }
 
     println!("Finished tests - going to sleep");
-    epd4in2.sleep(&mut spi, &mut delay)
+    epd4in2.sleep(&mut spi, &mut delay)?;
+    Ok(())
 }
 
 fn draw_text(display: &mut VarDisplay, text: &str, x: i32, y: i32) {
@@ -0,0 +1,672 @@
+//! Panels selected at runtime
+//!
+//! Products shipping with different panels, e.g. depending on the SKU, can pick the driver when
+//! booting instead of being generic over it. [`AnyEpd`] wraps every driver and implements
+//! [`WaveshareDisplay`] by forwarding to the selected one:
+//!
+//! ```rust,ignore
+//! let model = match sku {
+//!     Sku::Small => Model::Epd2in9V2,
+//!     Sku::Large => Model::Epd4in2,
+//! };
+//! let mut epd = AnyEpd::new_model(model, &mut spi, cs, busy, dc, rst, &mut delay)?;
+//! let mut buffer = [0xff; epd4in2::WIDTH as usize * epd4in2::HEIGHT as usize / 8];
+//! let buffer = &mut buffer[..model.buffer_len()];
+//! epd.update_and_display_frame(&mut spi, buffer, &mut delay)?;
+//! ```
+//!
+//! The background colors are [`Color`] for all panels. The frames are packed with one bit per
+//! pixel, except for the 4 bit [`OctColor`] pixels of [`Epd5in65f`](epd5in65f::Epd5in65f), see
+//! [`Model::buffer_len`]. Frames of another length fail with [`Error::Unsupported`], as do the
+//! partial frames of the Epd5in65f. Tri-color panels only get the b/w plane through
+//! [`WaveshareDisplay`], match on the variant to use their
+//! [`WaveshareThreeColorDisplay`](crate::traits::WaveshareThreeColorDisplay) methods.
+
+use core::time::Duration;
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::color::{Color, OctColor, TriColor};
+use crate::error::{DriverError, Error};
+use crate::interface::TrafficFn;
+use crate::source::PackedColor;
+use crate::traits::{PartialWindow, RefreshLut, RefreshMode, Timing, WaveshareDisplay};
+use crate::{
+    epd1in54, epd1in54b, epd1in54c, epd2in13_v2, epd2in13bc, epd2in7b, epd2in9, epd2in9_v2,
+    epd2in9bc, epd4in2, epd5in65f, epd7in5, epd7in5_hd, epd7in5_v2,
+};
+
+/// The panels supported by [`AnyEpd`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Model {
+    /// [`epd1in54::Epd1in54`]
+    Epd1in54,
+    /// [`epd1in54b::Epd1in54b`]
+    Epd1in54b,
+    /// [`epd1in54c::Epd1in54c`]
+    Epd1in54c,
+    /// [`epd2in13_v2::Epd2in13`]
+    Epd2in13V2,
+    /// [`epd2in13bc::Epd2in13bc`]
+    Epd2in13bc,
+    /// [`epd2in7b::Epd2in7b`]
+    Epd2in7b,
+    /// [`epd2in9::Epd2in9`]
+    Epd2in9,
+    /// [`epd2in9_v2::Epd2in9`]
+    Epd2in9V2,
+    /// [`epd2in9bc::Epd2in9bc`]
+    Epd2in9bc,
+    /// [`epd4in2::Epd4in2`]
+    Epd4in2,
+    /// [`epd5in65f::Epd5in65f`]
+    Epd5in65f,
+    /// [`epd7in5::Epd7in5`]
+    Epd7in5,
+    /// [`epd7in5_hd::Epd7in5`]
+    Epd7in5Hd,
+    /// [`epd7in5_v2::Epd7in5`]
+    Epd7in5V2,
+}
+
+impl Model {
+    /// All models
+    pub const ALL: [Model; 14] = [
+        Model::Epd1in54,
+        Model::Epd1in54b,
+        Model::Epd1in54c,
+        Model::Epd2in13V2,
+        Model::Epd2in13bc,
+        Model::Epd2in7b,
+        Model::Epd2in9,
+        Model::Epd2in9V2,
+        Model::Epd2in9bc,
+        Model::Epd4in2,
+        Model::Epd5in65f,
+        Model::Epd7in5,
+        Model::Epd7in5Hd,
+        Model::Epd7in5V2,
+    ];
+
+    /// Width of the panel, e.g. to size the buffers
+    pub const fn width(self) -> u32 {
+        match self {
+            Model::Epd1in54 => epd1in54::WIDTH,
+            Model::Epd1in54b => epd1in54b::WIDTH,
+            Model::Epd1in54c => epd1in54c::WIDTH,
+            Model::Epd2in13V2 => epd2in13_v2::WIDTH,
+            Model::Epd2in13bc => epd2in13bc::WIDTH,
+            Model::Epd2in7b => epd2in7b::WIDTH,
+            Model::Epd2in9 => epd2in9::WIDTH,
+            Model::Epd2in9V2 => epd2in9_v2::WIDTH,
+            Model::Epd2in9bc => epd2in9bc::WIDTH,
+            Model::Epd4in2 => epd4in2::WIDTH,
+            Model::Epd5in65f => epd5in65f::WIDTH,
+            Model::Epd7in5 => epd7in5::WIDTH,
+            Model::Epd7in5Hd => epd7in5_hd::WIDTH,
+            Model::Epd7in5V2 => epd7in5_v2::WIDTH,
+        }
+    }
+
+    /// Height of the panel
+    pub const fn height(self) -> u32 {
+        match self {
+            Model::Epd1in54 => epd1in54::HEIGHT,
+            Model::Epd1in54b => epd1in54b::HEIGHT,
+            Model::Epd1in54c => epd1in54c::HEIGHT,
+            Model::Epd2in13V2 => epd2in13_v2::HEIGHT,
+            Model::Epd2in13bc => epd2in13bc::HEIGHT,
+            Model::Epd2in7b => epd2in7b::HEIGHT,
+            Model::Epd2in9 => epd2in9::HEIGHT,
+            Model::Epd2in9V2 => epd2in9_v2::HEIGHT,
+            Model::Epd2in9bc => epd2in9bc::HEIGHT,
+            Model::Epd4in2 => epd4in2::HEIGHT,
+            Model::Epd5in65f => epd5in65f::HEIGHT,
+            Model::Epd7in5 => epd7in5::HEIGHT,
+            Model::Epd7in5Hd => epd7in5_hd::HEIGHT,
+            Model::Epd7in5V2 => epd7in5_v2::HEIGHT,
+        }
+    }
+
+    /// Maximum SPI clock of the controller in Hz, see [`WaveshareDisplay::MAX_SPI_HZ`]
+    pub const fn max_spi_hz(self) -> u32 {
+        match self {
+            Model::Epd1in54 => epd1in54::MAX_SPI_HZ,
+            Model::Epd1in54b => epd1in54b::MAX_SPI_HZ,
+            Model::Epd1in54c => epd1in54c::MAX_SPI_HZ,
+            Model::Epd2in13V2 => epd2in13_v2::MAX_SPI_HZ,
+            Model::Epd2in13bc => epd2in13bc::MAX_SPI_HZ,
+            Model::Epd2in7b => epd2in7b::MAX_SPI_HZ,
+            Model::Epd2in9 => epd2in9::MAX_SPI_HZ,
+            Model::Epd2in9V2 => epd2in9_v2::MAX_SPI_HZ,
+            Model::Epd2in9bc => epd2in9bc::MAX_SPI_HZ,
+            Model::Epd4in2 => epd4in2::MAX_SPI_HZ,
+            Model::Epd5in65f => epd5in65f::MAX_SPI_HZ,
+            Model::Epd7in5 => epd7in5::MAX_SPI_HZ,
+            Model::Epd7in5Hd => epd7in5_hd::MAX_SPI_HZ,
+            Model::Epd7in5V2 => epd7in5_v2::MAX_SPI_HZ,
+        }
+    }
+
+    /// Typical durations of the panel, see [`WaveshareDisplay::TIMING`]
+    pub const fn timing(self) -> Timing {
+        match self {
+            Model::Epd1in54 => epd1in54::TIMING,
+            Model::Epd1in54b => epd1in54b::TIMING,
+            Model::Epd1in54c => epd1in54c::TIMING,
+            Model::Epd2in13V2 => epd2in13_v2::TIMING,
+            Model::Epd2in13bc => epd2in13bc::TIMING,
+            Model::Epd2in7b => epd2in7b::TIMING,
+            Model::Epd2in9 => epd2in9::TIMING,
+            Model::Epd2in9V2 => epd2in9_v2::TIMING,
+            Model::Epd2in9bc => epd2in9bc::TIMING,
+            Model::Epd4in2 => epd4in2::TIMING,
+            Model::Epd5in65f => epd5in65f::TIMING,
+            Model::Epd7in5 => epd7in5::TIMING,
+            Model::Epd7in5Hd => epd7in5_hd::TIMING,
+            Model::Epd7in5V2 => epd7in5_v2::TIMING,
+        }
+    }
+
+    /// Bits per pixel of the frames of [`WaveshareDisplay::update_frame`]
+    pub const fn bits_per_pixel(self) -> u32 {
+        match self {
+            Model::Epd5in65f => OctColor::BITS_PER_PIXEL,
+            _ => Color::BITS_PER_PIXEL,
+        }
+    }
+
+    /// Length of the frames of [`WaveshareDisplay::update_frame`] in bytes, e.g. to size the
+    /// buffers
+    pub const fn buffer_len(self) -> usize {
+        crate::buffer_len(
+            (self.width() * self.bits_per_pixel()) as usize,
+            self.height() as usize,
+        )
+    }
+}
+
+// the slowest SPI clock of all models
+const fn min_spi_hz() -> u32 {
+    let mut hz = u32::MAX;
+    let mut i = 0;
+    while i < Model::ALL.len() {
+        let max_spi_hz = Model::ALL[i].max_spi_hz();
+        if max_spi_hz < hz {
+            hz = max_spi_hz;
+        }
+        i += 1;
+    }
+    hz
+}
+
+// the longest durations of all models, partial refreshes as long as full ones
+const fn slowest_timing() -> Timing {
+    let mut timing = Timing {
+        full_refresh_ms: 0,
+        partial_refresh_ms: None,
+        wake_ms: 0,
+    };
+    let mut i = 0;
+    while i < Model::ALL.len() {
+        let model = Model::ALL[i].timing();
+        if model.full_refresh_ms > timing.full_refresh_ms {
+            timing.full_refresh_ms = model.full_refresh_ms;
+        }
+        if model.wake_ms > timing.wake_ms {
+            timing.wake_ms = model.wake_ms;
+        }
+        i += 1;
+    }
+    timing
+}
+
+/// One of the drivers, selected at runtime
+///
+/// Created with [`new_model`](AnyEpd::new_model), or by wrapping a driver in its variant.
+pub enum AnyEpd<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// [`epd1in54::Epd1in54`]
+    Epd1in54(epd1in54::Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd1in54b::Epd1in54b`]
+    Epd1in54b(epd1in54b::Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd1in54c::Epd1in54c`]
+    Epd1in54c(epd1in54c::Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd2in13_v2::Epd2in13`]
+    Epd2in13V2(epd2in13_v2::Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd2in13bc::Epd2in13bc`]
+    Epd2in13bc(epd2in13bc::Epd2in13bc<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd2in7b::Epd2in7b`]
+    Epd2in7b(epd2in7b::Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd2in9::Epd2in9`]
+    Epd2in9(epd2in9::Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd2in9_v2::Epd2in9`]
+    Epd2in9V2(epd2in9_v2::Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd2in9bc::Epd2in9bc`]
+    Epd2in9bc(epd2in9bc::Epd2in9bc<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd4in2::Epd4in2`]
+    Epd4in2(epd4in2::Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd5in65f::Epd5in65f`]
+    Epd5in65f(epd5in65f::Epd5in65f<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd7in5::Epd7in5`]
+    Epd7in5(epd7in5::Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd7in5_hd::Epd7in5`]
+    Epd7in5Hd(epd7in5_hd::Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// [`epd7in5_v2::Epd7in5`]
+    Epd7in5V2(epd7in5_v2::Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>),
+}
+
+// runs `$body` with `$epd` bound to the selected driver
+macro_rules! dispatch {
+    ($any:expr, $epd:ident => $body:expr) => {
+        match $any {
+            AnyEpd::Epd1in54($epd) => $body,
+            AnyEpd::Epd1in54b($epd) => $body,
+            AnyEpd::Epd1in54c($epd) => $body,
+            AnyEpd::Epd2in13V2($epd) => $body,
+            AnyEpd::Epd2in13bc($epd) => $body,
+            AnyEpd::Epd2in7b($epd) => $body,
+            AnyEpd::Epd2in9($epd) => $body,
+            AnyEpd::Epd2in9V2($epd) => $body,
+            AnyEpd::Epd2in9bc($epd) => $body,
+            AnyEpd::Epd4in2($epd) => $body,
+            AnyEpd::Epd5in65f($epd) => $body,
+            AnyEpd::Epd7in5($epd) => $body,
+            AnyEpd::Epd7in5Hd($epd) => $body,
+            AnyEpd::Epd7in5V2($epd) => $body,
+        }
+    };
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> AnyEpd<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    /// Creates and initializes the driver of `model`
+    pub fn new_model(
+        model: Model,
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
+        Ok(match model {
+            Model::Epd1in54 => {
+                AnyEpd::Epd1in54(epd1in54::Epd1in54::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd1in54b => {
+                AnyEpd::Epd1in54b(epd1in54b::Epd1in54b::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd1in54c => {
+                AnyEpd::Epd1in54c(epd1in54c::Epd1in54c::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd2in13V2 => {
+                AnyEpd::Epd2in13V2(epd2in13_v2::Epd2in13::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd2in13bc => {
+                AnyEpd::Epd2in13bc(epd2in13bc::Epd2in13bc::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd2in7b => {
+                AnyEpd::Epd2in7b(epd2in7b::Epd2in7b::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd2in9 => {
+                AnyEpd::Epd2in9(epd2in9::Epd2in9::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd2in9V2 => {
+                AnyEpd::Epd2in9V2(epd2in9_v2::Epd2in9::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd2in9bc => {
+                AnyEpd::Epd2in9bc(epd2in9bc::Epd2in9bc::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd4in2 => {
+                AnyEpd::Epd4in2(epd4in2::Epd4in2::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd5in65f => {
+                AnyEpd::Epd5in65f(epd5in65f::Epd5in65f::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd7in5 => {
+                AnyEpd::Epd7in5(epd7in5::Epd7in5::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd7in5Hd => {
+                AnyEpd::Epd7in5Hd(epd7in5_hd::Epd7in5::new(spi, cs, busy, dc, rst, delay)?)
+            }
+            Model::Epd7in5V2 => {
+                AnyEpd::Epd7in5V2(epd7in5_v2::Epd7in5::new(spi, cs, busy, dc, rst, delay)?)
+            }
+        })
+    }
+
+    /// The selected panel
+    pub fn model(&self) -> Model {
+        match self {
+            AnyEpd::Epd1in54(_) => Model::Epd1in54,
+            AnyEpd::Epd1in54b(_) => Model::Epd1in54b,
+            AnyEpd::Epd1in54c(_) => Model::Epd1in54c,
+            AnyEpd::Epd2in13V2(_) => Model::Epd2in13V2,
+            AnyEpd::Epd2in13bc(_) => Model::Epd2in13bc,
+            AnyEpd::Epd2in7b(_) => Model::Epd2in7b,
+            AnyEpd::Epd2in9(_) => Model::Epd2in9,
+            AnyEpd::Epd2in9V2(_) => Model::Epd2in9V2,
+            AnyEpd::Epd2in9bc(_) => Model::Epd2in9bc,
+            AnyEpd::Epd4in2(_) => Model::Epd4in2,
+            AnyEpd::Epd5in65f(_) => Model::Epd5in65f,
+            AnyEpd::Epd7in5(_) => Model::Epd7in5,
+            AnyEpd::Epd7in5Hd(_) => Model::Epd7in5Hd,
+            AnyEpd::Epd7in5V2(_) => Model::Epd7in5V2,
+        }
+    }
+
+    // frames of the wrong size would be misread, e.g. 1 bit frames by the Epd5in65f
+    fn check_buffer(&self, buffer: &[u8]) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        if buffer.len() == self.model().buffer_len() {
+            Ok(())
+        } else {
+            Err(Error::Unsupported)
+        }
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for AnyEpd<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    type DisplayColor = Color;
+    /// The slowest clock of all models, [`Model::max_spi_hz`] has the one of the selected model
+    const MAX_SPI_HZ: u32 = min_spi_hz();
+    /// The longest durations of all models, [`Model::timing`] has the ones of the selected model
+    const TIMING: Timing = slowest_timing();
+
+    /// Always fails with [`Error::Unsupported`] since the model can't be chosen here, use
+    /// [`AnyEpd::new_model`]
+    fn new(
+        _spi: &mut SPI,
+        _cs: CS,
+        _busy: BUSY,
+        _dc: DC,
+        _rst: RST,
+        _delay: &mut DELAY,
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
+        Err(Error::Unsupported)
+    }
+
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        dispatch!(self, epd => epd.sleep(spi, delay))
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        dispatch!(self, epd => epd.wake_up(spi, delay))
+    }
+
+    #[allow(clippy::useless_conversion)]
+    fn set_background_color(&mut self, color: Color) {
+        // only the tri-color and 7-color drivers need a conversion
+        dispatch!(self, epd => epd.set_background_color(color.into()))
+    }
+
+    /// Chromatic backgrounds of the tri-color and 7-color panels read as black
+    fn background_color(&self) -> &Color {
+        match self {
+            AnyEpd::Epd2in13bc(epd) => match epd.background_color() {
+                TriColor::White => &Color::White,
+                _ => &Color::Black,
+            },
+            AnyEpd::Epd5in65f(epd) => match epd.background_color() {
+                OctColor::White => &Color::White,
+                _ => &Color::Black,
+            },
+            AnyEpd::Epd1in54(epd) => epd.background_color(),
+            AnyEpd::Epd1in54b(epd) => epd.background_color(),
+            AnyEpd::Epd1in54c(epd) => epd.background_color(),
+            AnyEpd::Epd2in13V2(epd) => epd.background_color(),
+            AnyEpd::Epd2in7b(epd) => epd.background_color(),
+            AnyEpd::Epd2in9(epd) => epd.background_color(),
+            AnyEpd::Epd2in9V2(epd) => epd.background_color(),
+            AnyEpd::Epd2in9bc(epd) => epd.background_color(),
+            AnyEpd::Epd4in2(epd) => epd.background_color(),
+            AnyEpd::Epd7in5(epd) => epd.background_color(),
+            AnyEpd::Epd7in5Hd(epd) => epd.background_color(),
+            AnyEpd::Epd7in5V2(epd) => epd.background_color(),
+        }
+    }
+
+    fn width(&self) -> u32 {
+        dispatch!(self, epd => epd.width())
+    }
+
+    fn height(&self) -> u32 {
+        dispatch!(self, epd => epd.height())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.check_buffer(buffer)?;
+        dispatch!(self, epd => epd.update_frame(spi, buffer, delay))
+    }
+
+    fn update_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        if let AnyEpd::Epd5in65f(_) = self {
+            return Err(Error::Unsupported);
+        }
+        dispatch!(self, epd => epd.update_partial_window(spi, buffer, window))
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        dispatch!(self, epd => epd.display_frame(spi, delay))
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.check_buffer(buffer)?;
+        dispatch!(self, epd => epd.update_and_display_frame(spi, buffer, delay))
+    }
+
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        dispatch!(self, epd => epd.clear_frame(spi, delay))
+    }
+
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        dispatch!(self, epd => epd.set_lut(spi, refresh_rate))
+    }
+
+    fn is_busy(&self) -> bool {
+        dispatch!(self, epd => epd.is_busy())
+    }
+
+    fn set_busy_yield(&mut self, busy_yield: Option<fn()>) {
+        dispatch!(self, epd => epd.set_busy_yield(busy_yield))
+    }
+
+    fn wait_until_idle_or_cancel<F>(
+        &mut self,
+        delay: &mut DELAY,
+        should_cancel: F,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        F: FnMut() -> bool,
+    {
+        dispatch!(self, epd => epd.wait_until_idle_or_cancel(delay, should_cancel))
+    }
+
+    fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        dispatch!(self, epd => epd.set_max_chunk_size(max_chunk_size))
+    }
+
+    fn set_traffic_hook(&mut self, traffic_hook: Option<TrafficFn>) {
+        dispatch!(self, epd => epd.set_traffic_hook(traffic_hook))
+    }
+
+    fn send_command_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        dispatch!(self, epd => epd.send_command_raw(spi, command))
+    }
+
+    fn send_data_raw(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        dispatch!(self, epd => epd.send_data_raw(spi, data))
+    }
+
+    fn set_temperature(&mut self, temperature: Option<i8>) {
+        dispatch!(self, epd => epd.set_temperature(temperature))
+    }
+
+    fn estimate_refresh(&self, mode: RefreshMode, temperature: i8, spi_hz: u32) -> Duration {
+        dispatch!(self, epd => epd.estimate_refresh(mode, temperature, spi_hz))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::region::Region;
+    use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+    use embedded_hal_mock::delay::MockNoop;
+
+    #[test]
+    fn model() {
+        for model in Model::ALL {
+            assert!(Model::ALL.iter().filter(|m| **m == model).count() == 1);
+        }
+        assert_eq!(Model::Epd4in2.width(), 400);
+        assert_eq!(Model::Epd2in9V2.height(), 296);
+        assert_eq!(Model::Epd7in5V2.timing(), epd7in5_v2::TIMING);
+        assert_eq!(Model::Epd4in2.buffer_len(), 400 * 300 / 8);
+        assert_eq!(Model::Epd2in13V2.buffer_len(), 16 * 250);
+        assert_eq!(Model::Epd5in65f.buffer_len(), 600 * 448 / 2);
+        assert!(Model::ALL.iter().all(|m| m.max_spi_hz() >= min_spi_hz()
+            && m.timing().full_refresh_ms <= slowest_timing().full_refresh_ms));
+    }
+
+    #[test]
+    fn forwards_to_the_model() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+
+        assert!(matches!(
+            AnyEpd::new(
+                &mut spi,
+                NoopPin,
+                IdleBusyPin,
+                recorder.dc(),
+                recorder.rst(),
+                &mut delay,
+            ),
+            Err(Error::Unsupported)
+        ));
+        assert_eq!(recorder.take_events(), []);
+
+        for model in Model::ALL {
+            let mut epd = AnyEpd::new_model(
+                model,
+                &mut spi,
+                NoopPin,
+                IdleBusyPin,
+                recorder.dc(),
+                recorder.rst(),
+                &mut delay,
+            )
+            .unwrap();
+            assert_eq!(recorder.take_events()[0], Event::Reset);
+            assert_eq!(epd.model(), model);
+            assert_eq!((epd.width(), epd.height()), (model.width(), model.height()));
+
+            epd.set_background_color(Color::Black);
+            assert_eq!(epd.background_color(), &Color::Black);
+            epd.send_command_raw(&mut spi, 0x12).unwrap();
+            assert_eq!(recorder.take_events(), [Event::Command(0x12)]);
+
+            // Frames of another length aren't sent
+            let buffer = std::vec![0xff; model.buffer_len() + 1];
+            assert_eq!(
+                epd.update_frame(&mut spi, &buffer, &mut delay),
+                Err(Error::Unsupported)
+            );
+            assert_eq!(
+                epd.update_and_display_frame(&mut spi, &buffer[1..], &mut delay),
+                Ok(())
+            );
+            recorder.take_events();
+        }
+    }
+
+    #[test]
+    fn no_partial_frames_of_7_color_panels() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = AnyEpd::new_model(
+            Model::Epd5in65f,
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+
+        let window = PartialWindow::new(Region::new(0, 0, 8, 8), 600, 448).unwrap();
+        assert_eq!(
+            epd.update_partial_window(&mut spi, &[0; 8], window),
+            Err(Error::Unsupported)
+        );
+        assert_eq!(recorder.take_events(), []);
+    }
+}
@@ -0,0 +1,224 @@
+//! Rendering in horizontal bands, for targets without the RAM for a frame buffer
+//!
+//! [`render_banded`] draws the scene into a strip of a few rows of the frame, sends it and
+//! repeats with the next rows until the whole frame is sent. The scene is drawn once per band,
+//! everything outside of the current band is dropped:
+//!
+//! ```rust,ignore
+//! // 16 rows of the 4.2" display instead of 300
+//! let mut strip = [0u8; WIDTH as usize / 8 * 16];
+//! render_banded(&mut epd, &mut spi, &mut strip, DisplayRotation::Rotate0, |band| {
+//!     Text::new("Hello", Point::new(10, 20), style).draw(band)?;
+//!     Ok(())
+//! })?;
+//! epd.display_frame(&mut spi, &mut delay)?;
+//! ```
+//!
+//! The bands are rows of the unrotated frame, as sent to the display. With a rotation they are
+//! columns of the scene.
+
+use core::convert::Infallible;
+use core::ops::Range;
+use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*};
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::error::DriverError;
+use crate::graphics::packing::{find_rotation, outside_display};
+use crate::graphics::DisplayRotation;
+use crate::traits::FrameStream;
+
+/// A few rows of a b/w frame, drawn on like the whole frame
+///
+/// Pixels outside of [`rows`](Band::rows) are ignored.
+pub struct Band<'a> {
+    buffer: &'a mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    top: u32,
+    rows: u32,
+}
+
+impl<'a> Band<'a> {
+    /// Rows `top..top + buffer.len() / row length` of a `width` x `height` frame, filled with
+    /// white
+    ///
+    /// The rows past the bottom of the frame are left out.
+    pub fn new(
+        buffer: &'a mut [u8],
+        width: u32,
+        height: u32,
+        rotation: DisplayRotation,
+        top: u32,
+    ) -> Self {
+        let rows = (buffer.len() / width.div_ceil(8) as usize) as u32;
+        let rows = rows.min(height.saturating_sub(top));
+        buffer.fill(0xff);
+        Band {
+            buffer,
+            width,
+            height,
+            rotation,
+            top,
+            rows,
+        }
+    }
+
+    /// The rows of the unrotated frame in the band
+    pub fn rows(&self) -> Range<u32> {
+        self.top..self.top + self.rows
+    }
+
+    /// The packed rows of the band, as sent to the display
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer[..self.rows as usize * self.width.div_ceil(8) as usize]
+    }
+}
+
+impl DrawTarget for Band<'_> {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let row_len = self.width.div_ceil(8);
+        for Pixel(point, color) in pixels {
+            if outside_display(point, self.width, self.height, self.rotation) {
+                continue;
+            }
+            let (nx, ny) = find_rotation(
+                point.x as u32,
+                point.y as u32,
+                self.width,
+                self.height,
+                self.rotation,
+            );
+            if !self.rows().contains(&ny) {
+                continue;
+            }
+            let index = (nx / 8 + row_len * (ny - self.top)) as usize;
+            let bit = 0x80 >> (nx % 8);
+            match color {
+                BinaryColor::On => self.buffer[index] &= !bit,
+                BinaryColor::Off => self.buffer[index] |= bit,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Band<'_> {
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0
+            | DisplayRotation::Rotate180
+            | DisplayRotation::FlipHorizontal
+            | DisplayRotation::FlipVertical => Size::new(self.width, self.height),
+            DisplayRotation::Rotate90
+            | DisplayRotation::Rotate270
+            | DisplayRotation::Rotate90FlipHorizontal
+            | DisplayRotation::Rotate270FlipHorizontal => Size::new(self.height, self.width),
+        }
+    }
+}
+
+/// Sends a full b/w frame drawn by `draw` band by band, each as tall as fits into `strip`
+///
+/// `draw` is called once per band with the band to draw on, it should draw the same scene every
+/// time. Follow it with [`display_frame`](crate::traits::WaveshareDisplay::display_frame).
+/// Panics if `strip` is shorter than a row.
+pub fn render_banded<SPI, CS, BUSY, DC, RST, DELAY, EPD, F>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    strip: &mut [u8],
+    rotation: DisplayRotation,
+    mut draw: F,
+) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: FrameStream<SPI, CS, BUSY, DC, RST, DELAY>,
+    F: FnMut(&mut Band<'_>) -> Result<(), Infallible>,
+{
+    let (width, height) = (epd.width(), epd.height());
+    assert!(
+        strip.len() >= width.div_ceil(8) as usize,
+        "strip is shorter than a row"
+    );
+    epd.begin_frame(spi)?;
+    let mut top = 0;
+    while top < height {
+        let mut band = Band::new(strip, width, height, rotation, top);
+        draw(&mut band).unwrap_or_else(|e| match e {});
+        epd.write_frame_chunk(spi, band.buffer())?;
+        top = band.rows().end;
+    }
+    epd.end_frame(spi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in7b::{Display2in7b, Epd2in7b, HEIGHT, WIDTH};
+    use crate::graphics::Display;
+    use crate::test_utils::{IdleBusyPin, NoopPin, Recorder};
+    use crate::traits::WaveshareDisplay;
+    use embedded_graphics::primitives::{Circle, Primitive, PrimitiveStyle};
+    use embedded_hal_mock::delay::MockNoop;
+
+    #[test]
+    fn bands_match_the_frame() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        fn scene<T: DrawTarget<Color = BinaryColor>>(target: &mut T) -> Result<(), T::Error> {
+            Circle::new(Point::new(20, 30), 100)
+                .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 3))
+                .draw(target)
+        }
+        let mut display = Display2in7b::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        scene(&mut display).unwrap();
+        recorder.take_events();
+        epd.update_frame(&mut spi, display.buffer(), &mut delay)
+            .unwrap();
+        let expected = recorder.take_events();
+
+        // 264 rows in bands of 10, the last one is shorter
+        let mut strip = [0u8; WIDTH as usize / 8 * 10];
+        let mut bands = 0;
+        render_banded(
+            &mut epd,
+            &mut spi,
+            &mut strip,
+            DisplayRotation::Rotate90,
+            |band| {
+                bands += 1;
+                assert_eq!(band.size(), Size::new(HEIGHT, WIDTH));
+                scene(band)
+            },
+        )
+        .unwrap();
+        assert_eq!(bands, 27);
+        assert_eq!(recorder.take_events(), expected);
+    }
+}
@@ -0,0 +1,33 @@
+//! Time source for the helpers that need to measure elapsed time
+//!
+//! The crate doesn't know about timers of the target, so these helpers take anything implementing
+//! [`Clock`], e.g. a wrapper around a HAL timer or the systick counter. Closures returning the
+//! current time in milliseconds implement it as well:
+//!
+//! ```rust
+//! use epd_waveshare::clock::Clock;
+//!
+//! let mut ticks = 0u64;
+//! let mut clock = move || {
+//!     ticks += 10;
+//!     ticks
+//! };
+//! assert_eq!(clock.now_ms(), 10);
+//! ```
+
+/// Monotonic millisecond clock
+pub trait Clock {
+    /// Returns the current time in milliseconds
+    ///
+    /// Only differences between two values are used, so the start point doesn't matter.
+    fn now_ms(&mut self) -> u64;
+}
+
+impl<F> Clock for F
+where
+    F: FnMut() -> u64,
+{
+    fn now_ms(&mut self) -> u64 {
+        self()
+    }
+}
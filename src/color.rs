@@ -27,6 +27,7 @@ impl OutOfColorRangeParseError {
 
 /// Only for the Black/White-Displays
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Color {
     /// Black color
     Black,
@@ -36,6 +37,7 @@ pub enum Color {
 
 /// Only for the Black/White/Color-Displays
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TriColor {
     /// Black color
     Black,
@@ -45,8 +47,45 @@ pub enum TriColor {
     Chromatic,
 }
 
+/// Color of the outer border of the display, outside of the frame buffer
+///
+/// Displays without a chromatic color show [`Chromatic`](BorderColor::Chromatic) as white.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BorderColor {
+    /// White border
+    #[default]
+    White,
+    /// Black border
+    Black,
+    /// Border in the chromatic color of tri color displays
+    Chromatic,
+    /// Not driven, the border keeps whatever it showed before
+    Floating,
+}
+
+impl From<Color> for TriColor {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Black => TriColor::Black,
+            Color::White => TriColor::White,
+        }
+    }
+}
+
+impl From<TriColor> for BorderColor {
+    fn from(color: TriColor) -> Self {
+        match color {
+            TriColor::Black => BorderColor::Black,
+            TriColor::White => BorderColor::White,
+            TriColor::Chromatic => BorderColor::Chromatic,
+        }
+    }
+}
+
 /// For the 5in65 7 Color Display
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OctColor {
     /// Black Color
     Black = 0x00,
@@ -66,6 +105,15 @@ pub enum OctColor {
     HiZ = 0x07,
 }
 
+impl From<Color> for OctColor {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Black => OctColor::Black,
+            Color::White => OctColor::White,
+        }
+    }
+}
+
 impl From<()> for OctColor {
     fn from(_: ()) -> OctColor {
         OctColor::White
@@ -205,7 +253,7 @@ mod tests {
     // test all values aside from 0 and 1 which all should panic
     #[test]
     fn from_u8_panic() {
-        for val in 2..=u8::max_value() {
+        for val in 2..=u8::MAX {
             extern crate std;
             let result = std::panic::catch_unwind(|| Color::from(val));
             assert!(result.is_err());
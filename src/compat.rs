@@ -0,0 +1,275 @@
+//! Adapters for HALs implementing embedded-hal 1.0
+//!
+//! The drivers are written against the embedded-hal 0.2 traits. The wrappers in this module
+//! implement them on top of the embedded-hal 1.0 traits, so the drivers work unchanged with HALs
+//! that only implement 1.0:
+//!
+//! ```rust,ignore
+//! use epd_waveshare::compat::{Delay, InputPin, NoCs, OutputPin, SpiDevice};
+//!
+//! let mut spi = SpiDevice(spi_device);
+//! let mut delay = Delay(delay);
+//! let mut epd = Epd4in2::new(
+//!     &mut spi,
+//!     NoCs,
+//!     InputPin::new(busy),
+//!     OutputPin(dc),
+//!     OutputPin(rst),
+//!     &mut delay,
+//! )?;
+//! ```
+//!
+//! A [`SpiDevice`] selects the chip itself for every write, pass [`NoCs`] as CS pin then. A
+//! [`SpiBus`] is shared with nothing else, so the CS pin is driven by the driver as usual.
+//!
+//! To share the bus with other devices, e.g. an SD card, get a `SpiDevice` for the display from
+//! [embedded-hal-bus](https://docs.rs/embedded-hal-bus) (`RefCellDevice`, `CriticalSectionDevice`,
+//! ...) and wrap it in [`SpiDevice`]. Every write is a transaction of its own, other devices can
+//! only use the bus between two of them while the display isn't selected. The controllers keep
+//! their state while not selected, so commands and the data of frames continue where they left
+//! off. Only the reads of [`ReadBack`](crate::traits::ReadBack) need CS low between the command
+//! and the response, keep the bus to yourself for them.
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+
+use embedded_hal::{blocking, digital::v2};
+use embedded_hal_1::{delay::DelayNs, digital, spi};
+
+/// A 1.0 `SpiDevice` used as SPI of a driver
+#[derive(Debug)]
+pub struct SpiDevice<T>(pub T);
+
+impl<T: spi::SpiDevice> blocking::spi::Write<u8> for SpiDevice<T> {
+    type Error = T::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.write(words)
+    }
+}
+
+impl<T: spi::SpiDevice> blocking::spi::Transfer<u8> for SpiDevice<T> {
+    type Error = T::Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.0.transfer_in_place(words)?;
+        Ok(words)
+    }
+}
+
+/// A 1.0 `SpiBus` used as SPI of a driver
+///
+/// Every write is flushed before it returns, the DC pin must not change while bytes are still
+/// being sent.
+#[derive(Debug)]
+pub struct SpiBus<T>(pub T);
+
+impl<T: spi::SpiBus> blocking::spi::Write<u8> for SpiBus<T> {
+    type Error = T::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.write(words)?;
+        self.0.flush()
+    }
+}
+
+impl<T: spi::SpiBus> blocking::spi::Transfer<u8> for SpiBus<T> {
+    type Error = T::Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.0.transfer_in_place(words)?;
+        self.0.flush()?;
+        Ok(words)
+    }
+}
+
+/// A 1.0 output pin used as CS, DC or RST pin of a driver
+#[derive(Debug)]
+pub struct OutputPin<P>(pub P);
+
+impl<P: digital::OutputPin> v2::OutputPin for OutputPin<P> {
+    type Error = P::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high()
+    }
+}
+
+/// A 1.0 input pin used as BUSY pin of a driver
+///
+/// Reading a 1.0 pin needs mutable access, which the 0.2 traits don't give.
+#[derive(Debug)]
+pub struct InputPin<P>(RefCell<P>);
+
+impl<P> InputPin<P> {
+    /// Wraps the pin
+    pub fn new(pin: P) -> Self {
+        InputPin(RefCell::new(pin))
+    }
+
+    /// Gives back the pin
+    pub fn release(self) -> P {
+        self.0.into_inner()
+    }
+}
+
+impl<P: digital::InputPin> v2::InputPin for InputPin<P> {
+    type Error = P::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.0.borrow_mut().is_high()
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.0.borrow_mut().is_low()
+    }
+}
+
+/// CS pin for a [`SpiDevice`], which selects the chip itself
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCs;
+
+impl v2::OutputPin for NoCs {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A 1.0 `DelayNs` used as delay of a driver
+#[derive(Debug)]
+pub struct Delay<D>(pub D);
+
+impl<D: DelayNs> blocking::delay::DelayMs<u8> for Delay<D> {
+    fn delay_ms(&mut self, ms: u8) {
+        self.0.delay_ms(u32::from(ms));
+    }
+}
+
+impl<D: DelayNs> blocking::delay::DelayMs<u16> for Delay<D> {
+    fn delay_ms(&mut self, ms: u16) {
+        self.0.delay_ms(u32::from(ms));
+    }
+}
+
+impl<D: DelayNs> blocking::delay::DelayMs<u32> for Delay<D> {
+    fn delay_ms(&mut self, ms: u32) {
+        self.0.delay_ms(ms);
+    }
+}
+
+impl<D: DelayNs> blocking::delay::DelayUs<u32> for Delay<D> {
+    fn delay_us(&mut self, us: u32) {
+        self.0.delay_us(us);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd4in2::Epd4in2;
+    use crate::test_utils::{Event, IdleBusyPin, MockNoop, NoopPin, Recorder};
+    use crate::traits::WaveshareDisplay;
+    use embedded_hal_1::spi::{ErrorType, Operation};
+    use std::{rc::Rc, vec::Vec};
+
+    #[derive(Clone, Default)]
+    struct Device(Rc<RefCell<Vec<u8>>>);
+
+    impl ErrorType for Device {
+        type Error = Infallible;
+    }
+
+    impl spi::SpiDevice for Device {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+            for operation in operations {
+                if let Operation::Write(words) = operation {
+                    self.0.borrow_mut().extend_from_slice(words);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    struct Pin(bool);
+
+    impl digital::ErrorType for Pin {
+        type Error = Infallible;
+    }
+
+    impl digital::OutputPin for Pin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    impl digital::InputPin for Pin {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(self.0)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.0)
+        }
+    }
+
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    #[test]
+    fn driver_over_1_0_traits() {
+        let device = Device::default();
+        let mut spi = SpiDevice(device.clone());
+        let mut delay = Delay(NoDelay);
+        // BUSY of the 4.2" is active low
+        Epd4in2::new(
+            &mut spi,
+            NoCs,
+            InputPin::new(Pin(true)),
+            OutputPin(Pin(false)),
+            OutputPin(Pin(false)),
+            &mut delay,
+        )
+        .unwrap();
+
+        // Same bytes as with the 0.2 traits
+        let recorder = Recorder::new();
+        Epd4in2::new(
+            &mut recorder.spi(),
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut MockNoop::new(),
+        )
+        .unwrap();
+        let expected: Vec<u8> = recorder
+            .take_events()
+            .into_iter()
+            .flat_map(|event| match event {
+                Event::Command(command) => std::vec![command],
+                Event::Data(data) => data,
+                Event::Reset => std::vec![],
+            })
+            .collect();
+        assert_eq!(*device.0.borrow(), expected);
+    }
+}
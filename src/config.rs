@@ -0,0 +1,416 @@
+//! Init time configuration for the drivers
+//!
+//! Drivers that support it can be created with a [`Config`] through their `new_with_config`
+//! constructor. Create the config with the [`Builder`]:
+//!
+//! ```rust
+//! use epd_waveshare::config::{Builder, Profile};
+//!
+//! let config = Builder::new().profile(Profile::LowPower).build();
+//! assert_eq!(config.profile(), Profile::LowPower);
+//! ```
+//!
+//! Clones that need longer reset pulses get their own [`ResetTiming`]:
+//!
+//! ```rust
+//! use epd_waveshare::config::{Builder, ResetTiming};
+//!
+//! let config = Builder::new()
+//!     .reset_timing(ResetTiming {
+//!         low_ms: 20,
+//!         settle_ms: 250,
+//!     })
+//!     .build();
+//! assert_eq!(config.reset_timing().unwrap().low_ms, 20);
+//! ```
+//!
+//! Panels whose factory waveform looks better than the tables of the driver use it with
+//! [`LutSource::Otp`]:
+//!
+//! ```rust
+//! use epd_waveshare::config::{Builder, LutSource};
+//!
+//! let config = Builder::new().lut_source(LutSource::Otp).build();
+//! assert_eq!(config.lut_source(), LutSource::Otp);
+//! ```
+//!
+//! The frame rate of a profile can be replaced, e.g. to save power with any profile:
+//!
+//! ```rust
+//! use epd_waveshare::config::{Builder, FrameRate};
+//!
+//! let config = Builder::new().frame_rate(FrameRate::Hz50).build();
+//! assert_eq!(config.frame_rate(), Some(FrameRate::Hz50));
+//! ```
+
+use core::convert::TryFrom;
+use core::fmt;
+
+/// Preset bundles of booster, PLL (frame rate) and VCOM and data interval settings
+///
+/// The exact register values are panel specific and chosen by each driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Profile {
+    /// The settings used by the Waveshare reference code
+    #[default]
+    Default,
+    /// Softer booster and a lower frame rate for battery powered devices
+    ///
+    /// Refreshes take longer but draw less current.
+    LowPower,
+    /// Stronger booster and a higher frame rate for more responsive refreshes
+    ///
+    /// Might lead to more ghosting on some panels.
+    Fast,
+}
+
+/// Frame rate of the refreshes, set through the PLL of the controller
+///
+/// Lower frame rates draw less current but make the refreshes take longer. Applied by
+/// [`Epd2in7b`](crate::epd2in7b::Epd2in7b) and [`Epd4in2`](crate::epd4in2::Epd4in2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameRate {
+    /// 50 Hz
+    Hz50,
+    /// 100 Hz, used by the Waveshare reference code
+    Hz100,
+    /// 150 Hz
+    Hz150,
+    /// 171 Hz
+    Hz171,
+    /// 200 Hz, doesn't work on every board
+    Hz200,
+}
+
+impl FrameRate {
+    /// The value of the PLL control register
+    pub fn register(self) -> u8 {
+        match self {
+            FrameRate::Hz50 => 0x3c,
+            FrameRate::Hz100 => 0x3a,
+            FrameRate::Hz150 => 0x29,
+            FrameRate::Hz171 => 0x31,
+            FrameRate::Hz200 => 0x39,
+        }
+    }
+}
+
+/// Mirroring of the image by the scan directions of the controller
+///
+/// Mirrors the image in hardware for panels mounted upside-down, instead of rotating every pixel
+/// in the buffer. The windows of partial updates are mirrored as well. Applied by
+/// [`Epd2in7b`](crate::epd2in7b::Epd2in7b) and [`Epd4in2`](crate::epd4in2::Epd4in2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mirror {
+    /// The scan directions of the reference code
+    #[default]
+    None,
+    /// Mirror left and right by reversing the source shift direction
+    Horizontal,
+    /// Mirror top and bottom by reversing the gate scan direction
+    Vertical,
+    /// Both, i.e. rotated by 180°
+    Both,
+}
+
+impl Mirror {
+    /// `panel_setting` with the scan direction bits UD (3) and SHL (2) cleared as needed
+    pub(crate) fn apply(self, panel_setting: u8) -> u8 {
+        match self {
+            Mirror::None => panel_setting,
+            Mirror::Horizontal => panel_setting & !0x04,
+            Mirror::Vertical => panel_setting & !0x08,
+            Mirror::Both => panel_setting & !0x0c,
+        }
+    }
+}
+
+/// Timing of the hardware reset through the RST pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResetTiming {
+    /// How long RST is held low in ms
+    pub low_ms: u32,
+    /// How long to wait for the controller after releasing RST in ms
+    pub settle_ms: u32,
+}
+
+/// VCOM DC voltage of the panel, as printed on the FPC of some panels
+///
+/// Stored in the encoding of the VCOM_DC setting of the controllers: -0.1 V to -3.0 V in steps of
+/// 50 mV. Serialized as the register value, deserializing checks its range like
+/// [`TryFrom<u8>`](VcomDc#impl-TryFrom<u8>-for-VcomDc).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u8", into = "u8"))]
+pub struct VcomDc(u8);
+
+/// Error of [`VcomDc::try_from`] for register values past -3.0 V
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidVcomDc;
+
+impl fmt::Display for InvalidVcomDc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("VCOM_DC register value out of range")
+    }
+}
+
+impl VcomDc {
+    /// The nearest setting to `millivolts`, `None` outside of -100 mV to -3000 mV
+    ///
+    /// ```rust
+    /// use epd_waveshare::config::VcomDc;
+    ///
+    /// let vcom = VcomDc::from_millivolts(-1250).unwrap();
+    /// assert_eq!(vcom.millivolts(), -1250);
+    /// assert_eq!(vcom.register(), 0x17);
+    /// assert_eq!(VcomDc::from_millivolts(-50), None);
+    /// ```
+    pub fn from_millivolts(millivolts: i16) -> Option<Self> {
+        if !(-3000..=-100).contains(&millivolts) {
+            return None;
+        }
+        Some(VcomDc(((-millivolts - 100 + 25) / 50) as u8))
+    }
+
+    /// The setting with the given value of the VCOM_DC register
+    pub(crate) const fn from_register(value: u8) -> Self {
+        VcomDc(value)
+    }
+
+    /// The voltage in mV
+    pub fn millivolts(self) -> i16 {
+        -100 - i16::from(self.0) * 50
+    }
+
+    /// The value of the VCOM_DC register
+    pub fn register(self) -> u8 {
+        self.0
+    }
+}
+
+/// The setting with the given value of the VCOM_DC register, at most 0x3a (-3.0 V)
+impl TryFrom<u8> for VcomDc {
+    type Error = InvalidVcomDc;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        if value > 0x3a {
+            return Err(InvalidVcomDc);
+        }
+        Ok(VcomDc(value))
+    }
+}
+
+impl From<VcomDc> for u8 {
+    fn from(vcom: VcomDc) -> u8 {
+        vcom.0
+    }
+}
+
+/// Where the controller takes the waveform of full refreshes from
+///
+/// Applied by [`Epd2in7b`](crate::epd2in7b::Epd2in7b). Quick refreshes always use the tables of
+/// the driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LutSource {
+    /// The LUT registers, loaded with the tables of the driver
+    #[default]
+    Registers,
+    /// The waveform programmed into the OTP memory of the controller by the panel manufacturer
+    Otp,
+}
+
+/// Configuration applied by a driver during init
+///
+/// Use the [`Builder`] to create one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Config {
+    profile: Profile,
+    reset_timing: Option<ResetTiming>,
+    lut_source: LutSource,
+    vcom_dc: Option<VcomDc>,
+    frame_rate: Option<FrameRate>,
+    mirror: Mirror,
+    full_refresh_interval: Option<u32>,
+}
+
+impl Config {
+    /// The selected power/speed profile
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// The reset timing replacing the `RESET_TIMING` of the driver, if any
+    pub fn reset_timing(&self) -> Option<ResetTiming> {
+        self.reset_timing
+    }
+
+    /// The source of the full refresh waveform
+    pub fn lut_source(&self) -> LutSource {
+        self.lut_source
+    }
+
+    /// The VCOM DC voltage replacing the one of the driver, if any
+    pub fn vcom_dc(&self) -> Option<VcomDc> {
+        self.vcom_dc
+    }
+
+    /// The frame rate replacing the one of the profile, if any
+    pub fn frame_rate(&self) -> Option<FrameRate> {
+        self.frame_rate
+    }
+
+    /// The mirroring of the image
+    pub fn mirror(&self) -> Mirror {
+        self.mirror
+    }
+
+    /// After how many quick refreshes the next one is a full refresh, if at all
+    pub fn full_refresh_interval(&self) -> Option<u32> {
+        self.full_refresh_interval
+    }
+}
+
+/// Builder for a [`Config`]
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Builder {
+    config: Config,
+}
+
+impl Builder {
+    /// Start with the default configuration
+    pub fn new() -> Self {
+        Builder::default()
+    }
+
+    /// Select the power/speed profile
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.config.profile = profile;
+        self
+    }
+
+    /// Replace the reset timing of the driver
+    pub fn reset_timing(mut self, reset_timing: ResetTiming) -> Self {
+        self.config.reset_timing = Some(reset_timing);
+        self
+    }
+
+    /// Select the source of the full refresh waveform
+    pub fn lut_source(mut self, lut_source: LutSource) -> Self {
+        self.config.lut_source = lut_source;
+        self
+    }
+
+    /// Replace the VCOM DC voltage of the driver
+    pub fn vcom_dc(mut self, vcom_dc: VcomDc) -> Self {
+        self.config.vcom_dc = Some(vcom_dc);
+        self
+    }
+
+    /// Replace the frame rate of the profile
+    pub fn frame_rate(mut self, frame_rate: FrameRate) -> Self {
+        self.config.frame_rate = Some(frame_rate);
+        self
+    }
+
+    /// Mirror the image in hardware
+    pub fn mirror(mut self, mirror: Mirror) -> Self {
+        self.config.mirror = mirror;
+        self
+    }
+
+    /// Turn every `interval`th quick refresh into a full refresh with the full LUT
+    ///
+    /// Quick refreshes accumulate ghosting, the panel vendors recommend a full refresh every few
+    /// of them. Applied by [`Epd4in2`](crate::epd4in2::Epd4in2).
+    pub fn full_refresh_interval(mut self, interval: u32) -> Self {
+        self.config.full_refresh_interval = Some(interval);
+        self
+    }
+
+    /// Finish the configuration
+    pub fn build(self) -> Config {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_default() {
+        assert_eq!(Builder::new().build(), Config::default());
+        assert_eq!(Config::default().profile(), Profile::Default);
+        assert_eq!(Config::default().reset_timing(), None);
+        assert_eq!(Config::default().lut_source(), LutSource::Registers);
+        assert_eq!(Config::default().vcom_dc(), None);
+        assert_eq!(Config::default().frame_rate(), None);
+        assert_eq!(Config::default().mirror(), Mirror::None);
+        assert_eq!(Config::default().full_refresh_interval(), None);
+    }
+
+    #[test]
+    fn vcom_dc_range() {
+        assert_eq!(VcomDc::from_millivolts(-100).unwrap().register(), 0x00);
+        assert_eq!(VcomDc::from_millivolts(-1000).unwrap().register(), 0x12);
+        assert_eq!(VcomDc::from_millivolts(-1024).unwrap().millivolts(), -1000);
+        assert_eq!(VcomDc::from_millivolts(-1025).unwrap().millivolts(), -1050);
+        assert_eq!(VcomDc::from_millivolts(-3000).unwrap().register(), 0x3a);
+        assert_eq!(VcomDc::from_millivolts(-3001), None);
+        assert_eq!(VcomDc::try_from(0x3a).unwrap().millivolts(), -3000);
+        assert_eq!(VcomDc::try_from(0x3b), Err(InvalidVcomDc));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn vcom_dc_deserialize_checks_range() {
+        use serde::de::{value::Error, IntoDeserializer};
+        use serde::Deserialize;
+
+        let vcom =
+            |value: u8| VcomDc::deserialize(IntoDeserializer::<Error>::into_deserializer(value));
+        assert_eq!(vcom(0x12), Ok(VcomDc::from_register(0x12)));
+        assert!(vcom(0xff).is_err());
+    }
+
+    #[test]
+    fn mirror_panel_setting() {
+        assert_eq!(Mirror::None.apply(0x3f), 0x3f);
+        assert_eq!(Mirror::Horizontal.apply(0x3f), 0x3b);
+        assert_eq!(Mirror::Vertical.apply(0x3f), 0x37);
+        assert_eq!(Mirror::Both.apply(0xaf), 0xa3);
+    }
+
+    #[test]
+    fn builder_profile() {
+        let config = Builder::new().profile(Profile::Fast).build();
+        assert_eq!(config.profile(), Profile::Fast);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize() {
+        use serde::de::{
+            value::{Error, MapDeserializer},
+            Deserialize, IntoDeserializer,
+        };
+
+        let profile = Profile::deserialize("LowPower".into_deserializer()) as Result<_, Error>;
+        assert_eq!(profile.unwrap(), Profile::LowPower);
+
+        // Missing fields keep their defaults
+        let fields = [("profile", "Fast")];
+        let map = MapDeserializer::<_, Error>::new(fields.iter().copied());
+        assert_eq!(Config::deserialize(map).unwrap().profile(), Profile::Fast);
+        let map = MapDeserializer::<_, Error>::new(fields[..0].iter().copied());
+        assert_eq!(Config::deserialize(map).unwrap(), Config::default());
+    }
+}
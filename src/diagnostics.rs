@@ -0,0 +1,388 @@
+//! Test patterns for hardware bring-up
+//!
+//! A [`TestPattern`] is rendered without any drawing code, so a wrong picture points to the
+//! wiring or the waveforms/LUTs instead of the application. E.g. a checkerboard with a pitch of
+//! 1 shows ghosting and contrast problems of the LUT, a shifted or wrapped border frame shows a
+//! wrong resolution or RAM window.
+//!
+//! ```rust
+//! use epd_waveshare::{color::Color, diagnostics::TestPattern};
+//!
+//! let pattern = TestPattern::Checkerboard { pitch: 8 };
+//! assert_eq!(pattern.color(0, 0), Color::Black);
+//! assert_eq!(pattern.color(8, 0), Color::White);
+//!
+//! let mut buffer = [0; 16 / 8 * 16];
+//! pattern.render(&mut buffer, 16, 16);
+//! assert_eq!(buffer[..2], [0x00, 0xff]);
+//! ```
+//!
+//! [`diagnose`] gathers what's needed to report a problem with a panel: the timing of the init
+//! sequence and of a full refresh, and with [`diagnose_with_reads`] the status, temperature and
+//! VCOM registers of controllers supporting [`ReadBack`].
+//!
+//! ```rust,ignore
+//! let report = diagnose_with_reads(&mut epd, &mut spi, &mut delay, || timer.millis())?;
+//! defmt::info!("{:?}", defmt::Debug2Format(&report));
+//! ```
+
+use embedded_hal::{
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
+    digital::v2::*,
+};
+
+use crate::buffer_len;
+use crate::clock::Clock;
+use crate::color::Color;
+use crate::error::DriverError;
+use crate::source::{pack, update_frame_from};
+use crate::traits::{FrameStream, ReadBack, Status, WaveshareDisplay};
+
+/// Black/white test patterns, `pitch` is in pixels
+///
+/// A pitch of 0 is treated as 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TestPattern {
+    /// Black and white squares of `pitch` pixels, black in the top left corner
+    Checkerboard {
+        /// Size of the squares
+        pitch: u32,
+    },
+    /// Black and white columns of `pitch` pixels, starting with black on the left
+    VerticalStripes {
+        /// Width of the stripes
+        pitch: u32,
+    },
+    /// Black and white rows of `pitch` pixels, starting with black at the top
+    HorizontalStripes {
+        /// Height of the stripes
+        pitch: u32,
+    },
+    /// Concentric 1 pixel wide frames every `pitch` pixels, starting at the edges
+    Border {
+        /// Distance between the frames
+        pitch: u32,
+    },
+}
+
+impl TestPattern {
+    /// Color of the pixel at (`x`, `y`)
+    ///
+    /// Assumes an unbounded display, so [`TestPattern::Border`] only has the frames at the top
+    /// and left edge. Use [`TestPattern::color_in`] for borders.
+    pub fn color(&self, x: u32, y: u32) -> Color {
+        self.color_in(x, y, u32::MAX, u32::MAX)
+    }
+
+    /// Color of the pixel at (`x`, `y`) of a `width`x`height` display
+    pub fn color_in(&self, x: u32, y: u32, width: u32, height: u32) -> Color {
+        let black = match *self {
+            TestPattern::Checkerboard { pitch } => {
+                let pitch = pitch.max(1);
+                (x / pitch + y / pitch).is_multiple_of(2)
+            }
+            TestPattern::VerticalStripes { pitch } => (x / pitch.max(1)).is_multiple_of(2),
+            TestPattern::HorizontalStripes { pitch } => (y / pitch.max(1)).is_multiple_of(2),
+            TestPattern::Border { pitch } => {
+                let distance = x
+                    .min(y)
+                    .min(width.saturating_sub(x + 1))
+                    .min(height.saturating_sub(y + 1));
+                distance.is_multiple_of(pitch.max(1))
+            }
+        };
+        if black {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    /// Renders the pattern into the b/w `buffer` of a `width`x`height` display
+    pub fn render(&self, buffer: &mut [u8], width: u32, height: u32) {
+        pack(
+            &|x, y| self.color_in(x, y, width, height),
+            width,
+            height,
+            0,
+            buffer,
+        );
+    }
+
+    /// Renders the pattern into `buffer` (the size of the display buffer) and displays it
+    pub fn show<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &mut [u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let (width, height) = (epd.width(), epd.height());
+        self.render(buffer, width, height);
+        let len = buffer_len(width as usize, height as usize);
+        epd.update_and_display_frame(spi, &buffer[..len], delay)
+    }
+
+    /// Streams the pattern to the display and displays it, without a frame buffer
+    pub fn stream<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: FrameStream<SPI, CS, BUSY, DC, RST, DELAY, DisplayColor = Color>,
+    {
+        let (width, height) = (epd.width(), epd.height());
+        update_frame_from(epd, spi, &|x, y| self.color_in(x, y, width, height))?;
+        epd.display_frame(spi, delay)
+    }
+}
+
+/// Results of [`diagnose`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    /// Width of the driver in pixels
+    pub width: u32,
+    /// Height of the driver in pixels
+    pub height: u32,
+    /// Duration of the reset and init sequence
+    pub init_ms: u64,
+    /// If BUSY reported idle after the init sequence
+    ///
+    /// `false` usually means a wrong or unconnected BUSY pin.
+    pub idle_after_init: bool,
+    /// Duration of a full refresh until BUSY reported idle
+    ///
+    /// A few ms instead of seconds means the panel didn't refresh at all (no power, wrong
+    /// controller) or BUSY isn't connected.
+    pub full_refresh_ms: u64,
+    /// Status flags, see [`ReadBack::read_status`]
+    pub status: Option<Status>,
+    /// Temperature in degrees Celsius, see [`ReadBack::read_temperature`]
+    pub temperature: Option<i8>,
+    /// VCOM value, see [`ReadBack::read_vcom`]
+    pub vcom: Option<u8>,
+}
+
+/// Resets and inits the panel and times a full refresh of a cleared frame
+///
+/// The register reads of the report are `None`, see [`diagnose_with_reads`].
+pub fn diagnose<SPI, CS, BUSY, DC, RST, DELAY, EPD, C>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    mut clock: C,
+) -> Result<Report, DriverError<SPI, CS, BUSY, DC, RST>>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    C: Clock,
+{
+    let start = clock.now_ms();
+    epd.wake_up(spi, delay)?;
+    let init_ms = clock.now_ms().saturating_sub(start);
+    let idle_after_init = !epd.is_busy();
+
+    epd.clear_frame(spi, delay)?;
+    let start = clock.now_ms();
+    epd.display_frame(spi, delay)?;
+    epd.wait_until_idle_with_timeout(delay)?;
+    let full_refresh_ms = clock.now_ms().saturating_sub(start);
+
+    Ok(Report {
+        width: epd.width(),
+        height: epd.height(),
+        init_ms,
+        idle_after_init,
+        full_refresh_ms,
+        status: None,
+        temperature: None,
+        vcom: None,
+    })
+}
+
+/// [`diagnose`] including the registers read back from the controller
+///
+/// Needs a readable data line, see [Requirements: SPI](crate#spi).
+pub fn diagnose_with_reads<SPI, CS, BUSY, DC, RST, DELAY, EPD, C>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    clock: C,
+) -> Result<Report, DriverError<SPI, CS, BUSY, DC, RST>>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: ReadBack<SPI, CS, BUSY, DC, RST, DELAY>,
+    C: Clock,
+{
+    let mut report = diagnose(epd, spi, delay, clock)?;
+    report.status = Some(epd.read_status(spi)?);
+    report.temperature = Some(epd.read_temperature(spi)?);
+    report.vcom = Some(epd.read_vcom(spi)?);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+    use crate::test_utils::{
+        Controller, Event, IdleBusyPin, MockNoop, NoopPin, Recorder, VirtualPanel,
+    };
+
+    #[test]
+    fn patterns() {
+        let mut buffer = [0; 2 * 4];
+        TestPattern::Checkerboard { pitch: 1 }.render(&mut buffer, 16, 4);
+        assert_eq!(buffer, [0x55, 0x55, 0xaa, 0xaa, 0x55, 0x55, 0xaa, 0xaa]);
+
+        TestPattern::VerticalStripes { pitch: 4 }.render(&mut buffer, 16, 4);
+        assert_eq!(buffer, [0x0f; 8]);
+
+        TestPattern::HorizontalStripes { pitch: 2 }.render(&mut buffer, 16, 4);
+        assert_eq!(buffer, [0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff]);
+
+        // Zero pitch doesn't panic
+        TestPattern::HorizontalStripes { pitch: 0 }.render(&mut buffer, 16, 4);
+        assert_eq!(buffer, [0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn border_with_padding() {
+        // 12 pixels wide, the last 4 bits of every row are padding
+        let mut buffer = [0; 2 * 5];
+        TestPattern::Border { pitch: 2 }.render(&mut buffer, 12, 5);
+        assert_eq!(
+            buffer,
+            [
+                0b0000_0000,
+                0b0000_1111,
+                0b0111_1111,
+                0b1110_1111,
+                0b0100_0000,
+                0b0010_1111,
+                0b0111_1111,
+                0b1110_1111,
+                0b0000_0000,
+                0b0000_1111,
+            ]
+        );
+    }
+
+    #[test]
+    fn show_and_stream_match() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        let pattern = TestPattern::Border { pitch: 10 };
+
+        recorder.take_events();
+        let mut buffer = [0; WIDTH as usize / 8 * HEIGHT as usize];
+        pattern
+            .show(&mut epd, &mut spi, &mut delay, &mut buffer)
+            .unwrap();
+        let shown = recorder.take_events();
+
+        pattern.stream(&mut epd, &mut spi, &mut delay).unwrap();
+        let streamed = recorder.take_events();
+        assert_eq!(streamed, shown);
+
+        let mut panel = VirtualPanel::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        panel.feed(&streamed);
+        assert_eq!(panel.refreshes(), 1);
+        assert_eq!(panel.pixel(0, 150), Color::Black);
+        assert_eq!(panel.pixel(1, 150), Color::White);
+        assert_eq!(panel.pixel(10, 150), Color::Black);
+        assert_eq!(panel.pixel(WIDTH - 1, 150), Color::Black);
+        assert_eq!(panel.pixel(200, HEIGHT - 11), Color::Black);
+    }
+
+    #[test]
+    fn diagnose_report() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+
+        let mut now = 0;
+        let clock = || {
+            now += 10;
+            now
+        };
+        let report = diagnose_with_reads(&mut epd, &mut spi, &mut delay, clock).unwrap();
+        assert_eq!(
+            report,
+            Report {
+                width: WIDTH,
+                height: HEIGHT,
+                init_ms: 10,
+                idle_after_init: true,
+                full_refresh_ms: 10,
+                // The recording SPI reads zeros
+                status: Some(Status {
+                    busy: true,
+                    ..Status::default()
+                }),
+                temperature: Some(0),
+                vcom: Some(0),
+            }
+        );
+
+        let events = recorder.take_events();
+        assert_eq!(events[0], Event::Reset);
+        let mut panel = VirtualPanel::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        panel.feed(&events);
+        assert_eq!(panel.refreshes(), 1);
+        for command in [0x71, 0x40, 0x81] {
+            assert!(events.contains(&Event::Command(command)));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Partial updates of only what changed between two frames
+//!
+//! Dashboards often change a few digits per update. Comparing the frame sent last with the new
+//! one gives the windows to send with
+//! [`update_partial_window`](crate::traits::WaveshareDisplay::update_partial_window), instead of
+//! the whole frame:
+//!
+//! ```rust,ignore
+//! let mut sent = [0xff; WIDTH as usize / 8 * HEIGHT as usize];
+//! let mut window = [0; WIDTH as usize / 8 * 64];
+//! loop {
+//!     draw(&mut display);
+//!     if update_changed(&mut epd, &mut spi, &mut delay, &mut sent, display.buffer(), &mut window)?.is_some() {
+//!         epd.display_frame(&mut spi, &mut delay)?;
+//!     }
+//! }
+//! ```
+//!
+//! The frames are b/w buffers in unrotated coordinates, as sent to the display.
+
+#[cfg(feature = "graphics")]
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+#[cfg(feature = "graphics")]
+use crate::error::{DriverError, Error};
+use crate::region::Region;
+use crate::traits::PartialWindow;
+#[cfg(feature = "graphics")]
+use crate::traits::WaveshareDisplay;
+
+/// The smallest window containing all changes from `old` to `new`, `None` if they are equal
+///
+/// Frames of different lengths can't be compared and are changed on the whole display.
+pub fn changed_window(old: &[u8], new: &[u8], width: u32, height: u32) -> Option<PartialWindow> {
+    let mut windows = [None];
+    changed_windows(old, new, width, height, u32::MAX, &mut windows);
+    windows[0]
+}
+
+/// The windows containing the changes from `old` to `new`, returns their number
+///
+/// Changed rows closer than `max_gap` unchanged rows share a window, a window per group of
+/// digits or lines of text keeps the unchanged pixels between them from being sent. If `windows`
+/// is too short, the last window covers the remaining changes, an empty `windows` gets none.
+/// Frames of different lengths can't be compared and get a single window of the whole display.
+pub fn changed_windows(
+    old: &[u8],
+    new: &[u8],
+    width: u32,
+    height: u32,
+    max_gap: u32,
+    windows: &mut [Option<PartialWindow>],
+) -> usize {
+    if windows.is_empty() {
+        return 0;
+    }
+    if old.len() != new.len() {
+        windows[0] = PartialWindow::enclosing(Region::new(0, 0, width, height), width, height);
+        return 1;
+    }
+    let row_len = width.div_ceil(8) as usize;
+    let mut count = 0;
+    // rows and byte columns of the window collecting changes
+    let mut current: Option<(u32, u32, usize, usize)> = None;
+    for (y, (old_row, new_row)) in (0..height).zip(old.chunks(row_len).zip(new.chunks(row_len))) {
+        let mut changed = old_row.iter().zip(new_row).map(|(o, n)| o != n);
+        let Some(first) = changed.position(|c| c) else {
+            continue;
+        };
+        let last = first + changed.rposition(|c| c).map_or(0, |last| last + 1);
+        current = match current {
+            Some((top, bottom, left, right))
+                if y - bottom <= max_gap.saturating_add(1) || count + 1 == windows.len() =>
+            {
+                Some((top, y, left.min(first), right.max(last)))
+            }
+            Some(window) => {
+                windows[count] = to_window(window, width, height);
+                count += 1;
+                Some((y, y, first, last))
+            }
+            None => Some((y, y, first, last)),
+        };
+    }
+    if let Some(window) = current {
+        if count < windows.len() {
+            windows[count] = to_window(window, width, height);
+            count += 1;
+        }
+    }
+    count
+}
+
+// the window of rows `top..=bottom` and byte columns `left..=right`
+fn to_window(
+    (top, bottom, left, right): (u32, u32, usize, usize),
+    width: u32,
+    height: u32,
+) -> Option<PartialWindow> {
+    let x = left as u32 * 8;
+    let region = Region::new(x, top, (right as u32 + 1) * 8 - x, bottom - top + 1);
+    PartialWindow::enclosing(region, width, height)
+}
+
+/// Sends the window of `new` that changed since `sent`, which is updated to `new` afterwards
+///
+/// The window is copied into `window` first. If it doesn't fit, the whole frame is sent instead.
+/// Returns the window sent, `None` if nothing changed. Follow it with
+/// [`display_frame`](WaveshareDisplay::display_frame). Fails with [`Error::Unsupported`] before
+/// sending anything if `sent` and `new` differ in length.
+#[cfg(feature = "graphics")]
+pub fn update_changed<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    sent: &mut [u8],
+    new: &[u8],
+    window: &mut [u8],
+) -> Result<Option<PartialWindow>, DriverError<SPI, CS, BUSY, DC, RST>>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+{
+    if sent.len() != new.len() {
+        return Err(Error::Unsupported);
+    }
+    let (width, height) = (epd.width(), epd.height());
+    let Some(changed) = changed_window(sent, new, width, height) else {
+        return Ok(None);
+    };
+    let len = changed.buffer_len();
+    if len <= window.len() {
+        let region = changed.region();
+        let window = &mut window[..len];
+        crate::graphics::packing::copy_window(
+            new,
+            width,
+            region.x,
+            region.y,
+            region.width,
+            region.height,
+            window,
+        );
+        epd.update_partial_window(spi, window, changed)?;
+    } else {
+        epd.update_frame(spi, new, delay)?;
+    }
+    sent.copy_from_slice(new);
+    Ok(Some(changed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 20;
+    const HEIGHT: u32 = 10;
+    const ROW: usize = 3;
+
+    fn frames() -> ([u8; ROW * HEIGHT as usize], [u8; ROW * HEIGHT as usize]) {
+        ([0xff; ROW * HEIGHT as usize], [0xff; ROW * HEIGHT as usize])
+    }
+
+    #[test]
+    fn single_window() {
+        let (old, mut new) = frames();
+        assert_eq!(changed_window(&old, &new, WIDTH, HEIGHT), None);
+
+        new[ROW + 1] = 0x00;
+        new[4 * ROW + 2] = 0x7f;
+        let window = changed_window(&old, &new, WIDTH, HEIGHT).unwrap();
+        assert_eq!(window.region(), Region::new(8, 1, 16, 4));
+    }
+
+    #[test]
+    fn windows_split_by_gaps() {
+        let (old, mut new) = frames();
+        new[0] = 0x00;
+        new[2 * ROW] = 0x00;
+        new[8 * ROW + 2] = 0x00;
+
+        let mut windows = [None; 3];
+        assert_eq!(
+            changed_windows(&old, &new, WIDTH, HEIGHT, 2, &mut windows),
+            2
+        );
+        assert_eq!(windows[0].unwrap().region(), Region::new(0, 0, 8, 3));
+        assert_eq!(windows[1].unwrap().region(), Region::new(16, 8, 8, 1));
+
+        // The last window takes the rest
+        let mut windows = [None; 1];
+        assert_eq!(
+            changed_windows(&old, &new, WIDTH, HEIGHT, 0, &mut windows),
+            1
+        );
+        assert_eq!(windows[0].unwrap().region(), Region::new(0, 0, 24, 9));
+
+        assert_eq!(changed_windows(&old, &new, WIDTH, HEIGHT, 0, &mut []), 0);
+    }
+
+    #[test]
+    fn frames_of_different_lengths() {
+        let (old, new) = frames();
+        let window = changed_window(&old, &new[..ROW], WIDTH, HEIGHT).unwrap();
+        assert_eq!(window.region(), Region::new(0, 0, 24, HEIGHT));
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn update_changed() {
+        use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+        use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+        use embedded_hal_mock::delay::MockNoop;
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+
+        let mut sent = [0xff; WIDTH as usize / 8 * HEIGHT as usize];
+        let mut new = sent;
+        let mut window = [0; 8];
+        assert_eq!(
+            super::update_changed(&mut epd, &mut spi, &mut delay, &mut sent, &new, &mut window),
+            Ok(None)
+        );
+        assert_eq!(recorder.take_events(), []);
+
+        new[WIDTH as usize / 8 * 3 + 1] = 0x00;
+        let changed =
+            super::update_changed(&mut epd, &mut spi, &mut delay, &mut sent, &new, &mut window)
+                .unwrap();
+        assert_eq!(changed.unwrap().region(), Region::new(8, 3, 8, 1));
+        assert!(recorder
+            .take_events()
+            .contains(&Event::Data([0x00].to_vec())));
+        assert_eq!(sent, new);
+        let short = &new[..8];
+        assert_eq!(
+            super::update_changed(
+                &mut epd,
+                &mut spi,
+                &mut delay,
+                &mut sent,
+                short,
+                &mut window
+            ),
+            Err(Error::Unsupported)
+        );
+        assert_eq!(recorder.take_events(), []);
+    }
+}
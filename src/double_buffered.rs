@@ -0,0 +1,227 @@
+//! Front and back buffers
+//!
+//! [`DoubleBuffered`] keeps the frame on the display (front) apart from the one drawn next
+//! (back). The next frame can be drawn while the display still refreshes the last one, and is
+//! only sent as a whole by [`present`](DoubleBuffered::present):
+//!
+//! ```rust,ignore
+//! let mut frames = DoubleBuffered::new(Display4in2::default(), Display4in2::default());
+//! loop {
+//!     frames.back_mut().clear_buffer(Color::White);
+//!     draw(&mut frames)?;
+//!     frames.present(&mut epd, &mut spi, &mut delay)?;
+//! }
+//! ```
+
+use embedded_graphics_core::{pixelcolor::PixelColor, prelude::*, primitives::Rectangle};
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::error::DriverError;
+use crate::graphics::Display;
+use crate::source::PackedColor;
+use crate::traits::{QuickRefresh, WaveshareDisplay};
+
+/// Two buffers of a display, drawn on through the back buffer
+pub struct DoubleBuffered<D> {
+    buffers: [D; 2],
+    front: usize,
+}
+
+impl<D> DoubleBuffered<D> {
+    /// Creates the buffers, `front` should match what the display shows
+    pub fn new(front: D, back: D) -> Self {
+        DoubleBuffered {
+            buffers: [front, back],
+            front: 0,
+        }
+    }
+
+    /// The frame last presented
+    pub fn front(&self) -> &D {
+        &self.buffers[self.front]
+    }
+
+    /// The frame drawn next
+    pub fn back(&self) -> &D {
+        &self.buffers[1 - self.front]
+    }
+
+    /// The frame drawn next, e.g. to clear it
+    pub fn back_mut(&mut self) -> &mut D {
+        &mut self.buffers[1 - self.front]
+    }
+
+    /// Exchanges the buffers, the back buffer becomes the front buffer
+    ///
+    /// The new back buffer holds the frame presented before, see
+    /// [`copy_front_to_back`](DoubleBuffered::copy_front_to_back) to draw on top of the current
+    /// one instead.
+    pub fn swap(&mut self) {
+        self.front = 1 - self.front;
+    }
+
+    /// Copies the front buffer into the back buffer
+    pub fn copy_front_to_back<C>(&mut self)
+    where
+        C: PackedColor + PixelColor,
+        D: Display<C>,
+    {
+        let [a, b] = &mut self.buffers;
+        let (front, back) = if self.front == 0 { (a, b) } else { (b, a) };
+        back.get_mut_buffer().copy_from_slice(front.buffer());
+    }
+
+    /// Swaps the buffers, then sends and displays the new front buffer
+    pub fn present<C, SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        C: PackedColor + PixelColor,
+        D: Display<C>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.swap();
+        epd.update_and_display_frame(spi, self.front().buffer(), delay)
+    }
+
+    /// Like [`present`](DoubleBuffered::present), but with a quick refresh
+    ///
+    /// The frame on the display is sent as the old frame and the new front buffer as the new one,
+    /// so the quick refresh only drives the pixels that changed, also after a full refresh or a
+    /// wake up.
+    pub fn present_quick<C, SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        C: PackedColor + PixelColor,
+        D: Display<C>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.swap();
+        epd.update_old_frame(spi, self.back().buffer(), delay)?;
+        epd.update_new_frame(spi, self.front().buffer(), delay)?;
+        epd.display_new_frame(spi, delay)
+    }
+}
+
+impl<D> DrawTarget for DoubleBuffered<D>
+where
+    D: DrawTarget + OriginDimensions,
+{
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.back_mut().draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.back_mut().fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.back_mut().fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.back_mut().clear(color)
+    }
+}
+
+impl<D> OriginDimensions for DoubleBuffered<D>
+where
+    D: OriginDimensions,
+{
+    fn size(&self) -> Size {
+        self.back().size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in7b::{command::Command, Display2in7b, Epd2in7b, HEIGHT, WIDTH};
+    use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use embedded_hal_mock::delay::MockNoop;
+
+    #[test]
+    fn present_quick() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+
+        let mut frames = DoubleBuffered::new(Display2in7b::default(), Display2in7b::default());
+        Pixel(Point::new(0, 0), BinaryColor::On)
+            .draw(&mut frames)
+            .unwrap();
+        assert_eq!(frames.back().buffer()[0], 0x7f);
+        assert_eq!(frames.front().buffer()[0], 0xff);
+
+        frames
+            .present_quick(&mut epd, &mut spi, &mut delay)
+            .unwrap();
+        assert_eq!(frames.front().buffer()[0], 0x7f);
+        // The data of the 2in7b is inverted
+        let events = recorder.take_events();
+        let plane = |command: Command| {
+            let at = events
+                .iter()
+                .position(|e| *e == Event::Command(command as u8))
+                .unwrap();
+            match &events[at + 1] {
+                Event::Data(data) => data.clone(),
+                e => panic!("unexpected {:?}", e),
+            }
+        };
+        let mut new = [0x00; WIDTH as usize / 8 * HEIGHT as usize];
+        new[0] = 0x80;
+        assert_eq!(
+            plane(Command::DataStartTransmission1),
+            [0x00; WIDTH as usize / 8 * HEIGHT as usize].to_vec()
+        );
+        assert_eq!(plane(Command::DataStartTransmission2), new.to_vec());
+        assert!(events.contains(&Event::Command(Command::DisplayRefresh as u8)));
+
+        // Drawing on top of the presented frame
+        frames.copy_front_to_back();
+        assert_eq!(frames.back().buffer()[0], 0x7f);
+    }
+}
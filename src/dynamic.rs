@@ -0,0 +1,244 @@
+//! Displays behind `dyn`
+//!
+//! [`WaveshareDisplay`] is generic over the SPI, pins and delay and has an associated color, so
+//! it can't be used as a trait object. [`Bound`] keeps a driver together with its SPI and delay
+//! and implements the object safe [`DynEpd`] on top of it, so displays of different types can be
+//! kept in one registry:
+//!
+//! ```rust,ignore
+//! let mut small = Bound::new(Epd2in9::new(&mut spi_a, cs_a, busy_a, dc_a, rst_a, &mut delay_a)?, spi_a, delay_a);
+//! let mut large = Bound::new(Epd4in2::new(&mut spi_b, cs_b, busy_b, dc_b, rst_b, &mut delay_b)?, spi_b, delay_b);
+//! let displays: [&mut dyn DynEpd<DriverError<Spi, Cs, Busy, Dc, Rst>>; 2] = [&mut small, &mut large];
+//! for epd in displays {
+//!     epd.clear_frame()?;
+//!     epd.display_frame()?;
+//! }
+//! ```
+//!
+//! The trait objects need the same error type, so SPI and pins with the same error types. Displays sharing a bus each get their own
+//! `SpiDevice` from [embedded-hal-bus](https://docs.rs/embedded-hal-bus), see the
+//! [`compat`](crate::compat) module.
+
+use core::marker::PhantomData;
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::color::Color;
+use crate::error::DriverError;
+use crate::traits::{PartialWindow, RefreshLut, WaveshareDisplay};
+
+/// The object safe part of [`WaveshareDisplay`], with the SPI and delay bound to the display
+///
+/// `E` is the error type of the driver, a [`DriverError`]. The methods do the same as the ones of [`WaveshareDisplay`]
+/// with the same name.
+pub trait DynEpd<E> {
+    /// See [`WaveshareDisplay::width`]
+    fn width(&self) -> u32;
+
+    /// See [`WaveshareDisplay::height`]
+    fn height(&self) -> u32;
+
+    /// See [`WaveshareDisplay::sleep`]
+    fn sleep(&mut self) -> Result<(), E>;
+
+    /// See [`WaveshareDisplay::wake_up`]
+    fn wake_up(&mut self) -> Result<(), E>;
+
+    /// See [`WaveshareDisplay::set_background_color`], converted to the color of the display
+    fn set_background_color(&mut self, color: Color);
+
+    /// See [`WaveshareDisplay::update_frame`]
+    fn update_frame(&mut self, buffer: &[u8]) -> Result<(), E>;
+
+    /// See [`WaveshareDisplay::update_partial_window`]
+    fn update_partial_window(&mut self, buffer: &[u8], window: PartialWindow) -> Result<(), E>;
+
+    /// See [`WaveshareDisplay::display_frame`]
+    fn display_frame(&mut self) -> Result<(), E>;
+
+    /// See [`WaveshareDisplay::update_and_display_frame`]
+    fn update_and_display_frame(&mut self, buffer: &[u8]) -> Result<(), E>;
+
+    /// See [`WaveshareDisplay::clear_frame`]
+    fn clear_frame(&mut self) -> Result<(), E>;
+
+    /// See [`WaveshareDisplay::set_lut`]
+    fn set_lut(&mut self, refresh_rate: Option<RefreshLut>) -> Result<(), E>;
+
+    /// See [`WaveshareDisplay::is_busy`]
+    fn is_busy(&self) -> bool;
+}
+
+/// A driver together with its SPI and delay
+///
+/// The pins stay in the driver, they only show up in the type.
+pub struct Bound<EPD, SPI, CS, BUSY, DC, RST, DELAY> {
+    epd: EPD,
+    spi: SPI,
+    delay: DELAY,
+    _pins: PhantomData<(CS, BUSY, DC, RST)>,
+}
+
+impl<EPD, SPI, CS, BUSY, DC, RST, DELAY> Bound<EPD, SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+{
+    /// Binds `spi` and `delay` to the initialized driver `epd`
+    pub fn new(epd: EPD, spi: SPI, delay: DELAY) -> Self {
+        Bound {
+            epd,
+            spi,
+            delay,
+            _pins: PhantomData,
+        }
+    }
+
+    /// The driver, SPI and delay, e.g. for the methods missing in [`DynEpd`]
+    pub fn parts(&mut self) -> (&mut EPD, &mut SPI, &mut DELAY) {
+        (&mut self.epd, &mut self.spi, &mut self.delay)
+    }
+
+    /// Gives back the driver, SPI and delay
+    pub fn release(self) -> (EPD, SPI, DELAY) {
+        (self.epd, self.spi, self.delay)
+    }
+}
+
+impl<EPD, SPI, CS, BUSY, DC, RST, DELAY> DynEpd<DriverError<SPI, CS, BUSY, DC, RST>>
+    for Bound<EPD, SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    EPD::DisplayColor: From<Color>,
+{
+    fn width(&self) -> u32 {
+        self.epd.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.epd.height()
+    }
+
+    fn sleep(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.epd.sleep(&mut self.spi, &mut self.delay)
+    }
+
+    fn wake_up(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.epd.wake_up(&mut self.spi, &mut self.delay)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.epd.set_background_color(color.into())
+    }
+
+    fn update_frame(&mut self, buffer: &[u8]) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.epd
+            .update_frame(&mut self.spi, buffer, &mut self.delay)
+    }
+
+    fn update_partial_window(
+        &mut self,
+        buffer: &[u8],
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.epd
+            .update_partial_window(&mut self.spi, buffer, window)
+    }
+
+    fn display_frame(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.epd.display_frame(&mut self.spi, &mut self.delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.epd
+            .update_and_display_frame(&mut self.spi, buffer, &mut self.delay)
+    }
+
+    fn clear_frame(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.epd.clear_frame(&mut self.spi, &mut self.delay)
+    }
+
+    fn set_lut(
+        &mut self,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.epd.set_lut(&mut self.spi, refresh_rate)
+    }
+
+    fn is_busy(&self) -> bool {
+        self.epd.is_busy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        Event, IdleBusyPin, NoopPin, Recorder, RecordingDc, RecordingRst, RecordingSpi,
+    };
+    use crate::{epd2in13bc, epd4in2};
+    use embedded_hal_mock::delay::MockNoop;
+
+    #[test]
+    fn registry() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let epd = epd4in2::Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        let mut large = Bound::new(epd, spi.clone(), MockNoop::new());
+        let epd = epd2in13bc::Epd2in13bc::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        let mut small = Bound::new(epd, spi, delay);
+        recorder.take_events();
+
+        type E = DriverError<RecordingSpi, NoopPin, IdleBusyPin, RecordingDc, RecordingRst>;
+        let displays: [&mut dyn DynEpd<E>; 2] = [&mut large, &mut small];
+        let sizes: [(u32, u32); 2] = displays.map(|epd| {
+            epd.set_background_color(Color::Black);
+            (epd.width(), epd.height())
+        });
+        assert_eq!(
+            sizes,
+            [
+                (epd4in2::WIDTH, epd4in2::HEIGHT),
+                (epd2in13bc::WIDTH, epd2in13bc::HEIGHT)
+            ]
+        );
+
+        large.wake_up().unwrap();
+        assert_eq!(recorder.take_events()[0], Event::Reset);
+        let (epd, _, _) = large.parts();
+        assert_eq!(epd.background_color(), &Color::Black);
+    }
+}
@@ -1,70 +1,16 @@
-use crate::epd1in54::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
-use crate::graphics::{Display, DisplayRotation};
-use embedded_graphics::pixelcolor::BinaryColor;
-use embedded_graphics_core::prelude::*;
+use crate::buffer_len;
+use crate::epd1in54::{HEIGHT, WIDTH};
+use crate::graphics::FixedDisplay;
 
 /// Full size buffer for use with the 1in54 EPD
-///
-/// Can also be manuall constructed:
-/// `buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH / 8 * HEIGHT]`
-pub struct Display1in54 {
-    buffer: [u8; WIDTH as usize * HEIGHT as usize / 8],
-    rotation: DisplayRotation,
-}
-
-impl Default for Display1in54 {
-    fn default() -> Self {
-        Display1in54 {
-            buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value();
-                WIDTH as usize * HEIGHT as usize / 8],
-            rotation: DisplayRotation::default(),
-        }
-    }
-}
-
-impl DrawTarget for Display1in54 {
-    type Color = BinaryColor;
-    type Error = core::convert::Infallible;
-
-    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
-    where
-        I: IntoIterator<Item = Pixel<Self::Color>>,
-    {
-        for pixel in pixels {
-            self.draw_helper(WIDTH, HEIGHT, pixel)?;
-        }
-        Ok(())
-    }
-}
-
-impl OriginDimensions for Display1in54 {
-    fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
-    }
-}
-
-impl Display for Display1in54 {
-    fn buffer(&self) -> &[u8] {
-        &self.buffer
-    }
-
-    fn get_mut_buffer(&mut self) -> &mut [u8] {
-        &mut self.buffer
-    }
-
-    fn set_rotation(&mut self, rotation: DisplayRotation) {
-        self.rotation = rotation;
-    }
-
-    fn rotation(&self) -> DisplayRotation {
-        self.rotation
-    }
-}
+pub type Display1in54 =
+    FixedDisplay<WIDTH, HEIGHT, { buffer_len(WIDTH as usize, HEIGHT as usize) }>;
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::color::{Black, Color};
+    use crate::epd1in54::DEFAULT_BACKGROUND_COLOR;
     use crate::graphics::{Display, DisplayRotation};
     use embedded_graphics::{
         prelude::*,
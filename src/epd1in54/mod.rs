@@ -4,7 +4,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::error::DriverError<spi::Mock, pin::Mock, pin::Mock, pin::Mock, pin::Mock>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyleBuilder},
 //!};
@@ -50,6 +50,14 @@ pub const WIDTH: u32 = 200;
 pub const HEIGHT: u32 = 200;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Maximum SPI clock of the SSD1608 controller in Hz
+pub const MAX_SPI_HZ: u32 = 20_000_000;
+/// Typical durations of the display
+pub const TIMING: Timing = Timing {
+    full_refresh_ms: 2000,
+    partial_refresh_ms: Some(300),
+    wake_ms: 250,
+};
 //const DPI: u16 = 184;
 const IS_BUSY_LOW: bool = false;
 
@@ -58,6 +66,7 @@ use embedded_hal::{
     digital::v2::*,
 };
 
+use crate::error::DriverError;
 use crate::type_a::{
     command::Command,
     constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
@@ -65,9 +74,12 @@ use crate::type_a::{
 
 use crate::color::Color;
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::region::Region;
+use crate::traits::{
+    DmaFrame, FrameStream, InterruptRefresh, PartialWindow, RefreshLut, Timing, WaveshareDisplay,
+};
 
-use crate::interface::DisplayInterface;
+use crate::interface::{DisplayInterface, TrafficFn};
 
 #[cfg(feature = "graphics")]
 mod graphics;
@@ -77,11 +89,14 @@ pub use crate::epd1in54::graphics::Display1in54;
 /// Epd1in54 driver
 pub struct Epd1in54<SPI, CS, BUSY, DC, RST, DELAY> {
     /// SPI
-    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, TrafficFn>,
     /// Color
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// A refresh was started by [`start_display_frame`](InterruptRefresh::start_display_frame)
+    /// and BUSY wasn't seen idle since
+    refreshing: bool,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
@@ -91,10 +106,14 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10);
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.reset(delay, 10)?;
 
         // 3 Databytes:
         // A[7:0]
@@ -134,7 +153,7 @@ where
 
         self.set_lut(spi, None)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle()?;
         Ok(())
     }
 }
@@ -147,9 +166,11 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+    const MAX_SPI_HZ: u32 = MAX_SPI_HZ;
+    const TIMING: Timing = TIMING;
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -165,13 +186,14 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
 
         let mut epd = Epd1in54 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
+            refreshing: false,
         };
 
         epd.init(spi, delay)?;
@@ -179,12 +201,20 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         //TODO: is 0x00 needed here or would 0x01 be even more efficient?
         self.interface
@@ -197,25 +227,26 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        self.use_full_frame(spi)?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
-        Ok(())
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.begin_frame(spi)?;
+        self.write_frame_chunk(spi, buffer)?;
+        self.end_frame(spi)
     }
 
     //TODO: update description: last 3 bits will be ignored for width and x_pos
-    fn update_partial_frame(
+    fn update_partial_window(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let Region {
+            x,
+            y,
+            width,
+            height,
+        } = window.region();
+        self.wait_until_idle()?;
         self.set_ram_area(spi, x, y, x + width, y + height)?;
         self.set_ram_counter(spi, x, y)?;
 
@@ -224,8 +255,12 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
         //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
         self.interface
@@ -243,14 +278,18 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.use_full_frame(spi)?;
 
         // clear the ram with the background color
@@ -274,7 +313,7 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
@@ -287,6 +326,134 @@ where
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn set_busy_yield(&mut self, busy_yield: Option<fn()>) {
+        self.interface.set_busy_yield(busy_yield);
+    }
+
+    fn wait_until_idle_or_cancel<F>(
+        &mut self,
+        delay: &mut DELAY,
+        should_cancel: F,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        F: FnMut() -> bool,
+    {
+        let timeout_ms = 2 * TIMING.full_refresh_ms;
+        self.interface
+            .wait_until_idle_or_cancel(IS_BUSY_LOW, delay, timeout_ms, should_cancel)
+    }
+
+    fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.interface.set_max_chunk_size(max_chunk_size);
+    }
+
+    fn set_traffic_hook(&mut self, traffic_hook: Option<TrafficFn>) {
+        self.interface.set_traffic_hook(traffic_hook);
+    }
+
+    fn send_command_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, data)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InterruptRefresh<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn start_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.display_frame(spi, delay)?;
+        self.refreshing = true;
+        Ok(())
+    }
+
+    fn on_busy_deasserted(&mut self) {
+        self.refreshing = false;
+    }
+
+    fn is_refreshing(&self) -> bool {
+        self.refreshing
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> FrameStream<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.use_full_frame(spi)?;
+        self.interface.cmd(spi, Command::WriteRam)
+    }
+
+    fn write_frame_chunk(
+        &mut self,
+        spi: &mut SPI,
+        chunk: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, chunk)
+    }
+
+    fn end_frame(&mut self, _spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> DmaFrame<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn prepare_frame(&self, _buffer: &mut [u8]) {}
+
+    fn begin_frame_transfer(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.begin_frame(spi)?;
+        self.interface.begin_data()
+    }
+
+    fn finish_frame_transfer(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.end_data()?;
+        self.end_frame(spi)
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
@@ -296,13 +463,18 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+    fn wait_until_idle(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.wait_until_idle(IS_BUSY_LOW)?;
+        self.refreshing = false;
+        Ok(())
     }
 
-    pub(crate) fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    pub(crate) fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -317,8 +489,8 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         assert!(start_x < end_x);
         assert!(start_y < end_y);
 
@@ -349,8 +521,8 @@ where
         spi: &mut SPI,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
@@ -365,8 +537,12 @@ where
         Ok(())
     }
 
-    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         assert!(buffer.len() == 30);
 
         self.interface
@@ -5,9 +5,11 @@ use embedded_hal::{
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
+use crate::error::DriverError;
+use crate::interface::{DelayMsExt, DisplayInterface, TrafficFn};
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    FrameStream, InternalWiAdditions, PartialWindow, RefreshLut, Timing, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 //The Lookup Tables for the Display
@@ -20,6 +22,14 @@ pub const WIDTH: u32 = 200;
 pub const HEIGHT: u32 = 200;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Maximum SPI clock of the IL0373 controller in Hz
+pub const MAX_SPI_HZ: u32 = 10_000_000;
+/// Typical durations of the display
+pub const TIMING: Timing = Timing {
+    full_refresh_ms: 8000,
+    partial_refresh_ms: None,
+    wake_ms: 300,
+};
 const IS_BUSY_LOW: bool = true;
 
 use crate::color::Color;
@@ -34,7 +44,7 @@ pub use self::graphics::Display1in54b;
 
 /// Epd1in54b driver
 pub struct Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY> {
-    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, TrafficFn>,
     color: Color,
 }
 
@@ -46,10 +56,14 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10);
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.reset(delay, 10)?;
 
         // set the power settings
         self.interface
@@ -62,7 +76,7 @@ where
         // power on
         self.command(spi, Command::PowerOn)?;
         delay.delay_ms(5);
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         // set the panel settings
         self.cmd_with_data(spi, Command::PanelSetting, &[0xCF])?;
@@ -79,7 +93,7 @@ where
 
         self.set_lut(spi, None)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         Ok(())
     }
@@ -93,20 +107,24 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     fn update_color_frame(
         &mut self,
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
 
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.send_resolution(spi)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
@@ -122,7 +140,7 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data(spi, chromatic)?;
         Ok(())
@@ -137,9 +155,11 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+    const MAX_SPI_HZ: u32 = MAX_SPI_HZ;
+    const TIMING: Timing = TIMING;
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -147,7 +167,7 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
         let color = DEFAULT_BACKGROUND_COLOR;
 
@@ -158,8 +178,12 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
 
@@ -169,7 +193,7 @@ where
         self.interface
             .cmd_with_data(spi, Command::PowerSetting, &[0x02, 0x00, 0x00, 0x00])?; //VG&VS to 0V fast
 
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         //NOTE: The example code has a 1s delay here
 
@@ -178,7 +202,11 @@ where
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.init(spi, delay)
     }
 
@@ -203,8 +231,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.send_resolution(spi)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
@@ -229,20 +257,21 @@ where
     }
 
     #[allow(unused)]
-    fn update_partial_frame(
+    fn update_partial_window(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         unimplemented!()
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
     }
@@ -252,14 +281,18 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.send_resolution(spi)?;
 
         let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
@@ -282,7 +315,7 @@ where
         &mut self,
         spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface
             .cmd_with_data(spi, Command::LutForVcom, LUT_VCOM0)?;
         self.interface
@@ -304,6 +337,86 @@ where
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn set_busy_yield(&mut self, busy_yield: Option<fn()>) {
+        self.interface.set_busy_yield(busy_yield);
+    }
+
+    fn wait_until_idle_or_cancel<F>(
+        &mut self,
+        delay: &mut DELAY,
+        should_cancel: F,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        F: FnMut() -> bool,
+    {
+        let timeout_ms = 2 * TIMING.full_refresh_ms;
+        self.interface
+            .wait_until_idle_or_cancel(IS_BUSY_LOW, delay, timeout_ms, should_cancel)
+    }
+
+    fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.interface.set_max_chunk_size(max_chunk_size);
+    }
+
+    fn set_traffic_hook(&mut self, traffic_hook: Option<TrafficFn>) {
+        self.interface.set_traffic_hook(traffic_hook);
+    }
+
+    fn send_command_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, data)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> FrameStream<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.send_resolution(spi)?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)
+    }
+
+    fn write_frame_chunk(
+        &mut self,
+        spi: &mut SPI,
+        chunk: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        for b in chunk {
+            // Two bits per pixel
+            let expanded = expand_bits(*b);
+            self.interface.data(spi, &expanded)?;
+        }
+        Ok(())
+    }
+
+    fn end_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        // Clear the read layer
+        let color = self.color.get_byte_value();
+        let nbits = WIDTH * (HEIGHT / 8);
+
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, nbits)
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY>
@@ -313,13 +426,21 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.data(spi, data)
     }
 
@@ -328,15 +449,18 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+    fn wait_until_idle(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.wait_until_idle(IS_BUSY_LOW)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         let w = self.width();
         let h = self.height();
 
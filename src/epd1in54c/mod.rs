@@ -5,9 +5,11 @@ use embedded_hal::{
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
+use crate::error::DriverError;
+use crate::interface::{DelayMsExt, DisplayInterface, TrafficFn};
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    FrameStream, InternalWiAdditions, PartialWindow, RefreshLut, Timing, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 /// Width of epd1in54 in pixels
@@ -16,6 +18,14 @@ pub const WIDTH: u32 = 152;
 pub const HEIGHT: u32 = 152;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Maximum SPI clock of the IL0373 controller in Hz
+pub const MAX_SPI_HZ: u32 = 10_000_000;
+/// Typical durations of the display
+pub const TIMING: Timing = Timing {
+    full_refresh_ms: 15000,
+    partial_refresh_ms: None,
+    wake_ms: 300,
+};
 const IS_BUSY_LOW: bool = true;
 const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
 
@@ -32,7 +42,7 @@ pub use self::graphics::Display1in54c;
 
 /// Epd1in54c driver
 pub struct Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY> {
-    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, TrafficFn>,
     color: Color,
 }
 
@@ -44,14 +54,18 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         // Based on Reference Program Code from:
         // https://www.waveshare.com/w/upload/a/ac/1.54inch_e-Paper_Module_C_Specification.pdf
         // and:
         // https://github.com/waveshare/e-Paper/blob/master/STM32/STM32-F103ZET6/User/e-Paper/EPD_1in54c.c
-        self.interface.reset(delay, 2);
+        self.interface.reset(delay, 2)?;
 
         // start the booster
         self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
@@ -59,7 +73,7 @@ where
         // power on
         self.command(spi, Command::PowerOn)?;
         delay.delay_ms(5);
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         // set the panel settings
         self.cmd_with_data(spi, Command::PanelSetting, &[0x0f, 0x0d])?;
@@ -81,20 +95,24 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     fn update_color_frame(
         &mut self,
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
 
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.cmd_with_data(spi, Command::DataStartTransmission1, black)?;
 
         Ok(())
@@ -104,8 +122,8 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)?;
 
         Ok(())
@@ -120,9 +138,11 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+    const MAX_SPI_HZ: u32 = MAX_SPI_HZ;
+    const TIMING: Timing = TIMING;
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -130,7 +150,7 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
         let color = DEFAULT_BACKGROUND_COLOR;
 
@@ -141,17 +161,26 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
 
         self.command(spi, Command::PowerOff)?;
-        self.wait_until_idle();
+        self.wait_until_idle()?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xa5])?;
+        self.interface.set_asleep();
 
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.init(spi, delay)
     }
 
@@ -176,7 +205,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_achromatic_frame(spi, buffer)?;
 
         // Clear the chromatic layer
@@ -189,21 +218,22 @@ where
     }
 
     #[allow(unused)]
-    fn update_partial_frame(
+    fn update_partial_window(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         unimplemented!()
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.command(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         Ok(())
     }
@@ -213,15 +243,19 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
 
         // Clear the black
@@ -239,13 +273,86 @@ where
         &mut self,
         _spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         Ok(())
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn set_busy_yield(&mut self, busy_yield: Option<fn()>) {
+        self.interface.set_busy_yield(busy_yield);
+    }
+
+    fn wait_until_idle_or_cancel<F>(
+        &mut self,
+        delay: &mut DELAY,
+        should_cancel: F,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        F: FnMut() -> bool,
+    {
+        let timeout_ms = 2 * TIMING.full_refresh_ms;
+        self.interface
+            .wait_until_idle_or_cancel(IS_BUSY_LOW, delay, timeout_ms, should_cancel)
+    }
+
+    fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.interface.set_max_chunk_size(max_chunk_size);
+    }
+
+    fn set_traffic_hook(&mut self, traffic_hook: Option<TrafficFn>) {
+        self.interface.set_traffic_hook(traffic_hook);
+    }
+
+    fn send_command_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, data)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> FrameStream<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.command(spi, Command::DataStartTransmission1)
+    }
+
+    fn write_frame_chunk(
+        &mut self,
+        spi: &mut SPI,
+        chunk: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, chunk)
+    }
+
+    fn end_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        // Clear the chromatic layer
+        let color = self.color.get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY>
@@ -255,13 +362,21 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.data(spi, data)
     }
 
@@ -270,15 +385,18 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+    fn wait_until_idle(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.wait_until_idle(IS_BUSY_LOW)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         let w = self.width();
         let h = self.height();
 
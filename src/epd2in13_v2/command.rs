@@ -89,7 +89,6 @@ impl DriverOutput {
 ///  | | `------------- load temp
 ///  | `--------------- enable clock
 ///  `----------------- enable analog
-
 pub(crate) struct DisplayUpdateControl2(pub u8);
 #[allow(dead_code)]
 impl DisplayUpdateControl2 {
@@ -134,7 +133,7 @@ impl DisplayUpdateControl2 {
 }
 
 #[allow(dead_code)]
-
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum DataEntryModeIncr {
     XDecrYDecr = 0x0,
     XIncrYDecr = 0x1,
@@ -143,7 +142,6 @@ pub(crate) enum DataEntryModeIncr {
 }
 
 #[allow(dead_code)]
-
 pub(crate) enum DataEntryModeDir {
     XDir = 0x0,
     YDir = 0x4,
@@ -15,8 +15,10 @@ use embedded_hal::{
 
 use crate::buffer_len;
 use crate::color::Color;
-use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::error::DriverError;
+use crate::interface::{DisplayInterface, TrafficFn};
+use crate::region::Region;
+use crate::traits::{InternalWiAdditions, PartialWindow, RefreshLut, Timing, WaveshareDisplay};
 
 pub(crate) mod command;
 use self::command::{
@@ -41,13 +43,21 @@ pub const HEIGHT: u32 = 250;
 
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Maximum SPI clock of the SSD1675 controller in Hz
+pub const MAX_SPI_HZ: u32 = 20_000_000;
+/// Typical durations of the display
+pub const TIMING: Timing = Timing {
+    full_refresh_ms: 2000,
+    partial_refresh_ms: Some(300),
+    wake_ms: 250,
+};
 const IS_BUSY_LOW: bool = false;
 
 /// Epd2in13 (V2) driver
 ///
 pub struct Epd2in13<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, TrafficFn>,
 
     sleep_mode: DeepSleepMode,
 
@@ -64,15 +74,19 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         // HW reset
-        self.interface.reset(delay, 10);
+        self.interface.reset(delay, 10)?;
 
         if self.refresh == RefreshLut::Quick {
             self.set_vcom_register(spi, (-9).vcom())?;
-            self.wait_until_idle();
+            self.wait_until_idle()?;
 
             self.set_lut(spi, Some(self.refresh))?;
 
@@ -86,7 +100,7 @@ where
                 DisplayUpdateControl2::new().enable_analog().enable_clock(),
             )?;
             self.command(spi, Command::MasterActivation)?;
-            self.wait_until_idle();
+            self.wait_until_idle()?;
 
             self.set_border_waveform(
                 spi,
@@ -97,9 +111,9 @@ where
                 },
             )?;
         } else {
-            self.wait_until_idle();
+            self.wait_until_idle()?;
             self.command(spi, Command::SwReset)?;
-            self.wait_until_idle();
+            self.wait_until_idle()?;
 
             self.set_driver_output(
                 spi,
@@ -145,7 +159,7 @@ where
             self.set_lut(spi, Some(self.refresh))?;
         }
 
-        self.wait_until_idle();
+        self.wait_until_idle()?;
         Ok(())
     }
 }
@@ -158,9 +172,11 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+    const MAX_SPI_HZ: u32 = MAX_SPI_HZ;
+    const TIMING: Timing = TIMING;
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -168,7 +184,7 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
         let mut epd = Epd2in13 {
             interface: DisplayInterface::new(cs, busy, dc, rst),
             sleep_mode: DeepSleepMode::Mode1,
@@ -180,12 +196,20 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
 
         // All sample code enables and disables analog/clocks...
         self.set_display_update_control_2(
@@ -199,6 +223,9 @@ where
         self.command(spi, Command::MasterActivation)?;
 
         self.set_sleep_mode(spi, self.sleep_mode)?;
+        if !matches!(self.sleep_mode, DeepSleepMode::Normal) {
+            self.interface.set_asleep();
+        }
         Ok(())
     }
 
@@ -207,7 +234,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, 0, 0)?;
@@ -227,15 +254,18 @@ where
     /// Updating only a part of the frame is not supported when using the
     /// partial refresh feature. The function will panic if called when set to
     /// use partial refresh.
-    fn update_partial_frame(
+    fn update_partial_window(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let Region {
+            x,
+            y,
+            width,
+            height,
+        } = window.region();
         assert!((width * height / 8) as usize == buffer.len());
 
         // This should not be used when doing partial refresh. The RAM_RED must
@@ -264,7 +294,11 @@ where
 
     /// Never use directly this function when using partial refresh, or also
     /// keep the base buffer in syncd using `set_partial_base_buffer` function.
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         if self.refresh == RefreshLut::Full {
             self.set_display_update_control_2(
                 spi,
@@ -279,7 +313,7 @@ where
             self.set_display_update_control_2(spi, DisplayUpdateControl2::new().display())?;
         }
         self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         Ok(())
     }
@@ -289,7 +323,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
@@ -299,7 +333,11 @@ where
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         let color = self.background_color.get_byte_value();
 
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
@@ -347,7 +385,7 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         let buffer = match refresh_rate {
             Some(RefreshLut::Full) | None => &LUT_FULL_UPDATE,
             Some(RefreshLut::Quick) => &LUT_PARTIAL_UPDATE,
@@ -359,6 +397,47 @@ where
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn set_busy_yield(&mut self, busy_yield: Option<fn()>) {
+        self.interface.set_busy_yield(busy_yield);
+    }
+
+    fn wait_until_idle_or_cancel<F>(
+        &mut self,
+        delay: &mut DELAY,
+        should_cancel: F,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        F: FnMut() -> bool,
+    {
+        let timeout_ms = 2 * TIMING.full_refresh_ms;
+        self.interface
+            .wait_until_idle_or_cancel(IS_BUSY_LOW, delay, timeout_ms, should_cancel)
+    }
+
+    fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.interface.set_max_chunk_size(max_chunk_size);
+    }
+
+    fn set_traffic_hook(&mut self, traffic_hook: Option<TrafficFn>) {
+        self.interface.set_traffic_hook(traffic_hook);
+    }
+
+    fn send_command_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, data)
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>
@@ -368,7 +447,7 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     /// When using partial refresh, the controller uses the provided buffer for
     /// comparison with new buffer.
@@ -376,7 +455,7 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         assert!(buffer_len(WIDTH as usize, HEIGHT as usize) == buffer.len());
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, 0, 0)?;
@@ -397,7 +476,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh: RefreshLut,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         if self.refresh != refresh {
             self.refresh = refresh;
             self.init(spi, delay)?;
@@ -409,7 +488,7 @@ where
         &mut self,
         spi: &mut SPI,
         start: u16,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         assert!(start <= 295);
         self.cmd_with_data(
             spi,
@@ -422,7 +501,7 @@ where
         &mut self,
         spi: &mut SPI,
         borderwaveform: BorderWaveForm,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.cmd_with_data(
             spi,
             Command::BorderWaveformControl,
@@ -430,7 +509,11 @@ where
         )
     }
 
-    fn set_vcom_register(&mut self, spi: &mut SPI, vcom: Vcom) -> Result<(), SPI::Error> {
+    fn set_vcom_register(
+        &mut self,
+        spi: &mut SPI,
+        vcom: Vcom,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.cmd_with_data(spi, Command::WriteVcomRegister, &[vcom.0])
     }
 
@@ -438,7 +521,7 @@ where
         &mut self,
         spi: &mut SPI,
         voltage: GateDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.cmd_with_data(spi, Command::GateDrivingVoltageCtrl, &[voltage.0])
     }
 
@@ -446,12 +529,16 @@ where
         &mut self,
         spi: &mut SPI,
         number_of_lines: u8,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         assert!(number_of_lines <= 127);
         self.cmd_with_data(spi, Command::SetDummyLinePeriod, &[number_of_lines])
     }
 
-    fn set_gate_line_width(&mut self, spi: &mut SPI, width: u8) -> Result<(), SPI::Error> {
+    fn set_gate_line_width(
+        &mut self,
+        spi: &mut SPI,
+        width: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.cmd_with_data(spi, Command::SetGateLineWidth, &[width & 0x0F])
     }
 
@@ -462,7 +549,7 @@ where
         vsh1: SourceDrivingVoltage,
         vsh2: SourceDrivingVoltage,
         vsl: SourceDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.cmd_with_data(
             spi,
             Command::SourceDrivingVoltageCtrl,
@@ -476,16 +563,24 @@ where
         &mut self,
         spi: &mut SPI,
         value: DisplayUpdateControl2,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[value.0])
     }
 
     /// Triggers the deep sleep mode
-    fn set_sleep_mode(&mut self, spi: &mut SPI, mode: DeepSleepMode) -> Result<(), SPI::Error> {
+    fn set_sleep_mode(
+        &mut self,
+        spi: &mut SPI,
+        mode: DeepSleepMode,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.cmd_with_data(spi, Command::DeepSleepMode, &[mode as u8])
     }
 
-    fn set_driver_output(&mut self, spi: &mut SPI, output: DriverOutput) -> Result<(), SPI::Error> {
+    fn set_driver_output(
+        &mut self,
+        spi: &mut SPI,
+        output: DriverOutput,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.cmd_with_data(spi, Command::DriverOutputControl, &output.to_bytes())
     }
 
@@ -496,7 +591,7 @@ where
         spi: &mut SPI,
         counter_incr_mode: DataEntryModeIncr,
         counter_direction: DataEntryModeDir,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         let mode = counter_incr_mode as u8 | counter_direction as u8;
         self.cmd_with_data(spi, Command::DataEntryModeSetting, &[mode])
     }
@@ -509,7 +604,7 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.cmd_with_data(
             spi,
             Command::SetRamXAddressStartEndPosition,
@@ -534,8 +629,8 @@ where
         spi: &mut SPI,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
 
         self.cmd_with_data(
@@ -546,7 +641,11 @@ where
         Ok(())
     }
 
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, command)
     }
 
@@ -555,12 +654,12 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+    fn wait_until_idle(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.wait_until_idle(IS_BUSY_LOW)
     }
 }
 
@@ -1,7 +1,9 @@
 use crate::color::TriColor;
 use crate::epd2in13bc::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
-use crate::graphics::{DisplayRotation, TriDisplay};
+use crate::graphics::{rotated_size, Display, DisplayRotation};
+use crate::region::Region;
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
 /// Full size buffer for use with the 2.13" b/c EPD
 ///
@@ -12,6 +14,7 @@ pub struct Display2in13bc {
     // * &buffer[NUM_DISPLAY_BITS..2*NUM_DISPLAY_BITS] for chromatic buffer
     buffer: [u8; 2 * NUM_DISPLAY_BITS as usize],
     rotation: DisplayRotation,
+    dirty: Option<Region>,
 }
 
 impl Default for Display2in13bc {
@@ -19,6 +22,7 @@ impl Default for Display2in13bc {
         Display2in13bc {
             buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 2 * NUM_DISPLAY_BITS as usize],
             rotation: DisplayRotation::default(),
+            dirty: None,
         }
     }
 }
@@ -31,19 +35,30 @@ impl DrawTarget for Display2in13bc {
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
         for pixel in pixels {
-            self.draw_helper_tri(WIDTH, HEIGHT, pixel)?;
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(WIDTH, HEIGHT, area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.fill_contiguous_helper(WIDTH, HEIGHT, area, colors)
+    }
 }
 
 impl OriginDimensions for Display2in13bc {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        rotated_size(WIDTH, HEIGHT, self.rotation)
     }
 }
 
-impl TriDisplay for Display2in13bc {
+impl Display<TriColor> for Display2in13bc {
     fn buffer(&self) -> &[u8] {
         &self.buffer
     }
@@ -60,15 +75,26 @@ impl TriDisplay for Display2in13bc {
         self.rotation
     }
 
-    fn chromatic_offset(&self) -> usize {
-        NUM_DISPLAY_BITS as usize
+    fn dirty_region_mut(&mut self) -> Option<&mut Option<Region>> {
+        Some(&mut self.dirty)
     }
+}
 
-    fn bw_buffer(&self) -> &[u8] {
-        &self.buffer[0..self.chromatic_offset()]
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::TriDisplay;
+
+    #[test]
+    fn clear_planes() {
+        let mut display = Display2in13bc::default();
+        display.clear_buffer(TriColor::Chromatic);
+        assert_eq!(display.chromatic_offset(), NUM_DISPLAY_BITS as usize);
+        assert!(display.bw_buffer().iter().all(|&b| b == 0xff));
+        assert!(display.chromatic_buffer().iter().all(|&b| b == 0x00));
 
-    fn chromatic_buffer(&self) -> &[u8] {
-        &self.buffer[self.chromatic_offset()..]
+        display.clear_buffer(TriColor::Black);
+        assert!(display.bw_buffer().iter().all(|&b| b == 0x00));
+        assert!(display.chromatic_buffer().iter().all(|&b| b == 0xff));
     }
 }
@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::error::DriverError<spi::Mock, pin::Mock, pin::Mock, pin::Mock, pin::Mock>> {
 //!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle, PrimitiveStyleBuilder}};
 //!use epd_waveshare::{epd2in13bc::*, prelude::*};
 //!#
@@ -54,9 +54,11 @@ use embedded_hal::{
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
+use crate::error::DriverError;
+use crate::interface::{DelayMsExt, DisplayInterface, TrafficFn};
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    FrameStream, InternalWiAdditions, PartialWindow, RefreshLut, Timing, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 /// Width of epd2in13bc in pixels
@@ -65,6 +67,14 @@ pub const WIDTH: u32 = 104;
 pub const HEIGHT: u32 = 212;
 /// Default background color (white) of epd2in13bc display
 pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Maximum SPI clock of the IL0373 controller in Hz
+pub const MAX_SPI_HZ: u32 = 10_000_000;
+/// Typical durations of the display
+pub const TIMING: Timing = Timing {
+    full_refresh_ms: 15000,
+    partial_refresh_ms: None,
+    wake_ms: 300,
+};
 
 /// Number of bits for b/w buffer and same for chromatic buffer
 const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
@@ -76,7 +86,7 @@ const BLACK_BORDER: u8 = 0x30;
 const CHROMATIC_BORDER: u8 = 0xb0;
 const FLOATING_BORDER: u8 = 0xF0;
 
-use crate::color::TriColor;
+use crate::color::{BorderColor, TriColor};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -88,8 +98,9 @@ pub use self::graphics::Display2in13bc;
 
 /// Epd2in13bc driver
 pub struct Epd2in13bc<SPI, CS, BUSY, DC, RST, DELAY> {
-    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, TrafficFn>,
     color: TriColor,
+    border: BorderColor,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -100,12 +111,16 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         // Values taken from datasheet and sample code
 
-        self.interface.reset(delay, 10);
+        self.interface.reset(delay, 10)?;
 
         // start the booster
         self.interface
@@ -114,7 +129,7 @@ where
         // power on
         self.command(spi, Command::PowerOn)?;
         delay.delay_ms(5);
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         // set the panel settings
         self.cmd_with_data(spi, Command::PanelSetting, &[0x8F])?;
@@ -122,7 +137,7 @@ where
         self.cmd_with_data(
             spi,
             Command::VcomAndDataIntervalSetting,
-            &[WHITE_BORDER | VCOM_DATA_INTERVAL],
+            &[border_setting(self.border)],
         )?;
 
         // set resolution
@@ -130,7 +145,7 @@ where
 
         self.cmd_with_data(spi, Command::VcmDcSetting, &[0x0A])?;
 
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         Ok(())
     }
@@ -144,14 +159,14 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     fn update_color_frame(
         &mut self,
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
@@ -159,7 +174,11 @@ where
     /// Update only the black/white data of the display.
     ///
     /// Finish by calling `update_chromatic_frame`.
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface.data(spi, black)?;
         Ok(())
@@ -172,11 +191,11 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data(spi, chromatic)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle()?;
         Ok(())
     }
 }
@@ -189,9 +208,11 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     type DisplayColor = TriColor;
+    const MAX_SPI_HZ: u32 = MAX_SPI_HZ;
+    const TIMING: Timing = TIMING;
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -199,18 +220,26 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd2in13bc { interface, color };
+        let mut epd = Epd2in13bc {
+            interface,
+            color,
+            border: BorderColor::default(),
+        };
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         // Section 8.2 from datasheet
         self.interface.cmd_with_data(
             spi,
@@ -220,14 +249,19 @@ where
 
         self.command(spi, Command::PowerOff)?;
         // The example STM code from Github has a wait after PowerOff
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_asleep();
 
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.init(spi, delay)
     }
 
@@ -252,10 +286,10 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
-        self.interface.data(spi, &buffer)?;
+        self.interface.data(spi, buffer)?;
 
         // Clear the chromatic layer
         let color = self.color.get_byte_value();
@@ -263,27 +297,28 @@ where
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle()?;
         Ok(())
     }
 
     #[allow(unused)]
-    fn update_partial_frame(
+    fn update_partial_window(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.command(spi, Command::DisplayRefresh)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle()?;
         Ok(())
     }
 
@@ -292,13 +327,17 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.send_resolution(spi)?;
 
         let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
@@ -312,7 +351,7 @@ where
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle()?;
         Ok(())
     }
 
@@ -320,13 +359,87 @@ where
         &mut self,
         _spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         Ok(())
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn set_busy_yield(&mut self, busy_yield: Option<fn()>) {
+        self.interface.set_busy_yield(busy_yield);
+    }
+
+    fn wait_until_idle_or_cancel<F>(
+        &mut self,
+        delay: &mut DELAY,
+        should_cancel: F,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        F: FnMut() -> bool,
+    {
+        let timeout_ms = 2 * TIMING.full_refresh_ms;
+        self.interface
+            .wait_until_idle_or_cancel(IS_BUSY_LOW, delay, timeout_ms, should_cancel)
+    }
+
+    fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.interface.set_max_chunk_size(max_chunk_size);
+    }
+
+    fn set_traffic_hook(&mut self, traffic_hook: Option<TrafficFn>) {
+        self.interface.set_traffic_hook(traffic_hook);
+    }
+
+    fn send_command_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, data)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> FrameStream<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in13bc<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.cmd(spi, Command::DataStartTransmission1)
+    }
+
+    fn write_frame_chunk(
+        &mut self,
+        spi: &mut SPI,
+        chunk: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, chunk)
+    }
+
+    fn end_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        // Clear the chromatic layer
+        let color = self.color.get_byte_value();
+
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+
+        self.wait_until_idle()
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in13bc<SPI, CS, BUSY, DC, RST, DELAY>
@@ -336,13 +449,21 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.data(spi, data)
     }
 
@@ -351,15 +472,18 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+    fn wait_until_idle(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.wait_until_idle(IS_BUSY_LOW)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         let w = self.width();
         let h = self.height();
 
@@ -371,16 +495,36 @@ where
     }
 
     /// Set the outer border of the display to the chosen color.
-    pub fn set_border_color(&mut self, spi: &mut SPI, color: TriColor) -> Result<(), SPI::Error> {
-        let border = match color {
-            TriColor::Black => BLACK_BORDER,
-            TriColor::White => WHITE_BORDER,
-            TriColor::Chromatic => CHROMATIC_BORDER,
-        };
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: TriColor,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.set_border(spi, color.into())
+    }
+
+    /// Sets the outer border of the display, kept for the next init
+    pub fn set_border(
+        &mut self,
+        spi: &mut SPI,
+        border: BorderColor,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.border = border;
         self.cmd_with_data(
             spi,
             Command::VcomAndDataIntervalSetting,
-            &[border | VCOM_DATA_INTERVAL],
+            &[border_setting(border)],
         )
     }
 }
+
+/// The VCOM and data interval setting with the given border
+fn border_setting(border: BorderColor) -> u8 {
+    let vbd = match border {
+        BorderColor::White => WHITE_BORDER,
+        BorderColor::Black => BLACK_BORDER,
+        BorderColor::Chromatic => CHROMATIC_BORDER,
+        BorderColor::Floating => FLOATING_BORDER,
+    };
+    vbd | VCOM_DATA_INTERVAL
+}
@@ -93,7 +93,9 @@ pub(crate) enum Command {
     ///
     /// Doesn't work! Waveshare doesn't connect the read pin
     GetStatus = 0x71,
-    /// Automatically measure VCOM. This command reads the IC status
+    /// Automatically measure VCOM, BUSY is low until the measurement is done.
+    ///
+    /// Bit 0 starts the measurement, bits 5:4 select its duration of 3, 5, 8 or 10 seconds.
     AutoMeasurementVcom = 0x80,
     /// This command gets the VCOM value
     ///
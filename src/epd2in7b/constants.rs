@@ -53,3 +53,122 @@ pub(crate) const LUT_WB: [u8; 42] =[
 0x00, 0x03, 0x0E, 0x00, 0x00, 0x0A,
 0x00, 0x23, 0x00, 0x00, 0x00, 0x01,
 ];
+
+// Quick refresh tables, a single phase of 14 frames after the quick tables of the 4.2" display.
+// They are used in the black/white mode of the panel, where the first data transmission holds the
+// old and the second one the new frame.
+
+#[rustfmt::skip]
+pub(crate) const LUT_VCOM_DC_QUICK: [u8; 44] = [
+0x00, 0x00,
+0x00, 0x0E, 0x00, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WW_QUICK: [u8; 42] =[
+0xA0, 0x0E, 0x00, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BW_QUICK: [u8; 42] =[
+0xA0, 0x0E, 0x00, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BB_QUICK: [u8; 42] =[
+0x50, 0x0E, 0x00, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WB_QUICK: [u8; 42] =[
+0x50, 0x0E, 0x00, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+// 4 gray tables of the Waveshare reference code, used in the black/white mode of the panel with
+// the high bits of the pixels in the first and the low bits in the second data transmission.
+
+#[rustfmt::skip]
+pub(crate) const LUT_VCOM_DC_GRAY: [u8; 44] = [
+0x00, 0x00,
+0x00, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x60, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x00, 0x14, 0x00, 0x00, 0x00, 0x01,
+0x00, 0x13, 0x0A, 0x01, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WW_GRAY: [u8; 42] =[
+0x40, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x10, 0x14, 0x0A, 0x00, 0x00, 0x01,
+0xA0, 0x13, 0x01, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BW_GRAY: [u8; 42] =[
+0x40, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x00, 0x14, 0x0A, 0x00, 0x00, 0x01,
+0x99, 0x0C, 0x01, 0x03, 0x04, 0x01,
+0x02, 0x04, 0x01, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WB_GRAY: [u8; 42] =[
+0x40, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x00, 0x14, 0x0A, 0x00, 0x00, 0x01,
+0x99, 0x0B, 0x04, 0x04, 0x01, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BB_GRAY: [u8; 42] =[
+0x80, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x20, 0x14, 0x0A, 0x00, 0x00, 0x01,
+0x50, 0x13, 0x01, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
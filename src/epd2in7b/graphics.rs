@@ -1,29 +1,37 @@
-use crate::epd2in7b::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
-use crate::graphics::{Display, DisplayRotation};
-use embedded_graphics::pixelcolor::BinaryColor;
+use crate::buffer_len;
+use crate::epd2in7b::{HEIGHT, WIDTH};
+use crate::graphics::{rotated_size, Display, DisplayRotation, FixedDisplay};
+use crate::region::Region;
+use embedded_graphics::pixelcolor::Gray2;
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
 /// Full size buffer for use with the 2in7B EPD
+pub type Display2in7b =
+    FixedDisplay<WIDTH, HEIGHT, { buffer_len(WIDTH as usize, HEIGHT as usize) }>;
+
+/// Full size 4 gray buffer for [`update_4gray_frame`](crate::epd2in7b::Epd2in7b::update_4gray_frame)
 ///
-/// Can also be manuall constructed:
-/// `buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH * HEIGHT / 8]`
-pub struct Display2in7b {
-    buffer: [u8; WIDTH as usize * HEIGHT as usize / 8],
+/// Two bits per pixel, see the [`PackedColor`](crate::source::PackedColor) implementation of
+/// [`Gray2`].
+pub struct Display2in7Gray {
+    buffer: [u8; WIDTH as usize * HEIGHT as usize / 4],
     rotation: DisplayRotation,
+    dirty: Option<Region>,
 }
 
-impl Default for Display2in7b {
+impl Default for Display2in7Gray {
     fn default() -> Self {
-        Display2in7b {
-            buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value();
-                WIDTH as usize * HEIGHT as usize / 8],
+        Display2in7Gray {
+            buffer: [0xff; WIDTH as usize * HEIGHT as usize / 4],
             rotation: DisplayRotation::default(),
+            dirty: None,
         }
     }
 }
 
-impl DrawTarget for Display2in7b {
-    type Color = BinaryColor;
+impl DrawTarget for Display2in7Gray {
+    type Color = Gray2;
     type Error = core::convert::Infallible;
 
     fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
@@ -35,15 +43,26 @@ impl DrawTarget for Display2in7b {
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(WIDTH, HEIGHT, area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.fill_contiguous_helper(WIDTH, HEIGHT, area, colors)
+    }
 }
 
-impl OriginDimensions for Display2in7b {
+impl OriginDimensions for Display2in7Gray {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        rotated_size(WIDTH, HEIGHT, self.rotation)
     }
 }
 
-impl Display for Display2in7b {
+impl Display<Gray2> for Display2in7Gray {
     fn buffer(&self) -> &[u8] {
         &self.buffer
     }
@@ -59,6 +78,10 @@ impl Display for Display2in7b {
     fn rotation(&self) -> DisplayRotation {
         self.rotation
     }
+
+    fn dirty_region_mut(&mut self) -> Option<&mut Option<Region>> {
+        Some(&mut self.dirty)
+    }
 }
 
 #[cfg(test)]
@@ -172,4 +195,21 @@ mod tests {
             assert_eq!(byte, epd2in7b::DEFAULT_BACKGROUND_COLOR.get_byte_value());
         }
     }
+
+    #[test]
+    fn gray_pixels() {
+        let mut display = Display2in7Gray::default();
+        assert_eq!(display.buffer().len(), 11616);
+
+        for (x, luma) in (0..4).zip([0, 1, 2, 3]) {
+            let _ = Pixel(Point::new(x, 0), Gray2::new(luma)).draw(&mut display);
+        }
+        let _ = Pixel(Point::new(0, 1), Gray2::BLACK).draw(&mut display);
+
+        let buffer = display.buffer();
+        assert_eq!(buffer[0], 0b00_01_10_11);
+        assert_eq!(buffer[1], 0xff);
+        // 44 bytes per row
+        assert_eq!(buffer[44], 0b00_11_11_11);
+    }
 }
@@ -3,13 +3,22 @@
 //! [Documentation](https://www.waveshare.com/wiki/2.7inch_e-Paper_HAT_(B))
 
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
+use crate::config::{Config, FrameRate, LutSource, Profile, ResetTiming, VcomDc};
+use crate::error::DriverError;
+use crate::interface::{DelayMsExt, DisplayInterface, TrafficFn};
+use crate::lut;
+use crate::region::Region;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    DmaFrame, FrameStream, InternalWiAdditions, InterruptRefresh, LutSet, PartialWindow,
+    QuickRefresh, ReadBack, RefreshLut, SleepMode, Status, Timing, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 // The Lookup Tables for the Display
@@ -22,6 +31,23 @@ pub const WIDTH: u32 = 176;
 pub const HEIGHT: u32 = 264;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Maximum SPI clock of the IL91874 controller in Hz
+pub const MAX_SPI_HZ: u32 = 10_000_000;
+/// Typical durations of the display
+pub const TIMING: Timing = Timing {
+    full_refresh_ms: 15000,
+    partial_refresh_ms: Some(800),
+    wake_ms: 300,
+};
+/// Default timing of the hardware reset, see [`Builder::reset_timing`](crate::config::Builder::reset_timing)
+pub const RESET_TIMING: ResetTiming = ResetTiming {
+    low_ms: 2,
+    settle_ms: 200,
+};
+/// Default longest wait for BUSY, twice a full refresh
+pub const BUSY_TIMEOUT_MS: u32 = 2 * TIMING.full_refresh_ms;
+/// Default VCOM DC voltage of -1.0 V, see [`Builder::vcom_dc`](crate::config::Builder::vcom_dc)
+pub const VCOM_DC: VcomDc = VcomDc::from_register(0x12);
 const IS_BUSY_LOW: bool = true;
 
 use crate::color::Color;
@@ -32,14 +58,33 @@ use self::command::Command;
 #[cfg(feature = "graphics")]
 mod graphics;
 #[cfg(feature = "graphics")]
-pub use self::graphics::Display2in7b;
+pub use self::graphics::{Display2in7Gray, Display2in7b};
 
 /// Epd2in7b driver
 pub struct Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, TrafficFn>,
     /// Background Color
     color: Color,
+    /// Init configuration
+    config: Config,
+    /// Refresh LUT, also selects between the tri-color and the black/white mode of the panel
+    refresh: RefreshLut,
+    /// VCOM DC voltage, see [`set_vcom`](Self::set_vcom)
+    vcom_dc: VcomDc,
+    /// Temperature the waveforms are stretched for, see [`WaveshareDisplay::set_temperature`]
+    temperature: Option<i8>,
+    /// What [`sleep`](WaveshareDisplay::sleep) does, see [`set_sleep_mode`](Self::set_sleep_mode)
+    sleep_mode: SleepMode,
+    /// Only powered off by [`standby`](Self::standby), RAM and registers are still valid
+    standby: bool,
+    /// A refresh was started by [`start_display_frame`](InterruptRefresh::start_display_frame)
+    /// and BUSY wasn't seen idle since
+    refreshing: bool,
+    /// Longest wait for BUSY before giving up with [`Error::BusyTimeout`](crate::error::Error::BusyTimeout)
+    busy_timeout_ms: u32,
+    /// Delay for the waits of the methods without one, see [`set_busy_delay`](Self::set_busy_delay)
+    busy_delay: Option<DELAY>,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -50,24 +95,32 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         // reset the device
-        self.interface.reset(delay, 2);
+        let reset_timing = self.config.reset_timing().unwrap_or(RESET_TIMING);
+        self.interface.reset_with_timing(delay, reset_timing)?;
 
         // power on
         self.command(spi, Command::PowerOn)?;
         delay.delay_ms(5);
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         // set panel settings, 0xbf is bw, 0xaf is multi-color
         self.interface
-            .cmd_with_data(spi, Command::PanelSetting, &[0xaf])?;
+            .cmd_with_data(spi, Command::PanelSetting, &[self.panel_setting()])?;
+
+        let (booster, frame_rate, interval) = profile_settings(self.config.profile());
+        let pll = self.config.frame_rate().unwrap_or(frame_rate).register();
 
         // pll control
         self.interface
-            .cmd_with_data(spi, Command::PllControl, &[0x3a])?;
+            .cmd_with_data(spi, Command::PllControl, &[pll])?;
 
         // set the power settings
         self.interface.cmd_with_data(
@@ -78,7 +131,7 @@ where
 
         // start the booster
         self.interface
-            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x07, 0x07, 0x17])?;
+            .cmd_with_data(spi, Command::BoosterSoftStart, &booster)?;
 
         // power optimization
         self.interface
@@ -93,18 +146,21 @@ where
             .cmd_with_data(spi, Command::PowerOptimization, &[0x73, 0x41])?;
 
         self.interface
-            .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+            .cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom_dc.register()])?;
 
-        self.interface
-            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x87])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[0x80 | interval],
+        )?;
 
-        self.set_lut(spi, None)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.write_luts(spi)?;
 
         self.interface
             .cmd_with_data(spi, Command::PartialDisplayRefresh, &[0x00])?;
 
-        self.wait_until_idle();
-        Ok(())
+        self.wait_until_idle_with_timeout(delay)
     }
 }
 
@@ -116,9 +172,11 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+    const MAX_SPI_HZ: u32 = MAX_SPI_HZ;
+    const TIMING: Timing = TIMING;
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -126,30 +184,41 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd2in7b { interface, color };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
+        Self::new_with_config(spi, cs, busy, dc, rst, delay, Config::default())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.init(spi, delay)
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        if self.standby {
+            // RAM and LUTs survived, powering on is enough
+            self.power_on(spi, delay)
+        } else {
+            self.init(spi, delay)
+        }
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        if self.sleep_mode == SleepMode::Standby {
+            return self.standby(spi);
+        }
+        self.wait_until_idle_with_timeout(delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xf7])?;
 
         self.command(spi, Command::PowerOff)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_asleep();
+        self.standby = false;
         Ok(())
     }
 
@@ -158,50 +227,36 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.send_buffer_helper(spi, buffer)?;
-
-        // Clear chromatic layer since we won't be using it here
-        self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface
-            .data_x_times(spi, !self.color.get_byte_value(), WIDTH * HEIGHT / 8)?;
-
-        self.interface.cmd(spi, Command::DataStop)?;
-        Ok(())
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.begin_frame(spi)?;
+        self.write_frame_chunk(spi, buffer)?;
+        self.end_frame(spi)
     }
 
-    fn update_partial_frame(
+    fn update_partial_window(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface
             .cmd(spi, Command::PartialDataStartTransmission1)?;
 
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        self.send_data(spi, &[(x & 0xf8) as u8])?;
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[(y & 0xff) as u8])?;
-        self.send_data(spi, &[(width >> 8) as u8])?;
-        self.send_data(spi, &[(width & 0xf8) as u8])?;
-        self.send_data(spi, &[(height >> 8) as u8])?;
-        self.send_data(spi, &[(height & 0xff) as u8])?;
-        self.wait_until_idle();
+        self.send_partial_window(spi, window)?;
+        self.wait_until_idle()?;
 
         self.send_buffer_helper(spi, buffer)?;
 
         self.interface.cmd(spi, Command::DataStop)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.command(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle();
-        Ok(())
+        self.wait_until_idle_with_timeout(delay)
     }
 
     fn update_and_display_frame(
@@ -209,16 +264,21 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_frame(spi, buffer, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
 
-        let color_value = self.color.get_byte_value();
+        // Flipped like the buffers
+        let color_value = !self.color.get_byte_value();
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface
             .data_x_times(spi, color_value, WIDTH * HEIGHT / 8)?;
@@ -227,7 +287,7 @@ where
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface
-            .data_x_times(spi, color_value, WIDTH * HEIGHT / 8)?;
+            .data_x_times(spi, self.second_plane_clear_value(), WIDTH * HEIGHT / 8)?;
         self.interface.cmd(spi, Command::DataStop)?;
         Ok(())
     }
@@ -251,20 +311,69 @@ where
     fn set_lut(
         &mut self,
         spi: &mut SPI,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_DC)?;
-        self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW)?;
-        self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW)?;
-        self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB)?;
-        self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB)?;
-        Ok(())
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        self.wait_until_idle()?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[self.panel_setting()])?;
+        self.write_luts(spi)
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn set_temperature(&mut self, temperature: Option<i8>) {
+        self.temperature = temperature;
+    }
+
+    fn set_busy_yield(&mut self, busy_yield: Option<fn()>) {
+        self.interface.set_busy_yield(busy_yield);
+    }
+
+    fn wait_until_idle_or_cancel<F>(
+        &mut self,
+        delay: &mut DELAY,
+        should_cancel: F,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        F: FnMut() -> bool,
+    {
+        self.interface.wait_until_idle_or_cancel(
+            IS_BUSY_LOW,
+            delay,
+            self.busy_timeout_ms,
+            should_cancel,
+        )?;
+        self.refreshing = false;
+        Ok(())
+    }
+
+    fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.interface.set_max_chunk_size(max_chunk_size);
+    }
+
+    fn set_traffic_hook(&mut self, traffic_hook: Option<TrafficFn>) {
+        self.interface.set_traffic_hook(traffic_hook);
+    }
+
+    fn send_command_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, data)
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>
@@ -275,14 +384,14 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     fn update_color_frame(
         &mut self,
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
@@ -294,7 +403,7 @@ where
         &mut self,
         spi: &mut SPI,
         achromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
         self.send_buffer_helper(spi, achromatic)?;
@@ -309,13 +418,13 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
 
         self.send_buffer_helper(spi, chromatic)?;
 
         self.interface.cmd(spi, Command::DataStop)?;
-        self.wait_until_idle();
+        self.wait_until_idle()?;
 
         Ok(())
     }
@@ -328,23 +437,175 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Creates a new driver like [`WaveshareDisplay::new`], but applies the given [`Config`] during init
+    pub fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: Config,
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd2in7b {
+            interface,
+            color,
+            config,
+            refresh: RefreshLut::Full,
+            temperature: None,
+            vcom_dc: config.vcom_dc().unwrap_or(VCOM_DC),
+            sleep_mode: SleepMode::Deep,
+            standby: false,
+            refreshing: false,
+            busy_timeout_ms: BUSY_TIMEOUT_MS,
+            busy_delay: None,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    /// Powers the display off without entering deep sleep
+    ///
+    /// Unlike [`sleep`](WaveshareDisplay::sleep) the controller keeps its RAM and registers, so the
+    /// next [`wake_up`](WaveshareDisplay::wake_up) only needs to power it on again instead of
+    /// running the whole init and LUT upload. This draws slightly more current than deep sleep.
+    pub fn standby(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.power_off(spi)
+    }
+
+    /// Turns the charge pumps off, the image stays at almost no current
+    ///
+    /// RAM and registers are kept, after [`power_on`](Self::power_on) the next (partial) update
+    /// can follow without the init and LUT upload of [`wake_up`](WaveshareDisplay::wake_up).
+    pub fn power_off(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle()?;
+        self.standby = true;
+        Ok(())
+    }
+
+    /// Turns the charge pumps on again after [`power_off`](Self::power_off) or
+    /// [`standby`](Self::standby)
+    pub fn power_on(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.standby = false;
+        self.command(spi, Command::PowerOn)?;
+        delay.delay_ms(5);
+        self.wait_until_idle_with_timeout(delay)
+    }
+
+    /// Selects if [`sleep`](WaveshareDisplay::sleep) enters deep sleep (the default) or only
+    /// [`standby`](Self::standby)
+    ///
+    /// Devices updating every few seconds save the init and LUT upload of each wake up with
+    /// [`SleepMode::Standby`].
+    pub fn set_sleep_mode(&mut self, sleep_mode: SleepMode) {
+        self.sleep_mode = sleep_mode;
+    }
+
+    /// Sets the longest wait for BUSY before giving up with [`Error::BusyTimeout`](crate::error::Error::BusyTimeout)
+    ///
+    /// The methods taking a delay wait with this timeout, the others once they have a delay from
+    /// [`set_busy_delay`](Self::set_busy_delay). Defaults to [`BUSY_TIMEOUT_MS`].
+    pub fn set_busy_timeout(&mut self, timeout_ms: u32) {
+        self.busy_timeout_ms = timeout_ms;
+    }
+
+    /// Gives the driver a delay of its own to time the BUSY waits of the methods without a delay
+    /// argument, like [`update_partial_window`](WaveshareDisplay::update_partial_window),
+    /// [`set_lut`](WaveshareDisplay::set_lut) or [`power_off`](Self::power_off)
+    ///
+    /// Without it those methods wait until BUSY is released, however long that takes.
+    pub fn set_busy_delay(&mut self, delay: DELAY) {
+        self.busy_delay = Some(delay);
+    }
+
+    /// Sets the VCOM DC voltage, e.g. to the one printed on the FPC of the panel
+    ///
+    /// Matching it improves the contrast. Kept for the next init, replacing the voltage of the
+    /// [`Config`] or [`VCOM_DC`].
+    pub fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        vcom_dc: VcomDc,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.vcom_dc = vcom_dc;
+        self.wait_until_idle()?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[vcom_dc.register()])
+    }
+
+    /// Makes the controller use `celsius` instead of its own sensor, e.g. the reading of an
+    /// external sensor in sub-zero deployments
+    ///
+    /// Selects the external sensor and writes `celsius` to its temperature register (pointer 0,
+    /// whole degrees in the first byte). The controller picks its OTP waveform with this
+    /// temperature, the LUTs of this driver don't depend on it. Reset by the next init, i.e.
+    /// [`wake_up`](WaveshareDisplay::wake_up) from deep sleep.
+    pub fn set_external_temperature(
+        &mut self,
+        spi: &mut SPI,
+        celsius: i8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.cmd_with_data(spi, Command::TemperatureSensorCalibration, &[0x80])?;
+        // two bytes to pointer 0
+        self.cmd_with_data(
+            spi,
+            Command::TemperatureSensorWrite,
+            &[0x80, celsius as u8, 0x00],
+        )
+    }
+
+    /// Loads the given waveform tables instead of the built-in ones
+    ///
+    /// The tables stay loaded until the next [`set_lut`](WaveshareDisplay::set_lut) or init, so
+    /// load them again after waking up from deep sleep. The panel stays in the mode of the last
+    /// `set_lut`, tri-color unless [`RefreshLut::Quick`] was selected. With [`LutSource::Otp`] the
+    /// tri-color mode ignores them.
+    pub fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        lut: &LutSet,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.write_lut_tables(spi, &lut.vcom, &lut.ww, &lut.bw, &lut.wb, &lut.bb)
+    }
+
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.data(spi, data)
     }
 
-    fn send_buffer_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
+    fn send_buffer_helper(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         // Based on the waveshare implementation, all data for color values is flipped. This helper
         // method makes that transmission easier
-        for b in buffer.iter() {
-            self.send_data(spi, &[!b])?;
-        }
-        Ok(())
+        self.interface.data_inverted(spi, buffer)
     }
 
     fn cmd_with_data(
@@ -352,33 +613,133 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+    /// Waits with the delay of [`set_busy_delay`](Self::set_busy_delay) and the busy timeout if
+    /// there is one
+    fn wait_until_idle(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        match self.busy_delay.as_mut() {
+            Some(delay) => self.interface.wait_until_idle_with_timeout(
+                IS_BUSY_LOW,
+                delay,
+                self.busy_timeout_ms,
+            )?,
+            None => self.interface.wait_until_idle(IS_BUSY_LOW)?,
+        }
+        self.refreshing = false;
+        Ok(())
+    }
+
+    // sends the position and size of `window` for the partial commands
+    fn send_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let Region {
+            x,
+            y,
+            width,
+            height,
+        } = window.region();
+        for value in [x, y, width, height] {
+            self.send_data(spi, &[(value >> 8) as u8])?;
+            self.send_data(spi, &[value as u8])?;
+        }
+        Ok(())
+    }
+
+    /// Clears the second RAM: no chromatic pixels in the tri-color mode, whatever the background
+    /// color, and the background as the new frame in the black/white mode of the quick tables
+    fn second_plane_clear_value(&self) -> u8 {
+        match self.refresh {
+            RefreshLut::Full => 0x00,
+            RefreshLut::Quick => !self.color.get_byte_value(),
+        }
+    }
+
+    /// The quick tables only drive black and white, the old and new frame take the places of the
+    /// black and red frame. Bit 5 selects the LUT registers instead of the OTP waveform.
+    fn panel_setting(&self) -> u8 {
+        let panel_setting = match (self.refresh, self.config.lut_source()) {
+            (RefreshLut::Full, LutSource::Registers) => 0xaf,
+            (RefreshLut::Full, LutSource::Otp) => 0x8f,
+            (RefreshLut::Quick, _) => 0xbf,
+        };
+        self.config.mirror().apply(panel_setting)
+    }
+
+    fn write_luts(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let (vcom, ww, bw, wb, bb) = match (self.refresh, self.config.lut_source()) {
+            (RefreshLut::Full, LutSource::Otp) => return Ok(()),
+            (RefreshLut::Full, LutSource::Registers) => {
+                (&LUT_VCOM_DC, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB)
+            }
+            (RefreshLut::Quick, _) => (
+                &LUT_VCOM_DC_QUICK,
+                &LUT_WW_QUICK,
+                &LUT_BW_QUICK,
+                &LUT_WB_QUICK,
+                &LUT_BB_QUICK,
+            ),
+        };
+        self.write_lut_tables(spi, vcom, ww, bw, wb, bb)
+    }
+
+    fn write_lut_tables(
+        &mut self,
+        spi: &mut SPI,
+        vcom: &[u8],
+        ww: &[u8],
+        bw: &[u8],
+        wb: &[u8],
+        bb: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let percent = self.temperature.map_or(100, lut::frame_percent);
+        // The VCOM table starts with two bytes before its phases
+        self.cmd_with_data(
+            spi,
+            Command::LutForVcom,
+            &lut::stretch::<44>(vcom, 2, percent),
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::LutWhiteToWhite,
+            &lut::stretch::<42>(ww, 0, percent),
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::LutBlackToWhite,
+            &lut::stretch::<42>(bw, 0, percent),
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::LutWhiteToBlack,
+            &lut::stretch::<42>(wb, 0, percent),
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::LutBlackToBlack,
+            &lut::stretch::<42>(bb, 0, percent),
+        )
     }
 
     /// Refresh display for partial frame
+    ///
+    /// Only the window is refreshed, which flashes less and is faster than
+    /// [`display_frame`](WaveshareDisplay::display_frame) for small regions. Waits for a running
+    /// refresh first, the controller ignores the command otherwise.
     pub fn display_partial_frame(
         &mut self,
         spi: &mut SPI,
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.command(spi, Command::PartialDisplayRefresh)?;
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        self.send_data(spi, &[(x & 0xf8) as u8])?;
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[(y & 0xff) as u8])?;
-        self.send_data(spi, &[(width >> 8) as u8])?;
-        self.send_data(spi, &[(width & 0xf8) as u8])?;
-        self.send_data(spi, &[(height >> 8) as u8])?;
-        self.send_data(spi, &[(height & 0xff) as u8])?;
-        self.wait_until_idle();
+        self.send_partial_window(spi, window)?;
+        self.wait_until_idle()?;
         Ok(())
     }
 
@@ -387,29 +748,15 @@ where
         &mut self,
         spi: &mut SPI,
         achromatic: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface
             .cmd(spi, Command::PartialDataStartTransmission1)?;
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        self.send_data(spi, &[(x & 0xf8) as u8])?;
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[(y & 0xff) as u8])?;
-        self.send_data(spi, &[(width >> 8) as u8])?;
-        self.send_data(spi, &[(width & 0xf8) as u8])?;
-        self.send_data(spi, &[(height >> 8) as u8])?;
-        self.send_data(spi, &[(height & 0xff) as u8])?;
-        self.wait_until_idle();
-
-        for b in achromatic.iter() {
-            // Flipping based on waveshare implementation
-            self.send_data(spi, &[!b])?;
-        }
+        self.send_partial_window(spi, window)?;
+        self.wait_until_idle()?;
 
-        Ok(())
+        // Flipping based on waveshare implementation
+        self.send_buffer_helper(spi, achromatic)
     }
 
     /// Update partial chromatic/red frame
@@ -417,35 +764,421 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface
             .cmd(spi, Command::PartialDataStartTransmission2)?;
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        self.send_data(spi, &[(x & 0xf8) as u8])?;
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[(y & 0xff) as u8])?;
-        self.send_data(spi, &[(width >> 8) as u8])?;
-        self.send_data(spi, &[(width & 0xf8) as u8])?;
-        self.send_data(spi, &[(height >> 8) as u8])?;
-        self.send_data(spi, &[(height & 0xff) as u8])?;
-        self.wait_until_idle();
-
-        for b in chromatic.iter() {
-            // Flipping based on waveshare implementation
-            self.send_data(spi, &[!b])?;
+        self.send_partial_window(spi, window)?;
+        self.wait_until_idle()?;
+
+        // Flipping based on waveshare implementation
+        self.send_buffer_helper(spi, chromatic)
+    }
+
+    /// Writes a 4 gray frame with two bits per pixel, e.g. of a [`Display2in7Gray`]
+    ///
+    /// Shown by [`display_4gray_frame`](Self::display_4gray_frame), the red frame is overwritten.
+    pub fn update_4gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        // The high bits of the pixels go to the old, the low bits to the new frame
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.send_gray_plane(spi, buffer, 1)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.send_gray_plane(spi, buffer, 0)?;
+        self.interface.cmd(spi, Command::DataStop)
+    }
+
+    /// Refreshes the display with the 4 gray frame written by
+    /// [`update_4gray_frame`](Self::update_4gray_frame)
+    ///
+    /// Runs the gray waveform of the Waveshare reference code in the black/white mode of the
+    /// panel and restores the mode and LUT selected with [`set_lut`](WaveshareDisplay::set_lut)
+    /// afterwards.
+    pub fn display_4gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        let panel_setting = self.config.mirror().apply(0xbf);
+        self.cmd_with_data(spi, Command::PanelSetting, &[panel_setting])?;
+        // 100 Hz
+        self.cmd_with_data(spi, Command::PllControl, &[0x90])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x57])?;
+        self.write_lut_tables(
+            spi,
+            &LUT_VCOM_DC_GRAY,
+            &LUT_WW_GRAY,
+            &LUT_BW_GRAY,
+            &LUT_WB_GRAY,
+            &LUT_BB_GRAY,
+        )?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        let (_, frame_rate, interval) = profile_settings(self.config.profile());
+        let pll = self.config.frame_rate().unwrap_or(frame_rate).register();
+        self.cmd_with_data(spi, Command::PllControl, &[pll])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x80 | interval])?;
+        self.set_lut(spi, None)
+    }
+
+    fn send_gray_plane(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        bit: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let mut plane = [0; 64];
+        for pixels in buffer.chunks(2 * plane.len()) {
+            let len = pixels.len() / 2;
+            for (byte, pixels) in plane.iter_mut().zip(pixels.chunks_exact(2)) {
+                *byte = gray_plane_byte(pixels[0], pixels[1], bit);
+            }
+            self.send_data(spi, &plane[..len])?;
         }
+        Ok(())
+    }
+}
+
+/// Quick refreshes in the black/white mode of the panel
+///
+/// Select the quick tables with [`set_lut`](WaveshareDisplay::set_lut) and
+/// [`RefreshLut::Quick`] first, they switch the panel to black/white, and switch back with
+/// [`RefreshLut::Full`] before showing red again. The old frame goes to the RAM of the black
+/// frame, the new frame to the RAM of the red frame. The selected tables stay loaded across
+/// [`sleep`](WaveshareDisplay::sleep) and [`wake_up`](WaveshareDisplay::wake_up).
+impl<SPI, CS, BUSY, DC, RST, DELAY> QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.send_buffer_helper(spi, buffer)?;
+        self.interface.cmd(spi, Command::DataStop)
+    }
+
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.send_buffer_helper(spi, buffer)?;
+        self.interface.cmd(spi, Command::DataStop)
+    }
 
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.display_frame(spi, delay)
+    }
+
+    /// Writes `buffer` as the old frame of the next quick refresh afterwards
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        self.update_old_frame(spi, buffer, delay)
+    }
+
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.update_partial_achromatic_frame(spi, buffer, window)
+    }
+
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.update_partial_chromatic_frame(spi, buffer, window)
+    }
+
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+
+        // Flipped like the buffers
+        let color_value = !self.color.get_byte_value();
+        for (command, value) in [
+            (Command::PartialDataStartTransmission1, color_value),
+            (
+                Command::PartialDataStartTransmission2,
+                self.second_plane_clear_value(),
+            ),
+        ] {
+            self.interface.cmd(spi, command)?;
+            self.send_partial_window(spi, window)?;
+            self.interface
+                .data_x_times(spi, value, window.buffer_len() as u32)?;
+        }
         Ok(())
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> InterruptRefresh<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn start_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.command(spi, Command::DisplayRefresh)?;
+        self.refreshing = true;
+        Ok(())
+    }
+
+    fn on_busy_deasserted(&mut self) {
+        self.refreshing = false;
+    }
+
+    fn is_refreshing(&self) -> bool {
+        self.refreshing
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> FrameStream<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.cmd(spi, Command::DataStartTransmission1)
+    }
+
+    fn write_frame_chunk(
+        &mut self,
+        spi: &mut SPI,
+        chunk: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.send_buffer_helper(spi, chunk)
+    }
+
+    fn end_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        // Clear chromatic layer since we won't be using it here
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, self.second_plane_clear_value(), WIDTH * HEIGHT / 8)?;
+
+        self.interface.cmd(spi, Command::DataStop)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> DmaFrame<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn prepare_frame(&self, buffer: &mut [u8]) {
+        // Flipping based on waveshare implementation
+        for b in buffer.iter_mut() {
+            *b = !*b;
+        }
+    }
+
+    fn begin_frame_transfer(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.begin_frame(spi)?;
+        self.interface.begin_data()
+    }
+
+    fn finish_frame_transfer(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.end_data()?;
+        self.end_frame(spi)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    /// Reads back the VCOM value the controller is currently using
+    ///
+    /// The value has the same encoding as the VCOM_DC setting (`-0.1V - value * 0.05V`).
+    ///
+    /// Needs a readable data line, see [Requirements: SPI](crate#spi).
+    pub fn read_vcom_value(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<u8, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let mut buf = [0u8];
+        self.interface
+            .cmd_read(spi, Command::ReadVcomValue, &mut buf)?;
+        Ok(buf[0] & 0x3f)
+    }
+
+    /// Runs the automatic VCOM measurement of the controller and returns the result
+    ///
+    /// Powers the display on (also from [`standby`](Self::standby)) and blocks for the 5 seconds
+    /// of the measurement. The value has the encoding of [`read_vcom_value`](Self::read_vcom_value).
+    /// Wake the display up first after a deep sleep.
+    ///
+    /// Needs a readable data line, see [Requirements: SPI](crate#spi).
+    pub fn measure_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<u8, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        self.power_on(spi, delay)?;
+        // 5 seconds
+        self.cmd_with_data(spi, Command::AutoMeasurementVcom, &[0x11])?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.read_vcom_value(spi)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> ReadBack<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn read_status(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<Status, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let mut buf = [0u8];
+        self.interface.cmd_read(spi, Command::GetStatus, &mut buf)?;
+        Ok(Status::from(buf[0]))
+    }
+
+    /// Only the integer part of the measurement (upper byte of the sensor value)
+    fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<i8, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let mut buf = [0u8];
+        self.interface
+            .cmd_read(spi, Command::TemperatureSensor, &mut buf)?;
+        Ok(buf[0] as i8)
+    }
+
+    fn read_vcom(&mut self, spi: &mut SPI) -> Result<u8, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.read_vcom_value(spi)
+    }
+
+    /// Drops the dummy byte the controller sends first
+    fn read_otp(
+        &mut self,
+        spi: &mut SPI,
+        buf: &mut [u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.interface.cmd_read_skip(spi, Command::ReadOtp, 1, buf)
+    }
+
+    /// LVD_N in bit 0 is low below the threshold of 2.5V
+    fn is_supply_low(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<bool, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let mut buf = [0u8];
+        self.interface
+            .cmd_read(spi, Command::LowPowerDetection, &mut buf)?;
+        Ok(buf[0] & 0x01 == 0)
+    }
+}
+
+/// Picks `bit` of the eight 2 bit pixels in `high` and `low`
+fn gray_plane_byte(high: u8, low: u8, bit: u8) -> u8 {
+    let pixels = (u16::from(high) << 8) | u16::from(low);
+    (0..8).fold(0, |byte, i| {
+        let pixel = pixels >> (14 - 2 * i);
+        (byte << 1) | ((pixel >> bit) & 1) as u8
+    })
+}
+
+/// Booster soft start, frame rate and VCOM and data interval for the given profile
+///
+/// The interval is the lower nibble of the VCOM and data interval setting, 0x07 are 10 lines.
+fn profile_settings(profile: Profile) -> ([u8; 3], FrameRate, u8) {
+    match profile {
+        Profile::Default => ([0x07, 0x07, 0x17], FrameRate::Hz100, 0x07),
+        // weaker booster phase C
+        Profile::LowPower => ([0x07, 0x07, 0x07], FrameRate::Hz50, 0x07),
+        // stronger booster phase C, 8 lines between the frames
+        Profile::Fast => ([0x07, 0x07, 0x1f], FrameRate::Hz150, 0x09),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Error;
+    use crate::test_utils::{Expectations, MockNoop, Mocks, PinMock, SpiMock};
 
     #[test]
     fn epd_size() {
@@ -453,4 +1186,650 @@ mod tests {
         assert_eq!(HEIGHT, 264);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn default_profile_matches_waveshare() {
+        assert_eq!(
+            profile_settings(Profile::Default),
+            ([0x07, 0x07, 0x17], FrameRate::Hz100, 0x07)
+        );
+    }
+
+    fn expect_init(e: &mut Expectations) {
+        e.reset()
+            .cmd(Command::PowerOn as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::PanelSetting as u8, &[0xaf])
+            .cmd_with_data(Command::PllControl as u8, &[0x3a])
+            .cmd_with_data(Command::PowerSetting as u8, &[0x03, 0x00, 0x2b, 0x2b, 0x09])
+            .cmd_with_data(Command::BoosterSoftStart as u8, &[0x07, 0x07, 0x17])
+            .cmd_with_data(Command::PowerOptimization as u8, &[0x60, 0xa5])
+            .cmd_with_data(Command::PowerOptimization as u8, &[0x89, 0xa5])
+            .cmd_with_data(Command::PowerOptimization as u8, &[0x90, 0x00])
+            .cmd_with_data(Command::PowerOptimization as u8, &[0x93, 0x2a])
+            .cmd_with_data(Command::PowerOptimization as u8, &[0x73, 0x41])
+            .cmd_with_data(Command::VcmDcSetting as u8, &[0x12])
+            .cmd_with_data(Command::VcomAndDataIntervalSetting as u8, &[0x87])
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::LutForVcom as u8, &LUT_VCOM_DC)
+            .cmd_with_data(Command::LutWhiteToWhite as u8, &LUT_WW)
+            .cmd_with_data(Command::LutBlackToWhite as u8, &LUT_BW)
+            .cmd_with_data(Command::LutWhiteToBlack as u8, &LUT_WB)
+            .cmd_with_data(Command::LutBlackToBlack as u8, &LUT_BB)
+            .cmd_with_data(Command::PartialDisplayRefresh as u8, &[0x00])
+            .busy_idle(IS_BUSY_LOW);
+    }
+
+    fn epd(mocks: &mut Mocks) -> Epd2in7b<SpiMock, PinMock, PinMock, PinMock, PinMock, MockNoop> {
+        Epd2in7b::new(
+            &mut mocks.spi,
+            mocks.cs.clone(),
+            mocks.busy.clone(),
+            mocks.dc.clone(),
+            mocks.rst.clone(),
+            &mut mocks.delay,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn init_and_sleep() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::VcomAndDataIntervalSetting as u8, &[0xf7])
+            .cmd(Command::PowerOff as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::DeepSleep as u8, &[0xa5]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.sleep(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn update_and_display_frame() {
+        let buffer = [0xf0; WIDTH as usize * HEIGHT as usize / 8];
+
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.cmd(Command::DataStartTransmission1 as u8)
+            .data_inverted(&buffer)
+            .cmd(Command::DataStartTransmission2 as u8)
+            .data_x_times(0x00, WIDTH * HEIGHT / 8)
+            .cmd(Command::DataStop as u8)
+            .cmd(Command::DisplayRefresh as u8)
+            .busy_idle(IS_BUSY_LOW);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.update_frame(&mut mocks.spi, &buffer, &mut mocks.delay)
+            .unwrap();
+        epd.display_frame(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn busy_timeout() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.cmd(Command::DisplayRefresh as u8);
+        // Polled once more than the timeout allows
+        for _ in 0..4 {
+            e.busy(IS_BUSY_LOW);
+        }
+        e.cmd(Command::DisplayRefresh as u8)
+            .busy(IS_BUSY_LOW)
+            .busy_idle(IS_BUSY_LOW);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_busy_timeout(3);
+        assert_eq!(
+            epd.display_frame(&mut mocks.spi, &mut mocks.delay),
+            Err(Error::BusyTimeout)
+        );
+        epd.display_frame(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn busy_delay() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        for _ in 0..4 {
+            e.busy(IS_BUSY_LOW);
+        }
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_busy_timeout(3);
+        epd.set_busy_delay(MockNoop::new());
+        assert_eq!(epd.power_off(&mut mocks.spi), Err(Error::BusyTimeout));
+        mocks.done();
+    }
+
+    #[test]
+    fn poll_display_frame() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.cmd(Command::DisplayRefresh as u8)
+            .busy(IS_BUSY_LOW)
+            .busy(IS_BUSY_LOW)
+            .busy_idle(IS_BUSY_LOW);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        // Nothing to wait for
+        assert_eq!(epd.poll_display_frame(), Ok(()));
+
+        epd.start_display_frame(&mut mocks.spi, &mut mocks.delay)
+            .unwrap();
+        assert_eq!(epd.poll_display_frame(), Err(nb::Error::WouldBlock));
+        assert_eq!(epd.poll_display_frame(), Err(nb::Error::WouldBlock));
+        assert_eq!(epd.poll_display_frame(), Ok(()));
+        assert!(!epd.is_refreshing());
+        // Doesn't touch BUSY anymore
+        assert_eq!(epd.poll_display_frame(), Ok(()));
+        mocks.done();
+    }
+
+    #[test]
+    fn display_partial_frame() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd(Command::PartialDisplayRefresh as u8);
+        for byte in [0x00, 0x10, 0x01, 0x00, 0x00, 0x20, 0x00, 0x08] {
+            e.data(&[byte]);
+        }
+        e.busy_idle(IS_BUSY_LOW);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        let window = PartialWindow::new(Region::new(0x10, 0x100, 0x20, 8), WIDTH, HEIGHT).unwrap();
+        epd.display_partial_frame(&mut mocks.spi, window).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn standby_sleep_mode() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd(Command::PowerOff as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::PowerOn as u8)
+            .busy_idle(IS_BUSY_LOW);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_sleep_mode(SleepMode::Standby);
+        epd.sleep(&mut mocks.spi, &mut mocks.delay).unwrap();
+        epd.wake_up(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn wake_up_from_standby() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd(Command::PowerOff as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::PowerOn as u8)
+            .busy_idle(IS_BUSY_LOW);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.standby(&mut mocks.spi).unwrap();
+        epd.wake_up(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn read_vcom_value() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_read(Command::ReadVcomValue as u8, &[0x12]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        assert_eq!(epd.read_vcom_value(&mut mocks.spi).unwrap(), 0x12);
+        mocks.done();
+    }
+
+    #[test]
+    fn measure_vcom() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd(Command::PowerOn as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::AutoMeasurementVcom as u8, &[0x11])
+            .busy_idle(IS_BUSY_LOW)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_read(Command::ReadVcomValue as u8, &[0x52]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        assert_eq!(
+            epd.measure_vcom(&mut mocks.spi, &mut mocks.delay).unwrap(),
+            0x12
+        );
+        mocks.done();
+    }
+
+    #[test]
+    fn set_vcom() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::VcmDcSetting as u8, &[0x17]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        let vcom_dc = VcomDc::from_millivolts(-1250).unwrap();
+        epd.set_vcom(&mut mocks.spi, vcom_dc).unwrap();
+        mocks.done();
+        assert_eq!(epd.vcom_dc, vcom_dc);
+    }
+
+    #[test]
+    fn external_temperature() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::TemperatureSensorCalibration as u8, &[0x80])
+            .cmd_with_data(Command::TemperatureSensorWrite as u8, &[0x80, 0xf6, 0x00]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_external_temperature(&mut mocks.spi, -10).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn dma_frame() {
+        use crate::test_utils::{IdleBusyPin, NoopPin, Recorder};
+        use embedded_hal::blocking::spi::Write as _;
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        let mut buffer = [0x00; WIDTH as usize * HEIGHT as usize / 8];
+        buffer[..4].copy_from_slice(&[0xf0, 0x0f, 0xaa, 0x55]);
+
+        recorder.take_events();
+        epd.update_frame(&mut spi, &buffer, &mut delay).unwrap();
+        let expected = recorder.take_events();
+
+        // The prepared buffer goes out in one write, bypassing the driver
+        epd.prepare_frame(&mut buffer);
+        epd.begin_frame_transfer(&mut spi).unwrap();
+        spi.write(&buffer).unwrap();
+        epd.finish_frame_transfer(&mut spi).unwrap();
+        assert_eq!(recorder.take_events(), expected);
+    }
+
+    #[test]
+    fn raw_commands() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.cmd(Command::PowerOptimization as u8).data(&[0x60, 0xa5]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.send_command_raw(&mut mocks.spi, 0xf8).unwrap();
+        epd.send_data_raw(&mut mocks.spi, &[0x60, 0xa5]).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn clear_frame() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DataStartTransmission1 as u8)
+            .data_x_times(0x00, WIDTH * HEIGHT / 8)
+            .cmd(Command::DataStop as u8)
+            .cmd(Command::DataStartTransmission2 as u8)
+            .data_x_times(0x00, WIDTH * HEIGHT / 8)
+            .cmd(Command::DataStop as u8);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.clear_frame(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn clear_frame_black() {
+        use crate::test_utils::{Controller, IdleBusyPin, NoopPin, Recorder, VirtualPanel};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        epd.set_background_color(Color::Black);
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+
+        let mut panel = VirtualPanel::new(Controller::Il91874, WIDTH, HEIGHT);
+        panel.feed(&recorder.take_events()[..]);
+        assert_eq!(panel.refreshes(), 1);
+        for (x, y) in [(0, 0), (WIDTH - 1, HEIGHT - 1)] {
+            assert_eq!(panel.pixel(x, y), Color::Black);
+            assert!(!panel.chromatic(x, y));
+        }
+
+        // Clearing a window to white leaves the rest black, nothing turns red either
+        epd.set_background_color(Color::White);
+        let window = PartialWindow::new(Region::new(8, 8, 16, 8), WIDTH, HEIGHT).unwrap();
+        epd.clear_partial_frame(&mut spi, window).unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        panel.feed(&recorder.take_events()[..]);
+        assert_eq!(panel.pixel(8, 8), Color::White);
+        assert_eq!(panel.pixel(7, 8), Color::Black);
+        assert!(!panel.chromatic(8, 8));
+        assert!(!panel.chromatic(7, 8));
+    }
+
+    #[test]
+    fn quick_refresh() {
+        let old = [0xff; WIDTH as usize * HEIGHT as usize / 8];
+        let new = [0xf0; WIDTH as usize * HEIGHT as usize / 8];
+
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::PanelSetting as u8, &[0xbf])
+            .cmd_with_data(Command::LutForVcom as u8, &LUT_VCOM_DC_QUICK)
+            .cmd_with_data(Command::LutWhiteToWhite as u8, &LUT_WW_QUICK)
+            .cmd_with_data(Command::LutBlackToWhite as u8, &LUT_BW_QUICK)
+            .cmd_with_data(Command::LutWhiteToBlack as u8, &LUT_WB_QUICK)
+            .cmd_with_data(Command::LutBlackToBlack as u8, &LUT_BB_QUICK)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DataStartTransmission1 as u8)
+            .data_inverted(&old)
+            .cmd(Command::DataStop as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DataStartTransmission2 as u8)
+            .data_inverted(&new)
+            .cmd(Command::DataStop as u8)
+            .cmd(Command::DisplayRefresh as u8)
+            .busy_idle(IS_BUSY_LOW)
+            // The new frame is the old one of the next quick refresh
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DataStartTransmission1 as u8)
+            .data_inverted(&new)
+            .cmd(Command::DataStop as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::PanelSetting as u8, &[0xaf])
+            .cmd_with_data(Command::LutForVcom as u8, &LUT_VCOM_DC)
+            .cmd_with_data(Command::LutWhiteToWhite as u8, &LUT_WW)
+            .cmd_with_data(Command::LutBlackToWhite as u8, &LUT_BW)
+            .cmd_with_data(Command::LutWhiteToBlack as u8, &LUT_WB)
+            .cmd_with_data(Command::LutBlackToBlack as u8, &LUT_BB);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_lut(&mut mocks.spi, Some(RefreshLut::Quick))
+            .unwrap();
+        epd.update_old_frame(&mut mocks.spi, &old, &mut mocks.delay)
+            .unwrap();
+        epd.update_and_display_new_frame(&mut mocks.spi, &new, &mut mocks.delay)
+            .unwrap();
+        epd.set_lut(&mut mocks.spi, Some(RefreshLut::Full)).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn clear_partial_frame() {
+        let window = [0x00, 0x10, 0x00, 0x20, 0x00, 0x18, 0x00, 0x08];
+
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW);
+        for command in [
+            Command::PartialDataStartTransmission1,
+            Command::PartialDataStartTransmission2,
+        ] {
+            e.cmd(command as u8);
+            for byte in window {
+                e.data(&[byte]);
+            }
+            e.data_x_times(0x00, 24);
+        }
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        let window = PartialWindow::new(Region::new(16, 32, 24, 8), WIDTH, HEIGHT).unwrap();
+        epd.clear_partial_frame(&mut mocks.spi, window).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn refresh_lut_survives_sleep() {
+        use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        recorder.take_events();
+
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        let events = recorder.take_events();
+        let after = |command: Command| {
+            let i = events
+                .iter()
+                .position(|e| *e == Event::Command(command as u8))
+                .unwrap();
+            events[i + 1].clone()
+        };
+        assert_eq!(after(Command::PanelSetting), Event::Data([0xbf].to_vec()));
+        assert_eq!(
+            after(Command::LutForVcom),
+            Event::Data(LUT_VCOM_DC_QUICK.to_vec())
+        );
+
+        // Without an argument the selected tables are loaded again
+        epd.set_lut(&mut spi, None).unwrap();
+        let events = recorder.take_events();
+        assert_eq!(events[1], Event::Data([0xbf].to_vec()));
+        assert_eq!(events[3], Event::Data(LUT_VCOM_DC_QUICK.to_vec()));
+    }
+
+    #[test]
+    fn custom_lut() {
+        let lut = LutSet {
+            vcom: [0x01; 44],
+            ww: [0x02; 42],
+            bw: [0x03; 42],
+            wb: [0x04; 42],
+            bb: [0x05; 42],
+        };
+
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::LutForVcom as u8, &lut.vcom)
+            .cmd_with_data(Command::LutWhiteToWhite as u8, &lut.ww)
+            .cmd_with_data(Command::LutBlackToWhite as u8, &lut.bw)
+            .cmd_with_data(Command::LutWhiteToBlack as u8, &lut.wb)
+            .cmd_with_data(Command::LutBlackToBlack as u8, &lut.bb);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_custom_lut(&mut mocks.spi, &lut).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn otp_lut() {
+        use crate::config::Builder;
+        use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let config = Builder::new().lut_source(LutSource::Otp).build();
+        let mut epd = Epd2in7b::new_with_config(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+            config,
+        )
+        .unwrap();
+        let events = recorder.take_events();
+        let panel_setting = Event::Command(Command::PanelSetting as u8);
+        let i = events.iter().position(|e| *e == panel_setting).unwrap();
+        assert_eq!(events[i + 1], Event::Data([0x8f].to_vec()));
+        assert!(!events.contains(&Event::Command(Command::LutForVcom as u8)));
+
+        // Quick refreshes still need the tables
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        let events = recorder.take_events();
+        assert_eq!(events[1], Event::Data([0xbf].to_vec()));
+        assert_eq!(events[3], Event::Data(LUT_VCOM_DC_QUICK.to_vec()));
+
+        epd.set_lut(&mut spi, Some(RefreshLut::Full)).unwrap();
+        assert_eq!(
+            recorder.take_events(),
+            [panel_setting, Event::Data([0x8f].to_vec())]
+        );
+    }
+
+    #[test]
+    fn gray_planes() {
+        // White, light gray, dark gray, black
+        assert_eq!(
+            gray_plane_byte(0b11_10_01_00, 0b11_10_01_00, 1),
+            0b1100_1100
+        );
+        assert_eq!(
+            gray_plane_byte(0b11_10_01_00, 0b11_10_01_00, 0),
+            0b1010_1010
+        );
+    }
+
+    #[test]
+    fn gray_frame() {
+        use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+
+        let buffer = [0b11_10_01_00; WIDTH as usize * HEIGHT as usize / 4];
+        epd.update_4gray_frame(&mut spi, &buffer, &mut delay)
+            .unwrap();
+        let plane = |byte| Event::Data([byte; WIDTH as usize * HEIGHT as usize / 8].to_vec());
+        assert_eq!(
+            recorder.take_events(),
+            [
+                Event::Command(Command::DataStartTransmission1 as u8),
+                plane(0b1100_1100),
+                Event::Command(Command::DataStartTransmission2 as u8),
+                plane(0b1010_1010),
+                Event::Command(Command::DataStop as u8),
+            ]
+        );
+
+        epd.display_4gray_frame(&mut spi, &mut delay).unwrap();
+        let events = recorder.take_events();
+        assert_eq!(events[1], Event::Data([0xbf].to_vec()));
+        assert_eq!(events[7], Event::Data(LUT_VCOM_DC_GRAY.to_vec()));
+        assert_eq!(events[16], Event::Command(Command::DisplayRefresh as u8));
+        // Back to the tri-color mode
+        assert_eq!(
+            events[events.len() - 12..events.len() - 10],
+            [
+                Event::Command(Command::PanelSetting as u8),
+                Event::Data([0xaf].to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn cold_luts() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::PanelSetting as u8, &[0xaf])
+            .cmd_with_data(
+                Command::LutForVcom as u8,
+                &lut::stretch::<44>(&LUT_VCOM_DC, 2, 200),
+            )
+            .cmd_with_data(
+                Command::LutWhiteToWhite as u8,
+                &lut::stretch::<42>(&LUT_WW, 0, 200),
+            )
+            .cmd_with_data(
+                Command::LutBlackToWhite as u8,
+                &lut::stretch::<42>(&LUT_BW, 0, 200),
+            )
+            .cmd_with_data(
+                Command::LutWhiteToBlack as u8,
+                &lut::stretch::<42>(&LUT_WB, 0, 200),
+            )
+            .cmd_with_data(
+                Command::LutBlackToBlack as u8,
+                &lut::stretch::<42>(&LUT_BB, 0, 200),
+            );
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_temperature(Some(-5));
+        epd.set_lut(&mut mocks.spi, None).unwrap();
+        mocks.done();
+        // The first phase of the VCOM table
+        assert_eq!(
+            lut::stretch::<44>(&LUT_VCOM_DC, 2, 200)[..8],
+            [0x00, 0x00, 0x00, 0x34, 0x34, 0x00, 0x00, 0x01]
+        );
+    }
 }
@@ -120,7 +120,9 @@ pub(crate) enum Command {
     ///
     /// Doesn't work! Waveshare doesn't connect the read pin
     GetStatus = 0x71,
-    /// Automatically measure VCOM. This command reads the IC status
+    /// Automatically measure VCOM, BUSY is low until the measurement is done.
+    ///
+    /// Bit 0 starts the measurement, bits 5:4 select its duration of 3, 5, 8 or 10 seconds.
     AutoMeasurementVcom = 0x80,
     /// This command gets the VCOM value
     ///
@@ -148,10 +150,15 @@ pub(crate) enum Command {
     ///
     /// The value of (n) is depending on the amount of programmed data, tha max address = 0xFFF.
     ReadOtp = 0xA2,
+    /// Cascade setting, TSFIX (bit 1) makes the controller use the temperature of
+    /// [`ForceTemperature`](Command::ForceTemperature) instead of its sensor
+    CascadeSetting = 0xE0,
     /// This command is set for saving power during fresh period. If the output voltage of VCOM / Source is from negative to positive or
     /// from positive to negative, the power saving mechanism will be activated. The active period width is defined by the following two
     /// parameters.
     PowerSaving = 0xE3,
+    /// The temperature used with TSFIX of the [`CascadeSetting`](Command::CascadeSetting)
+    ForceTemperature = 0xE5,
 }
 
 impl traits::Command for Command {
@@ -9,7 +9,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::error::DriverError<spi::Mock, pin::Mock, pin::Mock, pin::Mock, pin::Mock>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -50,12 +50,22 @@
 //! BE CAREFUL! The screen can get ghosting/burn-ins through the Partial Fast Update Drawing.
 
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::config::{Config, FrameRate, Profile, ResetTiming, VcomDc};
+use crate::error::DriverError;
+use crate::interface::{DelayMsExt, DisplayInterface, TrafficFn};
+use crate::lut;
+use crate::region::Region;
+use crate::traits::{
+    DmaFrame, FrameStream, InternalWiAdditions, InterruptRefresh, LutSet, PartialWindow,
+    QuickRefresh, ReadBack, RefreshLut, SleepMode, Status, Timing, WaveshareDisplay,
+};
 
 //The Lookup Tables for the Display
 mod constants;
@@ -67,9 +77,26 @@ pub const WIDTH: u32 = 400;
 pub const HEIGHT: u32 = 300;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Maximum SPI clock of the IL0398 controller in Hz
+pub const MAX_SPI_HZ: u32 = 10_000_000;
+/// Typical durations of the display
+pub const TIMING: Timing = Timing {
+    full_refresh_ms: 4000,
+    partial_refresh_ms: Some(800),
+    wake_ms: 300,
+};
+/// Default timing of the hardware reset, see [`Builder::reset_timing`](crate::config::Builder::reset_timing)
+pub const RESET_TIMING: ResetTiming = ResetTiming {
+    low_ms: 10,
+    settle_ms: 200,
+};
+/// Default longest wait for BUSY, twice a full refresh
+pub const BUSY_TIMEOUT_MS: u32 = 2 * TIMING.full_refresh_ms;
+/// Default VCOM DC voltage of -1.0 V, see [`Builder::vcom_dc`](crate::config::Builder::vcom_dc)
+pub const VCOM_DC: VcomDc = VcomDc::from_register(0x12);
 const IS_BUSY_LOW: bool = true;
 
-use crate::color::Color;
+use crate::color::{BorderColor, Color};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -83,11 +110,32 @@ pub use self::graphics::Display4in2;
 ///
 pub struct Epd4in2<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, TrafficFn>,
     /// Background Color
     color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// VCOM DC voltage, see [`set_vcom`](Self::set_vcom)
+    vcom_dc: VcomDc,
+    /// Outer border, see [`set_border`](Self::set_border)
+    border: BorderColor,
+    /// Quick refreshes since the last full one, see [`quick_refresh_count`](Self::quick_refresh_count)
+    quick_refreshes: u32,
+    /// Temperature the waveforms are stretched for, see [`WaveshareDisplay::set_temperature`]
+    temperature: Option<i8>,
+    /// Init configuration
+    config: Config,
+    /// What [`sleep`](WaveshareDisplay::sleep) does, see [`set_sleep_mode`](Self::set_sleep_mode)
+    sleep_mode: SleepMode,
+    /// Only powered off by [`standby`](Self::standby), RAM and registers are still valid
+    standby: bool,
+    /// A refresh was started by [`start_display_frame`](InterruptRefresh::start_display_frame)
+    /// and BUSY wasn't seen idle since
+    refreshing: bool,
+    /// Longest wait for BUSY before giving up with [`Error::BusyTimeout`](crate::error::Error::BusyTimeout)
+    busy_timeout_ms: u32,
+    /// Delay for the waits of the methods without one, see [`set_busy_delay`](Self::set_busy_delay)
+    busy_delay: Option<DELAY>,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -98,11 +146,16 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         // reset the device
-        self.interface.reset(delay, 10);
+        let reset_timing = self.config.reset_timing().unwrap_or(RESET_TIMING);
+        self.interface.reset_with_timing(delay, reset_timing)?;
 
         // set the power settings
         self.interface.cmd_with_data(
@@ -111,37 +164,40 @@ where
             &[0x03, 0x00, 0x2b, 0x2b, 0xff],
         )?;
 
+        let (booster, frame_rate, _) = profile_settings(self.config.profile());
+        let pll = self.config.frame_rate().unwrap_or(frame_rate).register();
+
         // start the booster
         self.interface
-            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
+            .cmd_with_data(spi, Command::BoosterSoftStart, &booster)?;
 
         // power on
         self.command(spi, Command::PowerOn)?;
         delay.delay_ms(5);
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         // set the panel settings
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x3F])?;
+        let panel_setting = self.config.mirror().apply(0x3F);
+        self.cmd_with_data(spi, Command::PanelSetting, &[panel_setting])?;
 
-        // Set Frequency, 200 Hz didn't work on my board
-        // 150Hz and 171Hz wasn't tested yet
-        // TODO: Test these other frequencies
-        // 3A 100HZ   29 150Hz 39 200HZ  31 171HZ DEFAULT: 3c 50Hz
-        self.cmd_with_data(spi, Command::PllControl, &[0x3A])?;
+        // Set the frame rate, see FrameRate for the supported ones
+        self.cmd_with_data(spi, Command::PllControl, &[pll])?;
 
         self.send_resolution(spi)?;
 
         self.interface
-            .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+            .cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom_dc.register()])?;
 
         //VBDF 17|D7 VBDW 97  VBDB 57  VBDF F7  VBDW 77  VBDB 37  VBDR B7
-        self.interface
-            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x97])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_and_data_interval(self.border)],
+        )?;
 
         self.set_lut(spi, None)?;
 
-        self.wait_until_idle();
-        Ok(())
+        self.wait_until_idle_with_timeout(delay)
     }
 }
 
@@ -153,9 +209,11 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+    const MAX_SPI_HZ: u32 = MAX_SPI_HZ;
+    const TIMING: Timing = TIMING;
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -163,27 +221,32 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd4in2 {
-            interface,
-            color,
-            refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
+        Self::new_with_config(spi, cs, busy, dc, rst, delay, Config::default())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.init(spi, delay)
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        if self.standby {
+            // RAM and LUTs survived, powering on is enough
+            self.power_on(spi, delay)
+        } else {
+            self.init(spi, delay)
+        }
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        if self.sleep_mode == SleepMode::Standby {
+            return self.standby(spi);
+        }
+        self.wait_until_idle_with_timeout(delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
         self.command(spi, Command::VcmDcSetting)?; // VCOM to 0V
@@ -195,9 +258,11 @@ where
         }
 
         self.command(spi, Command::PowerOff)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_asleep();
+        self.standby = false;
         Ok(())
     }
 
@@ -206,50 +271,27 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        let color_value = self.color.get_byte_value();
-
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.interface
-            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
-
-        self.interface
-            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
-        Ok(())
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.begin_frame(spi)?;
+        self.write_frame_chunk(spi, buffer)?;
+        self.end_frame(spi)
     }
 
-    fn update_partial_frame(
+    fn update_partial_window(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        if buffer.len() as u32 != width / 8 * height {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        if buffer.len() != window.buffer_len() {
             //TODO: panic!! or sth like that
             //return Err("Wrong buffersize");
         }
 
         self.command(spi, Command::PartialIn)?;
         self.command(spi, Command::PartialWindow)?;
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        let tmp = x & 0xf8;
-        self.send_data(spi, &[tmp as u8])?; // x should be the multiple of 8, the last 3 bit will always be ignored
-        let tmp = tmp + width - 1;
-        self.send_data(spi, &[(tmp >> 8) as u8])?;
-        self.send_data(spi, &[(tmp | 0x07) as u8])?;
-
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[y as u8])?;
-
-        self.send_data(spi, &[((y + height - 1) >> 8) as u8])?;
-        self.send_data(spi, &[(y + height - 1) as u8])?;
-
-        self.send_data(spi, &[0x01])?; // Gates scan both inside and outside of the partial window. (default)
+        self.shift_display(spi, window)?;
 
         //TODO: handle dtm somehow
         let is_dtm1 = false;
@@ -265,8 +307,23 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        match self.refresh {
+            RefreshLut::Full => self.quick_refreshes = 0,
+            RefreshLut::Quick => {
+                self.quick_refreshes += 1;
+                if let Some(interval) = self.config.full_refresh_interval() {
+                    if self.quick_refreshes >= interval {
+                        return self.full_refresh(spi, delay);
+                    }
+                }
+            }
+        }
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
     }
@@ -276,14 +333,17 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_frame(spi, buffer, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
-        Ok(())
+        self.display_frame(spi, delay)
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
         self.send_resolution(spi)?;
 
         let color_value = self.color.get_byte_value();
@@ -318,7 +378,7 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
@@ -340,6 +400,56 @@ where
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn set_temperature(&mut self, temperature: Option<i8>) {
+        self.temperature = temperature;
+    }
+
+    fn set_busy_yield(&mut self, busy_yield: Option<fn()>) {
+        self.interface.set_busy_yield(busy_yield);
+    }
+
+    fn wait_until_idle_or_cancel<F>(
+        &mut self,
+        delay: &mut DELAY,
+        should_cancel: F,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        F: FnMut() -> bool,
+    {
+        self.interface.wait_until_idle_or_cancel(
+            IS_BUSY_LOW,
+            delay,
+            self.busy_timeout_ms,
+            should_cancel,
+        )?;
+        self.refreshing = false;
+        Ok(())
+    }
+
+    fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.interface.set_max_chunk_size(max_chunk_size);
+    }
+
+    fn set_traffic_hook(&mut self, traffic_hook: Option<TrafficFn>) {
+        self.interface.set_traffic_hook(traffic_hook);
+    }
+
+    fn send_command_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, data)
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>
@@ -349,13 +459,189 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Creates a new driver like [`WaveshareDisplay::new`], but applies the given [`Config`] during init
+    pub fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: Config,
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd4in2 {
+            interface,
+            color,
+            refresh: RefreshLut::Full,
+            temperature: None,
+            vcom_dc: config.vcom_dc().unwrap_or(VCOM_DC),
+            border: BorderColor::default(),
+            quick_refreshes: 0,
+            config,
+            sleep_mode: SleepMode::Deep,
+            standby: false,
+            refreshing: false,
+            busy_timeout_ms: BUSY_TIMEOUT_MS,
+            busy_delay: None,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    /// Powers the display off without entering deep sleep
+    ///
+    /// Unlike [`sleep`](WaveshareDisplay::sleep) the controller keeps its RAM and registers, so the
+    /// next [`wake_up`](WaveshareDisplay::wake_up) only needs to power it on again instead of
+    /// running the whole init and LUT upload. This draws slightly more current than deep sleep.
+    pub fn standby(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.power_off(spi)
+    }
+
+    /// Turns the charge pumps off, the image stays at almost no current
+    ///
+    /// RAM and registers are kept, after [`power_on`](Self::power_on) the next (partial) update
+    /// can follow without the init and LUT upload of [`wake_up`](WaveshareDisplay::wake_up).
+    pub fn power_off(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle()?;
+        self.standby = true;
+        Ok(())
+    }
+
+    /// Turns the charge pumps on again after [`power_off`](Self::power_off) or
+    /// [`standby`](Self::standby)
+    pub fn power_on(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.standby = false;
+        self.command(spi, Command::PowerOn)?;
+        delay.delay_ms(5);
+        self.wait_until_idle_with_timeout(delay)
+    }
+
+    /// Selects if [`sleep`](WaveshareDisplay::sleep) enters deep sleep (the default) or only
+    /// [`standby`](Self::standby)
+    ///
+    /// Devices updating every few seconds save the init and LUT upload of each wake up with
+    /// [`SleepMode::Standby`].
+    pub fn set_sleep_mode(&mut self, sleep_mode: SleepMode) {
+        self.sleep_mode = sleep_mode;
+    }
+
+    /// Sets the VCOM DC voltage, e.g. to the one printed on the FPC of the panel
+    ///
+    /// Matching it improves the contrast. Kept for the next init, replacing the voltage of the
+    /// [`Config`] or [`VCOM_DC`].
+    pub fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        vcom_dc: VcomDc,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.vcom_dc = vcom_dc;
+        self.wait_until_idle()?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[vcom_dc.register()])
+    }
+
+    /// Sets the longest wait for BUSY before giving up with [`Error::BusyTimeout`](crate::error::Error::BusyTimeout)
+    ///
+    /// The methods taking a delay wait with this timeout, the others once they have a delay from
+    /// [`set_busy_delay`](Self::set_busy_delay). Defaults to [`BUSY_TIMEOUT_MS`].
+    pub fn set_busy_timeout(&mut self, timeout_ms: u32) {
+        self.busy_timeout_ms = timeout_ms;
+    }
+
+    /// Gives the driver a delay of its own to time the BUSY waits of the methods without a delay
+    /// argument, like [`update_partial_window`](WaveshareDisplay::update_partial_window),
+    /// [`set_lut`](WaveshareDisplay::set_lut) or [`power_off`](Self::power_off)
+    ///
+    /// Without it those methods wait until BUSY is released, however long that takes.
+    pub fn set_busy_delay(&mut self, delay: DELAY) {
+        self.busy_delay = Some(delay);
+    }
+
+    /// Quick refreshes since the last full refresh
+    ///
+    /// With [`Builder::full_refresh_interval`](crate::config::Builder::full_refresh_interval)
+    /// [`display_frame`](WaveshareDisplay::display_frame) turns the quick refresh reaching the
+    /// interval into a blocking full refresh with the full LUT, and starts counting again.
+    pub fn quick_refresh_count(&self) -> u32 {
+        self.quick_refreshes
+    }
+
+    /// Starts counting the quick refreshes from 0 again, e.g. after clearing the ghosting with
+    /// other means
+    pub fn reset_quick_refresh_count(&mut self) {
+        self.quick_refreshes = 0;
+    }
+
+    /// Sets the outer border of the display, kept for the next init
+    ///
+    /// The panel has no chromatic color, [`BorderColor::Chromatic`] shows white.
+    pub fn set_border(
+        &mut self,
+        spi: &mut SPI,
+        border: BorderColor,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.border = border;
+        self.wait_until_idle()?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_and_data_interval(border)],
+        )
+    }
+
+    /// Makes the controller use `celsius` instead of its own sensor, e.g. the reading of an
+    /// external sensor in sub-zero deployments
+    ///
+    /// The controller picks its OTP waveform with this temperature, the LUTs of this driver don't
+    /// depend on it. Reset by the next init, i.e. [`wake_up`](WaveshareDisplay::wake_up) from deep
+    /// sleep.
+    pub fn set_external_temperature(
+        &mut self,
+        spi: &mut SPI,
+        celsius: i8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])?;
+        self.cmd_with_data(spi, Command::ForceTemperature, &[celsius as u8])
+    }
+
+    /// Loads the given waveform tables instead of the built-in ones
+    ///
+    /// The tables stay loaded until the next [`set_lut`](WaveshareDisplay::set_lut) or init, so
+    /// load them again after waking up from deep sleep.
+    pub fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        lut: &LutSet,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.set_lut_helper(spi, &lut.vcom, &lut.ww, &lut.bw, &lut.wb, &lut.bb)
+    }
+
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.data(spi, data)
     }
 
@@ -364,15 +650,35 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+    /// Waits with the delay of [`set_busy_delay`](Self::set_busy_delay) and the busy timeout if
+    /// there is one
+    fn wait_until_idle(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        match self.busy_delay.as_mut() {
+            Some(delay) => self.interface.wait_until_idle_with_timeout(
+                IS_BUSY_LOW,
+                delay,
+                self.busy_timeout_ms,
+            )?,
+            None => self.interface.wait_until_idle(IS_BUSY_LOW)?,
+        }
+        self.refreshing = false;
+        Ok(())
+    }
+
+    /// The VCOM and data interval setting with `border` and the interval of the profile
+    fn vcom_and_data_interval(&self, border: BorderColor) -> u8 {
+        let (_, _, interval) = profile_settings(self.config.profile());
+        border_setting(border) | interval
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         let w = self.width();
         let h = self.height();
 
@@ -383,6 +689,19 @@ where
         self.send_data(spi, &[h as u8])
     }
 
+    /// Refreshes with the full LUT and waits for it, then selects the quick LUT again
+    fn full_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.set_lut_helper(spi, &LUT_VCOM0, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.quick_refreshes = 0;
+        self.set_lut(spi, None)
+    }
+
     fn set_lut_helper(
         &mut self,
         spi: &mut SPI,
@@ -391,41 +710,49 @@ where
         lut_bw: &[u8],
         lut_wb: &[u8],
         lut_bb: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let percent = self.temperature.map_or(100, lut::frame_percent);
         // LUT VCOM
-        self.cmd_with_data(spi, Command::LutForVcom, lut_vcom)?;
+        let lut_vcom = lut::stretch::<44>(lut_vcom, 0, percent);
+        self.cmd_with_data(spi, Command::LutForVcom, &lut_vcom)?;
 
         // LUT WHITE to WHITE
-        self.cmd_with_data(spi, Command::LutWhiteToWhite, lut_ww)?;
+        let lut_ww = lut::stretch::<42>(lut_ww, 0, percent);
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, &lut_ww)?;
 
         // LUT BLACK to WHITE
-        self.cmd_with_data(spi, Command::LutBlackToWhite, lut_bw)?;
+        let lut_bw = lut::stretch::<42>(lut_bw, 0, percent);
+        self.cmd_with_data(spi, Command::LutBlackToWhite, &lut_bw)?;
 
         // LUT WHITE to BLACK
-        self.cmd_with_data(spi, Command::LutWhiteToBlack, lut_wb)?;
+        let lut_wb = lut::stretch::<42>(lut_wb, 0, percent);
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, &lut_wb)?;
 
         // LUT BLACK to BLACK
-        self.cmd_with_data(spi, Command::LutBlackToBlack, lut_bb)?;
+        let lut_bb = lut::stretch::<42>(lut_bb, 0, percent);
+        self.cmd_with_data(spi, Command::LutBlackToBlack, &lut_bb)?;
         Ok(())
     }
 
-    /// Helper function. Sets up the display to send pixel data to a custom
-    /// starting point.
+    /// Helper function. Sets up the display to send pixel data to `window`.
     pub fn shift_display(
         &mut self,
         spi: &mut SPI,
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let Region {
+            x,
+            y,
+            width,
+            height,
+        } = window.region();
+        // x and width are multiples of 8 for a window
         self.send_data(spi, &[(x >> 8) as u8])?;
-        let tmp = x & 0xf8;
-        self.send_data(spi, &[tmp as u8])?; // x should be the multiple of 8, the last 3 bit will always be ignored
-        let tmp = tmp + width - 1;
-        self.send_data(spi, &[(tmp >> 8) as u8])?;
-        self.send_data(spi, &[(tmp | 0x07) as u8])?;
+        self.send_data(spi, &[x as u8])?;
+        let x_end = x + width - 1;
+        self.send_data(spi, &[(x_end >> 8) as u8])?;
+        self.send_data(spi, &[x_end as u8])?;
 
         self.send_data(spi, &[(y >> 8) as u8])?;
         self.send_data(spi, &[y as u8])?;
@@ -447,16 +774,16 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     /// To be followed immediately after by `update_old_frame`.
     fn update_old_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
@@ -470,9 +797,9 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
         // self.send_resolution(spi)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
@@ -484,36 +811,40 @@ where
 
     /// This is a wrapper around `display_frame` for using this device as a true
     /// `QuickRefresh` device.
-    fn display_new_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.display_frame(spi, delay)
     }
 
     /// This is wrapper around `update_new_frame` and `display_frame` for using
     /// this device as a true `QuickRefresh` device.
     ///
-    /// To be used immediately after `update_old_frame`.
+    /// To be used immediately after `update_old_frame`. Writes `buffer` as the old frame of the
+    /// next quick refresh once the refresh finished, so a sequence of quick refreshes only needs
+    /// this method.
     fn update_and_display_new_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         self.update_new_frame(spi, buffer, delay)?;
-        self.display_frame(spi, delay)
+        self.display_frame(spi, delay)?;
+        self.update_old_frame(spi, buffer, delay)
     }
 
     fn update_partial_old_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-
-        if buffer.len() as u32 != width / 8 * height {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+
+        if buffer.len() != window.buffer_len() {
             //TODO: panic!! or sth like that
             //return Err("Wrong buffersize");
         }
@@ -521,7 +852,7 @@ where
         self.interface.cmd(spi, Command::PartialIn)?;
         self.interface.cmd(spi, Command::PartialWindow)?;
 
-        self.shift_display(spi, x, y, width, height)?;
+        self.shift_display(spi, window)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
@@ -536,18 +867,15 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        if buffer.len() as u32 != width / 8 * height {
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        if buffer.len() != window.buffer_len() {
             //TODO: panic!! or sth like that
             //return Err("Wrong buffersize");
         }
 
-        self.shift_display(spi, x, y, width, height)?;
+        self.shift_display(spi, window)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
 
@@ -560,12 +888,9 @@ where
     fn clear_partial_frame(
         &mut self,
         spi: &mut SPI,
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
         self.send_resolution(spi)?;
 
         let color_value = self.color.get_byte_value();
@@ -573,24 +898,246 @@ where
         self.interface.cmd(spi, Command::PartialIn)?;
         self.interface.cmd(spi, Command::PartialWindow)?;
 
-        self.shift_display(spi, x, y, width, height)?;
+        self.shift_display(spi, window)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface
-            .data_x_times(spi, color_value, width / 8 * height)?;
+            .data_x_times(spi, color_value, window.buffer_len() as u32)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface
-            .data_x_times(spi, color_value, width / 8 * height)?;
+            .data_x_times(spi, color_value, window.buffer_len() as u32)?;
 
         self.interface.cmd(spi, Command::PartialOut)?;
         Ok(())
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> InterruptRefresh<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn start_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.display_frame(spi, delay)?;
+        self.refreshing = true;
+        Ok(())
+    }
+
+    fn on_busy_deasserted(&mut self) {
+        self.refreshing = false;
+    }
+
+    fn is_refreshing(&self) -> bool {
+        self.refreshing
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> FrameStream<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let color_value = self.color.get_byte_value();
+
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+
+        self.interface.cmd(spi, Command::DataStartTransmission2)
+    }
+
+    fn write_frame_chunk(
+        &mut self,
+        spi: &mut SPI,
+        chunk: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.data(spi, chunk)
+    }
+
+    fn end_frame(&mut self, _spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> DmaFrame<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn prepare_frame(&self, _buffer: &mut [u8]) {}
+
+    fn begin_frame_transfer(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.begin_frame(spi)?;
+        self.interface.begin_data()
+    }
+
+    fn finish_frame_transfer(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.interface.end_data()?;
+        self.end_frame(spi)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    /// Reads back the VCOM value the controller is currently using
+    ///
+    /// The value has the same encoding as the VCOM_DC setting (`-0.1V - value * 0.05V`).
+    ///
+    /// Needs a readable data line, see [Requirements: SPI](crate#spi).
+    pub fn read_vcom_value(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<u8, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let mut buf = [0u8];
+        self.interface
+            .cmd_read(spi, Command::ReadVcomValue, &mut buf)?;
+        Ok(buf[0] & 0x3f)
+    }
+
+    /// Runs the automatic VCOM measurement of the controller and returns the result
+    ///
+    /// Powers the display on (also from [`standby`](Self::standby)) and blocks for the 5 seconds
+    /// of the measurement. The value has the encoding of [`read_vcom_value`](Self::read_vcom_value).
+    /// Wake the display up first after a deep sleep.
+    ///
+    /// Needs a readable data line, see [Requirements: SPI](crate#spi).
+    pub fn measure_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<u8, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        self.power_on(spi, delay)?;
+        // 5 seconds
+        self.cmd_with_data(spi, Command::AutoMeasurementVcom, &[0x11])?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.read_vcom_value(spi)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> ReadBack<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    fn read_status(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<Status, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let mut buf = [0u8];
+        self.interface.cmd_read(spi, Command::GetStatus, &mut buf)?;
+        Ok(Status::from(buf[0]))
+    }
+
+    /// Only the integer part of the measurement (upper byte of the sensor value)
+    fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<i8, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let mut buf = [0u8];
+        self.interface
+            .cmd_read(spi, Command::TemperatureSensor, &mut buf)?;
+        Ok(buf[0] as i8)
+    }
+
+    fn read_vcom(&mut self, spi: &mut SPI) -> Result<u8, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.read_vcom_value(spi)
+    }
+
+    /// Drops the dummy byte the controller sends first
+    fn read_otp(
+        &mut self,
+        spi: &mut SPI,
+        buf: &mut [u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        self.interface.cmd_read_skip(spi, Command::ReadOtp, 1, buf)
+    }
+
+    /// LVD_N in bit 0 is low below the threshold of 2.5V
+    fn is_supply_low(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<bool, DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle()?;
+        let mut buf = [0u8];
+        self.interface
+            .cmd_read(spi, Command::LowPowerDetection, &mut buf)?;
+        Ok(buf[0] & 0x01 == 0)
+    }
+}
+
+/// Booster soft start, frame rate and VCOM and data interval for the given profile
+///
+/// The interval is the lower nibble of the VCOM and data interval setting, 0x07 are 10 lines.
+fn profile_settings(profile: Profile) -> ([u8; 3], FrameRate, u8) {
+    match profile {
+        Profile::Default => ([0x17, 0x17, 0x17], FrameRate::Hz100, 0x07),
+        // weaker booster
+        Profile::LowPower => ([0x07, 0x07, 0x07], FrameRate::Hz50, 0x07),
+        // not FrameRate::Hz200, it doesn't work on every board, and 8 lines between the frames
+        Profile::Fast => ([0x17, 0x17, 0x1f], FrameRate::Hz150, 0x09),
+    }
+}
+
+/// The border bits of the VCOM and data interval setting
+fn border_setting(border: BorderColor) -> u8 {
+    match border {
+        BorderColor::White | BorderColor::Chromatic => 0x90,
+        BorderColor::Black => 0x50,
+        BorderColor::Floating => 0x10,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Error;
+    use crate::test_utils::{Expectations, MockNoop, Mocks, PinMock, SpiMock};
+    use crate::traits::NewCheckedError;
 
     #[test]
     fn epd_size() {
@@ -598,4 +1145,503 @@ mod tests {
         assert_eq!(HEIGHT, 300);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    fn expect_init(e: &mut Expectations) {
+        e.reset()
+            .cmd_with_data(Command::PowerSetting as u8, &[0x03, 0x00, 0x2b, 0x2b, 0xff])
+            .cmd_with_data(Command::BoosterSoftStart as u8, &[0x17, 0x17, 0x17])
+            .cmd(Command::PowerOn as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::PanelSetting as u8, &[0x3f])
+            .cmd_with_data(Command::PllControl as u8, &[0x3a])
+            .cmd(Command::ResolutionSetting as u8)
+            .data(&[0x01])
+            .data(&[0x90])
+            .data(&[0x01])
+            .data(&[0x2c])
+            .cmd_with_data(Command::VcmDcSetting as u8, &[0x12])
+            .cmd_with_data(Command::VcomAndDataIntervalSetting as u8, &[0x97])
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::LutForVcom as u8, &LUT_VCOM0)
+            .cmd_with_data(Command::LutWhiteToWhite as u8, &LUT_WW)
+            .cmd_with_data(Command::LutBlackToWhite as u8, &LUT_BW)
+            .cmd_with_data(Command::LutWhiteToBlack as u8, &LUT_WB)
+            .cmd_with_data(Command::LutBlackToBlack as u8, &LUT_BB)
+            .busy_idle(IS_BUSY_LOW);
+    }
+
+    fn epd(mocks: &mut Mocks) -> Epd4in2<SpiMock, PinMock, PinMock, PinMock, PinMock, MockNoop> {
+        Epd4in2::new(
+            &mut mocks.spi,
+            mocks.cs.clone(),
+            mocks.busy.clone(),
+            mocks.dc.clone(),
+            mocks.rst.clone(),
+            &mut mocks.delay,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn init_and_sleep() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::VcomAndDataIntervalSetting as u8, &[0x17])
+            .cmd(Command::VcmDcSetting as u8)
+            .cmd(Command::PanelSetting as u8)
+            .cmd(Command::PowerSetting as u8)
+            .data_x_times(0x00, 1)
+            .data_x_times(0x00, 1)
+            .data_x_times(0x00, 1)
+            .data_x_times(0x00, 1)
+            .cmd(Command::PowerOff as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::DeepSleep as u8, &[0xa5]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.sleep(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn busy_timeout() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        // Polled once more than the timeout allows
+        for _ in 0..4 {
+            e.busy(IS_BUSY_LOW);
+        }
+        e.busy(IS_BUSY_LOW)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DisplayRefresh as u8);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_busy_timeout(3);
+        assert_eq!(
+            epd.display_frame(&mut mocks.spi, &mut mocks.delay),
+            Err(Error::BusyTimeout)
+        );
+        epd.display_frame(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn update_and_display_frame() {
+        let buffer = [0x0f; WIDTH as usize / 8 * HEIGHT as usize];
+
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DataStartTransmission1 as u8)
+            .data_x_times(0xff, WIDTH / 8 * HEIGHT)
+            .cmd(Command::DataStartTransmission2 as u8)
+            .data(&buffer)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DisplayRefresh as u8);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.update_frame(&mut mocks.spi, &buffer, &mut mocks.delay)
+            .unwrap();
+        epd.display_frame(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn quick_lut() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::LutForVcom as u8, &LUT_VCOM0_QUICK)
+            .cmd_with_data(Command::LutWhiteToWhite as u8, &LUT_WW_QUICK)
+            .cmd_with_data(Command::LutBlackToWhite as u8, &LUT_BW_QUICK)
+            .cmd_with_data(Command::LutWhiteToBlack as u8, &LUT_WB_QUICK)
+            .cmd_with_data(Command::LutBlackToBlack as u8, &LUT_BB_QUICK);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_lut(&mut mocks.spi, Some(RefreshLut::Quick))
+            .unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn read_back() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_read(Command::GetStatus as u8, &[0x0a])
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_read(Command::TemperatureSensor as u8, &[0xfb])
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_read(Command::ReadVcomValue as u8, &[0x52]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        let status = epd.read_status(&mut mocks.spi).unwrap();
+        assert!(status.busy);
+        assert!(status.power_off);
+        assert!(status.data_received);
+        assert!(!status.power_on);
+        assert_eq!(epd.read_temperature(&mut mocks.spi).unwrap(), -5);
+        assert_eq!(epd.read_vcom(&mut mocks.spi).unwrap(), 0x12);
+        mocks.done();
+    }
+
+    #[test]
+    fn new_checked() {
+        let mut mocks = Expectations::new().build();
+        let result = Epd4in2::new_checked(
+            &mut mocks.spi,
+            mocks.cs.clone(),
+            mocks.busy.clone(),
+            mocks.dc.clone(),
+            mocks.rst.clone(),
+            &mut mocks.delay,
+            20_000_000,
+        );
+        assert_eq!(
+            result.err(),
+            Some(NewCheckedError::SpiTooFast {
+                spi_hz: 20_000_000,
+                max_spi_hz: MAX_SPI_HZ,
+            })
+        );
+        mocks.done();
+
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        let mut mocks = e.build();
+        Epd4in2::new_checked(
+            &mut mocks.spi,
+            mocks.cs.clone(),
+            mocks.busy.clone(),
+            mocks.dc.clone(),
+            mocks.rst.clone(),
+            &mut mocks.delay,
+            4_000_000,
+        )
+        .unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn interrupt_refresh() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DisplayRefresh as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DisplayRefresh as u8)
+            // Not acknowledged, the next refresh polls BUSY
+            .busy(IS_BUSY_LOW)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DisplayRefresh as u8);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        assert!(!epd.is_refreshing());
+        epd.start_display_frame(&mut mocks.spi, &mut mocks.delay)
+            .unwrap();
+        assert!(epd.is_refreshing());
+        epd.on_busy_deasserted();
+        assert!(!epd.is_refreshing());
+
+        epd.start_display_frame(&mut mocks.spi, &mut mocks.delay)
+            .unwrap();
+        epd.display_frame(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn cancellable_wait() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DisplayRefresh as u8)
+            .busy(IS_BUSY_LOW)
+            .busy(IS_BUSY_LOW)
+            .busy_idle(IS_BUSY_LOW)
+            .busy(IS_BUSY_LOW)
+            .busy(IS_BUSY_LOW);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.start_display_frame(&mut mocks.spi, &mut mocks.delay)
+            .unwrap();
+
+        let mut polls = 0;
+        let result = epd.wait_until_idle_or_cancel(&mut mocks.delay, || {
+            polls += 1;
+            false
+        });
+        assert_eq!((result, polls), (Ok(()), 2));
+
+        let mut polls = 0;
+        let result = epd.wait_until_idle_or_cancel(&mut mocks.delay, || {
+            polls += 1;
+            polls == 2
+        });
+        assert_eq!(result, Err(Error::Cancelled));
+        mocks.done();
+    }
+
+    #[test]
+    fn reset_timing() {
+        extern crate std;
+        use crate::config::Builder;
+        use std::vec::Vec;
+
+        struct RecordingDelay(Vec<u32>);
+
+        impl DelayUs<u32> for RecordingDelay {
+            fn delay_us(&mut self, us: u32) {
+                self.0.push(us);
+            }
+        }
+
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        let mut mocks = e.build();
+        let mut delay = RecordingDelay(Vec::new());
+        let config = Builder::new()
+            .reset_timing(ResetTiming {
+                low_ms: 50,
+                settle_ms: 250,
+            })
+            .build();
+        Epd4in2::new_with_config(
+            &mut mocks.spi,
+            mocks.cs.clone(),
+            mocks.busy.clone(),
+            mocks.dc.clone(),
+            mocks.rst.clone(),
+            &mut delay,
+            config,
+        )
+        .unwrap();
+        assert_eq!(delay.0[..3], [10_000, 50_000, 250_000]);
+        mocks.done();
+    }
+
+    #[test]
+    fn external_temperature() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::CascadeSetting as u8, &[0x02])
+            .cmd_with_data(Command::ForceTemperature as u8, &[0xf6]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_external_temperature(&mut mocks.spi, -10).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn border() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_with_data(Command::VcomAndDataIntervalSetting as u8, &[0x57]);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.set_border(&mut mocks.spi, BorderColor::Black).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn read_otp() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW).cmd_read_skip(
+            Command::ReadOtp as u8,
+            &[0xff],
+            &[0x01, 0x02, 0x03],
+        );
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        let mut otp = [0; 3];
+        epd.read_otp(&mut mocks.spi, &mut otp).unwrap();
+        assert_eq!(otp, [0x01, 0x02, 0x03]);
+        mocks.done();
+    }
+
+    #[test]
+    fn low_supply_guard() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd_read(Command::LowPowerDetection as u8, &[0x00])
+            .busy_idle(IS_BUSY_LOW)
+            .cmd_read(Command::LowPowerDetection as u8, &[0x01])
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::DisplayRefresh as u8);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        assert_eq!(
+            epd.display_frame_guarded(&mut mocks.spi, &mut mocks.delay),
+            Err(Error::LowSupply)
+        );
+        epd.display_frame_guarded(&mut mocks.spi, &mut mocks.delay)
+            .unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn frame_rate() {
+        use crate::config::Builder;
+        use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let config = Builder::new()
+            .profile(Profile::Fast)
+            .frame_rate(FrameRate::Hz50)
+            .build();
+        Epd4in2::new_with_config(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut MockNoop::new(),
+            config,
+        )
+        .unwrap();
+        let events = recorder.take_events();
+        let pll = Event::Command(Command::PllControl as u8);
+        let i = events.iter().position(|e| *e == pll).unwrap();
+        assert_eq!(events[i + 1], Event::Data([0x3c].to_vec()));
+        // The booster of the profile stays
+        let booster = Event::Command(Command::BoosterSoftStart as u8);
+        let i = events.iter().position(|e| *e == booster).unwrap();
+        assert_eq!(events[i + 1], Event::Data([0x17, 0x17, 0x1f].to_vec()));
+        // So does its interval, next to the white border
+        let interval = Event::Command(Command::VcomAndDataIntervalSetting as u8);
+        let i = events.iter().position(|e| *e == interval).unwrap();
+        assert_eq!(events[i + 1], Event::Data([0x99].to_vec()));
+    }
+
+    #[test]
+    fn mirror() {
+        use crate::config::{Builder, Mirror};
+        use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let config = Builder::new().mirror(Mirror::Both).build();
+        Epd4in2::new_with_config(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut MockNoop::new(),
+            config,
+        )
+        .unwrap();
+        let events = recorder.take_events();
+        let panel_setting = Event::Command(Command::PanelSetting as u8);
+        let i = events.iter().position(|e| *e == panel_setting).unwrap();
+        assert_eq!(events[i + 1], Event::Data([0x33].to_vec()));
+    }
+
+    #[test]
+    fn power_off_and_on() {
+        let mut e = Expectations::new();
+        expect_init(&mut e);
+        e.busy_idle(IS_BUSY_LOW)
+            .cmd(Command::PowerOff as u8)
+            .busy_idle(IS_BUSY_LOW)
+            .cmd(Command::PowerOn as u8)
+            .busy_idle(IS_BUSY_LOW);
+
+        let mut mocks = e.build();
+        let mut epd = epd(&mut mocks);
+        epd.power_off(&mut mocks.spi).unwrap();
+        epd.power_on(&mut mocks.spi, &mut mocks.delay).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn full_refresh_interval() {
+        use crate::config::Builder;
+        use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let config = Builder::new().full_refresh_interval(2).build();
+        let mut epd = Epd4in2::new_with_config(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+            config,
+        )
+        .unwrap();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        recorder.take_events();
+
+        let refresh = Event::Command(Command::DisplayRefresh as u8);
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            recorder.take_events(),
+            [Event::Command(Command::DisplayRefresh as u8)]
+        );
+        assert_eq!(epd.quick_refresh_count(), 1);
+
+        // The second one uses the full LUT and restores the quick LUT
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        let events = recorder.take_events();
+        assert_eq!(events[1], Event::Data(LUT_VCOM0.to_vec()));
+        let i = events.iter().position(|e| *e == refresh).unwrap();
+        assert_eq!(events[i + 2], Event::Data(LUT_VCOM0_QUICK.to_vec()));
+        assert_eq!(epd.quick_refresh_count(), 0);
+
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        epd.reset_quick_refresh_count();
+        assert_eq!(epd.quick_refresh_count(), 0);
+    }
+
+    #[test]
+    fn asleep() {
+        use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        recorder.take_events();
+
+        // Nothing is sent to the sleeping controller
+        let buffer = [0u8; WIDTH as usize * HEIGHT as usize / 8];
+        assert_eq!(
+            epd.update_frame(&mut spi, &buffer, &mut delay),
+            Err(Error::Asleep)
+        );
+        assert_eq!(recorder.take_events(), []);
+
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(recorder.take_events()[0], Event::Reset);
+        epd.update_frame(&mut spi, &buffer, &mut delay).unwrap();
+    }
 }
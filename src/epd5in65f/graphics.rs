@@ -1,7 +1,9 @@
 use crate::color::OctColor;
 use crate::epd5in65f::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
-use crate::graphics::{DisplayRotation, OctDisplay};
+use crate::graphics::{rotated_size, Display, DisplayRotation};
+use crate::region::Region;
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
 /// Full size buffer for use with the 5in65f EPD
 ///
@@ -10,6 +12,7 @@ use embedded_graphics_core::prelude::*;
 pub struct Display5in65f {
     buffer: [u8; WIDTH as usize * HEIGHT as usize / 2],
     rotation: DisplayRotation,
+    dirty: Option<Region>,
 }
 
 impl Default for Display5in65f {
@@ -18,6 +21,7 @@ impl Default for Display5in65f {
             buffer: [OctColor::colors_byte(DEFAULT_BACKGROUND_COLOR, DEFAULT_BACKGROUND_COLOR);
                 WIDTH as usize * HEIGHT as usize / 2],
             rotation: DisplayRotation::default(),
+            dirty: None,
         }
     }
 }
@@ -35,15 +39,26 @@ impl DrawTarget for Display5in65f {
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(WIDTH, HEIGHT, area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.fill_contiguous_helper(WIDTH, HEIGHT, area, colors)
+    }
 }
 
 impl OriginDimensions for Display5in65f {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        rotated_size(WIDTH, HEIGHT, self.rotation)
     }
 }
 
-impl OctDisplay for Display5in65f {
+impl Display<OctColor> for Display5in65f {
     fn buffer(&self) -> &[u8] {
         &self.buffer
     }
@@ -59,13 +74,17 @@ impl OctDisplay for Display5in65f {
     fn rotation(&self) -> DisplayRotation {
         self.rotation
     }
+
+    fn dirty_region_mut(&mut self) -> Option<&mut Option<Region>> {
+        Some(&mut self.dirty)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::epd5in65f;
-    use crate::graphics::{DisplayRotation, OctDisplay};
+    use crate::graphics::{Display, DisplayRotation};
     use embedded_graphics::{
         prelude::*,
         primitives::{Line, PrimitiveStyle},
@@ -0,0 +1,128 @@
+//! Errors of the drivers
+
+use core::fmt;
+use embedded_hal::{
+    blocking::spi::Write,
+    digital::v2::{InputPin, OutputPin},
+};
+
+/// Errors of the drivers and the [`DisplayInterface`](crate::interface::DisplayInterface)
+///
+/// `PinE` is the error of the pins. The drivers and the interface are generic over four pins
+/// with unrelated error types and report a [`PinError`] holding the error of the failed pin,
+/// see [`DriverError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<SpiE, PinE = ()> {
+    /// Writing to or reading from the controller over SPI failed
+    Spi(SpiE),
+    /// BUSY didn't report the display idle in time, it's probably unplugged or wedged
+    ///
+    /// Only drivers waiting with a timeout report it, see e.g.
+    /// [`Epd2in7b::set_busy_timeout`](crate::epd2in7b::Epd2in7b::set_busy_timeout).
+    BusyTimeout,
+    /// Setting or reading a pin failed, with the error of the pin
+    Pin(PinE),
+    /// The supply voltage was too low to start a refresh, which would leave the panel half drawn
+    ///
+    /// See [`ReadBack::display_frame_guarded`](crate::traits::ReadBack::display_frame_guarded).
+    LowSupply,
+    /// The display is in deep sleep and ignores commands, wake it up first
+    ///
+    /// See [`WaveshareDisplay::wake_up`](crate::traits::WaveshareDisplay::wake_up).
+    Asleep,
+    /// The display or the driver doesn't support the operation
+    Unsupported,
+    /// The wait for the display was cancelled, see
+    /// [`WaveshareDisplay::wait_until_idle_or_cancel`](crate::traits::WaveshareDisplay::wait_until_idle_or_cancel)
+    Cancelled,
+    /// A buffer passed to the driver is too small for the data it has to hold
+    BufferTooSmall,
+}
+
+/// The pins of a display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pin {
+    /// Chip select
+    Cs,
+    /// Busy signal of the controller
+    Busy,
+    /// Data/command selection
+    Dc,
+    /// Reset
+    Rst,
+}
+
+/// The error of a driver over `SPI` and the pins `CS`, `BUSY`, `DC` and `RST`
+pub type DriverError<SPI, CS, BUSY, DC, RST> = Error<
+    <SPI as Write<u8>>::Error,
+    PinError<
+        <CS as OutputPin>::Error,
+        <BUSY as InputPin>::Error,
+        <DC as OutputPin>::Error,
+        <RST as OutputPin>::Error,
+    >,
+>;
+
+/// The failed pin of a driver together with its error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PinError<CsE, BusyE, DcE, RstE> {
+    /// Setting chip select failed
+    Cs(CsE),
+    /// Reading the busy signal failed
+    Busy(BusyE),
+    /// Setting data/command failed
+    Dc(DcE),
+    /// Setting reset failed
+    Rst(RstE),
+}
+
+impl<CsE, BusyE, DcE, RstE> PinError<CsE, BusyE, DcE, RstE> {
+    /// The pin that failed
+    pub fn pin(&self) -> Pin {
+        match self {
+            PinError::Cs(_) => Pin::Cs,
+            PinError::Busy(_) => Pin::Busy,
+            PinError::Dc(_) => Pin::Dc,
+            PinError::Rst(_) => Pin::Rst,
+        }
+    }
+}
+
+impl<SpiE, PinE> Error<SpiE, PinE> {
+    /// Converts the error of the pin, e.g. to drop it with `|_| ()`
+    pub fn map_pin<F, T>(self, f: F) -> Error<SpiE, T>
+    where
+        F: FnOnce(PinE) -> T,
+    {
+        match self {
+            Error::Spi(e) => Error::Spi(e),
+            Error::BusyTimeout => Error::BusyTimeout,
+            Error::Pin(e) => Error::Pin(f(e)),
+            Error::LowSupply => Error::LowSupply,
+            Error::Asleep => Error::Asleep,
+            Error::Unsupported => Error::Unsupported,
+            Error::Cancelled => Error::Cancelled,
+            Error::BufferTooSmall => Error::BufferTooSmall,
+        }
+    }
+}
+
+impl<SpiE: fmt::Debug, PinE: fmt::Debug> fmt::Display for Error<SpiE, PinE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Spi(e) => write!(f, "SPI error: {:?}", e),
+            Error::BusyTimeout => f.write_str("timeout waiting for BUSY"),
+            Error::Pin(e) => write!(f, "pin error: {:?}", e),
+            Error::LowSupply => f.write_str("supply voltage too low"),
+            Error::Asleep => f.write_str("display is in deep sleep"),
+            Error::Unsupported => f.write_str("operation not supported"),
+            Error::Cancelled => f.write_str("wait cancelled"),
+            Error::BufferTooSmall => f.write_str("buffer too small"),
+        }
+    }
+}
+
+impl<SpiE: fmt::Debug, PinE: fmt::Debug> core::error::Error for Error<SpiE, PinE> {}
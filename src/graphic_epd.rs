@@ -0,0 +1,291 @@
+//! A driver together with its frame buffer
+//!
+//! [`GraphicEpd`] owns the driver, a [`Display`] buffer, the SPI and the delay. It is drawn on
+//! like the buffer and sends it with [`flush`](GraphicEpd::flush):
+//!
+//! ```rust,ignore
+//! let epd = Epd4in2::new(&mut spi, cs, busy, dc, rst, &mut delay)?;
+//! let mut screen = GraphicEpd::new(epd, Display4in2::default(), spi, delay);
+//! Text::new("Hello", Point::new(10, 20), style).draw(&mut screen)?;
+//! screen.flush()?;
+//! ```
+
+use core::marker::PhantomData;
+use embedded_graphics_core::{pixelcolor::PixelColor, prelude::*, primitives::Rectangle};
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::color::TriColor;
+use crate::error::{DriverError, Error};
+use crate::graphics::{
+    packing::{copy_window_in, find_region},
+    rotated_size, Display, TriDisplay,
+};
+use crate::region::Region;
+use crate::source::PackedColor;
+use crate::traits::{PartialWindow, WaveshareDisplay, WaveshareThreeColorDisplay};
+
+/// A driver with its [`Display`] buffer, SPI and delay
+///
+/// Draws into the buffer, the display is only updated by the flushes.
+pub struct GraphicEpd<EPD, D, SPI, CS, BUSY, DC, RST, DELAY> {
+    epd: EPD,
+    display: D,
+    spi: SPI,
+    delay: DELAY,
+    _pins: PhantomData<(CS, BUSY, DC, RST)>,
+}
+
+impl<EPD, D, SPI, CS, BUSY, DC, RST, DELAY> GraphicEpd<EPD, D, SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+{
+    /// Combines the initialized driver `epd` with the buffer `display`, `spi` and `delay`
+    pub fn new(epd: EPD, display: D, spi: SPI, delay: DELAY) -> Self {
+        GraphicEpd {
+            epd,
+            display,
+            spi,
+            delay,
+            _pins: PhantomData,
+        }
+    }
+
+    /// The driver, SPI and delay, e.g. to send the display to sleep
+    pub fn parts(&mut self) -> (&mut EPD, &mut SPI, &mut DELAY) {
+        (&mut self.epd, &mut self.spi, &mut self.delay)
+    }
+
+    /// The buffer, e.g. to clear or rotate it
+    pub fn display(&mut self) -> &mut D {
+        &mut self.display
+    }
+
+    /// Gives back the driver, buffer, SPI and delay
+    pub fn release(self) -> (EPD, D, SPI, DELAY) {
+        (self.epd, self.display, self.spi, self.delay)
+    }
+
+    /// Sends the whole buffer and refreshes the display
+    ///
+    /// For displays with a single plane, tri color displays use
+    /// [`flush_color`](GraphicEpd::flush_color).
+    pub fn flush<C>(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        C: PackedColor + PixelColor,
+        D: Display<C>,
+    {
+        self.epd
+            .update_and_display_frame(&mut self.spi, self.display.buffer(), &mut self.delay)
+    }
+
+    /// Sends the partial window covering `region` and refreshes the display
+    ///
+    /// `region` is in the coordinates of the rotated buffer, like the drawing. The window is
+    /// packed into `scratch` first, which needs [`PartialWindow::buffer_len`] bytes, at most the
+    /// length of the buffer, or the flush fails with [`Error::BufferTooSmall`]. Nothing is sent if
+    /// `region` isn't on the display. For b/w displays.
+    pub fn flush_partial<C>(
+        &mut self,
+        region: Region,
+        scratch: &mut [u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        C: PackedColor + PixelColor,
+        D: Display<C>,
+    {
+        let (width, height) = (self.epd.width(), self.epd.height());
+        let rotation = self.display.rotation();
+        let size = rotated_size(width, height, rotation);
+        let region = Region::new(
+            region.x,
+            region.y,
+            region.width.min(size.width.saturating_sub(region.x)),
+            region.height.min(size.height.saturating_sub(region.y)),
+        );
+        if region.area() == 0 {
+            return Ok(());
+        }
+        let native = find_region(region, width, height, rotation);
+        let Some(window) = PartialWindow::enclosing(native, width, height) else {
+            return Ok(());
+        };
+        let Some(scratch) = scratch.get_mut(..window.buffer_len()) else {
+            return Err(Error::BufferTooSmall);
+        };
+        let Region {
+            x,
+            y,
+            width,
+            height,
+        } = window.region();
+        copy_window_in(
+            self.display.buffer(),
+            self.display.ram_layout(),
+            x,
+            y,
+            width,
+            height,
+            scratch,
+        );
+        self.epd
+            .update_partial_window(&mut self.spi, scratch, window)?;
+        self.epd.display_frame(&mut self.spi, &mut self.delay)
+    }
+}
+
+impl<EPD, D, SPI, CS, BUSY, DC, RST, DELAY> GraphicEpd<EPD, D, SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    D: Display<TriColor>,
+{
+    /// Sends both planes of a tri color buffer and refreshes the display
+    pub fn flush_color(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.epd.update_color_frame(
+            &mut self.spi,
+            self.display.bw_buffer(),
+            self.display.chromatic_buffer(),
+        )?;
+        self.epd.display_frame(&mut self.spi, &mut self.delay)
+    }
+}
+
+impl<EPD, D, SPI, CS, BUSY, DC, RST, DELAY> DrawTarget
+    for GraphicEpd<EPD, D, SPI, CS, BUSY, DC, RST, DELAY>
+where
+    D: DrawTarget + OriginDimensions,
+{
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.display.draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.display.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.clear(color)
+    }
+}
+
+impl<EPD, D, SPI, CS, BUSY, DC, RST, DELAY> OriginDimensions
+    for GraphicEpd<EPD, D, SPI, CS, BUSY, DC, RST, DELAY>
+where
+    D: OriginDimensions,
+{
+    fn size(&self) -> Size {
+        self.display.size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd4in2::{self, Display4in2, Epd4in2};
+    use crate::graphics::DisplayRotation;
+    use crate::test_utils::{Event, IdleBusyPin, NoopPin, Recorder};
+    use embedded_graphics::{pixelcolor::BinaryColor, primitives::Rectangle};
+    use embedded_hal_mock::delay::MockNoop;
+
+    #[test]
+    fn flush() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        let mut screen = GraphicEpd::new(epd, Display4in2::default(), spi, delay);
+        recorder.take_events();
+
+        screen
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 0), Size::new(8, 2)),
+                BinaryColor::On,
+            )
+            .unwrap();
+        screen.flush().unwrap();
+        let events = recorder.take_events();
+        let frame = events.iter().rev().find_map(|e| match e {
+            Event::Data(data) if data.len() == epd4in2::WIDTH as usize * 300 / 8 => Some(data),
+            _ => None,
+        });
+        assert_eq!(frame.unwrap()[..2], [0x00, 0xff]);
+
+        // Only the window at the bottom right of the unrotated buffer
+        let mut scratch = [0x55; 8];
+        screen.display().set_rotation(DisplayRotation::Rotate180);
+        screen
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 0), Size::new(8, 2)),
+                BinaryColor::On,
+            )
+            .unwrap();
+        screen
+            .flush_partial(Region::new(0, 0, 8, 2), &mut scratch)
+            .unwrap();
+        let events = recorder.take_events();
+        assert!(events.contains(&Event::Data(std::vec![0x00, 0x00])));
+        assert!(events.contains(&Event::Command(
+            crate::epd4in2::command::Command::DisplayRefresh as u8
+        )));
+        // x 392 to 399 and y 298 to 299
+        assert!(events.contains(&Event::Data(std::vec![
+            0x01, 0x88, 0x01, 0x8f, 0x01, 0x2a, 0x01, 0x2b, 0x01
+        ])));
+
+        // Unaligned regions are grown to whole bytes
+        screen
+            .flush_partial(Region::new(3, 0, 2, 1), &mut scratch)
+            .unwrap();
+        assert!(recorder
+            .take_events()
+            .contains(&Event::Data(std::vec![0x00])));
+
+        screen
+            .flush_partial(Region::new(400, 0, 8, 2), &mut scratch)
+            .unwrap();
+        assert_eq!(recorder.take_events(), []);
+
+        assert_eq!(
+            screen.flush_partial(Region::new(0, 0, 16, 2), &mut scratch[..3]),
+            Err(Error::BufferTooSmall)
+        );
+        assert_eq!(recorder.take_events(), []);
+    }
+}
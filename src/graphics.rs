@@ -2,13 +2,21 @@
 
 use crate::buffer_len;
 use crate::color::{Color, OctColor, TriColor};
-use embedded_graphics::pixelcolor::BinaryColor;
+use crate::region::Region;
+use crate::source::{BitOrder, InvertibleColor, MonoColor, PackedColor};
+use core::convert::TryFrom;
+use embedded_graphics::pixelcolor::{BinaryColor, Gray2, GrayColor, PixelColor};
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+pub mod packing;
 
 /// Displayrotation
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DisplayRotation {
     /// No rotation
+    #[default]
     Rotate0,
     /// Rotate by 90 degrees clockwise
     Rotate90,
@@ -16,11 +24,62 @@ pub enum DisplayRotation {
     Rotate180,
     /// Rotate 270 degrees clockwise
     Rotate270,
+    /// Flip horizontally, e.g. for a display seen in a mirror or through the back of the panel
+    FlipHorizontal,
+    /// Flip vertically
+    FlipVertical,
+    /// Rotate by 90 degrees clockwise, then flip horizontally
+    Rotate90FlipHorizontal,
+    /// Rotate by 270 degrees clockwise, then flip horizontally
+    Rotate270FlipHorizontal,
 }
 
-impl Default for DisplayRotation {
-    fn default() -> Self {
-        DisplayRotation::Rotate0
+/// Where the pixels of a display lie in its buffer
+///
+/// Some panels are narrower than the RAM of their controller or start a few pixels into its rows.
+/// Their buffers hold whole rows of the RAM, `stride` bytes each, with the pixels of the panel
+/// `x_offset` pixels into each row. The planes of multi plane colors follow each other. Buffers in
+/// another layout than [`packed`](RamLayout::packed) are sent with
+/// [`StridedFrame::with_layout`](crate::source::StridedFrame::with_layout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RamLayout {
+    /// Pixels in each row before the first pixel of the panel
+    pub x_offset: u32,
+    /// Bytes of each row
+    pub stride: u32,
+    /// Order of the pixels in each byte
+    pub bit_order: BitOrder,
+}
+
+impl RamLayout {
+    /// Rows of `stride` bytes with the first pixel `x_offset` pixels into each row, most
+    /// significant bit first
+    pub const fn new(x_offset: u32, stride: u32) -> Self {
+        RamLayout {
+            x_offset,
+            stride,
+            bit_order: BitOrder::MsbFirst,
+        }
+    }
+
+    /// Rows of `width` pixels padded to whole bytes, the layout of the displays of this crate
+    pub const fn packed(width: u32, bits_per_pixel: u32) -> Self {
+        RamLayout::new(0, (width * bits_per_pixel).div_ceil(8))
+    }
+
+    /// The layout with the pixels in `bit_order`, e.g. for buffers of converters packing the
+    /// first pixel into the least significant bit
+    pub const fn with_bit_order(self, bit_order: BitOrder) -> Self {
+        RamLayout {
+            x_offset: self.x_offset,
+            stride: self.stride,
+            bit_order,
+        }
+    }
+
+    /// Bytes of a plane of `height` rows
+    pub const fn plane_len(&self, height: u32) -> usize {
+        self.stride as usize * height as usize
     }
 }
 
@@ -30,12 +89,47 @@ impl Default for DisplayRotation {
 /// - Drawing (With the help of DrawTarget/Embedded Graphics)
 /// - Rotations
 /// - Clearing
-pub trait Display: DrawTarget<Color = BinaryColor> {
+///
+/// Generic over the color of the display, the buffer layout follows from its
+/// [`PackedColor`] implementation: b/w displays use [`BinaryColor`] (the default), tri color
+/// displays [`TriColor`] with the b/w plane followed by the chromatic plane, the 4 gray displays
+/// [`Gray2`] with 2 bits per pixel and the 7 color displays [`OctColor`] with 4 bits per pixel. Rotation and packing are the same for all of
+/// them.
+///
+/// Like the drawing, [`OriginDimensions::size`] follows the rotation, e.g. 176 x 264 pixels for a
+/// 264 x 176 panel rotated by 90 degrees. Implementations return [`rotated_size`].
+///
+/// The displays of the crate track the bounding box of the pixels drawn or cleared since the last
+/// [`take_dirty_region`](Display::take_dirty_region), e.g. for partial refreshes of only what
+/// changed.
+pub trait Display<C: PackedColor + PixelColor = BinaryColor>: DrawTarget<Color = C> {
     /// Clears the buffer of the display with the chosen background color
-    fn clear_buffer(&mut self, background_color: Color) {
-        for elem in self.get_mut_buffer().iter_mut() {
-            *elem = background_color.get_byte_value();
+    ///
+    /// Every plane gets the byte pattern of the color, no need to know how the color is packed:
+    ///
+    /// ```rust
+    /// # use epd_waveshare::epd2in13bc::Display2in13bc;
+    /// # use epd_waveshare::epd4in2::Display4in2;
+    /// # use epd_waveshare::prelude::*;
+    /// let mut display = Display4in2::default();
+    /// display.clear_buffer(Color::Black);
+    /// assert!(display.buffer().iter().all(|&byte| byte == 0x00));
+    ///
+    /// // Chromatic pixels are white in the b/w plane
+    /// let mut display = Display2in13bc::default();
+    /// display.clear_buffer(TriColor::Chromatic);
+    /// assert!(display.bw_buffer().iter().all(|&byte| byte == 0xff));
+    /// assert!(display.chromatic_buffer().iter().all(|&byte| byte == 0x00));
+    /// ```
+    fn clear_buffer<B: Into<C>>(&mut self, background_color: B) {
+        let color = background_color.into();
+        let planes = self.get_mut_buffer();
+        let plane_len = planes.len() / C::PLANES;
+        for (plane, elems) in planes.chunks_mut(plane_len).enumerate() {
+            elems.fill(packing::packed_byte(color, plane));
         }
+        let size = self.bounding_box().size;
+        self.mark_dirty(Region::new(0, 0, size.width, size.height));
     }
 
     /// Returns the buffer
@@ -50,181 +144,446 @@ pub trait Display: DrawTarget<Color = BinaryColor> {
     /// Get the current rotation of the display
     fn rotation(&self) -> DisplayRotation;
 
-    /// Helperfunction for the Embedded Graphics draw trait
+    /// Where the pixels lie in the buffer
     ///
-    /// Becomes uneccesary when const_generics become stablised
-    fn draw_helper(
-        &mut self,
-        width: u32,
-        height: u32,
-        pixel: Pixel<BinaryColor>,
-    ) -> Result<(), Self::Error> {
-        let rotation = self.rotation();
-        let buffer = self.get_mut_buffer();
-
-        let Pixel(point, color) = pixel;
-        if outside_display(point, width, height, rotation) {
-            return Ok(());
-        }
+    /// Rows of whole bytes, starting with the first pixel, unless the display says otherwise. All
+    /// drawing, filling, blitting, inverting and scrolling goes through it.
+    fn ram_layout(&self) -> RamLayout {
+        let (width, _) = native_size(self);
+        RamLayout::packed(width, C::BITS_PER_PIXEL)
+    }
 
-        // Give us index inside the buffer and the bit-position in that u8 which needs to be changed
-        let (index, bit) = find_position(point.x as u32, point.y as u32, width, height, rotation);
-        let index = index as usize;
+    /// Storage of the dirty region, `None` for displays not tracking it
+    fn dirty_region_mut(&mut self) -> Option<&mut Option<Region>> {
+        None
+    }
 
-        // "Draw" the Pixel on that bit
-        match color {
-            // Black
-            BinaryColor::On => {
-                buffer[index] &= !bit;
-            }
-            // White
-            BinaryColor::Off => {
-                buffer[index] |= bit;
-            }
+    /// Adds `region` to the dirty region, e.g. after writing to the buffer directly
+    fn mark_dirty(&mut self, region: Region) {
+        if let Some(dirty) = self.dirty_region_mut() {
+            *dirty = Some(dirty.map_or(region, |dirty| dirty.union(region)));
         }
-        Ok(())
     }
-}
 
-/// Necessary traits for all displays to implement for drawing
-///
-/// Adds support for:
-/// - Drawing (With the help of DrawTarget/Embedded Graphics)
-/// - Rotations
-/// - Clearing
-pub trait TriDisplay: DrawTarget<Color = TriColor> {
-    /// Clears the buffer of the display with the chosen background color
-    fn clear_buffer(&mut self, background_color: TriColor) {
-        for elem in self.get_mut_buffer().iter_mut() {
-            *elem = background_color.get_byte_value();
-        }
+    /// Takes the bounding box of the pixels drawn or cleared since the last call
+    ///
+    /// In the coordinates of the rotated buffer like the drawing, e.g. for
+    /// [`update_rotated_partial_frame`](crate::traits::WaveshareDisplay::update_rotated_partial_frame).
+    /// `None` if nothing changed or the display doesn't track it. Writes to
+    /// [`get_mut_buffer`](Display::get_mut_buffer) aren't seen, see
+    /// [`mark_dirty`](Display::mark_dirty).
+    fn take_dirty_region(&mut self) -> Option<Region> {
+        self.dirty_region_mut().and_then(Option::take)
     }
 
-    /// Returns the buffer
-    fn buffer(&self) -> &[u8];
+    /// Returns plane `index` of the buffer, e.g. the chromatic plane of a tri color display
+    fn plane(&self, index: usize) -> &[u8] {
+        let buffer = self.buffer();
+        let plane_len = buffer.len() / C::PLANES;
+        &buffer[index * plane_len..(index + 1) * plane_len]
+    }
 
-    /// Returns a mutable buffer
-    fn get_mut_buffer(&mut self) -> &mut [u8];
+    /// Flips black and white in the whole buffer, e.g. for a dark mode
+    ///
+    /// Inverts the b/w plane, tri color displays keep their chromatic pixels. Gray displays get
+    /// the inverted gray levels. Only for the colors with up to two bits per pixel, see
+    /// [`InvertibleColor`].
+    fn invert(&mut self)
+    where
+        C: InvertibleColor,
+    {
+        let buffer = self.get_mut_buffer();
+        let plane_len = buffer.len() / C::PLANES;
+        for byte in &mut buffer[..plane_len] {
+            *byte = !*byte;
+        }
+        let size = self.bounding_box().size;
+        self.mark_dirty(Region::new(0, 0, size.width, size.height));
+    }
 
-    /// Sets the rotation of the display
-    fn set_rotation(&mut self, rotation: DisplayRotation);
+    /// Like [`invert`](Display::invert), but only `region`, e.g. to highlight a selected item
+    ///
+    /// `region` is in the coordinates of the rotated buffer like the drawing, the part outside of
+    /// the display is ignored.
+    fn invert_region(&mut self, region: Region)
+    where
+        C: InvertibleColor,
+    {
+        let (width, height) = native_size(self);
+        let rotation = self.rotation();
+        let area = Rectangle::new(
+            Point::new(region.x as i32, region.y as i32),
+            Size::new(region.width, region.height),
+        );
+        let Some(region) = clip(&area, width, height, rotation) else {
+            return;
+        };
+        let layout = self.ram_layout();
+        let buffer = self.get_mut_buffer();
+        let plane_len = buffer.len() / C::PLANES;
+        packing::invert_region_in(
+            &mut buffer[..plane_len],
+            width,
+            height,
+            layout,
+            rotation,
+            region,
+            C::BITS_PER_PIXEL,
+        );
+        self.mark_dirty(region);
+    }
 
-    /// Get the current rotation of the display
-    fn rotation(&self) -> DisplayRotation;
+    /// Draws with black and white swapped, e.g. white text on a black bar
+    ///
+    /// ```rust
+    /// # use epd_waveshare::epd4in2::Display4in2;
+    /// # use epd_waveshare::prelude::*;
+    /// # use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+    /// let mut display = Display4in2::default();
+    /// display.clear_buffer(Color::Black);
+    /// Pixel(Point::new(0, 0), BinaryColor::On).draw(&mut display.inverted())?;
+    /// assert_eq!(display.buffer()[0], 0x80);
+    /// # Ok::<(), core::convert::Infallible>(())
+    /// ```
+    fn inverted(&mut self) -> Inverted<'_, Self>
+    where
+        Self: Sized,
+    {
+        Inverted { display: self }
+    }
 
-    /// Get the offset into buffer where chromatic data starts
-    fn chromatic_offset(&self) -> usize;
+    /// Moves the content up by `rows`, e.g. for a log, and fills the rows exposed at the bottom
+    /// with `background`
+    ///
+    /// Up in the coordinates of the rotated buffer like the drawing. Moves the packed bytes
+    /// instead of drawing everything again.
+    fn scroll_up<B: Into<C>>(&mut self, rows: u32, background: B) {
+        let (width, height) = native_size(self);
+        let rotation = self.rotation();
+        let layout = self.ram_layout();
+        let buffer = self.get_mut_buffer();
+        packing::scroll_up_in(
+            buffer,
+            width,
+            height,
+            layout,
+            rotation,
+            rows,
+            background.into(),
+        );
+        if rows > 0 {
+            let size = self.bounding_box().size;
+            self.mark_dirty(Region::new(0, 0, size.width, size.height));
+        }
+    }
 
-    /// return the b/w part of the buffer
-    fn bw_buffer(&self) -> &[u8];
+    /// Like [`scroll_up`](Display::scroll_up), but moves the content left by `columns`
+    fn scroll_left<B: Into<C>>(&mut self, columns: u32, background: B) {
+        let (width, height) = native_size(self);
+        let rotation = self.rotation();
+        let layout = self.ram_layout();
+        let buffer = self.get_mut_buffer();
+        packing::scroll_left_in(
+            buffer,
+            width,
+            height,
+            layout,
+            rotation,
+            columns,
+            background.into(),
+        );
+        if columns > 0 {
+            let size = self.bounding_box().size;
+            self.mark_dirty(Region::new(0, 0, size.width, size.height));
+        }
+    }
 
-    /// return the chromatic part of the buffer
-    fn chromatic_buffer(&self) -> &[u8];
+    /// Copies a packed b/w bitmap, e.g. an icon or a rendered glyph, to `x`, `y` of the buffer
+    ///
+    /// `data` is `width` x `height` pixels packed like a b/w buffer: rows padded to whole bytes,
+    /// the most significant bit first and cleared bits black. Much faster than drawing the pixels,
+    /// whole bytes are copied if the buffer isn't rotated. The parts past the display are left
+    /// out. Tri color displays get the bitmap in the b/w plane. Only for the colors with one bit
+    /// per pixel, see [`MonoColor`]. Leaves the buffer untouched if `data` is too short.
+    fn blit(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> Result<(), BitmapTooShort>
+    where
+        C: MonoColor,
+    {
+        let needed = width.div_ceil(8) as usize * height as usize;
+        if data.len() < needed {
+            return Err(BitmapTooShort { needed });
+        }
+        let (display_width, display_height) = native_size(self);
+        let rotation = self.rotation();
+        let layout = self.ram_layout();
+        let buffer = self.get_mut_buffer();
+        let plane_len = buffer.len() / C::PLANES;
+        let written = packing::blit_in(
+            &mut buffer[..plane_len],
+            display_width,
+            display_height,
+            layout,
+            rotation,
+            x,
+            y,
+            width,
+            height,
+            data,
+        );
+        if let Some(region) = written {
+            self.mark_dirty(region);
+        }
+        Ok(())
+    }
 
     /// Helperfunction for the Embedded Graphics draw trait
     ///
     /// Becomes uneccesary when const_generics become stablised
-    fn draw_helper_tri(
+    fn draw_helper(&mut self, width: u32, height: u32, pixel: Pixel<C>) -> Result<(), Self::Error> {
+        let rotation = self.rotation();
+        let layout = self.ram_layout();
+        let Pixel(point, color) = pixel;
+        let buffer = self.get_mut_buffer();
+        packing::set_packed_pixel_in(buffer, width, height, layout, rotation, point, color);
+        let (width, height) = logical_size(width, height, rotation);
+        if let (Ok(x), Ok(y)) = (u32::try_from(point.x), u32::try_from(point.y)) {
+            if x < width && y < height {
+                self.mark_dirty(Region::new(x, y, 1, 1));
+            }
+        }
+        Ok(())
+    }
+
+    /// Helperfunction for [`DrawTarget::fill_solid`], fills whole bytes of the buffer at once
+    fn fill_solid_helper(
         &mut self,
         width: u32,
         height: u32,
-        pixel: Pixel<TriColor>,
+        area: &Rectangle,
+        color: C,
     ) -> Result<(), Self::Error> {
         let rotation = self.rotation();
+        let Some(region) = clip(area, width, height, rotation) else {
+            return Ok(());
+        };
+        let layout = self.ram_layout();
+        packing::fill_packed_region_in(
+            self.get_mut_buffer(),
+            width,
+            height,
+            layout,
+            rotation,
+            region,
+            color,
+        );
+        self.mark_dirty(region);
+        Ok(())
+    }
 
-        let Pixel(point, color) = pixel;
-        if outside_display(point, width, height, rotation) {
+    /// Helperfunction for [`DrawTarget::fill_contiguous`], marks the area dirty once instead of
+    /// per pixel
+    fn fill_contiguous_helper<I>(
+        &mut self,
+        width: u32,
+        height: u32,
+        area: &Rectangle,
+        colors: I,
+    ) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = C>,
+    {
+        let rotation = self.rotation();
+        let Some(region) = clip(area, width, height, rotation) else {
             return Ok(());
-        }
+        };
+        let layout = self.ram_layout();
+        packing::fill_packed_contiguous_in(
+            self.get_mut_buffer(),
+            width,
+            height,
+            layout,
+            rotation,
+            area,
+            colors,
+        );
+        self.mark_dirty(region);
+        Ok(())
+    }
+}
 
-        // Give us index inside the buffer and the bit-position in that u8 which needs to be changed
-        let (index, bit) = find_position(point.x as u32, point.y as u32, width, height, rotation);
-        let index = index as usize;
-        let offset = self.chromatic_offset();
+/// Error of [`Display::blit`]: the bitmap data is shorter than its size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitmapTooShort {
+    /// Bytes needed for the size of the bitmap
+    pub needed: usize,
+}
 
-        let buffer = self.get_mut_buffer();
+/// Draws on a b/w display with black and white swapped, see [`Display::inverted`]
+pub struct Inverted<'a, D> {
+    display: &'a mut D,
+}
 
-        // "Draw" the Pixel on that bit
-        match color {
-            TriColor::Black => {
-                // clear bit in bw-buffer -> black
-                buffer[index] &= !bit;
-                // set bit in chromatic-buffer -> white
-                buffer[index + offset] |= bit;
-            }
-            TriColor::White => {
-                // set bit in bw-buffer -> white
-                buffer[index] |= bit;
-                // set bit in chromatic-buffer -> white
-                buffer[index + offset] |= bit;
-            }
-            TriColor::Chromatic => {
-                // set bit in b/w buffer (white)
-                buffer[index] |= bit;
-                // clear bit in chromatic buffer -> chromatic
-                buffer[index + offset] &= !bit;
-            }
-        }
-        Ok(())
+impl<D> DrawTarget for Inverted<'_, D>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.display.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, color.invert())),
+        )
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.display
+            .fill_contiguous(area, colors.into_iter().map(BinaryColor::invert))
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.fill_solid(area, color.invert())
     }
 }
 
-/// Necessary traits for all displays to implement for drawing
+impl<D> Dimensions for Inverted<'_, D>
+where
+    D: Dimensions,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.display.bounding_box()
+    }
+}
+
+// the part of `area` on the rotated buffer, `None` if there is none
+fn clip(area: &Rectangle, width: u32, height: u32, rotation: DisplayRotation) -> Option<Region> {
+    let (width, height) = logical_size(width, height, rotation);
+    let area = area.intersection(&Rectangle::new(Point::zero(), Size::new(width, height)));
+    if area.is_zero_sized() {
+        return None;
+    }
+    Some(Region::new(
+        area.top_left.x as u32,
+        area.top_left.y as u32,
+        area.size.width,
+        area.size.height,
+    ))
+}
+
+/// The size of a `width` x `height` display drawn with `rotation`
 ///
-/// Adds support for:
-/// - Drawing (With the help of DrawTarget/Embedded Graphics)
-/// - Rotations
-/// - Clearing
-pub trait OctDisplay: DrawTarget<Color = OctColor> {
-    /// Clears the buffer of the display with the chosen background color
-    fn clear_buffer(&mut self, background_color: OctColor) {
-        for elem in self.get_mut_buffer().iter_mut() {
-            *elem = OctColor::colors_byte(background_color, background_color);
-        }
+/// What [`OriginDimensions::size`] of a [`Display`] returns, e.g. for layouts centered on the
+/// rotated display.
+pub fn rotated_size(width: u32, height: u32, rotation: DisplayRotation) -> Size {
+    let (width, height) = logical_size(width, height, rotation);
+    Size::new(width, height)
+}
+
+// size of the unrotated buffer of a display, the rotations swap the axes or keep them
+fn native_size<C, D>(display: &D) -> (u32, u32)
+where
+    C: PackedColor + PixelColor,
+    D: Display<C> + ?Sized,
+{
+    let size = display.bounding_box().size;
+    logical_size(size.width, size.height, display.rotation())
+}
+
+// size of the rotated buffer
+fn logical_size(width: u32, height: u32, rotation: DisplayRotation) -> (u32, u32) {
+    match rotation {
+        DisplayRotation::Rotate0
+        | DisplayRotation::Rotate180
+        | DisplayRotation::FlipHorizontal
+        | DisplayRotation::FlipVertical => (width, height),
+        DisplayRotation::Rotate90
+        | DisplayRotation::Rotate270
+        | DisplayRotation::Rotate90FlipHorizontal
+        | DisplayRotation::Rotate270FlipHorizontal => (height, width),
     }
+}
 
-    /// Returns the buffer
-    fn buffer(&self) -> &[u8];
+/// Tri color displays, with accessors for the two planes of the buffer
+///
+/// Implemented for every [`Display<TriColor>`](Display).
+pub trait TriDisplay: Display<TriColor> {
+    /// Get the offset into buffer where chromatic data starts
+    fn chromatic_offset(&self) -> usize {
+        self.buffer().len() / TriColor::PLANES
+    }
 
-    /// Returns a mutable buffer
-    fn get_mut_buffer(&mut self) -> &mut [u8];
+    /// return the b/w part of the buffer
+    fn bw_buffer(&self) -> &[u8] {
+        self.plane(0)
+    }
 
-    /// Sets the rotation of the display
-    fn set_rotation(&mut self, rotation: DisplayRotation);
+    /// return the chromatic part of the buffer
+    fn chromatic_buffer(&self) -> &[u8] {
+        self.plane(1)
+    }
+}
 
-    /// Get the current rotation of the display
-    fn rotation(&self) -> DisplayRotation;
+impl<T: Display<TriColor>> TriDisplay for T {}
 
-    /// Helperfunction for the Embedded Graphics draw trait
-    ///
-    /// Becomes uneccesary when const_generics become stablised
-    fn draw_helper(
-        &mut self,
-        width: u32,
-        height: u32,
-        pixel: Pixel<OctColor>,
-    ) -> Result<(), Self::Error> {
-        let rotation = self.rotation();
-        let buffer = self.get_mut_buffer();
+/// 7 color displays
+///
+/// Implemented for every [`Display<OctColor>`](Display).
+pub trait OctDisplay: Display<OctColor> {}
 
-        let Pixel(point, color) = pixel;
-        if outside_display(point, width, height, rotation) {
-            return Ok(());
+impl<T: Display<OctColor>> OctDisplay for T {}
+
+/// `BinaryColor::On` is black
+impl PackedColor for BinaryColor {
+    const BITS_PER_PIXEL: u32 = 1;
+    const PLANES: usize = 1;
+    const PADDING: Self = BinaryColor::Off;
+
+    fn plane_bits(self, _plane: usize) -> u8 {
+        match self {
+            BinaryColor::On => 0,
+            BinaryColor::Off => 1,
         }
+    }
+}
 
-        // Give us index inside the buffer and the bit-position in that u8 which needs to be changed
-        let (index, upper) =
-            find_oct_position(point.x as u32, point.y as u32, width, height, rotation);
-        let index = index as usize;
+/// Two bits per pixel, the luma of the color: `0b11` is white and `0b00` black
+impl PackedColor for Gray2 {
+    const BITS_PER_PIXEL: u32 = 2;
+    const PLANES: usize = 1;
+    const PADDING: Self = Gray2::WHITE;
 
-        // "Draw" the Pixel on that bit
-        let (mask, color_nibble) = if upper {
-            (0x0f, color.get_nibble() << 4)
-        } else {
-            (0xf0, color.get_nibble())
-        };
-        buffer[index] = (buffer[index] & mask) | color_nibble;
-        Ok(())
+    fn plane_bits(self, _plane: usize) -> u8 {
+        self.luma()
+    }
+}
+
+impl InvertibleColor for BinaryColor {}
+
+impl MonoColor for BinaryColor {}
+
+impl InvertibleColor for Gray2 {}
+
+impl From<Color> for BinaryColor {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Black => BinaryColor::On,
+            Color::White => BinaryColor::Off,
+        }
     }
 }
 
@@ -258,23 +617,73 @@ pub struct VarDisplay<'a> {
     width: u32,
     height: u32,
     rotation: DisplayRotation,
-    buffer: &'a mut [u8], //buffer: Box<u8>//[u8; 15000]
+    buffer: &'a mut [u8],
+    len: usize,
+    layout: RamLayout,
+    dirty: Option<Region>,
 }
 
 impl<'a> VarDisplay<'a> {
     /// Create a new variable sized display.
     ///
-    /// Buffersize must be at least (width + 7) / 8 * height bytes.
+    /// Buffersize must be at least (width + 7) / 8 * height bytes, only that many bytes are used.
+    /// A larger buffer can be reused for other sizes with [`set_size`](VarDisplay::set_size).
     pub fn new(width: u32, height: u32, buffer: &'a mut [u8]) -> VarDisplay<'a> {
-        let len = buffer.len() as u32;
-        assert!(buffer_len(width as usize, height as usize) >= len as usize);
+        let len = buffer_len(width as usize, height as usize);
+        assert!(buffer.len() >= len, "buffer too small for the display");
         VarDisplay {
             width,
             height,
             rotation: DisplayRotation::default(),
             buffer,
+            len,
+            layout: RamLayout::packed(width, 1),
+            dirty: None,
         }
     }
+
+    /// Create a new variable sized display with the pixels at `layout` in the buffer, e.g. a
+    /// panel narrower than the RAM of its controller
+    ///
+    /// Buffersize must be at least `layout.stride * height` bytes, only that many bytes are used.
+    /// Panics if the rows of `layout` are too short for `width` pixels.
+    pub fn with_ram_layout(
+        width: u32,
+        height: u32,
+        layout: RamLayout,
+        buffer: &'a mut [u8],
+    ) -> VarDisplay<'a> {
+        assert!(
+            (layout.x_offset + width).div_ceil(8) <= layout.stride,
+            "rows too short for the display"
+        );
+        let len = layout.plane_len(height);
+        assert!(buffer.len() >= len, "buffer too small for the display");
+        VarDisplay {
+            width,
+            height,
+            rotation: DisplayRotation::default(),
+            buffer,
+            len,
+            layout,
+            dirty: None,
+        }
+    }
+
+    /// Changes the size of the display, e.g. to draw the next partial window into the same buffer
+    ///
+    /// The content of the buffer is kept as it is, clear it before drawing. The rows are packed
+    /// again, without the offset of [`with_ram_layout`](VarDisplay::with_ram_layout). Panics if
+    /// the buffer is too small for the new size.
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        let len = buffer_len(width as usize, height as usize);
+        assert!(self.buffer.len() >= len, "buffer too small for the display");
+        self.width = width;
+        self.height = height;
+        self.len = len;
+        self.layout = RamLayout::packed(width, 1);
+        self.dirty = None;
+    }
 }
 
 impl<'a> DrawTarget for VarDisplay<'a> {
@@ -290,21 +699,32 @@ impl<'a> DrawTarget for VarDisplay<'a> {
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(self.width, self.height, area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.fill_contiguous_helper(self.width, self.height, area, colors)
+    }
 }
 
 impl<'a> OriginDimensions for VarDisplay<'a> {
     fn size(&self) -> Size {
-        Size::new(self.width, self.height)
+        rotated_size(self.width, self.height, self.rotation)
     }
 }
 
 impl<'a> Display for VarDisplay<'a> {
     fn buffer(&self) -> &[u8] {
-        &self.buffer
+        &self.buffer[..self.len]
     }
 
     fn get_mut_buffer(&mut self) -> &mut [u8] {
-        &mut self.buffer
+        &mut self.buffer[..self.len]
     }
 
     fn set_rotation(&mut self, rotation: DisplayRotation) {
@@ -314,78 +734,222 @@ impl<'a> Display for VarDisplay<'a> {
     fn rotation(&self) -> DisplayRotation {
         self.rotation
     }
-}
 
-// Checks if a pos is outside the defined display
-fn outside_display(p: Point, width: u32, height: u32, rotation: DisplayRotation) -> bool {
-    if p.x < 0 || p.y < 0 {
-        return true;
+    fn ram_layout(&self) -> RamLayout {
+        self.layout
     }
-    let (x, y) = (p.x as u32, p.y as u32);
-    match rotation {
-        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
-            if x >= width || y >= height {
-                return true;
-            }
-        }
-        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
-            if y >= width || x >= height {
-                return true;
-            }
-        }
+
+    fn dirty_region_mut(&mut self) -> Option<&mut Option<Region>> {
+        Some(&mut self.dirty)
     }
-    false
 }
 
-fn find_rotation(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u32) {
-    let nx;
-    let ny;
-    match rotation {
-        DisplayRotation::Rotate0 => {
-            nx = x;
-            ny = y;
+/// A b/w display of a fixed size, with the buffer inline
+///
+/// The panels have aliases of their size, like [`Display4in2`](crate::epd4in2::Display4in2).
+/// `BYTES` is the length of the buffer and has to be [`buffer_len`]`(WIDTH, HEIGHT)`, stable Rust
+/// can't compute it from the size yet. Other lengths fail to compile. Also a buffer for a partial
+/// window of a known size:
+///
+/// ```rust
+/// # use epd_waveshare::buffer_len;
+/// # use epd_waveshare::graphics::FixedDisplay;
+/// # use epd_waveshare::prelude::*;
+/// type Window = FixedDisplay<64, 32, { buffer_len(64, 32) }>;
+///
+/// let window = Window::default();
+/// assert_eq!(window.buffer().len(), 64 / 8 * 32);
+/// ```
+pub struct FixedDisplay<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> {
+    buffer: [u8; BYTES],
+    rotation: DisplayRotation,
+    dirty: Option<Region>,
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> FixedDisplay<WIDTH, HEIGHT, BYTES> {
+    const LEN: () = assert!(
+        BYTES == buffer_len(WIDTH as usize, HEIGHT as usize),
+        "BYTES has to be buffer_len(WIDTH, HEIGHT)"
+    );
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> Default
+    for FixedDisplay<WIDTH, HEIGHT, BYTES>
+{
+    fn default() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::LEN;
+        FixedDisplay {
+            buffer: [Color::White.get_byte_value(); BYTES],
+            rotation: DisplayRotation::default(),
+            dirty: None,
         }
-        DisplayRotation::Rotate90 => {
-            nx = width - 1 - y;
-            ny = x;
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> DrawTarget
+    for FixedDisplay<WIDTH, HEIGHT, BYTES>
+{
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
         }
-        DisplayRotation::Rotate180 => {
-            nx = width - 1 - x;
-            ny = height - 1 - y;
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(WIDTH, HEIGHT, area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.fill_contiguous_helper(WIDTH, HEIGHT, area, colors)
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> OriginDimensions
+    for FixedDisplay<WIDTH, HEIGHT, BYTES>
+{
+    fn size(&self) -> Size {
+        rotated_size(WIDTH, HEIGHT, self.rotation)
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> Display
+    for FixedDisplay<WIDTH, HEIGHT, BYTES>
+{
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    fn dirty_region_mut(&mut self) -> Option<&mut Option<Region>> {
+        Some(&mut self.dirty)
+    }
+}
+
+/// A b/w display with its buffer on the heap
+///
+/// Like [`VarDisplay`], but owning the buffer. Keeps the large buffers of e.g. the 7.5" displays
+/// off the stack on targets with an allocator:
+///
+/// ```rust
+/// # use epd_waveshare::epd7in5_v2::{HEIGHT, WIDTH};
+/// # use epd_waveshare::graphics::HeapDisplay;
+/// # use epd_waveshare::prelude::*;
+/// let mut display = HeapDisplay::new(WIDTH, HEIGHT);
+/// display.set_rotation(DisplayRotation::Rotate90);
+/// assert_eq!(display.buffer().len(), WIDTH as usize / 8 * HEIGHT as usize);
+/// ```
+#[cfg(feature = "alloc")]
+pub struct HeapDisplay {
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    buffer: alloc::boxed::Box<[u8]>,
+    dirty: Option<Region>,
+}
+
+#[cfg(feature = "alloc")]
+impl HeapDisplay {
+    /// Allocates a white buffer of a `width` x `height` display
+    pub fn new(width: u32, height: u32) -> HeapDisplay {
+        let len = buffer_len(width as usize, height as usize);
+        HeapDisplay {
+            width,
+            height,
+            rotation: DisplayRotation::default(),
+            buffer: alloc::vec![Color::White.get_byte_value(); len].into_boxed_slice(),
+            dirty: None,
         }
-        DisplayRotation::Rotate270 => {
-            nx = y;
-            ny = height - 1 - x;
+    }
+
+    /// Gives back the buffer
+    pub fn into_buffer(self) -> alloc::boxed::Box<[u8]> {
+        self.buffer
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl DrawTarget for HeapDisplay {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(self.width, self.height, pixel)?;
         }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(self.width, self.height, area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.fill_contiguous_helper(self.width, self.height, area, colors)
     }
-    (nx, ny)
 }
 
-#[rustfmt::skip]
-//returns index position in the u8-slice and the bit-position inside that u8
-fn find_oct_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, bool) {
-    let (nx, ny) = find_rotation(x, y, width, height, rotation);
-    (
-        /* what byte address is this? */
-        nx / 2 + (width / 2) * ny,
-        /* is this the lower nibble (within byte)? */
-        (nx & 0x1) == 0,
-    )
+#[cfg(feature = "alloc")]
+impl OriginDimensions for HeapDisplay {
+    fn size(&self) -> Size {
+        rotated_size(self.width, self.height, self.rotation)
+    }
 }
 
-#[rustfmt::skip]
-//returns index position in the u8-slice and the bit-position inside that u8
-fn find_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u8) {
-    let (nx, ny) = find_rotation(x, y, width, height, rotation);
-    (
-        nx / 8 + ((width + 7) / 8) * ny,
-        0x80 >> (nx % 8),
-    )
+#[cfg(feature = "alloc")]
+impl Display for HeapDisplay {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    fn dirty_region_mut(&mut self) -> Option<&mut Option<Region>> {
+        Some(&mut self.dirty)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{buffer_len, find_position, outside_display, Display, DisplayRotation, VarDisplay};
+    use super::packing::{find_position, outside_display};
+    use super::{buffer_len, Display, DisplayRotation, RamLayout, VarDisplay};
     use crate::color::Black;
     use crate::color::Color;
     use embedded_graphics::{
@@ -415,8 +979,8 @@ mod tests {
     #[test]
     fn rotation_overflow() {
         use crate::epd4in2::{HEIGHT, WIDTH};
-        let width = WIDTH as u32;
-        let height = HEIGHT as u32;
+        let width = WIDTH;
+        let height = HEIGHT;
         test_rotation_overflow(width, height, DisplayRotation::Rotate0);
         test_rotation_overflow(width, height, DisplayRotation::Rotate90);
         test_rotation_overflow(width, height, DisplayRotation::Rotate180);
@@ -427,7 +991,7 @@ mod tests {
         let max_value = width / 8 * height;
         for x in 0..(width + height) {
             //limit x because it runs too long
-            for y in 0..(u32::max_value()) {
+            for y in 0..(u32::MAX) {
                 if outside_display(Point::new(x as i32, y as i32), width, height, rotation2) {
                     break;
                 } else {
@@ -486,4 +1050,337 @@ mod tests {
             assert_eq!(byte, DEFAULT_BACKGROUND_COLOR.get_byte_value());
         }
     }
+
+    #[test]
+    fn size_follows_rotation() {
+        use crate::epd2in13bc::Display2in13bc;
+        use crate::epd2in7b::{Display2in7Gray, Display2in7b};
+        use crate::epd5in65f::Display5in65f;
+        use embedded_graphics::primitives::Rectangle;
+
+        fn check<C, D>(mut display: D, width: u32, height: u32)
+        where
+            C: crate::source::PackedColor + PixelColor,
+            D: Display<C> + OriginDimensions,
+        {
+            for rotation in [DisplayRotation::Rotate0, DisplayRotation::Rotate180] {
+                display.set_rotation(rotation);
+                assert_eq!(display.size(), Size::new(width, height));
+            }
+            display.set_rotation(DisplayRotation::Rotate90);
+            assert_eq!(display.size(), Size::new(height, width));
+            // e.g. for centered layouts
+            let portrait = Rectangle::new(Point::zero(), Size::new(height, width));
+            assert_eq!(display.bounding_box().center(), portrait.center());
+            display.set_rotation(DisplayRotation::Rotate270FlipHorizontal);
+            assert_eq!(display.size(), Size::new(height, width));
+        }
+
+        let mut buffer = [0xff; 16 / 8 * 4];
+        check(VarDisplay::new(16, 4, &mut buffer), 16, 4);
+        check(Display2in7b::default(), 176, 264);
+        check(Display2in13bc::default(), 104, 212);
+        check(Display2in7Gray::default(), 176, 264);
+        check(Display5in65f::default(), 600, 448);
+    }
+
+    #[test]
+    fn var_display_reuses_buffer() {
+        let mut buffer = [0x00; 64];
+        let mut display = VarDisplay::new(16, 4, &mut buffer);
+        assert_eq!(display.buffer().len(), 8);
+        display.clear_buffer(Color::White);
+        assert_eq!(display.buffer(), [0xff; 8]);
+
+        // The next window, narrower but taller
+        display.set_size(10, 6);
+        assert_eq!(display.size(), Size::new(10, 6));
+        display.clear_buffer(Color::White);
+        let _ = Pixel(Point::new(9, 5), Black).draw(&mut display);
+        let mut expected = [0xff; 12];
+        expected[11] = 0xbf;
+        assert_eq!(display.buffer(), expected);
+        assert_eq!(buffer[12..], [0x00; 52]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn heap_display() {
+        use super::HeapDisplay;
+
+        let mut display = HeapDisplay::new(10, 2);
+        assert_eq!(display.buffer(), [0xff; 4]);
+        let _ = Pixel(Point::new(9, 1), Black).draw(&mut display);
+        assert_eq!(display.into_buffer()[..], [0xff, 0xff, 0xff, 0xbf]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn var_display_too_small() {
+        let mut buffer = [0x00; 7];
+        VarDisplay::new(16, 4, &mut buffer);
+    }
+
+    #[test]
+    fn var_display_ram_layout() {
+        use embedded_graphics::{pixelcolor::BinaryColor, primitives::Rectangle};
+
+        // 10 pixels, 3 pixels into rows of 3 bytes
+        let layout = RamLayout::new(3, 3);
+        let mut buffer = [0xff; 3 * 2];
+        let mut display = VarDisplay::with_ram_layout(10, 2, layout, &mut buffer);
+        assert_eq!(display.ram_layout(), layout);
+        assert_eq!(display.buffer().len(), 6);
+
+        Pixel(Point::new(0, 0), BinaryColor::On)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.buffer(), [0xef, 0xff, 0xff, 0xff, 0xff, 0xff]);
+
+        let row = Rectangle::new(Point::new(0, 1), Size::new(10, 1));
+        display.fill_solid(&row, BinaryColor::On).unwrap();
+        assert_eq!(display.buffer(), [0xef, 0xff, 0xff, 0xe0, 0x07, 0xff]);
+
+        display.blit(9, 0, 8, 1, &[0x00]).unwrap();
+        assert_eq!(display.buffer(), [0xef, 0xf7, 0xff, 0xe0, 0x07, 0xff]);
+
+        display.set_size(16, 3);
+        assert_eq!(display.ram_layout(), RamLayout::packed(16, 1));
+        assert_eq!(display.buffer().len(), 6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn var_display_ram_layout_too_narrow() {
+        let mut buffer = [0xff; 4];
+        let layout = RamLayout::new(7, 2);
+        VarDisplay::with_ram_layout(10, 2, layout, &mut buffer);
+    }
+
+    #[test]
+    fn var_display_lsb_first() {
+        use crate::source::{pack, BitOrder, StridedFrame};
+        use embedded_graphics::{pixelcolor::BinaryColor, primitives::Rectangle};
+
+        let scene = |display: &mut VarDisplay<'_>| {
+            Pixel(Point::new(0, 0), BinaryColor::On)
+                .draw(display)
+                .unwrap();
+            let row = Rectangle::new(Point::new(2, 1), Size::new(9, 1));
+            display.fill_solid(&row, BinaryColor::On).unwrap();
+            display.blit(5, 2, 8, 1, &[0x0f]).unwrap();
+            display.scroll_left(1, Color::White);
+        };
+
+        let layout = RamLayout::packed(12, 1).with_bit_order(BitOrder::LsbFirst);
+        let mut buffer = [0xff; 2 * 3];
+        let mut display = VarDisplay::with_ram_layout(12, 3, layout, &mut buffer);
+        scene(&mut display);
+        let mut expected = [0xff; 2 * 3];
+        let mut msb_first = VarDisplay::new(12, 3, &mut expected);
+        scene(&mut msb_first);
+
+        // the first pixel scrolled out, the rows start in the lowest bit
+        assert_eq!(display.buffer(), [0xff, 0x7f, 0x01, 0x7c, 0x0f, 0x7f]);
+        // sent like the display without the option, the padding is white in both
+        let sent = |buffer: &[u8], layout: RamLayout| {
+            let mut packed = [0; 2 * 3];
+            pack(
+                &StridedFrame::with_layout(buffer, layout),
+                12,
+                3,
+                0,
+                &mut packed,
+            );
+            packed
+        };
+        let packed = RamLayout::packed(12, 1);
+        assert_eq!(
+            sent(display.buffer(), layout),
+            sent(msb_first.buffer(), packed)
+        );
+    }
+
+    // the fast fills have to match drawing pixel by pixel
+    fn check_fills<C, D>(make: impl Fn() -> D, a: C, b: C)
+    where
+        C: crate::source::PackedColor + PixelColor,
+        D: Display<C> + DrawTarget<Error = core::convert::Infallible>,
+    {
+        use embedded_graphics::primitives::Rectangle;
+
+        let areas = [
+            Rectangle::new(Point::new(1, 2), Size::new(7, 5)),
+            Rectangle::new(Point::new(3, 3), Size::new(1, 1)),
+            Rectangle::new(Point::new(-3, -1), Size::new(20, 4)),
+            Rectangle::new(Point::new(2, 1), Size::new(0, 5)),
+            Rectangle::new(Point::new(-1, -1), Size::new(1000, 1000)),
+        ];
+        let pattern = |p: Point| if (p.x + p.y) % 3 == 0 { a } else { b };
+        for rotation in [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ] {
+            for area in &areas {
+                let mut slow = make();
+                slow.set_rotation(rotation);
+                slow.draw_iter(area.points().map(|p| Pixel(p, a))).unwrap();
+                let mut fast = make();
+                fast.set_rotation(rotation);
+                fast.fill_solid(area, a).unwrap();
+                assert_eq!(fast.buffer(), slow.buffer(), "{:?} {:?}", rotation, area);
+                assert_eq!(fast.take_dirty_region(), slow.take_dirty_region());
+
+                let mut slow = make();
+                slow.set_rotation(rotation);
+                slow.draw_iter(area.points().map(|p| Pixel(p, pattern(p))))
+                    .unwrap();
+                let mut fast = make();
+                fast.set_rotation(rotation);
+                fast.fill_contiguous(area, area.points().map(pattern))
+                    .unwrap();
+                assert_eq!(fast.buffer(), slow.buffer(), "{:?} {:?}", rotation, area);
+                assert_eq!(fast.take_dirty_region(), slow.take_dirty_region());
+            }
+        }
+    }
+
+    #[test]
+    fn fills() {
+        use super::FixedDisplay;
+        use crate::color::{OctColor, TriColor};
+        use embedded_graphics::pixelcolor::{BinaryColor, Gray2};
+
+        check_fills(
+            FixedDisplay::<10, 6, { buffer_len(10, 6) }>::default,
+            BinaryColor::On,
+            BinaryColor::Off,
+        );
+        check_fills(
+            crate::epd2in13bc::Display2in13bc::default,
+            TriColor::Chromatic,
+            TriColor::Black,
+        );
+        check_fills(
+            crate::epd2in7b::Display2in7Gray::default,
+            Gray2::new(1),
+            Gray2::new(2),
+        );
+        check_fills(
+            crate::epd5in65f::Display5in65f::default,
+            OctColor::Red,
+            OctColor::Blue,
+        );
+    }
+
+    #[test]
+    fn blit() {
+        use super::FixedDisplay;
+        use crate::region::Region;
+
+        let mut display = FixedDisplay::<16, 4, { buffer_len(16, 4) }>::default();
+        // A 10x2 bitmap, black but the last column
+        display
+            .blit(3, 1, 10, 2, &[0x00, 0x40, 0x00, 0x40])
+            .unwrap();
+        assert_eq!(
+            display.buffer(),
+            [0xff, 0xff, 0xe0, 0x0f, 0xe0, 0x0f, 0xff, 0xff]
+        );
+        assert_eq!(display.take_dirty_region(), Some(Region::new(3, 1, 10, 2)));
+
+        // Rotated and cut off at the bottom
+        display.set_rotation(DisplayRotation::Rotate180);
+        display.blit(0, 3, 8, 2, &[0x7f, 0x00]).unwrap();
+        assert_eq!(display.buffer()[..2], [0xff, 0xfe]);
+        assert_eq!(display.take_dirty_region(), Some(Region::new(0, 3, 8, 1)));
+
+        assert_eq!(
+            display.blit(0, 0, 10, 2, &[0x00; 3]),
+            Err(super::BitmapTooShort { needed: 4 })
+        );
+        assert_eq!(display.take_dirty_region(), None);
+    }
+
+    #[test]
+    fn invert() {
+        use super::FixedDisplay;
+        use crate::region::Region;
+        use embedded_graphics::pixelcolor::Gray2;
+
+        let mut display = FixedDisplay::<10, 2, { buffer_len(10, 2) }>::default();
+        display.invert();
+        assert_eq!(display.buffer(), [0x00; 4]);
+        assert_eq!(display.take_dirty_region(), Some(Region::new(0, 0, 10, 2)));
+
+        display.set_rotation(DisplayRotation::Rotate90);
+        display.invert_region(Region::new(1, 6, 5, 5));
+        assert_eq!(display.buffer(), [0x00, 0x00, 0xf0, 0x00]);
+        assert_eq!(display.take_dirty_region(), Some(Region::new(1, 6, 1, 4)));
+
+        let mut display = crate::epd2in7b::Display2in7Gray::default();
+        let _ = Pixel(Point::new(0, 0), Gray2::new(1)).draw(&mut display);
+        assert_eq!(display.buffer()[0], 0b01_11_11_11);
+        display.invert_region(Region::new(0, 0, 2, 1));
+        assert_eq!(display.buffer()[0], 0b10_00_11_11);
+    }
+
+    #[test]
+    fn scroll() {
+        use super::TriDisplay;
+        use crate::color::TriColor;
+        use crate::epd2in13bc::{Display2in13bc, HEIGHT, WIDTH};
+        use crate::region::Region;
+
+        let mut display = Display2in13bc::default();
+        let _ = Pixel(Point::new(3, 5), TriColor::Chromatic).draw(&mut display);
+        let _ = Pixel(Point::new(4, 5), TriColor::Black).draw(&mut display);
+        display.take_dirty_region();
+
+        // A line of 2 pixels up, the new line at the bottom black
+        display.scroll_up(2, TriColor::Black);
+        assert_eq!(display.chromatic_buffer()[3 * WIDTH as usize / 8], 0xef);
+        assert_eq!(display.bw_buffer()[3 * WIDTH as usize / 8], 0xf7);
+        let last = (HEIGHT as usize - 1) * WIDTH as usize / 8;
+        assert_eq!(display.bw_buffer()[last], 0x00);
+        assert_eq!(display.chromatic_buffer()[last], 0xff);
+        assert_eq!(
+            display.take_dirty_region(),
+            Some(Region::new(0, 0, WIDTH, HEIGHT))
+        );
+
+        display.scroll_left(0, TriColor::White);
+        assert_eq!(display.take_dirty_region(), None);
+    }
+
+    #[test]
+    fn dirty_region() {
+        use crate::epd2in7b::{Display2in7b, HEIGHT, WIDTH};
+        use crate::region::Region;
+
+        let mut display = Display2in7b::default();
+        assert_eq!(display.take_dirty_region(), None);
+
+        display.set_rotation(DisplayRotation::Rotate90);
+        let _ = Line::new(Point::new(10, 20), Point::new(30, 20))
+            .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+            .draw(&mut display);
+        let _ = Pixel(Point::new(5, 40), Black).draw(&mut display);
+        // Outside of the rotated buffer
+        let _ = Pixel(Point::new(-1, 0), Black).draw(&mut display);
+        let _ = Pixel(Point::new(HEIGHT as i32, 0), Black).draw(&mut display);
+        assert_eq!(
+            display.take_dirty_region(),
+            Some(Region::new(5, 20, 26, 21))
+        );
+        assert_eq!(display.take_dirty_region(), None);
+
+        display.clear_buffer(Color::White);
+        assert_eq!(
+            display.take_dirty_region(),
+            Some(Region::new(0, 0, HEIGHT, WIDTH))
+        );
+    }
 }
@@ -0,0 +1,1275 @@
+//! Pure pixel packing and rotation math used by the display buffers
+//!
+//! None of these functions touch any hardware, they only compute positions inside the buffers
+//! or read and write pixels in them. All coordinates passed in are in the rotated (logical)
+//! coordinate system, `width` and `height` are always the unrotated size of the display.
+
+use super::{logical_size, DisplayRotation, RamLayout};
+use crate::color::{OctColor, TriColor};
+use crate::region::Region;
+use crate::source::{copy_bits, BitOrder, PackedColor};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics_core::prelude::{Point, PointsIter};
+use embedded_graphics_core::primitives::Rectangle;
+
+/// Checks if a point is outside of the display for the given rotation
+pub fn outside_display(p: Point, width: u32, height: u32, rotation: DisplayRotation) -> bool {
+    if p.x < 0 || p.y < 0 {
+        return true;
+    }
+    let (x, y) = (p.x as u32, p.y as u32);
+    match rotation {
+        DisplayRotation::Rotate0
+        | DisplayRotation::Rotate180
+        | DisplayRotation::FlipHorizontal
+        | DisplayRotation::FlipVertical => x >= width || y >= height,
+        DisplayRotation::Rotate90
+        | DisplayRotation::Rotate270
+        | DisplayRotation::Rotate90FlipHorizontal
+        | DisplayRotation::Rotate270FlipHorizontal => y >= width || x >= height,
+    }
+}
+
+/// Maps a logical position to the unrotated position on the display
+pub fn find_rotation(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> (u32, u32) {
+    match rotation {
+        DisplayRotation::Rotate0 => (x, y),
+        DisplayRotation::Rotate90 => (width - 1 - y, x),
+        DisplayRotation::Rotate180 => (width - 1 - x, height - 1 - y),
+        DisplayRotation::Rotate270 => (y, height - 1 - x),
+        DisplayRotation::FlipHorizontal => (width - 1 - x, y),
+        DisplayRotation::FlipVertical => (x, height - 1 - y),
+        DisplayRotation::Rotate90FlipHorizontal => (width - 1 - y, height - 1 - x),
+        DisplayRotation::Rotate270FlipHorizontal => (y, x),
+    }
+}
+
+/// Inverse of [`find_rotation`], maps an unrotated position back to the logical one
+pub fn find_logical(
+    nx: u32,
+    ny: u32,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> (u32, u32) {
+    match rotation {
+        DisplayRotation::Rotate0 => (nx, ny),
+        DisplayRotation::Rotate90 => (ny, width - 1 - nx),
+        DisplayRotation::Rotate180 => (width - 1 - nx, height - 1 - ny),
+        DisplayRotation::Rotate270 => (height - 1 - ny, nx),
+        DisplayRotation::FlipHorizontal => (width - 1 - nx, ny),
+        DisplayRotation::FlipVertical => (nx, height - 1 - ny),
+        DisplayRotation::Rotate90FlipHorizontal => (height - 1 - ny, width - 1 - nx),
+        DisplayRotation::Rotate270FlipHorizontal => (ny, nx),
+    }
+}
+
+/// Maps a logical region to the unrotated region on the display covering the same pixels
+///
+/// The region has to lie on the display.
+pub fn find_region(region: Region, width: u32, height: u32, rotation: DisplayRotation) -> Region {
+    let (x0, y0) = find_rotation(region.x, region.y, width, height, rotation);
+    let (x1, y1) = find_rotation(
+        region.x + region.width - 1,
+        region.y + region.height - 1,
+        width,
+        height,
+        rotation,
+    );
+    Region::new(
+        x0.min(x1),
+        y0.min(y1),
+        x0.abs_diff(x1) + 1,
+        y0.abs_diff(y1) + 1,
+    )
+}
+
+/// Index of the byte and the mask of the bit of a pixel in a one bit per pixel buffer
+#[rustfmt::skip]
+pub fn find_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u8) {
+    let (nx, ny) = find_rotation(x, y, width, height, rotation);
+    (
+        nx / 8 + width.div_ceil(8) * ny,
+        0x80 >> (nx % 8),
+    )
+}
+
+/// Index of the byte of a pixel in a four bit per pixel buffer and if it's the upper nibble
+#[rustfmt::skip]
+pub fn find_oct_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, bool) {
+    let (nx, ny) = find_rotation(x, y, width, height, rotation);
+    (
+        nx / 2 + (width / 2) * ny,
+        (nx & 0x1) == 0,
+    )
+}
+
+/// A byte of `plane` filled with pixels of `color`
+pub fn packed_byte<C: PackedColor>(color: C, plane: usize) -> u8 {
+    let bits = color.plane_bits(plane);
+    (0..8 / C::BITS_PER_PIXEL).fold(0, |byte, _| {
+        // 8 bits per pixel would overflow the shift
+        ((u16::from(byte) << C::BITS_PER_PIXEL) as u8) | bits
+    })
+}
+
+/// Sets a pixel in a buffer with the layout of [`PackedColor`], for any color of the displays
+///
+/// The planes follow each other, each with rows padded to whole bytes. Pixels outside of the
+/// display are ignored.
+pub fn set_packed_pixel<C: PackedColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    point: Point,
+    color: C,
+) {
+    let layout = RamLayout::packed(width, C::BITS_PER_PIXEL);
+    set_packed_pixel_in(buffer, width, height, layout, rotation, point, color);
+}
+
+/// Like [`set_packed_pixel`], for buffers with the rows of `layout`
+pub fn set_packed_pixel_in<C: PackedColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    layout: RamLayout,
+    rotation: DisplayRotation,
+    point: Point,
+    color: C,
+) {
+    if outside_display(point, width, height, rotation) {
+        return;
+    }
+    let (nx, ny) = find_rotation(point.x as u32, point.y as u32, width, height, rotation);
+    set_native_packed_pixel(buffer, layout, height, nx, ny, color);
+}
+
+// sets the pixel at the unrotated position `nx`, `ny`
+fn set_native_packed_pixel<C: PackedColor>(
+    buffer: &mut [u8],
+    layout: RamLayout,
+    height: u32,
+    nx: u32,
+    ny: u32,
+    color: C,
+) {
+    let bit = (layout.x_offset + nx) * C::BITS_PER_PIXEL;
+    let index = (ny * layout.stride + bit / 8) as usize;
+    let shift = 8 - C::BITS_PER_PIXEL - bit % 8;
+    let mask = (((1u16 << C::BITS_PER_PIXEL) - 1) << shift) as u8;
+    let order = layout.bit_order;
+
+    let plane_len = layout.plane_len(height);
+    for plane in 0..C::PLANES {
+        let byte = &mut buffer[plane * plane_len + index];
+        let bits = (color.plane_bits(plane) << shift) & mask;
+        *byte = (*byte & !order.to_msb_first(mask)) | order.to_msb_first(bits);
+    }
+}
+
+/// Fills the logical `region` with `color`, whole bytes at once
+///
+/// The region has to lie on the display. Same layout as [`set_packed_pixel`].
+pub fn fill_packed_region<C: PackedColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    region: Region,
+    color: C,
+) {
+    let layout = RamLayout::packed(width, C::BITS_PER_PIXEL);
+    fill_packed_region_in(buffer, width, height, layout, rotation, region, color);
+}
+
+/// Like [`fill_packed_region`], for buffers with the rows of `layout`
+pub fn fill_packed_region_in<C: PackedColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    layout: RamLayout,
+    rotation: DisplayRotation,
+    region: Region,
+    color: C,
+) {
+    let native = find_region(region, width, height, rotation);
+    let planes = buffer.chunks_mut(layout.plane_len(height));
+    for (plane, bytes) in planes.take(C::PLANES).enumerate() {
+        let pattern = packed_byte(color, plane);
+        for_each_span(bytes, layout, native, C::BITS_PER_PIXEL, |byte, mask| {
+            (byte & !mask) | (pattern & mask)
+        });
+    }
+}
+
+/// Inverts all bits of the pixels of the logical `region` in a plane with `bits_per_pixel`
+///
+/// Flips black and white of b/w planes and the levels of gray planes. The region has to lie on
+/// the display.
+pub fn invert_region(
+    plane: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    region: Region,
+    bits_per_pixel: u32,
+) {
+    let layout = RamLayout::packed(width, bits_per_pixel);
+    invert_region_in(
+        plane,
+        width,
+        height,
+        layout,
+        rotation,
+        region,
+        bits_per_pixel,
+    );
+}
+
+/// Like [`invert_region`], for planes with the rows of `layout`
+pub fn invert_region_in(
+    plane: &mut [u8],
+    width: u32,
+    height: u32,
+    layout: RamLayout,
+    rotation: DisplayRotation,
+    region: Region,
+    bits_per_pixel: u32,
+) {
+    let native = find_region(region, width, height, rotation);
+    for_each_span(plane, layout, native, bits_per_pixel, |byte, mask| {
+        byte ^ mask
+    });
+}
+
+// updates the bits of the unrotated `native` region in each row of a plane, `update` gets each
+// byte and the mask of its bits in the region
+fn for_each_span(
+    plane: &mut [u8],
+    layout: RamLayout,
+    native: Region,
+    bits_per_pixel: u32,
+    update: impl Fn(u8, u8) -> u8,
+) {
+    let row_len = layout.stride as usize;
+    let start = ((layout.x_offset + native.x) * bits_per_pixel) as usize;
+    let end = start + (native.width * bits_per_pixel) as usize;
+    let (first, last) = (start / 8, (end - 1) / 8);
+    let head = (0xff >> (start % 8)) as u8;
+    let tail = !((0xffu16 >> ((end - 1) % 8 + 1)) as u8);
+    let (head, tail) = match layout.bit_order {
+        BitOrder::MsbFirst => (head, tail),
+        BitOrder::LsbFirst => (head.reverse_bits(), tail.reverse_bits()),
+    };
+    for ny in native.y..native.y + native.height {
+        let offset = ny as usize * row_len;
+        let row = &mut plane[offset..offset + row_len];
+        if first == last {
+            row[first] = update(row[first], head & tail);
+        } else {
+            row[first] = update(row[first], head);
+            for byte in &mut row[first + 1..last] {
+                *byte = update(*byte, 0xff);
+            }
+            row[last] = update(row[last], tail);
+        }
+    }
+}
+
+/// Moves the content of the logical buffer up by `rows`, the rows exposed at the bottom get
+/// `background`
+///
+/// Depending on the rotation, whole rows of the unrotated buffer are moved or its rows are
+/// shifted bitwise.
+pub fn scroll_up<C: PackedColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    rows: u32,
+    background: C,
+) {
+    let layout = RamLayout::packed(width, C::BITS_PER_PIXEL);
+    scroll_up_in(buffer, width, height, layout, rotation, rows, background);
+}
+
+/// Like [`scroll_up`], for buffers with the rows of `layout`
+pub fn scroll_up_in<C: PackedColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    layout: RamLayout,
+    rotation: DisplayRotation,
+    rows: u32,
+    background: C,
+) {
+    let (logical_width, logical_height) = logical_size(width, height, rotation);
+    let rows = rows.min(logical_height);
+    if rows == 0 {
+        return;
+    }
+    match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::FlipHorizontal => {
+            move_native_rows::<C>(buffer, layout, height, rows, true)
+        }
+        DisplayRotation::Rotate180 | DisplayRotation::FlipVertical => {
+            move_native_rows::<C>(buffer, layout, height, rows, false)
+        }
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate90FlipHorizontal => {
+            shift_native_rows::<C>(buffer, layout, height, rows, false)
+        }
+        DisplayRotation::Rotate270 | DisplayRotation::Rotate270FlipHorizontal => {
+            shift_native_rows::<C>(buffer, layout, height, rows, true)
+        }
+    }
+    let exposed = Region::new(0, logical_height - rows, logical_width, rows);
+    fill_packed_region_in(buffer, width, height, layout, rotation, exposed, background);
+}
+
+/// Moves the content of the logical buffer left by `columns`, the columns exposed on the right
+/// get `background`
+///
+/// See [`scroll_up`].
+pub fn scroll_left<C: PackedColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    columns: u32,
+    background: C,
+) {
+    let layout = RamLayout::packed(width, C::BITS_PER_PIXEL);
+    scroll_left_in(buffer, width, height, layout, rotation, columns, background);
+}
+
+/// Like [`scroll_left`], for buffers with the rows of `layout`
+pub fn scroll_left_in<C: PackedColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    layout: RamLayout,
+    rotation: DisplayRotation,
+    columns: u32,
+    background: C,
+) {
+    let (logical_width, logical_height) = logical_size(width, height, rotation);
+    let columns = columns.min(logical_width);
+    if columns == 0 {
+        return;
+    }
+    match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::FlipVertical => {
+            shift_native_rows::<C>(buffer, layout, height, columns, true)
+        }
+        DisplayRotation::Rotate180 | DisplayRotation::FlipHorizontal => {
+            shift_native_rows::<C>(buffer, layout, height, columns, false)
+        }
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270FlipHorizontal => {
+            move_native_rows::<C>(buffer, layout, height, columns, true)
+        }
+        DisplayRotation::Rotate270 | DisplayRotation::Rotate90FlipHorizontal => {
+            move_native_rows::<C>(buffer, layout, height, columns, false)
+        }
+    }
+    let exposed = Region::new(logical_width - columns, 0, columns, logical_height);
+    fill_packed_region_in(buffer, width, height, layout, rotation, exposed, background);
+}
+
+// moves the unrotated rows of every plane by `rows` towards the top or the bottom
+fn move_native_rows<C: PackedColor>(
+    buffer: &mut [u8],
+    layout: RamLayout,
+    height: u32,
+    rows: u32,
+    up: bool,
+) {
+    let row_len = layout.stride as usize;
+    let moved = (height - rows) as usize * row_len;
+    let offset = rows as usize * row_len;
+    for plane in buffer.chunks_mut(layout.plane_len(height)).take(C::PLANES) {
+        if up {
+            plane.copy_within(offset..offset + moved, 0);
+        } else {
+            plane.copy_within(0..moved, offset);
+        }
+    }
+}
+
+// shifts each unrotated row of every plane by `pixels` towards the left or the right
+//
+// Whole rows are shifted, including the pixels before `x_offset`. The pixels shifted onto the
+// panel from there are covered by the background afterwards.
+fn shift_native_rows<C: PackedColor>(
+    buffer: &mut [u8],
+    layout: RamLayout,
+    height: u32,
+    pixels: u32,
+    left: bool,
+) {
+    let bits = (pixels * C::BITS_PER_PIXEL) as usize;
+    let (bytes, bits) = (bits / 8, (bits % 8) as u32);
+    for plane in buffer.chunks_mut(layout.plane_len(height)).take(C::PLANES) {
+        for row in plane.chunks_mut(layout.stride as usize) {
+            let len = row.len();
+            // the rows are shifted in the order of the pixels
+            let reorder = |row: &mut [u8]| {
+                for byte in row.iter_mut() {
+                    *byte = layout.bit_order.to_msb_first(*byte);
+                }
+            };
+            reorder(row);
+            // the byte `i` of the shifted row is made of the bytes `j` and `j + 1` of the row
+            let shifted = |row: &[u8], j: usize| {
+                let high = row.get(j).copied().unwrap_or(0);
+                let low = row.get(j + 1).copied().unwrap_or(0);
+                ((u16::from_be_bytes([high, low]) << bits) >> 8) as u8
+            };
+            if left {
+                for i in 0..len {
+                    row[i] = shifted(row, i + bytes);
+                }
+            } else {
+                // reads the bytes before `i` only, shifted right by `bytes` and `8 - bits`
+                for i in (0..len).rev() {
+                    let j = i as isize - bytes as isize - 1;
+                    row[i] = if j < -1 {
+                        0
+                    } else if bits == 0 {
+                        row[(j + 1) as usize]
+                    } else {
+                        let high = if j < 0 { 0 } else { row[j as usize] };
+                        let low = row[(j + 1) as usize];
+                        (u16::from_be_bytes([high, low]) >> bits) as u8
+                    };
+                }
+            }
+            reorder(row);
+        }
+    }
+}
+
+/// Sets the pixels of the logical `area` row by row to `colors`, like
+/// [`DrawTarget::fill_contiguous`](embedded_graphics_core::draw_target::DrawTarget::fill_contiguous)
+///
+/// `area` may reach past the display, the pixels outside are skipped.
+pub fn fill_packed_contiguous<C, I>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    area: &Rectangle,
+    colors: I,
+) where
+    C: PackedColor,
+    I: IntoIterator<Item = C>,
+{
+    let layout = RamLayout::packed(width, C::BITS_PER_PIXEL);
+    fill_packed_contiguous_in(buffer, width, height, layout, rotation, area, colors);
+}
+
+/// Like [`fill_packed_contiguous`], for buffers with the rows of `layout`
+pub fn fill_packed_contiguous_in<C, I>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    layout: RamLayout,
+    rotation: DisplayRotation,
+    area: &Rectangle,
+    colors: I,
+) where
+    C: PackedColor,
+    I: IntoIterator<Item = C>,
+{
+    for (point, color) in area.points().zip(colors) {
+        if outside_display(point, width, height, rotation) {
+            continue;
+        }
+        let (nx, ny) = find_rotation(point.x as u32, point.y as u32, width, height, rotation);
+        set_native_packed_pixel(buffer, layout, height, nx, ny, color);
+    }
+}
+
+/// Sets a pixel in a b/w buffer, `BinaryColor::On` is black
+///
+/// Pixels outside of the display are ignored.
+pub fn set_pixel(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    point: Point,
+    color: BinaryColor,
+) {
+    if outside_display(point, width, height, rotation) {
+        return;
+    }
+    let (index, bit) = find_position(point.x as u32, point.y as u32, width, height, rotation);
+    let index = index as usize;
+    match color {
+        BinaryColor::On => buffer[index] &= !bit,
+        BinaryColor::Off => buffer[index] |= bit,
+    }
+}
+
+/// Reads a pixel of a b/w buffer, `None` if it's outside of the display
+pub fn get_pixel(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    point: Point,
+) -> Option<BinaryColor> {
+    if outside_display(point, width, height, rotation) {
+        return None;
+    }
+    let (index, bit) = find_position(point.x as u32, point.y as u32, width, height, rotation);
+    if buffer[index as usize] & bit == 0 {
+        Some(BinaryColor::On)
+    } else {
+        Some(BinaryColor::Off)
+    }
+}
+
+/// Sets a pixel in a tri color buffer with the chromatic part starting at `chromatic_offset`
+///
+/// Pixels outside of the display are ignored.
+pub fn set_tri_pixel(
+    buffer: &mut [u8],
+    chromatic_offset: usize,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    point: Point,
+    color: TriColor,
+) {
+    if outside_display(point, width, height, rotation) {
+        return;
+    }
+    let (index, bit) = find_position(point.x as u32, point.y as u32, width, height, rotation);
+    let index = index as usize;
+    match color {
+        TriColor::Black => {
+            // clear bit in bw-buffer -> black
+            buffer[index] &= !bit;
+            // set bit in chromatic-buffer -> white
+            buffer[index + chromatic_offset] |= bit;
+        }
+        TriColor::White => {
+            // set bit in bw-buffer -> white
+            buffer[index] |= bit;
+            // set bit in chromatic-buffer -> white
+            buffer[index + chromatic_offset] |= bit;
+        }
+        TriColor::Chromatic => {
+            // set bit in b/w buffer (white)
+            buffer[index] |= bit;
+            // clear bit in chromatic buffer -> chromatic
+            buffer[index + chromatic_offset] &= !bit;
+        }
+    }
+}
+
+/// Reads a pixel of a tri color buffer, `None` if it's outside of the display
+///
+/// A pixel set in the chromatic part is chromatic, regardless of its b/w part.
+pub fn get_tri_pixel(
+    buffer: &[u8],
+    chromatic_offset: usize,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    point: Point,
+) -> Option<TriColor> {
+    if outside_display(point, width, height, rotation) {
+        return None;
+    }
+    let (index, bit) = find_position(point.x as u32, point.y as u32, width, height, rotation);
+    let index = index as usize;
+    if buffer[index + chromatic_offset] & bit == 0 {
+        Some(TriColor::Chromatic)
+    } else if buffer[index] & bit == 0 {
+        Some(TriColor::Black)
+    } else {
+        Some(TriColor::White)
+    }
+}
+
+/// Sets a pixel in a four bit per pixel buffer
+///
+/// Pixels outside of the display are ignored.
+pub fn set_oct_pixel(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    point: Point,
+    color: OctColor,
+) {
+    if outside_display(point, width, height, rotation) {
+        return;
+    }
+    let (index, upper) = find_oct_position(point.x as u32, point.y as u32, width, height, rotation);
+    let index = index as usize;
+    let (mask, color_nibble) = if upper {
+        (0x0f, color.get_nibble() << 4)
+    } else {
+        (0xf0, color.get_nibble())
+    };
+    buffer[index] = (buffer[index] & mask) | color_nibble;
+}
+
+/// Reads a pixel of a four bit per pixel buffer, `None` if it's outside of the display or not a
+/// valid color
+pub fn get_oct_pixel(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    point: Point,
+) -> Option<OctColor> {
+    if outside_display(point, width, height, rotation) {
+        return None;
+    }
+    let (index, upper) = find_oct_position(point.x as u32, point.y as u32, width, height, rotation);
+    let byte = buffer[index as usize];
+    let nibble = if upper { byte >> 4 } else { byte & 0x0f };
+    OctColor::from_nibble(nibble).ok()
+}
+
+/// Copies a window of a b/w buffer into `window`, e.g. for `update_partial_frame`
+///
+/// The window is given in unrotated coordinates, `x` and `window_width` have to be multiples of 8.
+/// `window` needs to be exactly `window_width / 8 * window_height` bytes long.
+pub fn copy_window(
+    buffer: &[u8],
+    width: u32,
+    x: u32,
+    y: u32,
+    window_width: u32,
+    window_height: u32,
+    window: &mut [u8],
+) {
+    let layout = RamLayout::packed(width, 1);
+    copy_window_in(buffer, layout, x, y, window_width, window_height, window);
+}
+
+/// Like [`copy_window`], for buffers with the rows of `layout`
+///
+/// The rows are shifted if the offset of `layout` isn't a multiple of 8. `window` is always
+/// filled MSB first, like the controllers expect it.
+pub fn copy_window_in(
+    buffer: &[u8],
+    layout: RamLayout,
+    x: u32,
+    y: u32,
+    window_width: u32,
+    window_height: u32,
+    window: &mut [u8],
+) {
+    assert!(x.is_multiple_of(8) && window_width.is_multiple_of(8));
+    let row_len = (window_width / 8) as usize;
+    assert_eq!(window.len(), row_len * window_height as usize);
+
+    let stride = layout.stride as usize;
+    for (row, dst) in window.chunks_exact_mut(row_len).enumerate() {
+        let start = (y as usize + row) * stride;
+        let src = &buffer[start..start + stride];
+        copy_bits(src, (layout.x_offset + x) as usize, layout.bit_order, dst);
+    }
+}
+
+/// Copies the packed b/w bitmap `data` of `bitmap_width` x `bitmap_height` to the logical
+/// position `x`, `y` of a b/w buffer
+///
+/// `data` is packed like the buffer, rows padded to whole bytes and cleared bits black. Unrotated
+/// buffers are written a byte at a time, shifted if `x` isn't a multiple of 8. The parts of the
+/// bitmap past the display are left out. Returns the logical region written, `None` if it is
+/// outside of the display. Panics if `data` is too short.
+#[allow(clippy::too_many_arguments)]
+pub fn blit(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    x: u32,
+    y: u32,
+    bitmap_width: u32,
+    bitmap_height: u32,
+    data: &[u8],
+) -> Option<Region> {
+    let layout = RamLayout::packed(width, 1);
+    blit_in(
+        buffer,
+        width,
+        height,
+        layout,
+        rotation,
+        x,
+        y,
+        bitmap_width,
+        bitmap_height,
+        data,
+    )
+}
+
+/// Like [`blit`], for buffers with the rows of `layout`
+#[allow(clippy::too_many_arguments)]
+pub fn blit_in(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    layout: RamLayout,
+    rotation: DisplayRotation,
+    x: u32,
+    y: u32,
+    bitmap_width: u32,
+    bitmap_height: u32,
+    data: &[u8],
+) -> Option<Region> {
+    let src_row_len = bitmap_width.div_ceil(8) as usize;
+    assert!(
+        data.len() >= src_row_len * bitmap_height as usize,
+        "bitmap data too short"
+    );
+    let (logical_width, logical_height) = logical_size(width, height, rotation);
+    let w = bitmap_width.min(logical_width.saturating_sub(x));
+    let h = bitmap_height.min(logical_height.saturating_sub(y));
+    if w == 0 || h == 0 {
+        return None;
+    }
+
+    let rows = data.chunks(src_row_len).take(h as usize);
+    if rotation != DisplayRotation::Rotate0 || layout.bit_order == BitOrder::LsbFirst {
+        for (row, src) in (y..).zip(rows) {
+            for col in 0..w {
+                let white = src[col as usize / 8] & (0x80 >> (col % 8)) != 0;
+                let color = if white {
+                    BinaryColor::Off
+                } else {
+                    BinaryColor::On
+                };
+                let point = Point::new((x + col) as i32, row as i32);
+                set_packed_pixel_in(buffer, width, height, layout, rotation, point, color);
+            }
+        }
+        return Some(Region::new(x, y, w, h));
+    }
+
+    let dst_row_len = layout.stride as usize;
+    let start = layout.x_offset + x;
+    let shift = start % 8;
+    let whole = if shift == 0 { w as usize / 8 } else { 0 };
+    for (row, src) in (y as usize..).zip(rows) {
+        let dst = &mut buffer[row * dst_row_len + start as usize / 8..(row + 1) * dst_row_len];
+        // Aligned: whole bytes are copied as they are
+        dst[..whole].copy_from_slice(&src[..whole]);
+        // The rest goes through a 16 bit window, split over two bytes when shifted
+        for i in whole..w.div_ceil(8) as usize {
+            let bits = (w - 8 * i as u32).min(8);
+            let mask = ((0xff00u16 >> bits) & 0xff) as u8;
+            let value = (u16::from(src[i] & mask) << 8) >> shift;
+            let mask = (u16::from(mask) << 8) >> shift;
+            let [high, low] = mask.to_be_bytes();
+            let [high_value, low_value] = value.to_be_bytes();
+            dst[i] = (dst[i] & !high) | high_value;
+            if low != 0 {
+                dst[i + 1] = (dst[i + 1] & !low) | low_value;
+            }
+        }
+    }
+    Some(Region::new(x, y, w, h))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::buffer_len;
+    use proptest::prelude::*;
+
+    const ROTATIONS: [DisplayRotation; 8] = [
+        DisplayRotation::Rotate0,
+        DisplayRotation::Rotate90,
+        DisplayRotation::Rotate180,
+        DisplayRotation::Rotate270,
+        DisplayRotation::FlipHorizontal,
+        DisplayRotation::FlipVertical,
+        DisplayRotation::Rotate90FlipHorizontal,
+        DisplayRotation::Rotate270FlipHorizontal,
+    ];
+
+    /// Logical size of the display
+    fn rotated_size(width: u32, height: u32, rotation: DisplayRotation) -> (u32, u32) {
+        match rotation {
+            DisplayRotation::Rotate0
+            | DisplayRotation::Rotate180
+            | DisplayRotation::FlipHorizontal
+            | DisplayRotation::FlipVertical => (width, height),
+            DisplayRotation::Rotate90
+            | DisplayRotation::Rotate270
+            | DisplayRotation::Rotate90FlipHorizontal
+            | DisplayRotation::Rotate270FlipHorizontal => (height, width),
+        }
+    }
+
+    fn bit_order(lsb_first: bool) -> BitOrder {
+        if lsb_first {
+            BitOrder::LsbFirst
+        } else {
+            BitOrder::MsbFirst
+        }
+    }
+
+    /// A display size, a rotation and a point inside of the rotated display
+    fn display_and_point() -> impl Strategy<Value = (u32, u32, DisplayRotation, Point)> {
+        (1u32..100, 1u32..100, 0usize..ROTATIONS.len()).prop_flat_map(|(width, height, r)| {
+            let rotation = ROTATIONS[r];
+            let (w, h) = rotated_size(width, height, rotation);
+            (0..w, 0..h)
+                .prop_map(move |(x, y)| (width, height, rotation, Point::new(x as i32, y as i32)))
+        })
+    }
+
+    fn oct_color() -> impl Strategy<Value = OctColor> {
+        (0u8..8).prop_map(|n| OctColor::from_nibble(n).unwrap())
+    }
+
+    fn tri_color() -> impl Strategy<Value = TriColor> {
+        prop_oneof![
+            Just(TriColor::Black),
+            Just(TriColor::White),
+            Just(TriColor::Chromatic)
+        ]
+    }
+
+    #[test]
+    fn scroll_gray() {
+        use embedded_graphics::pixelcolor::Gray2;
+
+        // 6 pixels of two bits, padded to two bytes per row
+        let mut buffer = [0b00_01_10_11, 0b00_01_00_00, 0b11_11_11_11, 0b11_11_00_00];
+        scroll_left(
+            &mut buffer,
+            6,
+            2,
+            DisplayRotation::Rotate0,
+            1,
+            Gray2::new(2),
+        );
+        assert_eq!(buffer[..2], [0b01_10_11_00, 0b01_10_00_00]);
+        assert_eq!(buffer[2..], [0b11_11_11_11, 0b11_10_00_00]);
+
+        scroll_up(
+            &mut buffer,
+            6,
+            2,
+            DisplayRotation::Rotate180,
+            1,
+            Gray2::new(0),
+        );
+        assert_eq!(buffer[..2], [0b00_00_00_00, 0b00_00_00_00]);
+        assert_eq!(buffer[2..], [0b01_10_11_00, 0b01_10_00_00]);
+    }
+
+    proptest! {
+        #[test]
+        fn rotate_four_times_is_identity((width, height, _, p) in display_and_point()) {
+            // Inside of the display rotated by 90 degrees
+            prop_assume!((p.x as u32) < height && (p.y as u32) < width);
+
+            // Every rotation by 90 degrees swaps width and height
+            let (x, y) = (p.x as u32, p.y as u32);
+            let (x, y) = find_rotation(x, y, width, height, DisplayRotation::Rotate90);
+            let (x, y) = find_rotation(x, y, height, width, DisplayRotation::Rotate90);
+            let (x, y) = find_rotation(x, y, width, height, DisplayRotation::Rotate90);
+            let (x, y) = find_rotation(x, y, height, width, DisplayRotation::Rotate90);
+            prop_assert_eq!((x, y), (p.x as u32, p.y as u32));
+        }
+
+        #[test]
+        fn rotation_stays_on_display((width, height, rotation, p) in display_and_point()) {
+            let (nx, ny) = find_rotation(p.x as u32, p.y as u32, width, height, rotation);
+            prop_assert!(nx < width && ny < height);
+            prop_assert!(!outside_display(p, width, height, rotation));
+        }
+
+        #[test]
+        fn rotation_round_trips((width, height, rotation, p) in display_and_point()) {
+            let (nx, ny) = find_rotation(p.x as u32, p.y as u32, width, height, rotation);
+            prop_assert_eq!(
+                find_logical(nx, ny, width, height, rotation),
+                (p.x as u32, p.y as u32)
+            );
+        }
+
+        #[test]
+        fn flips_mirror_the_rotations((width, height, rotation, p) in display_and_point()) {
+            // Each flip is a rotation of the image mirrored left to right
+            let base = match rotation {
+                DisplayRotation::FlipHorizontal => DisplayRotation::Rotate0,
+                DisplayRotation::FlipVertical => DisplayRotation::Rotate180,
+                DisplayRotation::Rotate90FlipHorizontal => DisplayRotation::Rotate90,
+                DisplayRotation::Rotate270FlipHorizontal => DisplayRotation::Rotate270,
+                _ => return Ok(()),
+            };
+            let (w, _) = rotated_size(width, height, rotation);
+            prop_assert_eq!(
+                find_rotation(p.x as u32, p.y as u32, width, height, rotation),
+                find_rotation(w - 1 - p.x as u32, p.y as u32, width, height, base)
+            );
+        }
+
+        #[test]
+        fn position_inside_buffer((width, height, rotation, p) in display_and_point()) {
+            let (index, bit) = find_position(p.x as u32, p.y as u32, width, height, rotation);
+            prop_assert!((index as usize) < buffer_len(width as usize, height as usize));
+            prop_assert_eq!(bit.count_ones(), 1);
+        }
+
+        #[test]
+        fn set_then_get_pixel(
+            (width, height, rotation, p) in display_and_point(),
+            black in any::<bool>(),
+            background in any::<u8>(),
+        ) {
+            let color = if black { BinaryColor::On } else { BinaryColor::Off };
+            let mut buffer = std::vec![background; buffer_len(width as usize, height as usize)];
+            let before = buffer.clone();
+
+            set_pixel(&mut buffer, width, height, rotation, p, color);
+            prop_assert_eq!(get_pixel(&buffer, width, height, rotation, p), Some(color));
+
+            // Nothing but the pixel itself changed
+            let (index, bit) = find_position(p.x as u32, p.y as u32, width, height, rotation);
+            for (i, (a, b)) in before.iter().zip(buffer.iter()).enumerate() {
+                let mask = if i == index as usize { !bit } else { 0xff };
+                prop_assert_eq!(a & mask, b & mask);
+            }
+        }
+
+        #[test]
+        fn blit_matches_set_pixel(
+            (width, height, rotation, p) in display_and_point(),
+            (bitmap_width, bitmap_height) in (1u32..30, 1u32..10),
+            seed in any::<u64>(),
+        ) {
+            let (x, y) = (p.x as u32, p.y as u32);
+            let row_len = bitmap_width.div_ceil(8) as usize;
+            let data: std::vec::Vec<u8> = (0..row_len * bitmap_height as usize)
+                .map(|i| (seed.rotate_left(i as u32 * 7) as u8) ^ (i as u8))
+                .collect();
+            let len = buffer_len(width as usize, height as usize);
+            let background: std::vec::Vec<u8> =
+                (0..len).map(|i| (seed >> (i % 57)) as u8).collect();
+
+            let mut expected = background.clone();
+            for row in 0..bitmap_height {
+                for col in 0..bitmap_width {
+                    let white = data[row as usize * row_len + col as usize / 8] & (0x80 >> (col % 8)) != 0;
+                    let color = if white { BinaryColor::Off } else { BinaryColor::On };
+                    let point = Point::new((x + col) as i32, (y + row) as i32);
+                    set_pixel(&mut expected, width, height, rotation, point, color);
+                }
+            }
+            let mut buffer = background;
+            let region = blit(
+                &mut buffer, width, height, rotation, x, y, bitmap_width, bitmap_height, &data,
+            );
+            prop_assert_eq!(buffer, expected);
+            let (w, h) = rotated_size(width, height, rotation);
+            prop_assert_eq!(
+                region,
+                Some(Region::new(x, y, bitmap_width.min(w - x), bitmap_height.min(h - y)))
+            );
+        }
+
+        #[test]
+        fn scroll_matches_moving_pixels(
+            (width, height, rotation, p) in display_and_point(),
+            seed in any::<u64>(),
+            left in any::<bool>(),
+        ) {
+            let len = buffer_len(width as usize, height as usize);
+            let before: std::vec::Vec<u8> =
+                (0..len).map(|i| (seed.rotate_left(i as u32 * 5) as u8) ^ (i as u8)).collect();
+            let (w, h) = rotated_size(width, height, rotation);
+            let mut buffer = before.clone();
+            let (dx, dy) = if left {
+                scroll_left(&mut buffer, width, height, rotation, p.x as u32, BinaryColor::Off);
+                (p.x, 0)
+            } else {
+                scroll_up(&mut buffer, width, height, rotation, p.y as u32, BinaryColor::Off);
+                (0, p.y)
+            };
+            for y in 0..h as i32 {
+                for x in 0..w as i32 {
+                    let from = Point::new(x + dx, y + dy);
+                    let expected = get_pixel(&before, width, height, rotation, from)
+                        .unwrap_or(BinaryColor::Off);
+                    let point = Point::new(x, y);
+                    prop_assert_eq!(
+                        get_pixel(&buffer, width, height, rotation, point),
+                        Some(expected)
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn ram_layout_matches_packed(
+            (width, height, rotation, p) in display_and_point(),
+            x_offset in 0u32..16,
+            extra in 0u32..3,
+            lsb_first in any::<bool>(),
+            seed in any::<u64>(),
+        ) {
+            let packed = RamLayout::packed(width, 1);
+            let stride = (x_offset + width).div_ceil(8) + extra;
+            let layout = RamLayout::new(x_offset, stride).with_bit_order(bit_order(lsb_first));
+            let (w, h) = rotated_size(width, height, rotation);
+            let region = Region::new(p.x as u32 / 2, p.y as u32 / 2, w - p.x as u32, h - p.y as u32);
+            let bitmap: std::vec::Vec<u8> = (0..64).map(|i| seed.rotate_left(i * 3) as u8).collect();
+            // the same pixels, with garbage around them in the rows of `layout`
+            let noise = |i: usize| (seed.rotate_left(i as u32 * 7) as u8) ^ (i as u8);
+            let before: std::vec::Vec<u8> = (0..packed.plane_len(height)).map(noise).collect();
+            let mut copy: std::vec::Vec<u8> = (0..layout.plane_len(height)).map(noise).collect();
+            for y in 0..h as i32 {
+                for x in 0..w as i32 {
+                    let point = Point::new(x, y);
+                    let color = get_pixel(&before, width, height, rotation, point).unwrap();
+                    set_packed_pixel_in(&mut copy, width, height, layout, rotation, point, color);
+                }
+            }
+            let mut buffers = [(packed, before), (layout, copy)];
+            for (layout, buffer) in &mut buffers {
+                let layout = *layout;
+                set_packed_pixel_in(buffer, width, height, layout, rotation, p, BinaryColor::On);
+                fill_packed_region_in(buffer, width, height, layout, rotation, region, BinaryColor::Off);
+                blit_in(buffer, width, height, layout, rotation, p.x as u32, 0, 13, 4, &bitmap);
+                invert_region_in(buffer, width, height, layout, rotation, region, 1);
+                scroll_up_in(buffer, width, height, layout, rotation, p.y as u32 / 3, BinaryColor::On);
+                scroll_left_in(buffer, width, height, layout, rotation, p.x as u32 / 3, BinaryColor::Off);
+            }
+            // the pixels of the panel, wherever they are in the rows
+            let pixel = |buffer: &[u8], layout: RamLayout, x: i32, y: i32| {
+                let (nx, ny) = find_rotation(x as u32, y as u32, width, height, rotation);
+                let bit = layout.x_offset + nx;
+                let byte = buffer[(ny * layout.stride + bit / 8) as usize];
+                layout.bit_order.to_msb_first(byte) & (0x80 >> (bit % 8)) != 0
+            };
+            let [(packed, expected), (layout, buffer)] = &buffers;
+            for y in 0..h as i32 {
+                for x in 0..w as i32 {
+                    prop_assert_eq!(pixel(buffer, *layout, x, y), pixel(expected, *packed, x, y));
+                }
+            }
+        }
+
+        #[test]
+        fn set_then_get_tri_pixel(
+            (width, height, rotation, p) in display_and_point(),
+            color in tri_color(),
+        ) {
+            let len = buffer_len(width as usize, height as usize);
+            let mut buffer = std::vec![TriColor::White.get_byte_value(); 2 * len];
+
+            set_tri_pixel(&mut buffer, len, width, height, rotation, p, color);
+            prop_assert_eq!(get_tri_pixel(&buffer, len, width, height, rotation, p), Some(color));
+        }
+
+        #[test]
+        fn set_then_get_oct_pixel(
+            (width, height, rotation, p) in display_and_point(),
+            color in oct_color(),
+            background in oct_color(),
+        ) {
+            // The oct buffers need an even width
+            let width = width * 2;
+            let p = match rotation {
+                DisplayRotation::Rotate0
+                | DisplayRotation::Rotate180
+                | DisplayRotation::FlipHorizontal
+                | DisplayRotation::FlipVertical => Point::new(p.x * 2, p.y),
+                DisplayRotation::Rotate90
+                | DisplayRotation::Rotate270
+                | DisplayRotation::Rotate90FlipHorizontal
+                | DisplayRotation::Rotate270FlipHorizontal => Point::new(p.x, p.y * 2),
+            };
+            let byte = OctColor::colors_byte(background, background);
+            let mut buffer = std::vec![byte; (width * height / 2) as usize];
+
+            set_oct_pixel(&mut buffer, width, height, rotation, p, color);
+            prop_assert_eq!(get_oct_pixel(&buffer, width, height, rotation, p), Some(color));
+            let changed = buffer.iter().filter(|&&b| b != byte).count();
+            prop_assert!(changed <= 1);
+        }
+
+        #[test]
+        fn packed_pixel_matches_per_color_setters(
+            (width, height, rotation, p) in display_and_point(),
+            tri in tri_color(),
+            oct in oct_color(),
+            black in any::<bool>(),
+            background in any::<u8>(),
+        ) {
+            let len = buffer_len(width as usize, height as usize);
+            let color = if black { BinaryColor::On } else { BinaryColor::Off };
+            let mut expected = std::vec![background; len];
+            let mut packed = expected.clone();
+            set_pixel(&mut expected, width, height, rotation, p, color);
+            set_packed_pixel(&mut packed, width, height, rotation, p, color);
+            prop_assert_eq!(&packed, &expected);
+
+            let mut expected = std::vec![background; 2 * len];
+            let mut packed = expected.clone();
+            set_tri_pixel(&mut expected, len, width, height, rotation, p, tri);
+            set_packed_pixel(&mut packed, width, height, rotation, p, tri);
+            prop_assert_eq!(&packed, &expected);
+
+            // The oct buffers need an even width
+            let width = width * 2;
+            let p = match rotation {
+                DisplayRotation::Rotate0
+                | DisplayRotation::Rotate180
+                | DisplayRotation::FlipHorizontal
+                | DisplayRotation::FlipVertical => Point::new(p.x * 2, p.y),
+                DisplayRotation::Rotate90
+                | DisplayRotation::Rotate270
+                | DisplayRotation::Rotate90FlipHorizontal
+                | DisplayRotation::Rotate270FlipHorizontal => Point::new(p.x, p.y * 2),
+            };
+            let mut expected = std::vec![background; (width * height / 2) as usize];
+            let mut packed = expected.clone();
+            set_oct_pixel(&mut expected, width, height, rotation, p, oct);
+            set_packed_pixel(&mut packed, width, height, rotation, p, oct);
+            prop_assert_eq!(&packed, &expected);
+        }
+
+        #[test]
+        fn outside_is_ignored(
+            (width, height, rotation, _) in display_and_point(),
+            dx in 0i32..10,
+        ) {
+            let (w, h) = rotated_size(width, height, rotation);
+            let mut buffer = std::vec![0xaa; buffer_len(width as usize, height as usize)];
+            for &p in &[
+                Point::new(-1 - dx, 0),
+                Point::new(0, -1 - dx),
+                Point::new(w as i32 + dx, 0),
+                Point::new(0, h as i32 + dx),
+            ] {
+                set_pixel(&mut buffer, width, height, rotation, p, BinaryColor::On);
+                prop_assert_eq!(get_pixel(&buffer, width, height, rotation, p), None);
+            }
+            prop_assert!(buffer.iter().all(|&b| b == 0xaa));
+        }
+
+        #[test]
+        fn window_matches_pixels(
+            width_bytes in 1u32..10,
+            height in 1u32..30,
+            seed in any::<u64>(),
+            window in (0u32..10, 0u32..30, 1u32..10, 1u32..30),
+        ) {
+            let width = width_bytes * 8;
+            let (x, y, window_width, window_height) = window;
+            let x = x.min(width_bytes - 1) * 8;
+            let y = y.min(height - 1);
+            let window_width = window_width.min((width - x) / 8) * 8;
+            let window_height = window_height.min(height - y);
+
+            let buffer: std::vec::Vec<u8> = (0..width_bytes * height)
+                .map(|i| (seed.rotate_left(i % 64) ^ u64::from(i)) as u8)
+                .collect();
+            let mut out = std::vec![0; (window_width / 8 * window_height) as usize];
+            copy_window(&buffer, width, x, y, window_width, window_height, &mut out);
+
+            for wy in 0..window_height {
+                for wx in 0..window_width {
+                    let full = Point::new((x + wx) as i32, (y + wy) as i32);
+                    let part = Point::new(wx as i32, wy as i32);
+                    prop_assert_eq!(
+                        get_pixel(&buffer, width, height, DisplayRotation::Rotate0, full),
+                        get_pixel(&out, window_width, window_height, DisplayRotation::Rotate0, part)
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn window_in_matches_packed(
+            width_bytes in 1u32..10,
+            height in 1u32..30,
+            seed in any::<u64>(),
+            x_offset in 0u32..16,
+            extra in 0u32..3,
+            lsb_first in any::<bool>(),
+            window in (0u32..10, 0u32..30, 1u32..10, 1u32..30),
+        ) {
+            let width = width_bytes * 8;
+            let (x, y, window_width, window_height) = window;
+            let x = x.min(width_bytes - 1) * 8;
+            let y = y.min(height - 1);
+            let window_width = window_width.min((width - x) / 8) * 8;
+            let window_height = window_height.min(height - y);
+
+            let noise = |i: u32| (seed.rotate_left(i % 64) ^ u64::from(i)) as u8;
+            let packed: std::vec::Vec<u8> = (0..width_bytes * height).map(noise).collect();
+            // the same pixels in rows with an offset, garbage around them
+            let stride = (x_offset + width).div_ceil(8) + extra;
+            let layout = RamLayout::new(x_offset, stride).with_bit_order(bit_order(lsb_first));
+            let mut buffer: std::vec::Vec<u8> =
+                (0..layout.stride * height).map(|i| noise(i + 7)).collect();
+            for py in 0..height as i32 {
+                for px in 0..width as i32 {
+                    let point = Point::new(px, py);
+                    let rotation = DisplayRotation::Rotate0;
+                    let color = get_pixel(&packed, width, height, rotation, point).unwrap();
+                    set_packed_pixel_in(&mut buffer, width, height, layout, rotation, point, color);
+                }
+            }
+
+            let len = (window_width / 8 * window_height) as usize;
+            let (mut expected, mut out) = (std::vec![0; len], std::vec![0; len]);
+            copy_window(&packed, width, x, y, window_width, window_height, &mut expected);
+            copy_window_in(&buffer, layout, x, y, window_width, window_height, &mut out);
+            prop_assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn rotated_region() {
+        let region = Region::new(2, 8, 20, 4);
+        let find = |rotation| find_region(region, 32, 64, rotation);
+        assert_eq!(find(DisplayRotation::Rotate0), region);
+        // Logical x runs down the display, logical y from the right edge to the left
+        assert_eq!(find(DisplayRotation::Rotate90), Region::new(20, 2, 4, 20));
+        assert_eq!(find(DisplayRotation::Rotate180), Region::new(10, 52, 20, 4));
+        assert_eq!(find(DisplayRotation::Rotate270), Region::new(8, 42, 4, 20));
+        assert_eq!(
+            find(DisplayRotation::FlipHorizontal),
+            Region::new(10, 8, 20, 4)
+        );
+        assert_eq!(
+            find(DisplayRotation::FlipVertical),
+            Region::new(2, 52, 20, 4)
+        );
+        assert_eq!(
+            find(DisplayRotation::Rotate90FlipHorizontal),
+            Region::new(20, 42, 4, 20)
+        );
+        assert_eq!(
+            find(DisplayRotation::Rotate270FlipHorizontal),
+            Region::new(8, 2, 4, 20)
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! Guard against refreshing the panel more often than it is rated for
+//!
+//! E-Paper panels have a minimum interval between refreshes (for the full refresh Waveshare
+//! recommends at least 180s). A misbehaving application loop can easily exceed it.
+//! [`RefreshGuard`] measures the time between refreshes with a user supplied [`Clock`] and either
+//! rejects ([`RefreshGuard::display_frame`]) or defers
+//! ([`RefreshGuard::display_frame_deferred`]) refreshes issued too early.
+//!
+//! ```rust
+//! use epd_waveshare::guard::{RefreshGuard, TooSoon};
+//!
+//! let mut now = 0u64;
+//! let mut guard = RefreshGuard::new(|| now, 180_000);
+//! assert_eq!(guard.try_acquire(), Ok(()));
+//! assert_eq!(guard.try_acquire(), Err(TooSoon { remaining_ms: 180_000 }));
+//! ```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::clock::Clock;
+use crate::error::DriverError;
+use crate::interface::DelayMsExt;
+use crate::traits::WaveshareDisplay;
+
+/// A refresh was requested before the minimum interval passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooSoon {
+    /// Time until the next refresh is allowed
+    pub remaining_ms: u64,
+}
+
+/// Errors of the guarded refreshes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardError<E> {
+    /// The refresh was rejected, see [`TooSoon`]
+    TooSoon(TooSoon),
+    /// The refresh itself failed
+    Display(E),
+}
+
+/// Enforces a minimum interval between refreshes
+pub struct RefreshGuard<C> {
+    clock: C,
+    min_interval_ms: u64,
+    last_refresh: Option<u64>,
+}
+
+impl<C> RefreshGuard<C>
+where
+    C: Clock,
+{
+    /// Creates a new guard allowing one refresh every `min_interval_ms`
+    ///
+    /// The first refresh is always allowed.
+    pub fn new(clock: C, min_interval_ms: u64) -> Self {
+        RefreshGuard {
+            clock,
+            min_interval_ms,
+            last_refresh: None,
+        }
+    }
+
+    /// The configured minimum interval
+    pub fn min_interval_ms(&self) -> u64 {
+        self.min_interval_ms
+    }
+
+    /// Time until the next refresh is allowed, 0 if it is allowed right now
+    pub fn remaining_ms(&mut self) -> u64 {
+        let now = self.clock.now_ms();
+        self.remaining_at(now)
+    }
+
+    /// Records a refresh if one is allowed right now
+    ///
+    /// Use this if you trigger the refresh yourself, e.g. through a driver specific method.
+    pub fn try_acquire(&mut self) -> Result<(), TooSoon> {
+        let now = self.check()?;
+        self.record(now);
+        Ok(())
+    }
+
+    fn remaining_at(&self, now: u64) -> u64 {
+        match self.last_refresh {
+            Some(last) => {
+                let elapsed = now.saturating_sub(last);
+                self.min_interval_ms.saturating_sub(elapsed)
+            }
+            None => 0,
+        }
+    }
+
+    /// The current time if a refresh is allowed now, without recording it
+    pub(crate) fn check(&mut self) -> Result<u64, TooSoon> {
+        let now = self.clock.now_ms();
+        match self.remaining_at(now) {
+            0 => Ok(now),
+            remaining_ms => Err(TooSoon { remaining_ms }),
+        }
+    }
+
+    /// Records a refresh at `now`, as returned by [`check`](RefreshGuard::check)
+    pub(crate) fn record(&mut self, now: u64) {
+        self.last_refresh = Some(now);
+    }
+
+    /// Forgets the last refresh, so the next one is allowed immediately
+    pub fn reset(&mut self) {
+        self.last_refresh = None;
+    }
+
+    /// Calls [`WaveshareDisplay::display_frame`] or rejects it if the minimum interval didn't
+    /// pass yet
+    ///
+    /// Only successful refreshes count, a failed one can be retried right away.
+    #[allow(clippy::type_complexity)]
+    pub fn display_frame<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), GuardError<DriverError<SPI, CS, BUSY, DC, RST>>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let now = self.check().map_err(GuardError::TooSoon)?;
+        epd.display_frame(spi, delay).map_err(GuardError::Display)?;
+        self.last_refresh = Some(now);
+        Ok(())
+    }
+
+    /// Calls [`WaveshareDisplay::display_frame`], waiting until the minimum interval passed first
+    ///
+    /// Like [`display_frame`](RefreshGuard::display_frame), only successful refreshes count.
+    pub fn display_frame_deferred<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let now = loop {
+            match self.check() {
+                Ok(now) => break now,
+                Err(TooSoon { remaining_ms }) => {
+                    delay.delay_ms(remaining_ms.min(u32::MAX as u64) as u32);
+                }
+            }
+        };
+        epd.display_frame(spi, delay)?;
+        self.last_refresh = Some(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use core::cell::Cell;
+
+    #[test]
+    fn first_refresh_allowed() {
+        let mut guard = RefreshGuard::new(|| 1000, 500);
+        assert_eq!(guard.remaining_ms(), 0);
+        assert_eq!(guard.try_acquire(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_until_interval_passed() {
+        let now = Cell::new(0u64);
+        let mut guard = RefreshGuard::new(|| now.get(), 500);
+        assert_eq!(guard.try_acquire(), Ok(()));
+
+        now.set(200);
+        assert_eq!(guard.try_acquire(), Err(TooSoon { remaining_ms: 300 }));
+
+        now.set(500);
+        assert_eq!(guard.try_acquire(), Ok(()));
+        assert_eq!(guard.remaining_ms(), 500);
+
+        guard.reset();
+        assert_eq!(guard.try_acquire(), Ok(()));
+    }
+
+    #[test]
+    fn clock_read_once() {
+        let reads = Cell::new(0);
+        let mut guard = RefreshGuard::new(
+            || {
+                reads.set(reads.get() + 1);
+                0
+            },
+            500,
+        );
+        assert_eq!(guard.try_acquire(), Ok(()));
+        assert_eq!(reads.get(), 1);
+    }
+
+    /// Fails all writes while `fail` is set
+    struct FlakySpi<'a> {
+        fail: &'a Cell<bool>,
+    }
+
+    impl Write<u8> for FlakySpi<'_> {
+        type Error = ();
+
+        fn write(&mut self, _words: &[u8]) -> Result<(), ()> {
+            if self.fail.get() {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn failed_refresh_not_counted() {
+        use crate::epd2in9::Epd2in9;
+        use crate::test_utils::{IdleBusyPin, MockNoop, NoopPin};
+
+        let fail = Cell::new(false);
+        let mut spi = FlakySpi { fail: &fail };
+        let mut delay = MockNoop::new();
+        let mut epd =
+            Epd2in9::new(&mut spi, NoopPin, IdleBusyPin, NoopPin, NoopPin, &mut delay).unwrap();
+        let mut guard = RefreshGuard::new(|| 1000, 500);
+
+        fail.set(true);
+        assert_eq!(
+            guard.display_frame(&mut epd, &mut spi, &mut delay),
+            Err(GuardError::Display(Error::Spi(())))
+        );
+        assert_eq!(guard.remaining_ms(), 0);
+        assert_eq!(
+            guard.display_frame_deferred(&mut epd, &mut spi, &mut delay),
+            Err(Error::Spi(()))
+        );
+        assert_eq!(guard.remaining_ms(), 0);
+
+        // The retry goes through and counts
+        fail.set(false);
+        guard.display_frame(&mut epd, &mut spi, &mut delay).unwrap();
+        assert_eq!(guard.remaining_ms(), 500);
+    }
+}
@@ -1,13 +1,189 @@
-use crate::traits::Command;
+//! Building blocks for drivers of panels the crate doesn't support yet
+//!
+//! The drivers of the crate are built from a [`DisplayInterface`] (the SPI and pin handling), an
+//! enum of the controller commands implementing [`Command`] and the traits in the
+//! [`prelude`](crate::prelude), with [`InternalWiAdditions`] holding the init sequence. The same
+//! pieces are available to drivers outside of the crate, so unsupported panels don't need a fork:
+//!
+//! ```rust
+//! use embedded_hal::{
+//!     blocking::{delay::DelayUs, spi::Write},
+//!     digital::v2::{InputPin, OutputPin},
+//! };
+//! use epd_waveshare::{
+//!     error::DriverError,
+//!     interface::{Command, DisplayInterface},
+//! };
+//!
+//! #[derive(Clone, Copy)]
+//! enum MyCommand {
+//!     PowerOn = 0x04,
+//!     DisplayRefresh = 0x12,
+//! }
+//!
+//! impl Command for MyCommand {
+//!     fn address(self) -> u8 {
+//!         self as u8
+//!     }
+//! }
+//!
+//! struct MyPanel<SPI, CS, BUSY, DC, RST, DELAY> {
+//!     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+//! }
+//!
+//! impl<SPI, CS, BUSY, DC, RST, DELAY> MyPanel<SPI, CS, BUSY, DC, RST, DELAY>
+//! where
+//!     SPI: Write<u8>,
+//!     CS: OutputPin,
+//!     BUSY: InputPin,
+//!     DC: OutputPin,
+//!     RST: OutputPin,
+//!     DELAY: DelayUs<u32>,
+//! {
+//!     fn refresh(
+//!         &mut self,
+//!         spi: &mut SPI,
+//!         delay: &mut DELAY,
+//!     ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+//!         self.interface.reset(delay, 10)?;
+//!         self.interface.cmd(spi, MyCommand::PowerOn)?;
+//!         self.interface.wait_until_idle(true)?;
+//!         self.interface.cmd(spi, MyCommand::DisplayRefresh)?;
+//!         self.interface.wait_until_idle(true)?;
+//!         Ok(())
+//!     }
+//! }
+//! ```
+//!
+//! Buffers are sized with [`buffer_len`](crate::buffer_len) and filled with the helpers of
+//! [`graphics::packing`](crate::graphics::packing) and [`source`](crate::source).
+//!
+//! # Semantics
+//!
+//! The drivers of the crate rely on the following, so it only changes with a breaking release:
+//!
+//! - [`cmd`](DisplayInterface::cmd) sends one byte with DC low, [`data`](DisplayInterface::data)
+//!   and [`data_x_times`](DisplayInterface::data_x_times) send bytes with DC high. Each call is
+//!   framed by CS low and high, [`cmd_with_data`](DisplayInterface::cmd_with_data) and
+//!   [`cmd_read`](DisplayInterface::cmd_read) keep CS low for the command and its data.
+//! - Bytes are written in order, split only at the
+//!   [maximum chunk size](DisplayInterface::set_max_chunk_size).
+//! - CS is set high again when writing fails, the first failure is returned.
+//! - [`wait_until_idle`](DisplayInterface::wait_until_idle) returns as soon as BUSY reads idle,
+//!   it doesn't wait before the first read. The polarity is given by the driver.
+//! - [`reset`](DisplayInterface::reset) holds RST high for 10 ms, low for the given duration and
+//!   waits for the controller afterwards. It doesn't send any command.
+
 use core::marker::PhantomData;
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::*,
 };
 
+use crate::config::ResetTiming;
+use crate::error::{DriverError, Error, PinError};
+pub use crate::traits::{Command, InternalWiAdditions};
+
+// Logs the traffic with defmt if the `defmt` feature is enabled
+macro_rules! trace {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        defmt::trace!($fmt $(, $arg)*);
+        #[cfg(not(feature = "defmt"))]
+        let _ = ($(&$arg,)*);
+    }};
+}
+
+/// Default maximum size of a single SPI write, see [`DisplayInterface::set_max_chunk_size`]
+///
+/// Be careful!! Linux has a default limit of 4096 bytes per spi transfer, see
+/// <https://raspberrypi.stackexchange.com/questions/65595/spi-transfer-fails-with-buffer-size-greater-than-4096>
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = if cfg!(target_os = "linux") {
+    4096
+} else {
+    usize::MAX
+};
+
+/// Size of the scratch buffer on the stack used by [`DisplayInterface::data_inverted`]
+pub(crate) const INVERT_CHUNK_SIZE: usize = 256;
+
+/// Millisecond waits on top of the [`DelayUs<u32>`] of the HAL, which the drivers are generic over
+pub trait DelayMsExt {
+    /// Waits for `ms` milliseconds
+    fn delay_ms(&mut self, ms: u32);
+}
+
+impl<D: DelayUs<u32>> DelayMsExt for D {
+    fn delay_ms(&mut self, ms: u32) {
+        // whole seconds first, the microseconds of longer waits don't fit into an u32
+        for _ in 0..ms / 1000 {
+            self.delay_us(1_000_000);
+        }
+        self.delay_us(ms % 1000 * 1000);
+    }
+}
+
+/// Traffic between a [`DisplayInterface`] and the controller, see
+/// [`DisplayInterface::set_traffic_hook`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Traffic<'a> {
+    /// A command byte
+    Command(u8),
+    /// Data bytes as sent, i.e. after inversion
+    Data(&'a [u8]),
+    /// The same data byte sent `repetitions` times
+    RepeatedData {
+        /// The data byte
+        value: u8,
+        /// How often it is sent
+        repetitions: u32,
+    },
+    /// A hardware reset through the RST pin
+    Reset,
+}
+
+/// Sees the traffic of a [`DisplayInterface`] before it's sent, see
+/// [`DisplayInterface::set_traffic_hook`]
+///
+/// Implemented for closures, which can capture the state of the hook.
+pub trait TrafficHook {
+    /// Called before `traffic` happens, returning `false` drops it
+    fn traffic(&mut self, traffic: Traffic<'_>) -> bool;
+}
+
+impl<F: FnMut(Traffic<'_>) -> bool> TrafficHook for F {
+    fn traffic(&mut self, traffic: Traffic<'_>) -> bool {
+        self(traffic)
+    }
+}
+
+/// The [`TrafficHook`] of an interface without one, it allows everything
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoHook;
+
+impl TrafficHook for NoHook {
+    fn traffic(&mut self, _traffic: Traffic<'_>) -> bool {
+        true
+    }
+}
+
+/// The [`TrafficHook`] of the drivers, see
+/// [`WaveshareDisplay::set_traffic_hook`](crate::traits::WaveshareDisplay::set_traffic_hook)
+pub type TrafficFn = fn(Traffic<'_>) -> bool;
+
 /// The Connection Interface of all (?) Waveshare EPD-Devices
 ///
-pub(crate) struct DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY> {
+/// With a CS pin, CS stays low for a command together with its data, and for repeated data. On an
+/// SPI bus shared with other devices that are driven from other contexts (interrupts, tasks),
+/// lock the bus for a whole call, or use a `SpiDevice` through the `compat` module (feature
+/// `embedded-hal-1`) instead.
+///
+/// `HOOK` is the type of the [`TrafficHook`], see
+/// [`set_traffic_hook`](DisplayInterface::set_traffic_hook).
+pub struct DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, HOOK = NoHook> {
     /// SPI
     _spi: PhantomData<SPI>,
     /// DELAY
@@ -20,103 +196,287 @@ pub(crate) struct DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY> {
     dc: DC,
     /// Pin for Resetting
     rst: RST,
+    /// Called between two polls of BUSY
+    busy_yield: Option<fn()>,
+    /// Sees all traffic before it's sent
+    traffic_hook: Option<HOOK>,
+    /// Maximum number of bytes written to the SPI at once
+    max_chunk_size: usize,
+    /// Set by the drivers after deep sleep, only a reset wakes the controller again
+    asleep: bool,
 }
 
-impl<SPI, CS, BUSY, DC, RST, DELAY> DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>
+impl<SPI, CS, BUSY, DC, RST, DELAY, HOOK> DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, HOOK>
 where
     SPI: Write<u8>,
     CS: OutputPin,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
+    HOOK: TrafficHook,
 {
+    /// Creates the interface, the pins are only used through the methods of the interface
     pub fn new(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         DisplayInterface {
-            _spi: PhantomData::default(),
-            _delay: PhantomData::default(),
+            _spi: PhantomData,
+            _delay: PhantomData,
             cs,
             busy,
             dc,
             rst,
+            busy_yield: None,
+            traffic_hook: None,
+            max_chunk_size: DEFAULT_MAX_CHUNK_SIZE,
+            asleep: false,
+        }
+    }
+
+    /// Gives back the pins
+    pub fn release(self) -> (CS, BUSY, DC, RST) {
+        (self.cs, self.busy, self.dc, self.rst)
+    }
+
+    /// Calls `busy_yield` between two polls of BUSY while waiting for the display
+    ///
+    /// Without it the waits poll BUSY in a tight loop. Battery powered devices can sleep until the
+    /// next interrupt instead, e.g. with `cortex_m::asm::wfi`.
+    pub fn set_busy_yield(&mut self, busy_yield: Option<fn()>) {
+        self.busy_yield = busy_yield;
+    }
+
+    /// Splits writes to the SPI into chunks of at most `max_chunk_size` bytes
+    ///
+    /// For HALs with a limit on the length of a transfer, e.g. of their DMA. Defaults to
+    /// [`DEFAULT_MAX_CHUNK_SIZE`], chunks have at least one byte.
+    pub fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.max_chunk_size = max_chunk_size.max(1);
+    }
+
+    /// Calls `traffic_hook` for every command, data transfer and reset before it happens
+    ///
+    /// To record, mirror or audit the traffic, e.g. for simulators on the host. If the hook
+    /// returns `false`, the transfer or reset is dropped (CS is still toggled). Bytes written
+    /// directly after [`begin_data`](DisplayInterface::begin_data) and reads aren't seen.
+    ///
+    /// The interface owns the hook, a closure can capture what it records into.
+    pub fn set_traffic_hook(&mut self, traffic_hook: Option<HOOK>) {
+        self.traffic_hook = traffic_hook;
+    }
+
+    /// Marks the controller as in deep sleep
+    ///
+    /// Commands and data fail with [`Error::Asleep`] until the next
+    /// [`reset`](DisplayInterface::reset), as the controller ignores them anyway. The drivers call
+    /// it when they send the deep sleep command.
+    pub fn set_asleep(&mut self) {
+        self.asleep = true;
+    }
+
+    /// Whether the controller is in deep sleep, see [`set_asleep`](DisplayInterface::set_asleep)
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
+
+    // asks the traffic hook if `traffic` may happen
+    fn allow(&mut self, traffic: Traffic<'_>) -> bool {
+        self.traffic_hook
+            .as_mut()
+            .is_none_or(|hook| hook.traffic(traffic))
+    }
+
+    /// Calls the hook set with [`set_busy_yield`](DisplayInterface::set_busy_yield), if any
+    pub(crate) fn yield_busy(&self) {
+        if let Some(busy_yield) = self.busy_yield {
+            busy_yield();
         }
     }
 
     /// Basic function for sending [Commands](Command).
     ///
     /// Enables direct interaction with the device with the help of [data()](DisplayInterface::data())
-    pub(crate) fn cmd<T: Command>(&mut self, spi: &mut SPI, command: T) -> Result<(), SPI::Error> {
-        // low for commands
-        let _ = self.dc.set_low();
-
-        // Transfer the command over spi
-        self.write(spi, &[command.address()])
+    pub fn cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.selected(|interface| interface.write_cmd(spi, command))
     }
 
     /// Basic function for sending an array of u8-values of data over spi
     ///
-    /// Enables direct interaction with the device with the help of [command()](Epd4in2::command())
-    pub(crate) fn data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        // high for data
-        let _ = self.dc.set_high();
+    /// Enables direct interaction with the device with the help of [cmd()](DisplayInterface::cmd())
+    pub fn data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.selected(|interface| interface.write_data(spi, data))
+    }
 
-        // Transfer data (u8-array) over spi
-        self.write(spi, data)
+    /// Sends `data` with every bit inverted, for controllers with inverted color values
+    ///
+    /// Inverts into a scratch buffer on the stack and writes it in a few large chunks instead of
+    /// byte by byte, CS stays low for all of them.
+    pub fn data_inverted(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let mut scratch = [0; INVERT_CHUNK_SIZE];
+        let chunk_size = INVERT_CHUNK_SIZE.min(self.max_chunk_size);
+        trace!("epd: {=usize} bytes of inverted data", data.len());
+        self.selected(|interface| {
+            // high for data
+            interface
+                .dc
+                .set_high()
+                .map_err(|e| Error::Pin(PinError::Dc(e)))?;
+            for chunk in data.chunks(chunk_size) {
+                let scratch = &mut scratch[..chunk.len()];
+                for (inverted, b) in scratch.iter_mut().zip(chunk) {
+                    *inverted = !b;
+                }
+                if interface.allow(Traffic::Data(scratch)) {
+                    spi.write(scratch).map_err(Error::Spi)?;
+                }
+            }
+            Ok(())
+        })
     }
 
     /// Basic function for sending [Commands](Command) and the data belonging to it.
     ///
-    /// TODO: directly use ::write? cs wouldn't needed to be changed twice than
-    pub(crate) fn cmd_with_data<T: Command>(
+    /// CS stays low for the command and its data.
+    pub fn cmd_with_data<T: Command>(
         &mut self,
         spi: &mut SPI,
         command: T,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.cmd(spi, command)?;
-        self.data(spi, data)
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.selected(|interface| {
+            interface.write_cmd(spi, command)?;
+            interface.write_data(spi, data)
+        })
     }
 
     /// Basic function for sending the same byte of data (one u8) multiple times over spi
     ///
-    /// Enables direct interaction with the device with the help of [command()](ConnectionInterface::command())
-    pub(crate) fn data_x_times(
+    /// Enables direct interaction with the device with the help of [cmd()](DisplayInterface::cmd()),
+    /// CS stays low for all repetitions.
+    pub fn data_x_times(
         &mut self,
         spi: &mut SPI,
         val: u8,
         repetitions: u32,
-    ) -> Result<(), SPI::Error> {
-        // high for data
-        let _ = self.dc.set_high();
-        // Transfer data (u8) over spi
-        for _ in 0..repetitions {
-            self.write(spi, &[val])?;
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        trace!("epd: data {=u8:#04x} {=u32} times", val, repetitions);
+        if !self.allow(Traffic::RepeatedData {
+            value: val,
+            repetitions,
+        }) {
+            return Ok(());
         }
-        Ok(())
+        self.selected(|interface| {
+            // high for data
+            interface
+                .dc
+                .set_high()
+                .map_err(|e| Error::Pin(PinError::Dc(e)))?;
+            // Transfer data (u8) over spi
+            for _ in 0..repetitions {
+                interface.write(spi, &[val])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Selects the display for data written directly to the SPI, e.g. with DMA
+    ///
+    /// Sets DC high and CS low until [`end_data`](DisplayInterface::end_data).
+    pub fn begin_data(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        if self.asleep {
+            return Err(Error::Asleep);
+        }
+        trace!("epd: data written directly");
+        self.dc
+            .set_high()
+            .map_err(|e| Error::Pin(PinError::Dc(e)))?;
+        self.cs.set_low().map_err(|e| Error::Pin(PinError::Cs(e)))
+    }
+
+    /// Deselects the display after [`begin_data`](DisplayInterface::begin_data)
+    pub fn end_data(&mut self) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.cs.set_high().map_err(|e| Error::Pin(PinError::Cs(e)))
     }
 
-    // spi write helper/abstraction function
-    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    // runs `f` with cs low, cs is set high again also after a failure of `f`
+    fn selected<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, DriverError<SPI, CS, BUSY, DC, RST>>,
+    ) -> Result<R, DriverError<SPI, CS, BUSY, DC, RST>> {
+        if self.asleep {
+            return Err(Error::Asleep);
+        }
         // activate spi with cs low
-        let _ = self.cs.set_low();
+        self.cs.set_low().map_err(|e| Error::Pin(PinError::Cs(e)))?;
+        let res = f(self);
+        // deactivate spi with cs high
+        let cs = self.cs.set_high();
+        let res = res?;
+        cs.map_err(|e| Error::Pin(PinError::Cs(e)))?;
+        Ok(res)
+    }
 
-        // transfer spi data
-        // Be careful!! Linux has a default limit of 4096 bytes per spi transfer
-        // see https://raspberrypi.stackexchange.com/questions/65595/spi-transfer-fails-with-buffer-size-greater-than-4096
-        if cfg!(target_os = "linux") {
-            for data_chunk in data.chunks(4096) {
-                spi.write(data_chunk)?;
-            }
-        } else {
-            spi.write(data)?;
+    fn write_cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let command = command.address();
+        trace!("epd: command {=u8:#04x}", command);
+
+        // low for commands
+        self.dc.set_low().map_err(|e| Error::Pin(PinError::Dc(e)))?;
+
+        // Transfer the command over spi
+        if self.allow(Traffic::Command(command)) {
+            self.write(spi, &[command])?;
         }
+        Ok(())
+    }
 
-        // deactivate spi with cs high
-        let _ = self.cs.set_high();
+    fn write_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        trace!("epd: {=usize} bytes of data", data.len());
+
+        // high for data
+        self.dc
+            .set_high()
+            .map_err(|e| Error::Pin(PinError::Dc(e)))?;
 
+        // Transfer data (u8-array) over spi
+        if self.allow(Traffic::Data(data)) {
+            self.write(spi, data)?;
+        }
         Ok(())
     }
 
+    // spi write helper/abstraction function, cs has to be low already
+    fn write(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        // transfer spi data
+        data.chunks(self.max_chunk_size)
+            .try_for_each(|data_chunk| spi.write(data_chunk))
+            .map_err(Error::Spi)
+    }
+
     /// Waits until device isn't busy anymore (busy == HIGH)
     ///
     /// This is normally handled by the more complicated commands themselves,
@@ -130,14 +490,64 @@ where
     ///
     /// Most likely there was a mistake with the 2in9 busy connection
     /// //TODO: use the #cfg feature to make this compile the right way for the certain types
-    pub(crate) fn wait_until_idle(&mut self, is_busy_low: bool) {
+    pub fn wait_until_idle(
+        &mut self,
+        is_busy_low: bool,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         // //tested: worked without the delay for all tested devices
         // //self.delay_ms(1);
-        while self.is_busy(is_busy_low) {
+        let mut polls: u32 = 0;
+        while self.read_busy(is_busy_low)? {
             // //tested: REMOVAL of DELAY: it's only waiting for the signal anyway and should continue work asap
             // //old: shorten the time? it was 100 in the beginning
             // //self.delay_ms(5);
+            self.yield_busy();
+            polls = polls.saturating_add(1);
+        }
+        trace!("epd: busy for {=u32} polls", polls);
+        Ok(())
+    }
+
+    /// Waits like [`wait_until_idle`](DisplayInterface::wait_until_idle), but gives up after
+    /// `timeout_ms` with [`Error::BusyTimeout`]
+    ///
+    /// BUSY is polled once per millisecond.
+    pub fn wait_until_idle_with_timeout(
+        &mut self,
+        is_busy_low: bool,
+        delay: &mut DELAY,
+        timeout_ms: u32,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_or_cancel(is_busy_low, delay, timeout_ms, || false)
+    }
+
+    /// Like [`wait_until_idle_with_timeout`](DisplayInterface::wait_until_idle_with_timeout), but
+    /// gives up with [`Error::Cancelled`] as soon as `should_cancel` returns true
+    ///
+    /// `should_cancel` is called before every millisecond of waiting.
+    pub fn wait_until_idle_or_cancel(
+        &mut self,
+        is_busy_low: bool,
+        delay: &mut DELAY,
+        timeout_ms: u32,
+        mut should_cancel: impl FnMut() -> bool,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let mut waited_ms = 0;
+        while self.read_busy(is_busy_low)? {
+            if waited_ms >= timeout_ms {
+                trace!("epd: still busy after {=u32} ms", waited_ms);
+                return Err(Error::BusyTimeout);
+            }
+            if should_cancel() {
+                trace!("epd: wait cancelled after {=u32} ms", waited_ms);
+                return Err(Error::Cancelled);
+            }
+            self.yield_busy();
+            delay.delay_ms(1);
+            waited_ms += 1;
         }
+        trace!("epd: busy for {=u32} ms", waited_ms);
+        Ok(())
     }
 
     /// Checks if device is still busy
@@ -153,27 +563,291 @@ where
     ///
     /// Most likely there was a mistake with the 2in9 busy connection
     /// //TODO: use the #cfg feature to make this compile the right way for the certain types
-    pub(crate) fn is_busy(&self, is_busy_low: bool) -> bool {
-        (is_busy_low && self.busy.is_low().unwrap_or(false))
-            || (!is_busy_low && self.busy.is_high().unwrap_or(false))
+    ///
+    /// A failed read of the pin counts as idle, the waits report it as [`Error::Pin`] instead.
+    pub fn is_busy(&self, is_busy_low: bool) -> bool {
+        self.read_busy(is_busy_low).unwrap_or(false)
+    }
+
+    pub(crate) fn read_busy(
+        &self,
+        is_busy_low: bool,
+    ) -> Result<bool, DriverError<SPI, CS, BUSY, DC, RST>> {
+        let busy = if is_busy_low {
+            self.busy.is_low()
+        } else {
+            self.busy.is_high()
+        };
+        busy.map_err(|e| Error::Pin(PinError::Busy(e)))
     }
 
     /// Resets the device.
     ///
-    /// Often used to awake the module from deep sleep. See [Epd4in2::sleep()](Epd4in2::sleep())
+    /// Often used to awake the module from deep sleep. See [Epd4in2::sleep()](crate::traits::WaveshareDisplay::sleep())
     ///
     /// The timing of keeping the reset pin low seems to be important and different per device.
     /// Most displays seem to require keeping it low for 10ms, but the 7in5_v2 only seems to reset
     /// properly with 2ms
-    pub(crate) fn reset(&mut self, delay: &mut DELAY, duration: u8) {
-        let _ = self.rst.set_high();
-        delay.delay_ms(10);
-
-        let _ = self.rst.set_low();
-        delay.delay_ms(duration);
-        let _ = self.rst.set_high();
+    pub fn reset(
+        &mut self,
+        delay: &mut DELAY,
+        duration: u32,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
         //TODO: the upstream libraries always sleep for 200ms here
         // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
-        delay.delay_ms(200);
+        self.reset_with_timing(
+            delay,
+            ResetTiming {
+                low_ms: duration,
+                settle_ms: 200,
+            },
+        )
+    }
+
+    /// Resets the device like [`reset`](DisplayInterface::reset), but with custom timing
+    pub fn reset_with_timing(
+        &mut self,
+        delay: &mut DELAY,
+        timing: ResetTiming,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        trace!(
+            "epd: reset, low for {=u32} ms, settle for {=u32} ms",
+            timing.low_ms,
+            timing.settle_ms
+        );
+        self.asleep = false;
+        if !self.allow(Traffic::Reset) {
+            return Ok(());
+        }
+        self.rst
+            .set_high()
+            .map_err(|e| Error::Pin(PinError::Rst(e)))?;
+        delay.delay_ms(10);
+
+        self.rst
+            .set_low()
+            .map_err(|e| Error::Pin(PinError::Rst(e)))?;
+        delay.delay_ms(timing.low_ms);
+        self.rst
+            .set_high()
+            .map_err(|e| Error::Pin(PinError::Rst(e)))?;
+        delay.delay_ms(timing.settle_ms);
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY, HOOK> DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY, HOOK>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    HOOK: TrafficHook,
+{
+    /// Sends a [Command] and reads `buf.len()` bytes of data back from the controller
+    ///
+    /// The controllers only answer on the bidirectional data line (3-wire SPI). This only works
+    /// if it is wired up and the SPI HAL switches direction while reading. Most Waveshare HATs
+    /// don't connect it.
+    pub fn cmd_read<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        buf: &mut [u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.cmd_read_skip(spi, command, 0, buf)
+    }
+
+    /// Like [`cmd_read`](Self::cmd_read), but drops the first `skip` bytes the controller answers
+    /// with, e.g. the dummy byte before the OTP content
+    pub fn cmd_read_skip<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        skip: usize,
+        buf: &mut [u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.selected(|interface| {
+            interface.write_cmd(spi, command)?;
+
+            // high for data
+            interface
+                .dc
+                .set_high()
+                .map_err(|e| Error::Pin(PinError::Dc(e)))?;
+            for _ in 0..skip {
+                spi.transfer(&mut [0x00]).map_err(Error::Spi)?;
+            }
+            for b in buf.iter_mut() {
+                *b = 0x00;
+            }
+            spi.transfer(buf).map_err(Error::Spi)?;
+            trace!("epd: read {=[u8]:#04x}", buf);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_utils::{MockNoop, PinMock, PinState, PinTransaction, SpiMock};
+    use crate::type_a::command::Command as TypeACommand;
+    use embedded_hal_mock::MockError;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn pin_errors() {
+        let failed = || MockError::Io(ErrorKind::NotConnected);
+        let mut spi = SpiMock::new(&[]);
+        // CS is released again after the failure
+        let mut cs = PinMock::new(&[
+            PinTransaction::set(PinState::Low),
+            PinTransaction::set(PinState::High),
+        ]);
+        let mut busy = PinMock::new(&[PinTransaction::get(PinState::Low).with_error(failed())]);
+        let mut dc = PinMock::new(&[PinTransaction::set(PinState::Low).with_error(failed())]);
+        let mut rst = PinMock::new(&[PinTransaction::set(PinState::High).with_error(failed())]);
+        let mut interface = DisplayInterface::<_, _, _, _, _, MockNoop>::new(
+            cs.clone(),
+            busy.clone(),
+            dc.clone(),
+            rst.clone(),
+        );
+
+        // Nothing is sent after a failed pin
+        assert_eq!(
+            interface.cmd(&mut spi, TypeACommand::SwReset),
+            Err(Error::Pin(PinError::Dc(failed())))
+        );
+        assert_eq!(
+            interface.wait_until_idle(true),
+            Err(Error::Pin(PinError::Busy(failed())))
+        );
+        assert_eq!(
+            interface.reset(&mut MockNoop::new(), 10),
+            Err(Error::Pin(PinError::Rst(failed())))
+        );
+
+        spi.done();
+        cs.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn busy_yield() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static YIELDS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut busy = PinMock::new(&[
+            PinTransaction::get(PinState::Low),
+            PinTransaction::get(PinState::Low),
+            PinTransaction::get(PinState::High),
+            PinTransaction::get(PinState::High),
+        ]);
+        let mut interface = DisplayInterface::<SpiMock, _, _, _, _, MockNoop>::new(
+            PinMock::new(&[]),
+            busy.clone(),
+            PinMock::new(&[]),
+            PinMock::new(&[]),
+        );
+
+        interface.set_busy_yield(Some(|| {
+            YIELDS.fetch_add(1, Ordering::Relaxed);
+        }));
+        interface.wait_until_idle(true).unwrap();
+        assert_eq!(YIELDS.load(Ordering::Relaxed), 2);
+
+        // Not called when the display is idle already
+        interface.wait_until_idle(true).unwrap();
+        assert_eq!(YIELDS.load(Ordering::Relaxed), 2);
+        busy.done();
+    }
+
+    #[test]
+    fn long_delays() {
+        use std::vec::Vec;
+
+        struct RecordingDelay(Vec<u32>);
+
+        impl DelayUs<u32> for RecordingDelay {
+            fn delay_us(&mut self, us: u32) {
+                self.0.push(us);
+            }
+        }
+
+        let mut delay = RecordingDelay(Vec::new());
+        delay.delay_ms(2500);
+        assert_eq!(delay.0, [1_000_000, 1_000_000, 500_000]);
+    }
+
+    #[test]
+    fn max_chunk_size() {
+        let mut e = crate::test_utils::Expectations::new();
+        e.max_chunk_size(2)
+            .cmd_with_data(0x11, &[1, 2, 3, 4, 5])
+            .data(&[6, 7]);
+        let mut mocks = e.build();
+        let mut interface = DisplayInterface::<_, _, _, _, _, MockNoop>::new(
+            mocks.cs.clone(),
+            mocks.busy.clone(),
+            mocks.dc.clone(),
+            mocks.rst.clone(),
+        );
+
+        interface.set_max_chunk_size(2);
+        interface
+            .cmd_with_data(
+                &mut mocks.spi,
+                TypeACommand::DataEntryModeSetting,
+                &[1, 2, 3, 4, 5],
+            )
+            .unwrap();
+        interface.data(&mut mocks.spi, &[6, 7]).unwrap();
+        mocks.done();
+    }
+
+    #[test]
+    fn traffic_hook() {
+        use std::vec::Vec;
+
+        let mut seen = Vec::new();
+
+        // Only the command goes out, the data is vetoed
+        let mut e = crate::test_utils::Expectations::new();
+        e.cmd_with_data(0x11, &[]);
+        let mut mocks = e.build();
+        let mut interface = DisplayInterface::<_, _, _, _, _, MockNoop, _>::new(
+            mocks.cs.clone(),
+            mocks.busy.clone(),
+            mocks.dc.clone(),
+            mocks.rst.clone(),
+        );
+
+        interface.set_traffic_hook(Some(|traffic: Traffic<'_>| {
+            match traffic {
+                Traffic::Command(command) => seen.push((command, 0)),
+                Traffic::Data(data) => seen.push((0, data.len())),
+                _ => unreachable!(),
+            }
+            !matches!(traffic, Traffic::Data(_))
+        }));
+        interface
+            .cmd_with_data(
+                &mut mocks.spi,
+                TypeACommand::DataEntryModeSetting,
+                &[1, 2, 3],
+            )
+            .unwrap();
+        drop(interface);
+        assert_eq!(seen, [(0x11, 0), (0, 3)]);
+        mocks.done();
     }
 }
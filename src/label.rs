@@ -0,0 +1,287 @@
+//! Layouts for shelf labels and asset tags
+//!
+//! A [`LabelLayout`] splits the display into the fields most price tags share: a title line with
+//! a status icon next to it, the price below and a barcode strip at the bottom. Each field can be
+//! redrawn on its own with [`LabelLayout::update`], which clears the field, draws into it and marks
+//! only its area as changed in a [`Scheduler`], so a new price is shown with a small partial
+//! refresh.
+//!
+//! ```rust
+//! use embedded_graphics::{
+//!     mono_font::{ascii::FONT_6X10, MonoTextStyle},
+//!     pixelcolor::BinaryColor,
+//!     prelude::*,
+//!     text::{Baseline, Text},
+//! };
+//! use epd_waveshare::{
+//!     epd2in9::Display2in9,
+//!     label::{Field, LabelLayout},
+//!     prelude::*,
+//!     scheduler::Scheduler,
+//! };
+//!
+//! let layout = LabelLayout::epd2in9();
+//! let mut display = Display2in9::default();
+//! display.set_rotation(layout.rotation());
+//! let mut scheduler = Scheduler::new(|| 0, layout.width(), layout.height(), 0);
+//!
+//! let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+//! layout
+//!     .update(&mut display, &mut scheduler, Field::Price, |target| {
+//!         Text::with_baseline("4.99", Point::zero(), style, Baseline::Top)
+//!             .draw(target)
+//!             .map(|_| ())
+//!     })
+//!     .unwrap();
+//! assert_eq!(scheduler.dirty(), Some(layout.region(Field::Price)));
+//! ```
+
+use embedded_graphics::draw_target::{Clipped, Cropped, DrawTargetExt};
+use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+
+use crate::clock::Clock;
+use crate::graphics::{packing::find_rotation, Display, DisplayRotation};
+use crate::region::Region;
+use crate::scheduler::Scheduler;
+
+/// A field of a [`LabelLayout`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Name of the product or asset, top left
+    Title,
+    /// Price (or any other large value), in the middle
+    Price,
+    /// Strip for a barcode, at the bottom
+    Barcode,
+    /// Square for a status icon, top right
+    Status,
+}
+
+impl Field {
+    /// All fields of a layout
+    pub const ALL: [Field; 4] = [Field::Title, Field::Price, Field::Barcode, Field::Status];
+}
+
+/// The fields of a label on a display of a given size and rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LabelLayout {
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    title: Rectangle,
+    price: Rectangle,
+    barcode: Rectangle,
+    status: Rectangle,
+}
+
+impl LabelLayout {
+    /// Lays out the fields for a display of `width` x `height` pixels (unrotated) drawn with
+    /// `rotation`
+    ///
+    /// The title line and the barcode strip each take a quarter of the (rotated) height, the
+    /// price the rest. The status icon is a square at the end of the title line.
+    pub fn new(width: u32, height: u32, rotation: DisplayRotation) -> Self {
+        let (w, h) = match rotation {
+            DisplayRotation::Rotate0
+            | DisplayRotation::Rotate180
+            | DisplayRotation::FlipHorizontal
+            | DisplayRotation::FlipVertical => (width, height),
+            DisplayRotation::Rotate90
+            | DisplayRotation::Rotate270
+            | DisplayRotation::Rotate90FlipHorizontal
+            | DisplayRotation::Rotate270FlipHorizontal => (height, width),
+        };
+        let line = h / 4;
+        let icon = line.min(w);
+        let rect = |x: u32, y: u32, w: u32, h: u32| {
+            Rectangle::new(Point::new(x as i32, y as i32), Size::new(w, h))
+        };
+        LabelLayout {
+            width,
+            height,
+            rotation,
+            title: rect(0, 0, w - icon, line),
+            status: rect(w - icon, 0, icon, line),
+            price: rect(0, line, w, h - 2 * line),
+            barcode: rect(0, h - line, w, line),
+        }
+    }
+
+    /// Layout for the 1.54" displays, unrotated
+    pub fn epd1in54() -> Self {
+        LabelLayout::new(
+            crate::epd1in54::WIDTH,
+            crate::epd1in54::HEIGHT,
+            DisplayRotation::Rotate0,
+        )
+    }
+
+    /// Layout for the 2.13" displays, in landscape
+    pub fn epd2in13() -> Self {
+        LabelLayout::new(
+            crate::epd2in13_v2::WIDTH,
+            crate::epd2in13_v2::HEIGHT,
+            DisplayRotation::Rotate90,
+        )
+    }
+
+    /// Layout for the 2.9" displays, in landscape
+    pub fn epd2in9() -> Self {
+        LabelLayout::new(
+            crate::epd2in9::WIDTH,
+            crate::epd2in9::HEIGHT,
+            DisplayRotation::Rotate90,
+        )
+    }
+
+    /// Unrotated width of the display
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Unrotated height of the display
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Rotation the display has to be drawn with
+    pub fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    /// Area of a field in the rotated coordinates used for drawing
+    pub fn field(&self, field: Field) -> Rectangle {
+        match field {
+            Field::Title => self.title,
+            Field::Price => self.price,
+            Field::Barcode => self.barcode,
+            Field::Status => self.status,
+        }
+    }
+
+    /// Area of a field on the display, aligned to whole bytes like the partial refreshes need it
+    pub fn region(&self, field: Field) -> Region {
+        let area = self.field(field);
+        let bottom_right = match area.bottom_right() {
+            Some(point) => point,
+            None => return Region::new(0, 0, 0, 0),
+        };
+        let (x0, y0) = self.unrotated(area.top_left);
+        let (x1, y1) = self.unrotated(bottom_right);
+        Region::new(
+            x0.min(x1),
+            y0.min(y1),
+            x0.abs_diff(x1) + 1,
+            y0.abs_diff(y1) + 1,
+        )
+        .aligned(self.width, self.height)
+    }
+
+    /// Clears a field, draws it with `draw` and marks it as changed in `scheduler`
+    ///
+    /// `draw` gets a target cropped to the field, its origin is the top left corner of the field
+    /// and nothing is drawn outside of it.
+    pub fn update<D, C, F>(
+        &self,
+        display: &mut D,
+        scheduler: &mut Scheduler<C>,
+        field: Field,
+        draw: F,
+    ) -> Result<(), D::Error>
+    where
+        D: Display,
+        C: Clock,
+        F: FnOnce(&mut Clipped<'_, Cropped<'_, D>>) -> Result<(), D::Error>,
+    {
+        let area = self.field(field);
+        display.fill_solid(&area, BinaryColor::Off)?;
+        let mut cropped = display.cropped(&area);
+        draw(&mut cropped.clipped(&Rectangle::new(Point::zero(), area.size)))?;
+        scheduler.mark_dirty(self.region(field));
+        Ok(())
+    }
+
+    fn unrotated(&self, point: Point) -> (u32, u32) {
+        find_rotation(
+            point.x as u32,
+            point.y as u32,
+            self.width,
+            self.height,
+            self.rotation,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in9::Display2in9;
+    use crate::graphics::Display;
+    use embedded_graphics::primitives::{PrimitiveStyle, StyledDrawable};
+
+    #[test]
+    fn fields_tile_the_display() {
+        for layout in [
+            LabelLayout::epd1in54(),
+            LabelLayout::epd2in13(),
+            LabelLayout::epd2in9(),
+        ] {
+            let area: u32 = Field::ALL
+                .iter()
+                .map(|&field| layout.field(field).size)
+                .map(|size| size.width * size.height)
+                .sum();
+            assert_eq!(area, layout.width() * layout.height());
+            for (i, &a) in Field::ALL.iter().enumerate() {
+                for &b in &Field::ALL[i + 1..] {
+                    assert!(layout
+                        .field(a)
+                        .intersection(&layout.field(b))
+                        .is_zero_sized());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn regions_are_unrotated() {
+        let layout = LabelLayout::epd2in9();
+        // 296 x 128 in landscape, the price is the middle half
+        assert_eq!(
+            layout.field(Field::Price),
+            Rectangle::new(Point::new(0, 32), Size::new(296, 64))
+        );
+        assert_eq!(layout.region(Field::Price), Region::new(32, 0, 64, 296));
+        // Top right in landscape is bottom right on the panel
+        assert_eq!(layout.region(Field::Status), Region::new(96, 264, 32, 32));
+    }
+
+    #[test]
+    fn update_stays_inside_the_field() {
+        let layout = LabelLayout::epd2in9();
+        let mut display = Display2in9::default();
+        display.set_rotation(layout.rotation());
+        let mut scheduler = Scheduler::new(|| 0, layout.width(), layout.height(), 0);
+
+        // Fill everything black, then redraw the status icon only
+        display.clear_buffer(BinaryColor::On);
+        layout
+            .update(&mut display, &mut scheduler, Field::Status, |target| {
+                Rectangle::new(Point::new(-10, -10), Size::new(1000, 1000))
+                    .draw_styled(&PrimitiveStyle::with_fill(BinaryColor::Off), target)
+            })
+            .unwrap();
+        assert_eq!(scheduler.dirty(), Some(layout.region(Field::Status)));
+
+        let status = layout.region(Field::Status);
+        let row = (layout.width() / 8) as usize;
+        for (i, &byte) in display.buffer().iter().enumerate() {
+            let (x, y) = ((i % row) as u32 * 8, (i / row) as u32);
+            let inside = x >= status.x
+                && x < status.x + status.width
+                && y >= status.y
+                && y < status.y + status.height;
+            assert_eq!(byte, if inside { 0xff } else { 0x00 }, "byte {}", i);
+        }
+    }
+}
@@ -12,7 +12,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::error::DriverError<spi::Mock, pin::Mock, pin::Mock, pin::Mock, pin::Mock>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -60,18 +60,92 @@
 //!
 //! Maximum speed tested by myself was 8Mhz but more should be possible (Ben Krasnow used 18Mhz with his implemenation)
 //!
+//! Reading from the controller (e.g. `read_vcom_value`) needs a SPI implementing `Transfer<u8>` and
+//! the bidirectional data line of the controller connected. Most Waveshare HATs don't connect it.
+//!
 #![no_std]
 #![deny(missing_docs)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 #[cfg(feature = "graphics")]
 pub mod graphics;
 
 mod traits;
 
+mod lut;
+
+pub mod error;
+
 pub mod color;
 
-/// Interface for the physical connection between display and the controlling device
-mod interface;
+pub mod config;
+
+pub mod clock;
+
+pub mod guard;
+
+pub mod region;
+
+pub mod scheduler;
+
+pub mod diff;
+
+pub mod maintenance;
+
+#[cfg(feature = "graphics")]
+pub mod transition;
+
+#[cfg(feature = "graphics")]
+pub mod tiled;
+
+#[cfg(feature = "graphics")]
+pub mod label;
+
+#[cfg(feature = "graphics")]
+pub mod graphic_epd;
+
+#[cfg(feature = "graphics")]
+pub mod double_buffered;
+
+#[cfg(feature = "graphics")]
+pub mod banded;
+
+#[cfg(feature = "graphics")]
+pub mod prerotated;
+
+#[cfg(feature = "storage")]
+pub mod storage;
+
+#[cfg(feature = "async")]
+pub mod upload;
+
+#[cfg(feature = "sdmmc")]
+pub mod sdmmc;
+
+#[cfg(feature = "embedded-hal-1")]
+pub mod compat;
+
+pub mod raw_frame;
+
+pub mod diagnostics;
+
+pub mod queue;
+
+pub mod source;
+
+pub mod probe;
+
+pub mod power;
+
+pub mod any;
+
+pub mod dynamic;
+
+pub mod pins;
+
+pub mod interface;
 
 pub mod epd1in54;
 pub mod epd1in54b;
@@ -90,11 +164,16 @@ pub mod epd7in5_v2;
 
 pub(crate) mod type_a;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+
 /// Includes everything important besides the chosen Display
 pub mod prelude {
-    pub use crate::color::{Color, OctColor, TriColor};
+    pub use crate::color::{BorderColor, Color, OctColor, TriColor};
     pub use crate::traits::{
-        QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+        DmaFrame, FrameStream, InterruptRefresh, LutSet, NewCheckedError, PartialWindow,
+        QuickRefresh, ReadBack, RefreshLut, RefreshMode, SleepMode, Status, Timing,
+        WaveshareDisplay, WaveshareThreeColorDisplay,
     };
 
     pub use crate::SPI_MODE;
@@ -113,7 +192,7 @@ pub mod prelude {
 /// \[XXXXX210\]\[76543210\]...\[76543210\] | height
 /// \[XXXXX210\]\[76543210\]...\[76543210\] v
 pub const fn buffer_len(width: usize, height: usize) -> usize {
-    (width + 7) / 8 * height
+    width.div_ceil(8) * height
 }
 
 use embedded_hal::spi::{Mode, Phase, Polarity};
@@ -0,0 +1,53 @@
+//! Temperature compensation of the register waveforms
+//!
+//! The LUTs of the UC81xx style controllers are groups of six bytes per phase: the voltage
+//! levels, four frame counts and the repeat count. In the cold the particles move slower, so the
+//! frame counts are stretched; in the heat they are shortened.
+
+/// Length of the waveforms at `temperature` in °C, in percent of the room temperature waveform
+///
+/// Also the base of [`Timing::refresh_ms`](crate::traits::Timing::refresh_ms).
+pub(crate) fn frame_percent(temperature: i8) -> u32 {
+    match temperature {
+        t if t < 0 => 200,
+        t if t < 10 => 150,
+        t if t > 40 => 75,
+        _ => 100,
+    }
+}
+
+/// Copy of `table` with the frame counts of the phases from `offset` on scaled by `percent`
+///
+/// Frame counts stay between 1 and 255, unused phases (0 frames) stay unused.
+pub(crate) fn stretch<const N: usize>(table: &[u8], offset: usize, percent: u32) -> [u8; N] {
+    let mut out = [0; N];
+    out.copy_from_slice(table);
+    if percent == 100 {
+        return out;
+    }
+    for phase in out[offset..].chunks_exact_mut(6) {
+        for frames in phase[1..5].iter_mut().filter(|frames| **frames != 0) {
+            *frames = (u32::from(*frames) * percent / 100).clamp(1, 255) as u8;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stretch_frames() {
+        let table = [0x00, 0x00, 0x90, 0x1a, 0x00, 0x01, 0xf0, 0x02];
+        assert_eq!(stretch::<8>(&table, 2, 100), table);
+        assert_eq!(
+            stretch::<8>(&table, 2, 200),
+            [0x00, 0x00, 0x90, 0x34, 0x00, 0x02, 0xff, 0x02]
+        );
+        assert_eq!(
+            stretch::<8>(&table, 2, 75),
+            [0x00, 0x00, 0x90, 0x13, 0x00, 0x01, 0xb4, 0x02]
+        );
+    }
+}
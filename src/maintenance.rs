@@ -0,0 +1,218 @@
+//! Periodic full refreshes to keep the panel healthy
+//!
+//! Waveshare recommends a full refresh after a few partial refreshes to remove their ghosting
+//! and at least once every 24h even if the content doesn't change. [`Maintenance`] keeps track
+//! of both and tells when the next full refresh is due. Since a full refresh flashes the whole
+//! panel, the application decides through a hook if now is a good moment for it (e.g. nobody is
+//! looking at a badge in the pocket), up to a configurable limit.
+//!
+//! Together with a [`Scheduler`](crate::scheduler::Scheduler), request the refresh with
+//! [`Scheduler::mark_all_dirty`](crate::scheduler::Scheduler::mark_all_dirty) instead of doing it
+//! directly.
+//!
+//! ```rust
+//! use epd_waveshare::maintenance::Maintenance;
+//!
+//! let mut now = 0u64;
+//! let mut maintenance = Maintenance::new(|| now, 5, 24 * 60 * 60 * 1000);
+//! for _ in 0..5 {
+//!     maintenance.record_partial_refresh();
+//! }
+//!
+//! // Due, but the hook wants to wait for a better moment
+//! assert!(maintenance.is_due());
+//! assert!(!maintenance.poll(|| false));
+//! assert!(maintenance.poll(|| true));
+//! ```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::clock::Clock;
+use crate::error::DriverError;
+use crate::traits::WaveshareDisplay;
+
+/// Tracks partial refreshes and time since the last full refresh
+pub struct Maintenance<C> {
+    clock: C,
+    max_partial_refreshes: u32,
+    max_interval_ms: u64,
+    max_postpone_ms: Option<u64>,
+    partial_refreshes: u32,
+    last_full: Option<u64>,
+    due_since: Option<u64>,
+}
+
+impl<C> Maintenance<C>
+where
+    C: Clock,
+{
+    /// A full refresh is due after `max_partial_refreshes` partial refreshes or `max_interval_ms`
+    /// after the last full refresh, whichever comes first
+    ///
+    /// Until the first full refresh is recorded, it is due right away.
+    pub fn new(clock: C, max_partial_refreshes: u32, max_interval_ms: u64) -> Self {
+        Maintenance {
+            clock,
+            max_partial_refreshes,
+            max_interval_ms,
+            max_postpone_ms: None,
+            partial_refreshes: 0,
+            last_full: None,
+            due_since: None,
+        }
+    }
+
+    /// Limits how long the hook of [`Maintenance::poll`] can postpone a due refresh
+    ///
+    /// `None` (the default) lets the hook postpone it forever.
+    pub fn set_max_postpone(&mut self, max_postpone_ms: Option<u64>) {
+        self.max_postpone_ms = max_postpone_ms;
+    }
+
+    /// Records a partial refresh done by the application
+    pub fn record_partial_refresh(&mut self) {
+        self.partial_refreshes = self.partial_refreshes.saturating_add(1);
+    }
+
+    /// Records a full refresh, e.g. one the application did anyway
+    pub fn record_full_refresh(&mut self) {
+        self.partial_refreshes = 0;
+        self.last_full = Some(self.clock.now_ms());
+        self.due_since = None;
+    }
+
+    /// Number of partial refreshes since the last full one
+    pub fn partial_refreshes(&self) -> u32 {
+        self.partial_refreshes
+    }
+
+    /// If a full refresh is due
+    pub fn is_due(&mut self) -> bool {
+        let now = self.clock.now_ms();
+        let due = match self.last_full {
+            Some(last) => {
+                self.partial_refreshes >= self.max_partial_refreshes
+                    || now.saturating_sub(last) >= self.max_interval_ms
+            }
+            None => true,
+        };
+        if due && self.due_since.is_none() {
+            self.due_since = Some(now);
+        }
+        due
+    }
+
+    /// Checks if a full refresh should be done right now
+    ///
+    /// `good_moment` is only called if a refresh is due and decides if the flash is acceptable
+    /// right now. Once the refresh was postponed longer than [`Maintenance::set_max_postpone`]
+    /// it is done regardless. Record the refresh with [`Maintenance::record_full_refresh`].
+    pub fn poll<F>(&mut self, good_moment: F) -> bool
+    where
+        F: FnOnce() -> bool,
+    {
+        if !self.is_due() {
+            return false;
+        }
+        let postponed = self
+            .due_since
+            .map_or(0, |since| self.clock.now_ms().saturating_sub(since));
+        let overdue = matches!(self.max_postpone_ms, Some(max) if postponed >= max);
+        overdue || good_moment()
+    }
+
+    /// Does a full refresh of `frame` if [`Maintenance::poll`] says so
+    ///
+    /// Returns if the refresh was done.
+    pub fn maintain<SPI, CS, BUSY, DC, RST, DELAY, EPD, F>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        frame: &[u8],
+        good_moment: F,
+    ) -> Result<bool, DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+        F: FnOnce() -> bool,
+    {
+        if !self.poll(good_moment) {
+            return Ok(false);
+        }
+        epd.update_and_display_frame(spi, frame, delay)?;
+        self.record_full_refresh();
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    #[test]
+    fn due_after_partial_refreshes() {
+        let mut maintenance = Maintenance::new(|| 0, 2, 1000);
+        assert!(maintenance.is_due());
+        maintenance.record_full_refresh();
+        assert!(!maintenance.is_due());
+
+        maintenance.record_partial_refresh();
+        assert!(!maintenance.is_due());
+        maintenance.record_partial_refresh();
+        assert!(maintenance.is_due());
+        assert_eq!(maintenance.partial_refreshes(), 2);
+
+        maintenance.record_full_refresh();
+        assert!(!maintenance.is_due());
+        assert_eq!(maintenance.partial_refreshes(), 0);
+    }
+
+    #[test]
+    fn due_after_interval() {
+        let now = Cell::new(0u64);
+        let mut maintenance = Maintenance::new(|| now.get(), 5, 1000);
+        maintenance.record_full_refresh();
+
+        now.set(999);
+        assert!(!maintenance.is_due());
+        now.set(1000);
+        assert!(maintenance.is_due());
+    }
+
+    #[test]
+    fn hook_postpones_until_limit() {
+        let now = Cell::new(0u64);
+        let mut maintenance = Maintenance::new(|| now.get(), 1, 1000);
+        maintenance.set_max_postpone(Some(500));
+        maintenance.record_full_refresh();
+        maintenance.record_partial_refresh();
+
+        // Due since 10
+        now.set(10);
+        assert!(!maintenance.poll(|| false));
+        now.set(509);
+        assert!(!maintenance.poll(|| false));
+        now.set(510);
+        assert!(maintenance.poll(|| false));
+
+        maintenance.record_full_refresh();
+        assert!(!maintenance.is_due());
+    }
+
+    #[test]
+    fn hook_not_called_when_not_due() {
+        let mut maintenance = Maintenance::new(|| 0, 1, 1000);
+        maintenance.record_full_refresh();
+        assert!(!maintenance.poll(|| panic!("not due")));
+    }
+}
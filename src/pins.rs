@@ -0,0 +1,199 @@
+//! Pins borrowed from the application
+//!
+//! The SPI bus and the delay are only borrowed for each call, but the drivers own their pins.
+//! embedded-hal 0.2 doesn't implement the pin traits for `&mut` references, so [`Borrowed`] does
+//! it instead. A driver created over borrowed pins gives them back when it is dropped, e.g. to
+//! share them with other peripherals between two updates:
+//!
+//! ```rust,ignore
+//! use epd_waveshare::pins::Borrowed;
+//!
+//! {
+//!     // Creating the driver runs the init sequence, just like waking it up
+//!     let mut epd = Epd4in2::new(
+//!         &mut spi,
+//!         Borrowed(&mut cs),
+//!         Borrowed(&mut busy),
+//!         Borrowed(&mut dc),
+//!         Borrowed(&mut rst),
+//!         &mut delay,
+//!     )?;
+//!     epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!     epd.sleep(&mut spi, &mut delay)?;
+//! }
+//! // The pins are free again
+//! dc.set_high()?;
+//! ```
+//!
+//! Boards that don't route RST or BUSY use [`NoRst`] and [`NoBusy`] in their place.
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use embedded_hal::{
+    blocking::delay::DelayUs,
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::interface::DelayMsExt;
+
+/// A pin borrowed for the lifetime of a driver
+#[derive(Debug)]
+pub struct Borrowed<'a, P>(pub &'a mut P);
+
+impl<P: OutputPin> OutputPin for Borrowed<'_, P> {
+    type Error = P::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high()
+    }
+}
+
+impl<P: InputPin> InputPin for Borrowed<'_, P> {
+    type Error = P::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.0.is_high()
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.0.is_low()
+    }
+}
+
+/// A RST line that isn't connected, e.g. because it's tied to the reset of the MCU
+///
+/// The reset sequence of the drivers only waits then. The controllers are reset together with the
+/// MCU, and the drivers of controllers with a software reset command send it in their init anyway.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoRst;
+
+impl OutputPin for NoRst {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A BUSY line that isn't connected
+///
+/// Every check of BUSY waits `wait_ms` with its own delay and then reports the display idle, for
+/// either polarity. Use the worst case of the slowest operation, usually the full refresh (see
+/// [`Timing`](crate::traits::Timing)), and some margin for the cold.
+#[derive(Debug)]
+pub struct NoBusy<D> {
+    delay: RefCell<D>,
+    wait_ms: u32,
+}
+
+impl<D> NoBusy<D> {
+    /// Waits `wait_ms` with `delay` for every check of BUSY
+    pub fn new(delay: D, wait_ms: u32) -> Self {
+        NoBusy {
+            delay: RefCell::new(delay),
+            wait_ms,
+        }
+    }
+
+    /// Gives back the delay
+    pub fn release(self) -> D {
+        self.delay.into_inner()
+    }
+}
+
+impl<D: DelayUs<u32>> NoBusy<D> {
+    fn idle(&self) -> Result<bool, Infallible> {
+        self.delay.borrow_mut().delay_ms(self.wait_ms);
+        Ok(false)
+    }
+}
+
+impl<D: DelayUs<u32>> InputPin for NoBusy<D> {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.idle()
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.idle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd4in2::Epd4in2;
+    use crate::test_utils::{Event, IdleBusyPin, MockNoop, NoopPin, Recorder};
+    use crate::traits::WaveshareDisplay;
+
+    #[test]
+    fn drivers_over_borrowed_pins() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let (mut cs, mut busy, mut dc, mut rst) =
+            (NoopPin, IdleBusyPin, recorder.dc(), recorder.rst());
+
+        let mut expected = None;
+        for _ in 0..2 {
+            let mut epd = Epd4in2::new(
+                &mut spi,
+                Borrowed(&mut cs),
+                Borrowed(&mut busy),
+                Borrowed(&mut dc),
+                Borrowed(&mut rst),
+                &mut delay,
+            )
+            .unwrap();
+            epd.sleep(&mut spi, &mut delay).unwrap();
+
+            // Used by someone else in between
+            dc.set_low().unwrap();
+            let events = recorder.take_events();
+            assert_eq!(events[0], Event::Reset);
+            assert_eq!(*expected.get_or_insert(events.clone()), events);
+        }
+    }
+
+    #[test]
+    fn without_rst_and_busy() {
+        use core::cell::Cell;
+
+        struct CountingDelay<'a>(&'a Cell<u32>);
+
+        impl DelayUs<u32> for CountingDelay<'_> {
+            fn delay_us(&mut self, us: u32) {
+                self.0.set(self.0.get() + us / 1000);
+            }
+        }
+
+        let waited = Cell::new(0);
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            NoBusy::new(CountingDelay(&waited), 5000),
+            recorder.dc(),
+            NoRst,
+            &mut MockNoop::new(),
+        )
+        .unwrap();
+        let waits = waited.get() / 5000;
+        assert!(waits > 0);
+        assert_eq!(waited.get() % 5000, 0);
+
+        epd.display_frame(&mut spi, &mut MockNoop::new()).unwrap();
+        assert_eq!(waited.get() / 5000, waits + 1);
+        assert!(!recorder.take_events().contains(&Event::Reset));
+    }
+}
@@ -0,0 +1,209 @@
+//! Panels with a switched supply
+//!
+//! Battery powered designs often switch the VCC of the panel through a MOSFET, since even deep
+//! sleep draws a few µA. [`PowerSwitch`] owns the enable pin next to the driver and sequences it:
+//! the supply is switched on and given time to settle before the controller is initialized, and
+//! only switched off after the controller entered deep sleep.
+//!
+//! ```rust,ignore
+//! let mut epd = PowerSwitch::new(power_pin, &mut delay, |delay| {
+//!     Epd4in2::new(&mut spi, cs, busy, dc, rst, delay)
+//! })?;
+//! epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//! // Deep sleep, then the supply is switched off
+//! epd.sleep(&mut spi, &mut delay)?;
+//! // Supply on, then the full init
+//! epd.wake_up(&mut spi, &mut delay)?;
+//! ```
+//!
+//! Drive the SPI, CS, DC and RST lines low while the panel is switched off, otherwise the
+//! controller is partially powered through them.
+
+use core::ops::{Deref, DerefMut};
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::error::DriverError;
+use crate::interface::DelayMsExt;
+use crate::traits::WaveshareDisplay;
+
+/// Time for the supply of the panel to settle after switching it on, in milliseconds
+pub const SETTLE_MS: u32 = 10;
+
+/// A driver together with the pin switching the supply of its panel, active high
+///
+/// Dereferences to the driver for everything besides [`sleep`](PowerSwitch::sleep) and
+/// [`wake_up`](PowerSwitch::wake_up).
+pub struct PowerSwitch<EPD, PWR> {
+    epd: EPD,
+    power: PWR,
+    powered: bool,
+}
+
+impl<EPD, PWR> PowerSwitch<EPD, PWR>
+where
+    PWR: OutputPin,
+{
+    /// Switches the supply on and creates the driver with `init` once it settled
+    ///
+    /// The supply is switched off again if `init` fails.
+    pub fn new<DELAY, E, F>(mut power: PWR, delay: &mut DELAY, init: F) -> Result<Self, E>
+    where
+        DELAY: DelayUs<u32>,
+        F: FnOnce(&mut DELAY) -> Result<EPD, E>,
+    {
+        let _ = power.set_high();
+        delay.delay_ms(SETTLE_MS);
+        match init(delay) {
+            Ok(epd) => Ok(PowerSwitch {
+                epd,
+                power,
+                powered: true,
+            }),
+            Err(e) => {
+                let _ = power.set_low();
+                Err(e)
+            }
+        }
+    }
+
+    /// Puts the display into deep sleep and switches the supply off
+    pub fn sleep<SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        if self.powered {
+            self.epd.sleep(spi, delay)?;
+            let _ = self.power.set_low();
+            self.powered = false;
+        }
+        Ok(())
+    }
+
+    /// Switches the supply on and initializes the display again
+    pub fn wake_up<SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        if !self.powered {
+            let _ = self.power.set_high();
+            delay.delay_ms(SETTLE_MS);
+            self.powered = true;
+        }
+        self.epd.wake_up(spi, delay)
+    }
+
+    /// Returns true if the supply is switched on
+    pub fn is_powered(&self) -> bool {
+        self.powered
+    }
+
+    /// Gives back the driver and the pin, the supply stays as it is
+    pub fn release(self) -> (EPD, PWR) {
+        (self.epd, self.power)
+    }
+}
+
+impl<EPD, PWR> Deref for PowerSwitch<EPD, PWR> {
+    type Target = EPD;
+
+    fn deref(&self) -> &EPD {
+        &self.epd
+    }
+}
+
+impl<EPD, PWR> DerefMut for PowerSwitch<EPD, PWR> {
+    fn deref_mut(&mut self) -> &mut EPD {
+        &mut self.epd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd4in2::Epd4in2;
+    use crate::test_utils::{
+        Event, IdleBusyPin, MockNoop, NoopPin, PinMock, PinState, PinTransaction, Recorder,
+    };
+
+    #[test]
+    fn sequence() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut power = PinMock::new(&[
+            PinTransaction::set(PinState::High),
+            PinTransaction::set(PinState::Low),
+            PinTransaction::set(PinState::High),
+        ]);
+
+        let mut epd = PowerSwitch::new(power.clone(), &mut delay, |delay| {
+            Epd4in2::new(
+                &mut spi,
+                NoopPin,
+                IdleBusyPin,
+                recorder.dc(),
+                recorder.rst(),
+                delay,
+            )
+        })
+        .unwrap();
+        assert!(epd.is_powered());
+        assert_eq!(recorder.take_events()[0], Event::Reset);
+
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        // Sleeping twice doesn't touch the display or the supply
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        assert!(!epd.is_powered());
+        let events = recorder.take_events();
+        assert_eq!(
+            events[events.len() - 2..],
+            [Event::Command(0x07), Event::Data(std::vec![0xa5])]
+        );
+
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert!(epd.is_powered());
+        assert_eq!(recorder.take_events()[0], Event::Reset);
+        assert_eq!(epd.width(), crate::epd4in2::WIDTH);
+
+        power.done();
+    }
+
+    #[test]
+    fn switched_off_on_failed_init() {
+        let mut delay = MockNoop::new();
+        let mut power = PinMock::new(&[
+            PinTransaction::set(PinState::High),
+            PinTransaction::set(PinState::Low),
+        ]);
+        let result: Result<PowerSwitch<(), _>, ()> =
+            PowerSwitch::new(power.clone(), &mut delay, |_| Err(()));
+        assert!(result.is_err());
+        power.done();
+    }
+}
@@ -0,0 +1,259 @@
+//! Buffers in the orientation of the drawing, for fast drawing on rotated displays
+//!
+//! The displays of [`graphics`](crate::graphics) keep their buffers in the orientation of the
+//! controller and map every pixel drawn with a rotation to it, which dominates the time to render
+//! e.g. text heavy screens in portrait mode. A [`PrerotatedDisplay`] keeps the rows of the rotated
+//! drawing instead, drawing on it is as fast as without a rotation. The frame is rotated once
+//! while it's sent, it's a [`PixelSource`] in the orientation of the controller:
+//!
+//! ```rust,ignore
+//! // The rows of the 2.7" display in portrait mode, 264 pixels wide
+//! let mut buffer = [0u8; buffer_len(HEIGHT as usize, WIDTH as usize)];
+//! let mut display =
+//!     PrerotatedDisplay::new(WIDTH, HEIGHT, DisplayRotation::Rotate90, &mut buffer);
+//! Text::new("Hello", Point::new(10, 20), style).draw(&mut display)?;
+//! update_frame_from(&mut epd, &mut spi, &display)?;
+//! epd.display_frame(&mut spi, &mut delay)?;
+//! ```
+
+use core::convert::Infallible;
+use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+
+use crate::buffer_len;
+use crate::color::Color;
+use crate::graphics::packing::{
+    fill_packed_contiguous, fill_packed_region, find_logical, get_pixel, set_packed_pixel,
+};
+use crate::graphics::{rotated_size, DisplayRotation};
+use crate::region::Region;
+use crate::source::{pack_byte, PixelSource};
+
+/// A b/w display with the buffer in the orientation of the drawing
+///
+/// The buffer is packed like the buffer of an unrotated display of the rotated size. It's not
+/// the layout of [`update_frame`](crate::traits::WaveshareDisplay::update_frame), send the
+/// display as a [`PixelSource`] or the bytes of [`native_bytes`](PrerotatedDisplay::native_bytes).
+pub struct PrerotatedDisplay<'a> {
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    buffer: &'a mut [u8],
+}
+
+impl<'a> PrerotatedDisplay<'a> {
+    /// A `width` x `height` display drawn with `rotation`
+    ///
+    /// `width` and `height` are unrotated like the sizes of the drivers. Buffersize must be at
+    /// least [`buffer_len`] of the rotated size, only that many bytes are used.
+    pub fn new(
+        width: u32,
+        height: u32,
+        rotation: DisplayRotation,
+        buffer: &'a mut [u8],
+    ) -> PrerotatedDisplay<'a> {
+        let size = rotated_size(width, height, rotation);
+        let len = buffer_len(size.width as usize, size.height as usize);
+        assert!(buffer.len() >= len, "buffer too small for the display");
+        PrerotatedDisplay {
+            width,
+            height,
+            rotation,
+            buffer: &mut buffer[..len],
+        }
+    }
+
+    /// The rotation the display is drawn with
+    pub fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    /// The packed rows of the drawing
+    pub fn buffer(&self) -> &[u8] {
+        self.buffer
+    }
+
+    /// The frame buffer in the orientation of the controller, byte by byte as
+    /// [`update_frame`](crate::traits::WaveshareDisplay::update_frame) expects it
+    ///
+    /// E.g. for [`update_frame_from_iter`](crate::traits::FrameStream::update_frame_from_iter).
+    pub fn native_bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        let len = buffer_len(self.width as usize, self.height as usize);
+        (0..len).map(move |index| pack_byte(self, self.width, 0, index))
+    }
+}
+
+/// The pixels in the orientation of the controller, rotated on the fly
+impl PixelSource<Color> for PrerotatedDisplay<'_> {
+    fn pixel(&self, x: u32, y: u32) -> Color {
+        let (x, y) = find_logical(x, y, self.width, self.height, self.rotation);
+        let size = self.size();
+        let point = Point::new(x as i32, y as i32);
+        match get_pixel(
+            self.buffer,
+            size.width,
+            size.height,
+            DisplayRotation::Rotate0,
+            point,
+        ) {
+            Some(BinaryColor::On) => Color::Black,
+            _ => Color::White,
+        }
+    }
+}
+
+impl DrawTarget for PrerotatedDisplay<'_> {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let size = self.size();
+        for Pixel(point, color) in pixels {
+            set_packed_pixel(
+                self.buffer,
+                size.width,
+                size.height,
+                DisplayRotation::Rotate0,
+                point,
+                color,
+            );
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+        let region = Region::new(
+            area.top_left.x as u32,
+            area.top_left.y as u32,
+            area.size.width,
+            area.size.height,
+        );
+        let size = self.size();
+        let rotation = DisplayRotation::Rotate0;
+        fill_packed_region(
+            self.buffer,
+            size.width,
+            size.height,
+            rotation,
+            region,
+            color,
+        );
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let size = self.size();
+        let rotation = DisplayRotation::Rotate0;
+        fill_packed_contiguous(self.buffer, size.width, size.height, rotation, area, colors);
+        Ok(())
+    }
+}
+
+impl OriginDimensions for PrerotatedDisplay<'_> {
+    fn size(&self) -> Size {
+        rotated_size(self.width, self.height, self.rotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd2in7b::{Display2in7b, Epd2in7b, HEIGHT, WIDTH};
+    use crate::graphics::{Display, VarDisplay};
+    use crate::source::update_frame_from;
+    use crate::test_utils::{IdleBusyPin, NoopPin, Recorder};
+    use crate::traits::WaveshareDisplay;
+    use embedded_graphics::primitives::{Circle, Primitive, PrimitiveStyle};
+    use embedded_hal_mock::delay::MockNoop;
+
+    fn scene<T: DrawTarget<Color = BinaryColor>>(target: &mut T) -> Result<(), T::Error> {
+        target.fill_solid(
+            &Rectangle::new(Point::new(3, 1), Size::new(7, 2)),
+            BinaryColor::On,
+        )?;
+        Circle::new(Point::new(2, 3), 9)
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(target)
+    }
+
+    #[test]
+    fn same_frame_as_display() {
+        let rotations = [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+            DisplayRotation::FlipHorizontal,
+            DisplayRotation::FlipVertical,
+            DisplayRotation::Rotate90FlipHorizontal,
+            DisplayRotation::Rotate270FlipHorizontal,
+        ];
+        // Neither side a multiple of 8
+        let (width, height) = (13, 10);
+        for rotation in rotations {
+            let mut expected = [0xff; 2 * 10];
+            let mut display = VarDisplay::new(width, height, &mut expected);
+            display.set_rotation(rotation);
+            scene(&mut display).unwrap();
+
+            let mut buffer = [0xff; 2 * 13];
+            let mut prerotated = PrerotatedDisplay::new(width, height, rotation, &mut buffer);
+            assert_eq!(prerotated.size(), display.size());
+            scene(&mut prerotated).unwrap();
+            // The padding at the end of the rows is white in both
+            let frame: std::vec::Vec<u8> = prerotated.native_bytes().collect();
+            assert_eq!(&frame[..], display.buffer(), "{:?}", rotation);
+        }
+    }
+
+    #[test]
+    fn sent_like_display() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        let mut display = Display2in7b::default();
+        display.set_rotation(DisplayRotation::Rotate270);
+        scene(&mut display).unwrap();
+        recorder.take_events();
+        epd.update_frame(&mut spi, display.buffer(), &mut delay)
+            .unwrap();
+        let expected = recorder.take_events();
+
+        let mut buffer = [0; HEIGHT as usize / 8 * WIDTH as usize];
+        let mut prerotated =
+            PrerotatedDisplay::new(WIDTH, HEIGHT, DisplayRotation::Rotate270, &mut buffer);
+        prerotated.clear(BinaryColor::Off).unwrap();
+        scene(&mut prerotated).unwrap();
+        update_frame_from(&mut epd, &mut spi, &prerotated).unwrap();
+        assert_eq!(recorder.take_events(), expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn buffer_too_small() {
+        // 10 rows of 2 bytes when rotated
+        let mut buffer = [0; 19];
+        PrerotatedDisplay::new(10, 13, DisplayRotation::Rotate90, &mut buffer);
+    }
+}
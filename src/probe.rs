@@ -0,0 +1,230 @@
+//! Detection of the panel revision
+//!
+//! Revisions of a panel look the same but need different drivers, e.g. the 2.9" V1 (IL3820)
+//! and V2 (SSD1680). Picking the wrong one is the most common reason for a blank display during
+//! bring-up. [`probe`] resets the controller and looks at how it reacts:
+//!
+//! * the idle level of BUSY tells the controller families apart, it is low for the SSD16xx
+//!   and high for the UC81xx type controllers
+//! * the status register contains the chip ID on the SSD1680, the older controllers don't answer
+//! * the duration of a software reset is reported for bug reports
+//!
+//! Reading the status needs the bidirectional data line, see the crate documentation. Without it
+//! the status reads as 0 and newer revisions can't be recognized.
+//!
+//! ```rust,ignore
+//! let (probe, cs, busy, dc, rst) = probe(&mut spi, cs, busy, dc, rst, &mut delay, || timer.millis())?;
+//! match probe.driver_2in9() {
+//!     Some(Driver::Epd2in9V2) => { /* epd2in9_v2::Epd2in9::new(&mut spi, cs, busy, dc, rst, &mut delay)? */ }
+//!     Some(Driver::Epd2in9) => { /* epd2in9::Epd2in9::new(&mut spi, cs, busy, dc, rst, &mut delay)? */ }
+//!     None => defmt::error!("not a 2.9\" b/w panel: {:?}", defmt::Debug2Format(&probe)),
+//! }
+//! ```
+
+use embedded_hal::{
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
+    digital::v2::*,
+};
+
+use crate::clock::Clock;
+use crate::error::DriverError;
+use crate::interface::{DelayMsExt, DisplayInterface};
+use crate::traits;
+
+/// Longest software reset waited for, in milliseconds
+const SW_RESET_TIMEOUT_MS: u64 = 100;
+
+/// Commands used for probing, shared by the revisions
+#[derive(Copy, Clone)]
+enum Command {
+    /// SSD16xx: software reset, BUSY is high until it's done
+    SwReset = 0x12,
+    /// SSD16xx: status bits, the chip ID in the lowest 2 bits
+    StatusBitRead = 0x2F,
+    /// UC81xx: status flags
+    GetStatus = 0x71,
+}
+
+impl traits::Command for Command {
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Controller families, they differ in the BUSY polarity and the command set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    /// Solomon SSD16xx and compatible (IL3820, IL3895), BUSY is high while busy
+    Ssd16xx,
+    /// UltraChip UC81xx and compatible (IL0373, IL0398), BUSY is low while busy
+    Uc81xx,
+}
+
+/// Drivers a probed panel can be used with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Driver {
+    /// [`epd2in9::Epd2in9`](crate::epd2in9::Epd2in9), the V1 with an IL3820
+    Epd2in9,
+    /// [`epd2in9_v2::Epd2in9`](crate::epd2in9_v2::Epd2in9), the V2 with a SSD1680
+    Epd2in9V2,
+}
+
+/// Reaction of the controller to the probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Probe {
+    /// Family according to the idle level of BUSY
+    pub family: Family,
+    /// Status register, 0 if the controller doesn't answer
+    pub status: u8,
+    /// Duration of the software reset in milliseconds, `None` for UC81xx type controllers
+    ///
+    /// A value of at least 100 means the controller never got idle.
+    pub sw_reset_ms: Option<u64>,
+}
+
+impl Probe {
+    /// Chip ID of the SSD1680
+    const SSD1680_ID: u8 = 0b01;
+
+    /// Driver for a 2.9" b/w panel, `None` if it isn't one of the supported revisions
+    pub fn driver_2in9(&self) -> Option<Driver> {
+        match self.family {
+            Family::Ssd16xx if self.status & 0x03 == Self::SSD1680_ID => Some(Driver::Epd2in9V2),
+            Family::Ssd16xx => Some(Driver::Epd2in9),
+            Family::Uc81xx => None,
+        }
+    }
+}
+
+/// Resets the controller and checks how it reacts, gives back the pins for the actual driver
+///
+/// The controller is left in its reset state, the driver initializes it as usual.
+#[allow(clippy::type_complexity)]
+pub fn probe<SPI, CS, BUSY, DC, RST, DELAY, CLOCK>(
+    spi: &mut SPI,
+    cs: CS,
+    busy: BUSY,
+    dc: DC,
+    rst: RST,
+    delay: &mut DELAY,
+    mut clock: CLOCK,
+) -> Result<(Probe, CS, BUSY, DC, RST), DriverError<SPI, CS, BUSY, DC, RST>>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    CLOCK: Clock,
+{
+    let mut interface = DisplayInterface::<SPI, CS, BUSY, DC, RST, DELAY>::new(cs, busy, dc, rst);
+    interface.reset(delay, 10)?;
+
+    // Idle after the reset: BUSY is low for the SSD16xx, high for the UC81xx
+    let family = if interface.is_busy(false) {
+        Family::Uc81xx
+    } else {
+        Family::Ssd16xx
+    };
+
+    let mut status = [0];
+    let sw_reset_ms = match family {
+        Family::Ssd16xx => {
+            interface.cmd(spi, Command::SwReset)?;
+            let start = clock.now_ms();
+            let mut elapsed = 0;
+            while interface.is_busy(false) && elapsed < SW_RESET_TIMEOUT_MS {
+                delay.delay_ms(1);
+                elapsed = clock.now_ms() - start;
+            }
+            interface.cmd_read(spi, Command::StatusBitRead, &mut status)?;
+            Some(elapsed)
+        }
+        Family::Uc81xx => {
+            interface.cmd_read(spi, Command::GetStatus, &mut status)?;
+            None
+        }
+    };
+
+    let (cs, busy, dc, rst) = interface.release();
+    let probe = Probe {
+        family,
+        status: status[0],
+        sw_reset_ms,
+    };
+    Ok((probe, cs, busy, dc, rst))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{Expectations, Mocks};
+
+    fn run(mocks: &mut Mocks) -> Probe {
+        let mut ticks = 0;
+        let (probe, ..) = probe(
+            &mut mocks.spi,
+            mocks.cs.clone(),
+            mocks.busy.clone(),
+            mocks.dc.clone(),
+            mocks.rst.clone(),
+            &mut mocks.delay,
+            move || {
+                ticks += 3;
+                ticks
+            },
+        )
+        .unwrap();
+        mocks.done();
+        probe
+    }
+
+    #[test]
+    fn ssd1680() {
+        let mut e = Expectations::new();
+        e.reset()
+            .busy_idle(false)
+            .cmd(0x12)
+            .busy(false)
+            .busy(false)
+            .busy_idle(false)
+            .cmd_read(0x2F, &[0x21]);
+        let probe = run(&mut e.build());
+        assert_eq!(probe.family, Family::Ssd16xx);
+        assert_eq!(probe.sw_reset_ms, Some(6));
+        assert_eq!(probe.driver_2in9(), Some(Driver::Epd2in9V2));
+    }
+
+    #[test]
+    fn il3820() {
+        let mut e = Expectations::new();
+        e.reset()
+            .busy_idle(false)
+            .cmd(0x12)
+            .busy_idle(false)
+            .cmd_read(0x2F, &[0x00]);
+        let probe = run(&mut e.build());
+        assert_eq!(probe.sw_reset_ms, Some(0));
+        assert_eq!(probe.driver_2in9(), Some(Driver::Epd2in9));
+    }
+
+    #[test]
+    fn uc81xx() {
+        let mut e = Expectations::new();
+        e.reset().busy_idle(true).cmd_read(0x71, &[0x02]);
+        let probe = run(&mut e.build());
+        assert_eq!(
+            probe,
+            Probe {
+                family: Family::Uc81xx,
+                status: 0x02,
+                sw_reset_ms: None,
+            }
+        );
+        assert_eq!(probe.driver_2in9(), None);
+    }
+}
@@ -0,0 +1,478 @@
+//! Deferred commands, recorded into a buffer and replayed later
+//!
+//! A driver created with the pins of a [`CommandQueue`] ([`QueueSpi`], [`QueueCs`],
+//! [`QueueBusy`] and [`QueueDc`]) doesn't talk to the display. Everything it sends is recorded
+//! into the caller's buffer instead, so an update can be prepared while the bus is used by other
+//! peripherals and sent in one go with [`CommandQueue::replay`]. The recording is also an exact
+//! transcript of the bytes sent to the controller, see [`CommandQueue::entries`].
+//!
+//! Waits for the BUSY pin are recorded and done during the replay. The reset pin and the delays
+//! aren't recorded, pass the real ones to the driver.
+//!
+//! ```rust,ignore
+//! let mut buffer = [0u8; 16 * 1024];
+//! let queue = RefCell::new(CommandQueue::new(&mut buffer));
+//! let mut epd = Epd1in54::new(
+//!     &mut QueueSpi::new(&queue),
+//!     QueueCs,
+//!     QueueBusy::new(&queue),
+//!     QueueDc::new(&queue),
+//!     rst,
+//!     &mut delay,
+//! )?;
+//! epd.update_frame(&mut QueueSpi::new(&queue), display.buffer(), &mut delay)?;
+//! epd.display_frame(&mut QueueSpi::new(&queue), &mut delay)?;
+//!
+//! // Later, once the bus is free
+//! queue.borrow().replay(&mut spi, &mut cs, &mut dc, &busy)?;
+//! ```
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use embedded_hal::{blocking::spi::Write, digital::v2::*};
+
+use crate::error::{Error, PinError};
+
+const COMMAND: u8 = 0;
+const DATA: u8 = 1;
+const WAIT_BUSY_LOW: u8 = 2;
+const WAIT_BUSY_HIGH: u8 = 3;
+
+/// Error of [`QueueSpi`] when the buffer of the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull;
+
+/// One recorded step of a [`CommandQueue`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry<'a> {
+    /// Bytes sent with DC low, usually a single command
+    Command(&'a [u8]),
+    /// Bytes sent with DC high
+    Data(&'a [u8]),
+    /// Wait until the BUSY pin is idle
+    WaitIdle {
+        /// If the display is busy while BUSY is low
+        is_busy_low: bool,
+    },
+}
+
+/// Commands and data recorded into a caller-provided buffer
+///
+/// Consecutive writes of the same kind are merged, every entry takes 1 byte plus 2 bytes of
+/// length for commands and data.
+pub struct CommandQueue<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+    /// Start of the last entry
+    last: Option<usize>,
+    dc_high: bool,
+}
+
+impl<'a> CommandQueue<'a> {
+    /// An empty queue recording into `buffer`
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        CommandQueue {
+            buffer,
+            len: 0,
+            last: None,
+            dc_high: false,
+        }
+    }
+
+    /// Used bytes of the buffer
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// If nothing was recorded
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all entries
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.last = None;
+    }
+
+    /// The recorded entries, in order
+    pub fn entries(&self) -> Entries<'_> {
+        Entries {
+            buffer: &self.buffer[..self.len],
+        }
+    }
+
+    /// Sends all entries to the display
+    ///
+    /// Every command or data entry is sent in its own CS cycle, waits poll `busy` until the
+    /// display is idle. The queue isn't cleared. A failing pin is reported with its error, the
+    /// reset pin isn't used.
+    #[allow(clippy::type_complexity)]
+    pub fn replay<SPI, CS, DC, BUSY>(
+        &self,
+        spi: &mut SPI,
+        cs: &mut CS,
+        dc: &mut DC,
+        busy: &BUSY,
+    ) -> Result<(), Error<SPI::Error, PinError<CS::Error, BUSY::Error, DC::Error, Infallible>>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        DC: OutputPin,
+        BUSY: InputPin,
+    {
+        for entry in self.entries() {
+            let bytes = match entry {
+                Entry::Command(bytes) => {
+                    dc.set_low().map_err(|e| Error::Pin(PinError::Dc(e)))?;
+                    bytes
+                }
+                Entry::Data(bytes) => {
+                    dc.set_high().map_err(|e| Error::Pin(PinError::Dc(e)))?;
+                    bytes
+                }
+                Entry::WaitIdle { is_busy_low } => {
+                    loop {
+                        let busy = if is_busy_low {
+                            busy.is_low()
+                        } else {
+                            busy.is_high()
+                        };
+                        if !busy.map_err(|e| Error::Pin(PinError::Busy(e)))? {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+            };
+            cs.set_low().map_err(|e| Error::Pin(PinError::Cs(e)))?;
+            let result = spi.write(bytes);
+            let cs = cs.set_high();
+            result.map_err(Error::Spi)?;
+            cs.map_err(|e| Error::Pin(PinError::Cs(e)))?;
+        }
+        Ok(())
+    }
+
+    fn push_bytes(&mut self, mut bytes: &[u8]) -> Result<(), QueueFull> {
+        let kind = if self.dc_high { DATA } else { COMMAND };
+        while !bytes.is_empty() {
+            // Extend the last entry if it has the same kind
+            let start = match self.last {
+                Some(start) if self.buffer[start] == kind => start,
+                _ => self.push_header(kind, 3)?,
+            };
+            let entry_len = usize::from(u16::from_le_bytes([
+                self.buffer[start + 1],
+                self.buffer[start + 2],
+            ]));
+            let n = bytes
+                .len()
+                .min(usize::from(u16::MAX) - entry_len)
+                .min(self.buffer.len() - self.len);
+            if n == 0 {
+                if entry_len == usize::from(u16::MAX) {
+                    // The entry is full, start a new one
+                    self.last = None;
+                    continue;
+                }
+                return Err(QueueFull);
+            }
+            self.buffer[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+            self.len += n;
+            let entry_len = (entry_len + n) as u16;
+            self.buffer[start + 1..start + 3].copy_from_slice(&entry_len.to_le_bytes());
+            bytes = &bytes[n..];
+        }
+        Ok(())
+    }
+
+    fn push_wait(&mut self, is_busy_low: bool) -> Result<(), QueueFull> {
+        let kind = if is_busy_low {
+            WAIT_BUSY_LOW
+        } else {
+            WAIT_BUSY_HIGH
+        };
+        match self.last {
+            // Polling BUSY in a loop only needs one wait
+            Some(start) if self.buffer[start] == kind => Ok(()),
+            _ => self.push_header(kind, 1).map(|_| ()),
+        }
+    }
+
+    /// Starts an entry of `kind` with a header of `len` bytes
+    fn push_header(&mut self, kind: u8, len: usize) -> Result<usize, QueueFull> {
+        if self.buffer.len() - self.len < len {
+            return Err(QueueFull);
+        }
+        let start = self.len;
+        self.buffer[start] = kind;
+        for b in &mut self.buffer[start + 1..start + len] {
+            *b = 0;
+        }
+        self.len += len;
+        self.last = Some(start);
+        Ok(start)
+    }
+}
+
+/// Iterator over the entries of a [`CommandQueue`]
+#[derive(Debug, Clone)]
+pub struct Entries<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Entry<'a>> {
+        let (&kind, rest) = self.buffer.split_first()?;
+        let entry = match kind {
+            WAIT_BUSY_LOW | WAIT_BUSY_HIGH => {
+                self.buffer = rest;
+                return Some(Entry::WaitIdle {
+                    is_busy_low: kind == WAIT_BUSY_LOW,
+                });
+            }
+            _ => {
+                let len = usize::from(u16::from_le_bytes([rest[0], rest[1]]));
+                let (bytes, rest) = rest[2..].split_at(len);
+                self.buffer = rest;
+                bytes
+            }
+        };
+        Some(if kind == COMMAND {
+            Entry::Command(entry)
+        } else {
+            Entry::Data(entry)
+        })
+    }
+}
+
+/// SPI recording into a [`CommandQueue`]
+pub struct QueueSpi<'q, 'a>(&'q RefCell<CommandQueue<'a>>);
+
+impl<'q, 'a> QueueSpi<'q, 'a> {
+    /// Records into `queue`
+    pub fn new(queue: &'q RefCell<CommandQueue<'a>>) -> Self {
+        QueueSpi(queue)
+    }
+}
+
+impl<'q, 'a> Write<u8> for QueueSpi<'q, 'a> {
+    type Error = QueueFull;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push_bytes(words)
+    }
+}
+
+/// Data/Command pin recording into a [`CommandQueue`]
+pub struct QueueDc<'q, 'a>(&'q RefCell<CommandQueue<'a>>);
+
+impl<'q, 'a> QueueDc<'q, 'a> {
+    /// Records into `queue`
+    pub fn new(queue: &'q RefCell<CommandQueue<'a>>) -> Self {
+        QueueDc(queue)
+    }
+}
+
+impl<'q, 'a> OutputPin for QueueDc<'q, 'a> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().dc_high = false;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().dc_high = true;
+        Ok(())
+    }
+}
+
+/// BUSY pin recording waits into a [`CommandQueue`]
+///
+/// Always reads as idle, the polarity of the wait is taken from the method the driver calls.
+pub struct QueueBusy<'q, 'a>(&'q RefCell<CommandQueue<'a>>);
+
+impl<'q, 'a> QueueBusy<'q, 'a> {
+    /// Records into `queue`
+    pub fn new(queue: &'q RefCell<CommandQueue<'a>>) -> Self {
+        QueueBusy(queue)
+    }
+}
+
+impl<'q, 'a> InputPin for QueueBusy<'q, 'a> {
+    type Error = QueueFull;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.0.borrow_mut().push_wait(false)?;
+        Ok(false)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.0.borrow_mut().push_wait(true)?;
+        Ok(false)
+    }
+}
+
+/// CS pin of a driver recording into a [`CommandQueue`], CS is driven during the replay
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueueCs;
+
+impl OutputPin for QueueCs {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd4in2::Epd4in2;
+    use crate::test_utils::{
+        IdleBusyPin, MockNoop, NoopPin, PinMock, PinState, PinTransaction, Recorder, SpiMock,
+    };
+    use crate::traits::WaveshareDisplay;
+    use embedded_hal_mock::MockError;
+    use std::io::ErrorKind;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn merges_entries() {
+        let mut buffer = [0; 16];
+        let queue = RefCell::new(CommandQueue::new(&mut buffer));
+        let mut spi = QueueSpi::new(&queue);
+        let mut dc = QueueDc::new(&queue);
+        let busy = QueueBusy::new(&queue);
+
+        dc.set_low().unwrap();
+        spi.write(&[0x10]).unwrap();
+        dc.set_high().unwrap();
+        spi.write(&[1, 2]).unwrap();
+        spi.write(&[3]).unwrap();
+        assert_eq!(busy.is_low(), Ok(false));
+        assert_eq!(busy.is_low(), Ok(false));
+        dc.set_low().unwrap();
+        spi.write(&[0x12]).unwrap();
+
+        let queue = queue.borrow();
+        let entries: Vec<Entry> = queue.entries().collect();
+        assert_eq!(
+            entries,
+            [
+                Entry::Command(&[0x10]),
+                Entry::Data(&[1, 2, 3]),
+                Entry::WaitIdle { is_busy_low: true },
+                Entry::Command(&[0x12]),
+            ]
+        );
+        assert_eq!(queue.len(), 4 + 6 + 1 + 4);
+    }
+
+    #[test]
+    fn full() {
+        let mut buffer = [0; 6];
+        let queue = RefCell::new(CommandQueue::new(&mut buffer));
+        let mut spi = QueueSpi::new(&queue);
+        assert_eq!(spi.write(&[1, 2, 3]), Ok(()));
+        assert_eq!(spi.write(&[4]), Err(QueueFull));
+        queue.borrow_mut().clear();
+        assert!(queue.borrow().is_empty());
+    }
+
+    #[test]
+    fn long_writes_are_split() {
+        let mut buffer = vec![0; 70_000];
+        let queue = RefCell::new(CommandQueue::new(&mut buffer));
+        let mut spi = QueueSpi::new(&queue);
+        spi.write(&[0xaa; 66_000]).unwrap();
+        let queue = queue.borrow();
+        let lengths: Vec<usize> = queue
+            .entries()
+            .map(|e| match e {
+                Entry::Command(bytes) => bytes.len(),
+                _ => panic!("unexpected entry"),
+            })
+            .collect();
+        assert_eq!(lengths, [65_535, 465]);
+    }
+
+    #[test]
+    fn replay_matches_direct() {
+        let frame: Vec<u8> = (0..400 / 8 * 300).map(|i| i as u8).collect();
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+        epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        let direct = recorder.take_events();
+
+        let mut buffer = vec![0; 64 * 1024];
+        let queue = RefCell::new(CommandQueue::new(&mut buffer));
+        let mut epd = Epd4in2::new(
+            &mut QueueSpi::new(&queue),
+            QueueCs,
+            QueueBusy::new(&queue),
+            QueueDc::new(&queue),
+            NoopPin,
+            &mut delay,
+        )
+        .unwrap();
+        queue.borrow_mut().clear();
+        epd.update_and_display_frame(&mut QueueSpi::new(&queue), &frame, &mut delay)
+            .unwrap();
+        assert!(queue
+            .borrow()
+            .entries()
+            .any(|e| e == Entry::WaitIdle { is_busy_low: true }));
+
+        queue
+            .borrow()
+            .replay(&mut spi, &mut NoopPin, &mut recorder.dc(), &IdleBusyPin)
+            .unwrap();
+        assert_eq!(recorder.take_events(), direct);
+    }
+
+    #[test]
+    fn replay_reports_pin_error() {
+        let failed = || MockError::Io(ErrorKind::NotConnected);
+        let mut buffer = [0; 16];
+        let queue = RefCell::new(CommandQueue::new(&mut buffer));
+        QueueDc::new(&queue).set_low().unwrap();
+        QueueSpi::new(&queue).write(&[0x10]).unwrap();
+
+        let mut spi = SpiMock::new(&[]);
+        let mut cs = PinMock::new(&[]);
+        let mut dc = PinMock::new(&[PinTransaction::set(PinState::Low).with_error(failed())]);
+        let busy = PinMock::new(&[]);
+        assert_eq!(
+            queue.borrow().replay(&mut spi, &mut cs, &mut dc, &busy),
+            Err(Error::Pin(PinError::Dc(failed())))
+        );
+        spi.done();
+        cs.done();
+        dc.done();
+    }
+}
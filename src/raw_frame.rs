@@ -0,0 +1,194 @@
+//! Pre-rendered frames embedded into the binary
+//!
+//! Splash screens and other static images are often rendered on the host and embedded with
+//! `include_bytes!`. A [`RawFrame`] checks at compile time that the data has the buffer size of
+//! its dimensions and pushes it to the display with one call.
+//!
+//! ```rust,ignore
+//! use epd_waveshare::{epd4in2::{HEIGHT, WIDTH}, raw_frame::RawFrame};
+//!
+//! static SPLASH: RawFrame<WIDTH, HEIGHT> = RawFrame::new(include_bytes!("splash.bin"));
+//!
+//! SPLASH.show(&mut epd, &mut spi, &mut delay)?;
+//! ```
+//!
+//! Data of the wrong size doesn't compile:
+//!
+//! ```rust,compile_fail
+//! use epd_waveshare::raw_frame::RawFrame;
+//!
+//! // 16x2 needs 4 bytes
+//! static LOGO: RawFrame<16, 2> = RawFrame::new(&[0xff; 3]);
+//! ```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::buffer_len;
+use crate::error::{DriverError, Error};
+use crate::region::Region;
+use crate::traits::{PartialWindow, WaveshareDisplay};
+
+/// Compile-time check that `N` bytes are a buffer of `WIDTH`x`HEIGHT` pixels
+struct SizeCheck<const WIDTH: u32, const HEIGHT: u32, const N: usize>;
+
+impl<const WIDTH: u32, const HEIGHT: u32, const N: usize> SizeCheck<WIDTH, HEIGHT, N> {
+    const OK: () = assert!(
+        N == buffer_len(WIDTH as usize, HEIGHT as usize),
+        "data doesn't match the size of the frame"
+    );
+}
+
+/// A black/white buffer of `WIDTH`x`HEIGHT` pixels in the format of
+/// [`WaveshareDisplay::update_frame`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawFrame<'a, const WIDTH: u32, const HEIGHT: u32> {
+    data: &'a [u8],
+}
+
+impl<'a, const WIDTH: u32, const HEIGHT: u32> RawFrame<'a, WIDTH, HEIGHT> {
+    /// Wraps `data`, fails to compile if its size doesn't match the dimensions
+    pub const fn new<const N: usize>(data: &'a [u8; N]) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = SizeCheck::<WIDTH, HEIGHT, N>::OK;
+        RawFrame { data }
+    }
+
+    /// Width in pixels
+    pub const fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    /// Height in pixels
+    pub const fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    /// The buffer
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Transmits and displays the frame
+    ///
+    /// Fails with [`Error::Unsupported`] before sending anything unless the frame has the size of
+    /// the display, use [`show_at`](RawFrame::show_at) for smaller frames.
+    pub fn show<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        if epd.width() != WIDTH || epd.height() != HEIGHT {
+            return Err(Error::Unsupported);
+        }
+        epd.update_and_display_frame(spi, self.data, delay)
+    }
+
+    /// Transmits the frame into the window at (`x`, `y`) and displays it
+    ///
+    /// Fails with [`Error::Unsupported`] unless `x` and `WIDTH` are multiples of 8 and the frame
+    /// fits on the display. Put the driver into its partial/quick refresh mode first if it has
+    /// one, the rest of the display keeps its content.
+    pub fn show_at<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let region = Region::new(x, y, WIDTH, HEIGHT);
+        let window =
+            PartialWindow::new(region, epd.width(), epd.height()).ok_or(Error::Unsupported)?;
+        epd.update_partial_window(spi, self.data, window)?;
+        epd.display_frame(spi, delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+    use crate::test_utils::{Controller, IdleBusyPin, MockNoop, NoopPin, Recorder, VirtualPanel};
+
+    const LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    static BLACK: [u8; LEN] = [0x00; LEN];
+    static SPLASH: RawFrame<WIDTH, HEIGHT> = RawFrame::new(&BLACK);
+    static LOGO: RawFrame<16, 2> = RawFrame::new(&[0x00, 0x0f, 0xf0, 0x00]);
+
+    #[test]
+    fn show_and_show_at() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        assert_eq!((SPLASH.width(), SPLASH.height()), (WIDTH, HEIGHT));
+
+        SPLASH.show(&mut epd, &mut spi, &mut delay).unwrap();
+        let mut panel = VirtualPanel::from_recorder(Controller::Uc81xx, WIDTH, HEIGHT, &recorder);
+        assert_eq!(panel.image(), &BLACK[..]);
+
+        recorder.take_events();
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        LOGO.show_at(&mut epd, &mut spi, &mut delay, 8, 1).unwrap();
+        panel.feed(&recorder.take_events());
+        assert_eq!(panel.pixel(8, 1), Color::Black);
+        assert_eq!(panel.pixel(20, 1), Color::White);
+        assert_eq!(panel.pixel(8, 2), Color::White);
+        assert_eq!(panel.pixel(20, 2), Color::Black);
+        assert_eq!(panel.pixel(7, 1), Color::White);
+        assert_eq!(panel.refreshes(), 2);
+    }
+
+    #[test]
+    fn show_wrong_size() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+        assert_eq!(
+            LOGO.show(&mut epd, &mut spi, &mut delay),
+            Err(Error::Unsupported)
+        );
+        assert!(recorder.take_events().is_empty());
+    }
+}
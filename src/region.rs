@@ -0,0 +1,93 @@
+//! Rectangular areas of the display, shared by the drawing, the drivers and the scheduling
+
+/// A rectangular area of the display in pixel coordinates
+///
+/// Whether they're rotated depends on where the region comes from: the [`Scheduler`] and the
+/// partial windows of the drivers use the unrotated coordinates of the controller,
+/// [`Display::take_dirty_region`] the rotated ones of the drawing.
+///
+/// [`Scheduler`]: crate::scheduler::Scheduler
+/// [`Display::take_dirty_region`]: crate::graphics::Display::take_dirty_region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    /// Left edge
+    pub x: u32,
+    /// Top edge
+    pub y: u32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
+impl Region {
+    /// Creates a new region
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Region {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The smallest region containing both regions
+    pub fn union(self, other: Region) -> Region {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = self
+            .x
+            .saturating_add(self.width)
+            .max(other.x.saturating_add(other.width));
+        let bottom = self
+            .y
+            .saturating_add(self.height)
+            .max(other.y.saturating_add(other.height));
+        Region::new(x, y, right - x, bottom - y)
+    }
+
+    /// Number of pixels in the region
+    pub fn area(&self) -> u32 {
+        self.width.saturating_mul(self.height)
+    }
+
+    /// Grows the region horizontally to whole bytes of the buffer and clips it to the display
+    ///
+    /// The controllers can only address partial windows in whole bytes.
+    pub fn aligned(self, display_width: u32, display_height: u32) -> Region {
+        let x = self.x.min(display_width) / 8 * 8;
+        let y = self.y.min(display_height);
+        let right = self
+            .x
+            .saturating_add(self.width)
+            .min(display_width)
+            .div_ceil(8)
+            * 8;
+        let bottom = self.y.saturating_add(self.height).min(display_height);
+        Region::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_union_and_alignment() {
+        let a = Region::new(3, 4, 2, 2);
+        let b = Region::new(10, 1, 4, 2);
+        assert_eq!(a.union(b), Region::new(3, 1, 11, 5));
+        assert_eq!(a.union(b).aligned(100, 100), Region::new(0, 1, 16, 5));
+        // Clipped to the display
+        assert_eq!(
+            Region::new(90, 90, 20, 20).aligned(100, 95),
+            Region::new(88, 90, 16, 5)
+        );
+        // Doesn't overflow, e.g. with deserialized regions
+        let huge = Region::new(u32::MAX - 3, u32::MAX - 3, 8, 8);
+        assert_eq!(huge.aligned(400, 300), Region::new(400, 300, 0, 0));
+        assert_eq!(huge.union(a), Region::new(3, 4, u32::MAX - 3, u32::MAX - 4));
+        assert_eq!(Region::new(0, 0, u32::MAX, 2).area(), u32::MAX);
+    }
+}
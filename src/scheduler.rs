@@ -0,0 +1,389 @@
+//! Coalescing of display updates into refreshes
+//!
+//! Applications usually change the content of the display much more often than the panel can
+//! (or should) be refreshed. The [`Scheduler`] collects the changed regions, waits until the
+//! minimum interval between refreshes passed and then decides if the batched changes are shown
+//! with a partial or a full refresh:
+//!
+//! - a full refresh if it was requested with [`Scheduler::mark_all_dirty`]
+//! - a full refresh if the changed area covers at least [`Scheduler::set_full_refresh_threshold`]
+//!   percent of the display
+//! - a full refresh after [`Scheduler::set_max_partial_refreshes`] partial refreshes in a row, to
+//!   clean up the ghosting of the partial ones
+//! - a partial refresh of the bounding box of all changes otherwise
+//!
+//! ```rust
+//! use epd_waveshare::scheduler::{Refresh, Region, Scheduler};
+//!
+//! let mut now = 0u64;
+//! let mut scheduler = Scheduler::new(|| now, 400, 300, 1_000);
+//! scheduler.mark_dirty(Region::new(10, 10, 20, 20));
+//! scheduler.mark_dirty(Region::new(50, 10, 20, 20));
+//!
+//! // Both changes are shown by one refresh, the window is aligned to whole bytes
+//! assert_eq!(
+//!     scheduler.poll(),
+//!     Some(Refresh::Partial(Region::new(8, 10, 64, 20)))
+//! );
+//! assert_eq!(scheduler.poll(), None);
+//! ```
+
+#[cfg(feature = "graphics")]
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::clock::Clock;
+#[cfg(feature = "graphics")]
+use crate::error::DriverError;
+use crate::guard::RefreshGuard;
+pub use crate::region::Region;
+#[cfg(feature = "graphics")]
+use crate::traits::{PartialWindow, WaveshareDisplay};
+
+/// Refresh decided by the [`Scheduler`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refresh {
+    /// Update the region and refresh it with a partial refresh
+    Partial(Region),
+    /// Update the whole frame and do a full refresh
+    Full,
+}
+
+/// Batches changes and decides when and how to refresh
+pub struct Scheduler<C> {
+    guard: RefreshGuard<C>,
+    width: u32,
+    height: u32,
+    dirty: Option<Region>,
+    full_requested: bool,
+    full_threshold_percent: u8,
+    max_partial_refreshes: u32,
+    partial_refreshes: u32,
+}
+
+impl<C> Scheduler<C>
+where
+    C: Clock,
+{
+    /// Creates a scheduler for a display of `width` x `height` pixels allowing one refresh every
+    /// `min_interval_ms`
+    ///
+    /// Changed areas of at least 50% of the display and every 5th refresh in a row are shown with
+    /// a full refresh by default.
+    pub fn new(clock: C, width: u32, height: u32, min_interval_ms: u64) -> Self {
+        Scheduler {
+            guard: RefreshGuard::new(clock, min_interval_ms),
+            width,
+            height,
+            dirty: None,
+            full_requested: false,
+            full_threshold_percent: 50,
+            max_partial_refreshes: 4,
+            partial_refreshes: 0,
+        }
+    }
+
+    /// Changed areas covering at least `percent` of the display are shown with a full refresh
+    pub fn set_full_refresh_threshold(&mut self, percent: u8) {
+        self.full_threshold_percent = percent.min(100);
+    }
+
+    /// Do a full refresh after `count` partial refreshes in a row
+    ///
+    /// `0` always uses full refreshes, `u32::MAX` practically never forces one.
+    pub fn set_max_partial_refreshes(&mut self, count: u32) {
+        self.max_partial_refreshes = count;
+    }
+
+    /// Marks a region as changed
+    pub fn mark_dirty(&mut self, region: Region) {
+        let region = region.aligned(self.width, self.height);
+        if region.area() == 0 {
+            return;
+        }
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(region),
+            None => region,
+        });
+    }
+
+    /// Marks the whole display as changed and requests a full refresh
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = Some(Region::new(0, 0, self.width, self.height));
+        self.full_requested = true;
+    }
+
+    /// The bounding box of all changes not shown yet
+    pub fn dirty(&self) -> Option<Region> {
+        self.dirty
+    }
+
+    /// Number of partial refreshes since the last full one
+    pub fn partial_refreshes(&self) -> u32 {
+        self.partial_refreshes
+    }
+
+    /// Decides the next refresh, call it regularly (e.g. every tick of the main loop)
+    ///
+    /// Returns `None` if nothing changed or the minimum interval didn't pass yet, the changes
+    /// stay batched in that case. Otherwise the changes are considered shown once the returned
+    /// refresh was done.
+    pub fn poll(&mut self) -> Option<Refresh> {
+        let (now, refresh) = self.decide()?;
+        self.commit(now, refresh);
+        Some(refresh)
+    }
+
+    // the next refresh and the time it is allowed at, without changing any state
+    fn decide(&mut self) -> Option<(u64, Refresh)> {
+        let dirty = self.dirty?;
+        let now = self.guard.check().ok()?;
+
+        let total = u64::from(self.width) * u64::from(self.height);
+        let large = u64::from(dirty.area()) * 100 >= total * u64::from(self.full_threshold_percent);
+        if self.full_requested || large || self.partial_refreshes >= self.max_partial_refreshes {
+            Some((now, Refresh::Full))
+        } else {
+            Some((now, Refresh::Partial(dirty)))
+        }
+    }
+
+    // records `refresh` done at `now`, the changes are shown
+    fn commit(&mut self, now: u64, refresh: Refresh) {
+        self.guard.record(now);
+        self.dirty = None;
+        match refresh {
+            Refresh::Full => {
+                self.full_requested = false;
+                self.partial_refreshes = 0;
+            }
+            Refresh::Partial(_) => self.partial_refreshes += 1,
+        }
+    }
+
+    /// Polls the scheduler and does the decided refresh with `frame`
+    ///
+    /// `frame` is the whole buffer of the display. Partial refreshes copy the changed window into
+    /// `window` first, if it is too small a full refresh is done instead.
+    ///
+    /// The changes are only considered shown if the refresh succeeded, after an error they stay
+    /// batched and the next call can retry right away.
+    #[cfg(feature = "graphics")]
+    pub fn flush<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        frame: &[u8],
+        window: &mut [u8],
+    ) -> Result<Option<Refresh>, DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let (now, refresh) = match self.decide() {
+            Some(decided) => decided,
+            None => return Ok(None),
+        };
+        let partial = match refresh {
+            Refresh::Partial(region) => PartialWindow::new(region, self.width, self.height)
+                .filter(|partial| partial.buffer_len() <= window.len()),
+            Refresh::Full => None,
+        };
+        let refresh = match partial {
+            Some(partial) => {
+                let region = partial.region();
+                let window = &mut window[..partial.buffer_len()];
+                crate::graphics::packing::copy_window(
+                    frame,
+                    self.width,
+                    region.x,
+                    region.y,
+                    region.width,
+                    region.height,
+                    window,
+                );
+                epd.update_partial_window(spi, window, partial)?;
+                epd.display_frame(spi, delay)?;
+                Refresh::Partial(region)
+            }
+            None => {
+                // A partial refresh falling back to a full one cleans the ghosting as well
+                epd.update_and_display_frame(spi, frame, delay)?;
+                Refresh::Full
+            }
+        };
+        self.commit(now, refresh);
+        Ok(Some(refresh))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    #[test]
+    fn batches_until_interval_passed() {
+        let now = Cell::new(0u64);
+        let mut scheduler = Scheduler::new(|| now.get(), 400, 300, 1000);
+        assert_eq!(scheduler.poll(), None);
+
+        scheduler.mark_dirty(Region::new(0, 0, 8, 8));
+        assert_eq!(
+            scheduler.poll(),
+            Some(Refresh::Partial(Region::new(0, 0, 8, 8)))
+        );
+
+        now.set(500);
+        scheduler.mark_dirty(Region::new(16, 0, 8, 8));
+        scheduler.mark_dirty(Region::new(0, 16, 8, 8));
+        assert_eq!(scheduler.poll(), None);
+        assert_eq!(scheduler.dirty(), Some(Region::new(0, 0, 24, 24)));
+
+        now.set(1000);
+        assert_eq!(
+            scheduler.poll(),
+            Some(Refresh::Partial(Region::new(0, 0, 24, 24)))
+        );
+        assert_eq!(scheduler.dirty(), None);
+    }
+
+    #[test]
+    fn decides_full_refreshes() {
+        let mut scheduler = Scheduler::new(|| 0, 400, 300, 0);
+
+        // Large changes
+        scheduler.mark_dirty(Region::new(0, 0, 400, 150));
+        assert_eq!(scheduler.poll(), Some(Refresh::Full));
+
+        // Requested
+        scheduler.mark_all_dirty();
+        assert_eq!(scheduler.poll(), Some(Refresh::Full));
+
+        // After too many partial refreshes
+        scheduler.set_max_partial_refreshes(2);
+        for _ in 0..2 {
+            scheduler.mark_dirty(Region::new(0, 0, 8, 8));
+            assert!(matches!(scheduler.poll(), Some(Refresh::Partial(_))));
+        }
+        assert_eq!(scheduler.partial_refreshes(), 2);
+        scheduler.mark_dirty(Region::new(0, 0, 8, 8));
+        assert_eq!(scheduler.poll(), Some(Refresh::Full));
+        assert_eq!(scheduler.partial_refreshes(), 0);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn flush_partial_window() {
+        use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+        use crate::test_utils::{
+            Controller, IdleBusyPin, MockNoop, NoopPin, Recorder, VirtualPanel,
+        };
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        // A black byte at (16, 5)
+        let mut frame = [0xff; WIDTH as usize / 8 * HEIGHT as usize];
+        frame[5 * WIDTH as usize / 8 + 2] = 0x00;
+        let mut window = [0; 32];
+
+        let mut scheduler = Scheduler::new(|| 0, WIDTH, HEIGHT, 0);
+        scheduler.mark_dirty(Region::new(17, 4, 3, 2));
+        let refresh = scheduler
+            .flush(&mut epd, &mut spi, &mut delay, &frame, &mut window)
+            .unwrap();
+        assert_eq!(refresh, Some(Refresh::Partial(Region::new(16, 4, 8, 2))));
+
+        let panel = VirtualPanel::from_recorder(Controller::Uc81xx, WIDTH, HEIGHT, &recorder);
+        assert_eq!(panel.refreshes(), 1);
+        assert_eq!(panel.pixel(16, 5), crate::color::Color::Black);
+        assert_eq!(panel.pixel(16, 4), crate::color::Color::White);
+
+        // Nothing changed
+        let refresh = scheduler
+            .flush(&mut epd, &mut spi, &mut delay, &frame, &mut window)
+            .unwrap();
+        assert_eq!(refresh, None);
+
+        // Too large for the window, the full refresh starts counting again
+        scheduler.mark_dirty(Region::new(0, 0, 64, 8));
+        let refresh = scheduler
+            .flush(&mut epd, &mut spi, &mut delay, &frame, &mut window)
+            .unwrap();
+        assert_eq!(refresh, Some(Refresh::Full));
+        assert_eq!(scheduler.partial_refreshes(), 0);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn failed_flush_stays_dirty() {
+        use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+        use crate::error::Error;
+        use crate::test_utils::{IdleBusyPin, MockNoop, NoopPin};
+        use embedded_hal::blocking::spi::Write;
+
+        // Fails all writes while `fail` is set
+        struct FlakySpi<'a> {
+            fail: &'a Cell<bool>,
+        }
+
+        impl Write<u8> for FlakySpi<'_> {
+            type Error = ();
+
+            fn write(&mut self, _words: &[u8]) -> Result<(), ()> {
+                if self.fail.get() {
+                    Err(())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+
+        let fail = Cell::new(false);
+        let mut spi = FlakySpi { fail: &fail };
+        let mut delay = MockNoop::new();
+        let mut epd =
+            Epd4in2::new(&mut spi, NoopPin, IdleBusyPin, NoopPin, NoopPin, &mut delay).unwrap();
+        let frame = [0xff; WIDTH as usize / 8 * HEIGHT as usize];
+        let mut window = [0; 32];
+
+        let mut scheduler = Scheduler::new(|| 0, WIDTH, HEIGHT, 1000);
+        scheduler.mark_dirty(Region::new(16, 4, 8, 2));
+        fail.set(true);
+        assert_eq!(
+            scheduler.flush(&mut epd, &mut spi, &mut delay, &frame, &mut window),
+            Err(Error::Spi(()))
+        );
+        assert_eq!(scheduler.dirty(), Some(Region::new(16, 4, 8, 2)));
+        assert_eq!(scheduler.partial_refreshes(), 0);
+
+        // The retry isn't held back by the failed refresh
+        fail.set(false);
+        assert_eq!(
+            scheduler.flush(&mut epd, &mut spi, &mut delay, &frame, &mut window),
+            Ok(Some(Refresh::Partial(Region::new(16, 4, 8, 2))))
+        );
+        assert_eq!(scheduler.dirty(), None);
+        assert_eq!(scheduler.partial_refreshes(), 1);
+        assert_eq!(scheduler.poll(), None);
+    }
+}
@@ -0,0 +1,372 @@
+//! Frames stored in files on SD cards
+//!
+//! Enabled with the `sdmmc` feature. [`update_frame_from_file`] reads a frame from a file opened
+//! through an [`embedded_sdmmc::VolumeManager`] in chunks and streams it to a driver implementing
+//! [`FrameStream`], so photo frames on small MCUs don't need RAM for the whole image. The file is
+//! either the raw buffer of the display ([`Format::Raw`]) or a 1 bit BMP of exactly the size of
+//! the display ([`Format::Bmp`]), as exported by most image editors.
+//!
+//! ```rust,ignore
+//! let file = volume_mgr.open_file_in_dir(root, "PHOTO.BMP", Mode::ReadOnly)?;
+//! let mut chunk = [0u8; 128];
+//! update_frame_from_file(&mut epd, &mut spi, &mut volume_mgr, file, Format::Bmp, &mut chunk)?;
+//! volume_mgr.close_file(file)?;
+//! epd.display_frame(&mut spi, &mut delay)?;
+//! ```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+use embedded_sdmmc::{BlockDevice, File, TimeSource, VolumeManager};
+
+use crate::error::DriverError;
+use crate::traits::FrameStream;
+
+/// Layout of the frame in the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The buffer of the display, as passed to
+    /// [`update_frame`](crate::traits::WaveshareDisplay::update_frame)
+    Raw,
+    /// Uncompressed BMP with 1 bit per pixel and the size of the display
+    ///
+    /// Bottom-up and top-down BMPs are supported. A palette with white as the first color is
+    /// inverted while streaming.
+    Bmp,
+}
+
+/// Errors of [`update_frame_from_file`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileError<S, F> {
+    /// Sending to the display failed, over SPI or one of its pins
+    Spi(S),
+    /// Reading the file failed
+    File(F),
+    /// The file doesn't contain a frame of the display in the given [`Format`]
+    Format,
+}
+
+/// Transmits the full frame stored in `file` to the display
+///
+/// The file is read in pieces of `chunk.len()` bytes, the position of the file is changed. A
+/// frame that turns out to be too short is transmitted as far as it goes before
+/// [`FileError::Format`] is returned.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_frame_from_file<
+    SPI,
+    CS,
+    BUSY,
+    DC,
+    RST,
+    DELAY,
+    EPD,
+    D,
+    T,
+    const MAX_DIRS: usize,
+    const MAX_FILES: usize,
+    const MAX_VOLUMES: usize,
+>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    volume_mgr: &mut VolumeManager<D, T, MAX_DIRS, MAX_FILES, MAX_VOLUMES>,
+    file: File,
+    format: Format,
+    chunk: &mut [u8],
+) -> Result<(), FileError<DriverError<SPI, CS, BUSY, DC, RST>, embedded_sdmmc::Error<D::Error>>>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: FrameStream<SPI, CS, BUSY, DC, RST, DELAY>,
+    D: BlockDevice,
+    T: TimeSource,
+    D::Error: core::fmt::Debug,
+{
+    let mut read_at = |offset: u32, buf: &mut [u8]| {
+        volume_mgr.file_seek_from_start(file, offset)?;
+        volume_mgr.read(file, buf)
+    };
+    update_frame_from_reader(epd, spi, &mut read_at, format, chunk)
+}
+
+/// Byte offsets and sizes of a BMP
+struct Bmp {
+    data_offset: u32,
+    /// Bytes of a row including the padding to 4 bytes
+    stride: u32,
+    top_down: bool,
+    inverted: bool,
+}
+
+impl Bmp {
+    /// Size of the file header and the BITMAPINFOHEADER
+    const HEADER_LEN: usize = 54;
+
+    /// Parses the headers of a 1 bit BMP with a size of `width`x`height`
+    fn parse<E, R>(read_at: &mut R, width: u32, height: u32) -> Result<Option<Bmp>, E>
+    where
+        R: FnMut(u32, &mut [u8]) -> Result<usize, E>,
+    {
+        let mut header = [0; Self::HEADER_LEN];
+        let mut palette = [0; 4];
+        if !read_exact(read_at, 0, &mut header)? {
+            return Ok(None);
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]);
+        let u32_at =
+            |i: usize| u32::from_le_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+
+        let bmp_height = u32_at(22) as i32;
+        let valid = &header[..2] == b"BM"
+            && u32_at(18) == width
+            && bmp_height.unsigned_abs() == height
+            && u16_at(28) == 1
+            && u32_at(30) == 0;
+        // The palette follows the info header
+        if !valid || !read_exact(read_at, 14 + u32_at(14), &mut palette)? {
+            return Ok(None);
+        }
+        let brightness: u32 = palette[..3].iter().map(|&c| u32::from(c)).sum();
+        Ok(Some(Bmp {
+            data_offset: u32_at(10),
+            stride: width.div_ceil(32) * 4,
+            top_down: bmp_height < 0,
+            inverted: brightness > 3 * 0x80,
+        }))
+    }
+}
+
+/// Reads `buf.len()` bytes at `offset`, false if the file ends before
+fn read_exact<E, R>(read_at: &mut R, offset: u32, buf: &mut [u8]) -> Result<bool, E>
+where
+    R: FnMut(u32, &mut [u8]) -> Result<usize, E>,
+{
+    let mut filled = 0;
+    while filled < buf.len() {
+        match read_at(offset + filled as u32, &mut buf[filled..])? {
+            0 => return Ok(false),
+            n => filled += n,
+        }
+    }
+    Ok(true)
+}
+
+/// [`update_frame_from_file`] for anything reading at an offset of a file
+#[allow(clippy::type_complexity)]
+fn update_frame_from_reader<SPI, CS, BUSY, DC, RST, DELAY, EPD, E, R>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    read_at: &mut R,
+    format: Format,
+    chunk: &mut [u8],
+) -> Result<(), FileError<DriverError<SPI, CS, BUSY, DC, RST>, E>>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: FrameStream<SPI, CS, BUSY, DC, RST, DELAY>,
+    R: FnMut(u32, &mut [u8]) -> Result<usize, E>,
+{
+    assert!(!chunk.is_empty());
+    let (width, height) = (epd.width(), epd.height());
+    let row_len = width.div_ceil(8);
+
+    // The raw buffer is a BMP with unpadded top-down rows
+    let bmp = match format {
+        Format::Raw => Bmp {
+            data_offset: 0,
+            stride: row_len,
+            top_down: true,
+            inverted: false,
+        },
+        Format::Bmp => Bmp::parse(read_at, width, height)
+            .map_err(FileError::File)?
+            .ok_or(FileError::Format)?,
+    };
+
+    epd.begin_frame(spi).map_err(FileError::Spi)?;
+    let mut complete = true;
+    'rows: for y in 0..height {
+        let row = if bmp.top_down { y } else { height - 1 - y };
+        let start = bmp.data_offset + row * bmp.stride;
+        let mut sent = 0;
+        while sent < row_len {
+            let n = (chunk.len() as u32).min(row_len - sent);
+            let buf = &mut chunk[..n as usize];
+            if !read_exact(read_at, start + sent, buf).map_err(FileError::File)? {
+                complete = false;
+                break 'rows;
+            }
+            if bmp.inverted {
+                buf.iter_mut().for_each(|b| *b = !*b);
+            }
+            epd.write_frame_chunk(spi, buf).map_err(FileError::Spi)?;
+            sent += n;
+        }
+    }
+    epd.end_frame(spi).map_err(FileError::Spi)?;
+
+    if complete {
+        Ok(())
+    } else {
+        Err(FileError::Format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+    use crate::test_utils::{
+        IdleBusyPin, MockNoop, NoopPin, Recorder, RecordingDc, RecordingRst, RecordingSpi,
+    };
+    use crate::traits::WaveshareDisplay;
+    use std::vec::Vec;
+
+    type Epd = Epd4in2<RecordingSpi, NoopPin, IdleBusyPin, RecordingDc, RecordingRst, MockNoop>;
+
+    const ROW_LEN: usize = WIDTH as usize / 8;
+
+    fn setup() -> (Recorder, RecordingSpi, Epd) {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let epd = Epd::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+        (recorder, spi, epd)
+    }
+
+    fn frame() -> Vec<u8> {
+        (0..ROW_LEN * HEIGHT as usize)
+            .map(|i| (i * 7) as u8)
+            .collect()
+    }
+
+    /// A 1 bit BMP of `frame` with the given palette, bottom-up unless `top_down`
+    fn bmp(frame: &[u8], white_first: bool, top_down: bool) -> Vec<u8> {
+        // 400 pixels are 50 bytes, padded to 52
+        let stride = 52;
+        let data_offset = 54 + 8;
+        let height = if top_down {
+            -(HEIGHT as i32)
+        } else {
+            HEIGHT as i32
+        };
+        let mut file = Vec::new();
+        file.extend_from_slice(b"BM");
+        file.extend_from_slice(&(data_offset + stride * HEIGHT).to_le_bytes());
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(&data_offset.to_le_bytes());
+        file.extend_from_slice(&40u32.to_le_bytes());
+        file.extend_from_slice(&WIDTH.to_le_bytes());
+        file.extend_from_slice(&height.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&[0; 24]);
+        let (first, second) = if white_first {
+            ([0xff; 4], [0; 4])
+        } else {
+            ([0; 4], [0xff; 4])
+        };
+        file.extend_from_slice(&first);
+        file.extend_from_slice(&second);
+        assert_eq!(file.len(), data_offset as usize);
+
+        let mut rows: Vec<&[u8]> = frame.chunks(ROW_LEN).collect();
+        if !top_down {
+            rows.reverse();
+        }
+        for row in rows {
+            for &byte in row {
+                file.push(if white_first { !byte } else { byte });
+            }
+            file.extend_from_slice(&[0xaa; 2]);
+        }
+        file
+    }
+
+    /// Reads from `file` at most 100 bytes at a time, like a file system at a block boundary
+    fn reader(file: &[u8]) -> impl FnMut(u32, &mut [u8]) -> Result<usize, ()> + '_ {
+        move |offset, buf| {
+            let rest = file.get(offset as usize..).ok_or(())?;
+            let n = buf.len().min(rest.len()).min(100);
+            buf[..n].copy_from_slice(&rest[..n]);
+            Ok(n)
+        }
+    }
+
+    fn expected(frame: &[u8]) -> Vec<crate::test_utils::Event> {
+        let (recorder, mut spi, mut epd) = setup();
+        epd.update_frame(&mut spi, frame, &mut MockNoop::new())
+            .unwrap();
+        recorder.take_events()
+    }
+
+    #[test]
+    fn raw_and_bmp_same_as_update_frame() {
+        let frame = frame();
+        let expected = expected(&frame);
+        let files = [
+            (Format::Raw, frame.clone()),
+            (Format::Bmp, bmp(&frame, false, false)),
+            (Format::Bmp, bmp(&frame, true, false)),
+            (Format::Bmp, bmp(&frame, false, true)),
+        ];
+        for (format, file) in files.iter() {
+            let (recorder, mut spi, mut epd) = setup();
+            let mut chunk = [0; 16];
+            update_frame_from_reader(&mut epd, &mut spi, &mut reader(file), *format, &mut chunk)
+                .unwrap();
+            let events = recorder.take_events();
+            assert!(events == expected, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn wrong_files() {
+        let frame = frame();
+        let (_recorder, mut spi, mut epd) = setup();
+        let mut chunk = [0; 64];
+
+        let short = &frame[..frame.len() - 1];
+        let result = update_frame_from_reader(
+            &mut epd,
+            &mut spi,
+            &mut reader(short),
+            Format::Raw,
+            &mut chunk,
+        );
+        assert_eq!(result, Err(FileError::Format));
+
+        // Not a BMP, and a BMP of the wrong size
+        let mut small = bmp(&frame, false, false);
+        small[18] = 200;
+        for file in [&frame[..], &small[..]].iter() {
+            let result = update_frame_from_reader(
+                &mut epd,
+                &mut spi,
+                &mut reader(file),
+                Format::Bmp,
+                &mut chunk,
+            );
+            assert_eq!(result, Err(FileError::Format));
+        }
+    }
+}
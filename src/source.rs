@@ -0,0 +1,567 @@
+//! Frames from external renderers
+//!
+//! Applications with their own rendering pipeline (LVGL, Slint, a custom rasterizer, ...) don't
+//! need to go through the buffer types of the crate. Anything implementing [`PixelSource`] (e.g.
+//! a closure returning the color of a pixel) is packed into the layout the drivers expect,
+//! described by [`PackedColor`] for each [`DisplayColor`](crate::traits::WaveshareDisplay::DisplayColor):
+//!
+//! * rows of pixels, each padded to whole bytes
+//! * the first pixel in the most significant bits of a byte
+//! * one plane per bit of a color, e.g. a b/w and a chromatic plane for [`TriColor`]
+//!
+//! Inverting bits for a controller is left to the drivers, like for any other frame buffer.
+//!
+//! Frames already packed like this, but in a larger buffer, are sent with [`StridedFrame`].
+//!
+//! ```rust
+//! use epd_waveshare::{color::Color, source::pack};
+//!
+//! // A black diagonal on 10x2 pixels
+//! let source = |x: u32, y: u32| if x == y { Color::Black } else { Color::White };
+//! let mut buffer = [0; 4];
+//! pack(&source, 10, 2, 0, &mut buffer);
+//! assert_eq!(buffer, [0b0111_1111, 0b1111_1111, 0b1011_1111, 0b1111_1111]);
+//! ```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::color::{Color, OctColor, TriColor};
+use crate::error::DriverError;
+use crate::traits::{FrameStream, PartialWindow, WaveshareDisplay};
+
+/// Order of the pixels in the bytes of a packed buffer
+///
+/// The drivers and the buffers of the crate put the first pixel in the most significant bits,
+/// some image converters and toolchains the other way around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitOrder {
+    /// The first pixel in the most significant bits
+    #[default]
+    MsbFirst,
+    /// The first pixel in the least significant bits, the bytes mirrored
+    LsbFirst,
+}
+
+impl BitOrder {
+    /// Converts a byte between this order and [`MsbFirst`](BitOrder::MsbFirst), in both
+    /// directions
+    pub const fn to_msb_first(self, byte: u8) -> u8 {
+        match self {
+            BitOrder::MsbFirst => byte,
+            BitOrder::LsbFirst => byte.reverse_bits(),
+        }
+    }
+}
+
+/// How pixels of a color are packed into the frame buffers of the drivers
+pub trait PackedColor: Copy {
+    /// Bits of a pixel in each plane, has to divide 8
+    const BITS_PER_PIXEL: u32;
+    /// Number of planes (separate buffers) of a frame
+    const PLANES: usize;
+    /// Color of the padding at the end of the rows
+    const PADDING: Self;
+
+    /// Bits of this color in `plane`, in the lowest [`BITS_PER_PIXEL`](PackedColor::BITS_PER_PIXEL)
+    fn plane_bits(self, plane: usize) -> u8;
+}
+
+/// A [`PackedColor`] with at most two bits per pixel, inverting its bits flips black and white
+/// or the gray levels
+///
+/// Needed by [`Display::invert`](crate::graphics::Display::invert). Not implemented for
+/// [`OctColor`], whose inverted nibbles aren't colors of the panel.
+pub trait InvertibleColor: PackedColor {}
+
+impl InvertibleColor for Color {}
+
+impl InvertibleColor for TriColor {}
+
+/// A [`PackedColor`] with one bit per pixel in its b/w plane, e.g. for packed b/w bitmaps
+///
+/// Needed by [`Display::blit`](crate::graphics::Display::blit).
+pub trait MonoColor: InvertibleColor {}
+
+impl MonoColor for Color {}
+
+impl MonoColor for TriColor {}
+
+impl PackedColor for Color {
+    const BITS_PER_PIXEL: u32 = 1;
+    const PLANES: usize = 1;
+    const PADDING: Self = Color::White;
+
+    fn plane_bits(self, _plane: usize) -> u8 {
+        self.get_bit_value()
+    }
+}
+
+/// The b/w plane followed by the chromatic plane
+impl PackedColor for TriColor {
+    const BITS_PER_PIXEL: u32 = 1;
+    const PLANES: usize = 2;
+    const PADDING: Self = TriColor::White;
+
+    fn plane_bits(self, plane: usize) -> u8 {
+        // Chromatic pixels are white in the b/w plane, cleared bits are chromatic
+        match (plane, self) {
+            (0, TriColor::Black) | (1, TriColor::Chromatic) => 0,
+            _ => 1,
+        }
+    }
+}
+
+impl PackedColor for OctColor {
+    const BITS_PER_PIXEL: u32 = 4;
+    const PLANES: usize = 1;
+    const PADDING: Self = OctColor::White;
+
+    fn plane_bits(self, _plane: usize) -> u8 {
+        self.get_nibble()
+    }
+}
+
+/// Anything that knows the color of a pixel
+pub trait PixelSource<C> {
+    /// Color of the pixel at (`x`, `y`), only called for pixels inside the frame
+    fn pixel(&self, x: u32, y: u32) -> C;
+}
+
+impl<C, F> PixelSource<C> for F
+where
+    F: Fn(u32, u32) -> C,
+{
+    fn pixel(&self, x: u32, y: u32) -> C {
+        self(x, y)
+    }
+}
+
+/// Bytes of one plane of a `width`x`height` frame
+pub fn plane_len<C: PackedColor>(width: u32, height: u32) -> usize {
+    (width * C::BITS_PER_PIXEL).div_ceil(8) as usize * height as usize
+}
+
+/// Byte `index` of `plane` of a frame `width` pixels wide
+pub fn pack_byte<C, S>(source: &S, width: u32, plane: usize, index: usize) -> u8
+where
+    C: PackedColor,
+    S: PixelSource<C> + ?Sized,
+{
+    let pixels_per_byte = 8 / C::BITS_PER_PIXEL;
+    let row_len = width.div_ceil(pixels_per_byte) as usize;
+    let y = (index / row_len) as u32;
+    let x0 = (index % row_len) as u32 * pixels_per_byte;
+    (0..pixels_per_byte).fold(0, |byte, i| {
+        let x = x0 + i;
+        let color = if x < width {
+            source.pixel(x, y)
+        } else {
+            C::PADDING
+        };
+        // 8 bits per pixel would overflow the shift
+        ((u16::from(byte) << C::BITS_PER_PIXEL) as u8) | color.plane_bits(plane)
+    })
+}
+
+/// Packs `plane` of a `width`x`height` frame into `buffer`
+pub fn pack<C, S>(source: &S, width: u32, height: u32, plane: usize, buffer: &mut [u8])
+where
+    C: PackedColor,
+    S: PixelSource<C> + ?Sized,
+{
+    let len = plane_len::<C>(width, height);
+    for (index, byte) in buffer[..len].iter_mut().enumerate() {
+        *byte = pack_byte(source, width, plane, index);
+    }
+}
+
+/// Transmits a frame from `source` to a single plane driver, without a frame buffer
+///
+/// Same as [`WaveshareDisplay::update_frame`] with the packed frame.
+pub fn update_frame_from<SPI, CS, BUSY, DC, RST, DELAY, EPD, S>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    source: &S,
+) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: FrameStream<SPI, CS, BUSY, DC, RST, DELAY>,
+    EPD::DisplayColor: PackedColor,
+    S: PixelSource<EPD::DisplayColor> + ?Sized,
+{
+    assert_eq!(EPD::DisplayColor::PLANES, 1, "only single plane drivers");
+    let width = epd.width();
+    let len = plane_len::<EPD::DisplayColor>(width, epd.height());
+    let mut chunk = [0; 32];
+    epd.begin_frame(spi)?;
+    for start in (0..len).step_by(chunk.len()) {
+        let n = chunk.len().min(len - start);
+        for (i, byte) in chunk[..n].iter_mut().enumerate() {
+            *byte = pack_byte(source, width, 0, start + i);
+        }
+        epd.write_frame_chunk(spi, &chunk[..n])?;
+    }
+    epd.end_frame(spi)
+}
+
+/// A b/w frame in a buffer owned by another library, e.g. the draw buffer of LVGL or Slint
+///
+/// The frame of the display starts `x` pixels into row `y` of the buffer and each row of the
+/// buffer is `stride` bytes. Otherwise it's packed like the frames of the crate: the first pixel
+/// in the most significant bit and cleared bits black. Sent without copying it into a frame
+/// buffer of the crate first:
+///
+/// ```rust,ignore
+/// // A 400x300 frame at 16, 8 of a 480 pixels wide canvas
+/// let frame = StridedFrame::new(&canvas, 480 / 8, 16, 8);
+/// frame.update_frame(&mut epd, &mut spi)?;
+/// epd.display_frame(&mut spi, &mut delay)?;
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct StridedFrame<'a> {
+    buffer: &'a [u8],
+    stride: usize,
+    x: u32,
+    y: u32,
+    bit_order: BitOrder,
+}
+
+impl<'a> StridedFrame<'a> {
+    /// The frame starting at `x`, `y` of `buffer` with rows of `stride` bytes
+    pub fn new(buffer: &'a [u8], stride: usize, x: u32, y: u32) -> Self {
+        StridedFrame {
+            buffer,
+            stride,
+            x,
+            y,
+            bit_order: BitOrder::MsbFirst,
+        }
+    }
+
+    /// The frame of a display buffer with the rows of `layout`, e.g. of a
+    /// [`VarDisplay`](crate::graphics::VarDisplay) with
+    /// [`with_ram_layout`](crate::graphics::VarDisplay::with_ram_layout)
+    #[cfg(feature = "graphics")]
+    pub fn with_layout(buffer: &'a [u8], layout: crate::graphics::RamLayout) -> Self {
+        StridedFrame::new(buffer, layout.stride as usize, layout.x_offset, 0)
+            .with_bit_order(layout.bit_order)
+    }
+
+    /// The frame with the pixels in `bit_order`, converted while it's sent
+    pub fn with_bit_order(self, bit_order: BitOrder) -> Self {
+        StridedFrame { bit_order, ..self }
+    }
+
+    /// Packs `row` of the frame into `dst`, starting at pixel `x` of the row
+    ///
+    /// The bits past the end of the buffer are white.
+    fn copy_row(&self, x: u32, row: u32, dst: &mut [u8]) {
+        let start = (self.y + row) as usize * self.stride;
+        let buffer = self.buffer.get(start..).unwrap_or(&[]);
+        copy_bits(buffer, (self.x + x) as usize, self.bit_order, dst);
+    }
+
+    // panics if rows of `width` pixels don't fit into the buffer
+    fn assert_fits(&self, width: u32, height: u32) {
+        let row_len = (self.x + width).div_ceil(8) as usize;
+        assert!(row_len <= self.stride, "stride too short for the display");
+        let len = (self.y + height - 1) as usize * self.stride + row_len;
+        assert!(len <= self.buffer.len(), "buffer too small for the display");
+    }
+
+    /// Transmits the frame, like [`WaveshareDisplay::update_frame`] with the frame packed into
+    /// the layout of the crate
+    ///
+    /// Rows starting on a whole byte are sent straight from the buffer, otherwise they're shifted
+    /// or reordered in chunks of a small buffer on the stack. Panics if the frame of the display
+    /// doesn't fit into the buffer.
+    pub fn update_frame<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: FrameStream<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let (width, height) = (epd.width(), epd.height());
+        self.assert_fits(width, height);
+        let row_len = width.div_ceil(8) as usize;
+        let mut chunk = [0; 32];
+        epd.begin_frame(spi)?;
+        for row in 0..height {
+            if self.x.is_multiple_of(8) && self.bit_order == BitOrder::MsbFirst {
+                let start = (self.y + row) as usize * self.stride + self.x as usize / 8;
+                epd.write_frame_chunk(spi, &self.buffer[start..start + row_len])?;
+                continue;
+            }
+            for start in (0..row_len).step_by(chunk.len()) {
+                let n = chunk.len().min(row_len - start);
+                self.copy_row(start as u32 * 8, row, &mut chunk[..n]);
+                epd.write_frame_chunk(spi, &chunk[..n])?;
+            }
+        }
+        epd.end_frame(spi)
+    }
+
+    /// Packs `window` of the frame into `buffer`, as
+    /// [`update_partial_window`](WaveshareDisplay::update_partial_window) expects it
+    ///
+    /// `buffer` needs [`PartialWindow::buffer_len`] bytes.
+    pub fn copy_window(&self, window: PartialWindow, buffer: &mut [u8]) {
+        let region = window.region();
+        let row_len = (region.width / 8) as usize;
+        let buffer = &mut buffer[..window.buffer_len()];
+        for (row, dst) in (region.y..).zip(buffer.chunks_exact_mut(row_len)) {
+            self.copy_row(region.x, row, dst);
+        }
+    }
+
+    /// Updates `window` of the display with the frame, packed into `scratch` first
+    ///
+    /// `scratch` needs [`PartialWindow::buffer_len`] bytes.
+    pub fn update_partial_window<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        window: PartialWindow,
+        scratch: &mut [u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let region = window.region();
+        self.assert_fits(region.x + region.width, region.y + region.height);
+        self.copy_window(window, scratch);
+        epd.update_partial_window(spi, &scratch[..window.buffer_len()], window)
+    }
+}
+
+/// The pixels of the frame, e.g. for [`pack`]
+impl PixelSource<Color> for StridedFrame<'_> {
+    fn pixel(&self, x: u32, y: u32) -> Color {
+        let mut byte = [0];
+        self.copy_row(x, y, &mut byte);
+        if byte[0] & 0x80 == 0 {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+}
+
+/// Copies the b/w pixels of `src` in `bit_order` starting at pixel `start` into `dst`, most
+/// significant bit first, the pixels past the end of `src` are white
+pub(crate) fn copy_bits(src: &[u8], start: usize, bit_order: BitOrder, dst: &mut [u8]) {
+    let (first, shift) = (start / 8, (start % 8) as u32);
+    let aligned = shift == 0 && bit_order == BitOrder::MsbFirst;
+    if let Some(bytes) = src.get(first..first + dst.len()).filter(|_| aligned) {
+        dst.copy_from_slice(bytes);
+        return;
+    }
+    let byte = |i: usize| bit_order.to_msb_first(src.get(i).copied().unwrap_or(0xff));
+    for (i, dst) in dst.iter_mut().enumerate() {
+        let (high, low) = (byte(first + i), byte(first + i + 1));
+        *dst = (u16::from_be_bytes([high, low]) << shift >> 8) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    #[test]
+    fn tri_color_planes() {
+        let colors = [TriColor::Black, TriColor::White, TriColor::Chromatic];
+        let source = |x: u32, _y: u32| colors[x as usize % 3];
+        let mut bw = [0; 2];
+        let mut chromatic = [0; 2];
+        pack(&source, 9, 1, 0, &mut bw);
+        pack(&source, 9, 1, 1, &mut chromatic);
+        assert_eq!(bw, [0b0110_1101, 0b1111_1111]);
+        assert_eq!(chromatic, [0b1101_1011, 0b0111_1111]);
+    }
+
+    #[test]
+    fn oct_color_nibbles() {
+        let source = |x: u32, y: u32| match (x + y) % 3 {
+            0 => OctColor::Red,
+            1 => OctColor::Blue,
+            _ => OctColor::Black,
+        };
+        let mut buffer = [0; 4];
+        pack(&source, 3, 2, 0, &mut buffer);
+        assert_eq!(buffer, [0x43, 0x01, 0x30, 0x41]);
+        assert_eq!(plane_len::<OctColor>(3, 2), 4);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn same_as_display() {
+        use crate::epd2in9::{Display2in9, HEIGHT, WIDTH};
+        use crate::graphics::Display;
+        use embedded_graphics::{
+            pixelcolor::BinaryColor,
+            prelude::*,
+            primitives::{Circle, PrimitiveStyle},
+        };
+
+        let mut display = Display2in9::default();
+        Circle::new(Point::new(20, 40), 60)
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(&mut display)
+            .unwrap();
+
+        let source = |x: u32, y: u32| {
+            let inside =
+                Circle::new(Point::new(20, 40), 60).contains(Point::new(x as i32, y as i32));
+            if inside {
+                Color::Black
+            } else {
+                Color::White
+            }
+        };
+        let mut buffer = [0; WIDTH as usize / 8 * HEIGHT as usize];
+        pack(&source, WIDTH, HEIGHT, 0, &mut buffer);
+        assert_eq!(&buffer[..], display.buffer());
+    }
+
+    // `frame` of `width` pixels packed into a canvas with rows of `stride` bytes at `x`, `y`,
+    // the rest of the canvas is noise
+    fn canvas(frame: &[u8], width: u32, stride: usize, x: u32, y: u32) -> std::vec::Vec<u8> {
+        let row_len = width.div_ceil(8) as usize;
+        let height = (frame.len() / row_len) as u32;
+        let mut canvas: std::vec::Vec<u8> = (0..stride * (y + height + 1) as usize)
+            .map(|i| (i * 37 % 251) as u8)
+            .collect();
+        for row in 0..height {
+            for col in 0..width {
+                let white = frame[row as usize * row_len + col as usize / 8] & (0x80 >> (col % 8));
+                let bit = (x + col) as usize;
+                let byte = &mut canvas[(y + row) as usize * stride + bit / 8];
+                let mask = 0x80 >> (bit % 8);
+                *byte = if white != 0 {
+                    *byte | mask
+                } else {
+                    *byte & !mask
+                };
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn strided_frame_sent_like_packed() {
+        use crate::epd2in9::{Epd2in9, HEIGHT, WIDTH};
+        use crate::test_utils::{IdleBusyPin, MockNoop, NoopPin, Recorder};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        let frame: std::vec::Vec<u8> = (0..WIDTH / 8 * HEIGHT)
+            .map(|i| (i * 7 % 256) as u8)
+            .collect();
+        recorder.take_events();
+        epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+        let expected = recorder.take_events();
+
+        // Straight from the canvas and shifted
+        for (x, y) in [(16, 3), (5, 2)] {
+            let stride = WIDTH as usize / 8 + 4;
+            let canvas = canvas(&frame, WIDTH, stride, x, y);
+            let strided = StridedFrame::new(&canvas, stride, x, y);
+            strided.update_frame(&mut epd, &mut spi).unwrap();
+            assert_eq!(recorder.take_events(), expected, "at {}, {}", x, y);
+
+            // The same canvas with the first pixel in the lowest bit
+            let lsb_first: std::vec::Vec<u8> = canvas.iter().map(|b| b.reverse_bits()).collect();
+            StridedFrame::new(&lsb_first, stride, x, y)
+                .with_bit_order(BitOrder::LsbFirst)
+                .update_frame(&mut epd, &mut spi)
+                .unwrap();
+            assert_eq!(
+                recorder.take_events(),
+                expected,
+                "lsb first at {}, {}",
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn strided_frame_windows() {
+        let frame: std::vec::Vec<u8> = (0..4 * 6).map(|i| (i * 53 % 256) as u8).collect();
+        let canvas = canvas(&frame, 32, 7, 3, 1);
+        let strided = StridedFrame::new(&canvas, 7, 3, 1);
+
+        let region = crate::region::Region::new(8, 2, 16, 3);
+        let window = PartialWindow::new(region, 32, 6).unwrap();
+        let mut buffer = [0; 6];
+        strided.copy_window(window, &mut buffer);
+        assert_eq!(
+            buffer,
+            [frame[9], frame[10], frame[13], frame[14], frame[17], frame[18]]
+        );
+
+        // Pixel 3 of the second row of the frame
+        let black = frame[4] & 0x10 == 0;
+        assert_eq!(strided.pixel(3, 1) == Color::Black, black);
+    }
+
+    #[test]
+    #[should_panic]
+    fn strided_frame_too_small() {
+        use crate::epd2in9::Epd2in9;
+        use crate::test_utils::{IdleBusyPin, MockNoop, NoopPin, Recorder};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut MockNoop::new(),
+        )
+        .unwrap();
+        let canvas = [0xff; 16 * 296];
+        // Starts one pixel too far right for rows of 16 bytes
+        StridedFrame::new(&canvas, 16, 1, 0)
+            .update_frame(&mut epd, &mut spi)
+            .unwrap();
+    }
+}
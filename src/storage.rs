@@ -0,0 +1,291 @@
+//! Frames stored in external flash
+//!
+//! Enabled with the `storage` feature. [`update_frame_from_flash`] reads a frame from any
+//! [`ReadNorFlash`] (e.g. a SPI NOR flash with pre-rendered images) in chunks and streams it to a
+//! driver implementing [`FrameStream`], so only one chunk has to fit into RAM.
+//!
+//! ```rust,ignore
+//! // The frame is stored at offset 0x1000 of the flash
+//! let mut chunk = [0u8; 256];
+//! update_frame_from_flash(&mut epd, &mut spi, &mut flash, 0x1000, &mut chunk)?;
+//! epd.display_frame(&mut spi, &mut delay)?;
+//! ```
+
+use core::convert::TryFrom;
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+use embedded_storage::nor_flash::ReadNorFlash;
+
+use crate::error::DriverError;
+use crate::traits::FrameStream;
+
+/// Errors of [`update_frame_from_flash`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamError<S, F> {
+    /// Sending to the display failed, over SPI or one of its pins
+    Spi(S),
+    /// Reading from the flash failed
+    Flash(F),
+    /// The chunk is empty or not a multiple of [`ReadNorFlash::READ_SIZE`]
+    Chunk,
+    /// The frame at the offset runs past the 32 bit addresses of the flash
+    Offset,
+}
+
+/// Transmits the full frame stored at `offset` of `flash` to the display
+///
+/// The frame has the size of the display buffer. It's read in pieces of `chunk.len()` bytes,
+/// which has to be a non-zero multiple of [`ReadNorFlash::READ_SIZE`], or
+/// [`StreamError::Chunk`] is returned before anything is sent. The last read is padded to
+/// `READ_SIZE`, so it might read a few bytes past the end of the frame.
+#[allow(clippy::type_complexity)]
+pub fn update_frame_from_flash<SPI, CS, BUSY, DC, RST, DELAY, EPD, FLASH>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    flash: &mut FLASH,
+    offset: u32,
+    chunk: &mut [u8],
+) -> Result<(), StreamError<DriverError<SPI, CS, BUSY, DC, RST>, FLASH::Error>>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: FrameStream<SPI, CS, BUSY, DC, RST, DELAY>,
+    FLASH: ReadNorFlash,
+{
+    if chunk.is_empty() || !chunk.len().is_multiple_of(FLASH::READ_SIZE) {
+        return Err(StreamError::Chunk);
+    }
+    let len = crate::buffer_len(epd.width() as usize, epd.height() as usize);
+    u32::try_from(len)
+        .ok()
+        .and_then(|len| offset.checked_add(len))
+        .ok_or(StreamError::Offset)?;
+
+    epd.begin_frame(spi).map_err(StreamError::Spi)?;
+    let mut sent = 0;
+    while sent < len {
+        let n = chunk.len().min(len - sent);
+        let read = n.div_ceil(FLASH::READ_SIZE) * FLASH::READ_SIZE;
+        // Can't overflow, the end of the frame was checked above
+        flash
+            .read(offset + sent as u32, &mut chunk[..read])
+            .map_err(StreamError::Flash)?;
+        epd.write_frame_chunk(spi, &chunk[..n])
+            .map_err(StreamError::Spi)?;
+        sent += n;
+    }
+    epd.end_frame(spi).map_err(StreamError::Spi)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_utils::{
+        IdleBusyPin, MockNoop, NoopPin, Recorder, RecordingDc, RecordingRst, RecordingSpi,
+    };
+    use crate::traits::WaveshareDisplay;
+    use embedded_storage::nor_flash::{ErrorType, NorFlashError, NorFlashErrorKind};
+    use std::vec::Vec;
+
+    #[derive(Debug)]
+    struct OutOfBounds;
+
+    impl NorFlashError for OutOfBounds {
+        fn kind(&self) -> NorFlashErrorKind {
+            NorFlashErrorKind::OutOfBounds
+        }
+    }
+
+    /// Flash in RAM reading 4 bytes at a time
+    struct RamFlash(Vec<u8>);
+
+    impl ErrorType for RamFlash {
+        type Error = OutOfBounds;
+    }
+
+    impl ReadNorFlash for RamFlash {
+        const READ_SIZE: usize = 4;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            assert_eq!(offset as usize % Self::READ_SIZE, 0);
+            assert_eq!(bytes.len() % Self::READ_SIZE, 0);
+            let start = offset as usize;
+            let data = self.0.get(start..start + bytes.len()).ok_or(OutOfBounds)?;
+            bytes.copy_from_slice(data);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    /// Checks that streaming from flash sends the same as `update_frame`
+    fn same_as_update_frame<EPD>(len: usize)
+    where
+        EPD: FrameStream<RecordingSpi, NoopPin, IdleBusyPin, RecordingDc, RecordingRst, MockNoop>,
+    {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = EPD::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        let frame: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+        let mut flash = RamFlash([&[0xaa; 16][..], &frame, &[0xaa; 16]].concat());
+
+        recorder.take_events();
+        epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+        let expected = recorder.take_events();
+
+        // A chunk size which doesn't divide the frame
+        let mut chunk = [0; 12];
+        update_frame_from_flash(&mut epd, &mut spi, &mut flash, 16, &mut chunk).unwrap();
+        assert_eq!(recorder.take_events(), expected);
+    }
+
+    #[test]
+    fn stream_epd4in2() {
+        use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd4in2<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd2in7b() {
+        use crate::epd2in7b::{Epd2in7b, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd2in7b<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd1in54() {
+        use crate::epd1in54::{Epd1in54, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd1in54<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd2in9() {
+        use crate::epd2in9::{Epd2in9, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd2in9<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd1in54b() {
+        use crate::epd1in54b::{Epd1in54b, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd1in54b<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd1in54c() {
+        use crate::epd1in54c::{Epd1in54c, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd1in54c<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd2in13bc() {
+        use crate::epd2in13bc::{Epd2in13bc, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd2in13bc<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd2in9bc() {
+        use crate::epd2in9bc::{Epd2in9bc, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd2in9bc<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd2in9_v2() {
+        use crate::epd2in9_v2::{Epd2in9, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd2in9<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd7in5() {
+        use crate::epd7in5::{Epd7in5, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd7in5<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn stream_epd7in5_hd() {
+        use crate::epd7in5_hd::{Epd7in5, HEIGHT, WIDTH};
+        same_as_update_frame::<Epd7in5<_, _, _, _, _, _>>(crate::buffer_len(
+            WIDTH as usize,
+            HEIGHT as usize,
+        ));
+    }
+
+    #[test]
+    fn flash_error() {
+        use crate::epd4in2::Epd4in2;
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        let mut flash = RamFlash(std::vec![0; 100]);
+        let mut chunk = [0; 16];
+        let result = update_frame_from_flash(&mut epd, &mut spi, &mut flash, 0, &mut chunk);
+        assert!(matches!(result, Err(StreamError::Flash(OutOfBounds))));
+
+        recorder.take_events();
+        let result = update_frame_from_flash(&mut epd, &mut spi, &mut flash, 0, &mut chunk[..6]);
+        assert!(matches!(result, Err(StreamError::Chunk)));
+        let result =
+            update_frame_from_flash(&mut epd, &mut spi, &mut flash, u32::MAX - 8, &mut chunk);
+        assert!(matches!(result, Err(StreamError::Offset)));
+        assert!(recorder.take_events().is_empty());
+    }
+}
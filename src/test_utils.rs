@@ -0,0 +1,429 @@
+//! Host side test utilities built on [`embedded-hal-mock`]
+//!
+//! Enabled with the `test-utils` feature. [`Expectations`] describes the traffic a driver should
+//! produce in terms of the display interface primitives (commands, data,
+//! busy waits, resets) and turns it into the exact SPI and pin expectations for the mocks. Use it
+//! to test the rendering logic of your firmware on the host:
+//!
+//! ```rust,no_run
+//! use epd_waveshare::{epd4in2::*, prelude::*, test_utils::Expectations};
+//!
+//! let mut expectations = Expectations::new();
+//! expectations.reset();
+//! // [...] the rest of the init sequence
+//! expectations.busy_idle(true).cmd(0x12);
+//!
+//! let mut mocks = expectations.build();
+//! let mut epd = Epd4in2::new(
+//!     &mut mocks.spi,
+//!     mocks.cs.clone(),
+//!     mocks.busy.clone(),
+//!     mocks.dc.clone(),
+//!     mocks.rst.clone(),
+//!     &mut mocks.delay,
+//! )
+//! .unwrap();
+//! epd.display_frame(&mut mocks.spi, &mut mocks.delay).unwrap();
+//!
+//! mocks.done();
+//! ```
+//!
+//! To test what ends up on the display rather than the exact traffic, record the command stream
+//! with a [`Recorder`] and decode it with a [`VirtualPanel`].
+//!
+//! [`embedded-hal-mock`]: https://docs.rs/embedded-hal-mock
+
+extern crate std;
+
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::{InputPin, OutputPin},
+};
+use std::rc::Rc;
+use std::vec::Vec;
+
+use crate::interface::{DEFAULT_MAX_CHUNK_SIZE, INVERT_CHUNK_SIZE};
+
+pub use embedded_hal_mock::{
+    delay::MockNoop,
+    pin::{Mock as PinMock, State as PinState, Transaction as PinTransaction},
+    spi::{Mock as SpiMock, Transaction as SpiTransaction},
+};
+
+#[cfg(test)]
+mod golden;
+mod virtual_panel;
+
+pub use self::virtual_panel::{Controller, VirtualPanel};
+
+/// Expected traffic between a driver and the display
+///
+/// Every method mirrors one primitive of the interface used by the drivers, including the
+/// toggling of the CS, DC and RST pins and the reads of the BUSY pin.
+#[derive(Debug, Clone, Default)]
+pub struct Expectations {
+    spi: Vec<SpiTransaction>,
+    cs: Vec<PinTransaction>,
+    busy: Vec<PinTransaction>,
+    dc: Vec<PinTransaction>,
+    rst: Vec<PinTransaction>,
+    max_chunk_size: Option<usize>,
+}
+
+impl Expectations {
+    /// Creates an empty set of expectations
+    pub fn new() -> Self {
+        Expectations::default()
+    }
+
+    /// Splits the following data into chunks like
+    /// [`DisplayInterface::set_max_chunk_size`](crate::interface::DisplayInterface::set_max_chunk_size)
+    pub fn max_chunk_size(&mut self, max_chunk_size: usize) -> &mut Self {
+        self.max_chunk_size = Some(max_chunk_size.max(1));
+        self
+    }
+
+    /// A hardware reset through the RST pin
+    pub fn reset(&mut self) -> &mut Self {
+        self.rst.push(PinTransaction::set(PinState::High));
+        self.rst.push(PinTransaction::set(PinState::Low));
+        self.rst.push(PinTransaction::set(PinState::High));
+        self
+    }
+
+    /// A single command byte
+    pub fn cmd(&mut self, command: u8) -> &mut Self {
+        self.dc.push(PinTransaction::set(PinState::Low));
+        self.write(&[command])
+    }
+
+    /// Data bytes sent in one go
+    pub fn data(&mut self, data: &[u8]) -> &mut Self {
+        self.dc.push(PinTransaction::set(PinState::High));
+        self.write(data)
+    }
+
+    /// A command followed by its data, with CS low for both
+    pub fn cmd_with_data(&mut self, command: u8, data: &[u8]) -> &mut Self {
+        self.cs.push(PinTransaction::set(PinState::Low));
+        self.dc.push(PinTransaction::set(PinState::Low));
+        self.spi.push(SpiTransaction::write(std::vec![command]));
+        self.dc.push(PinTransaction::set(PinState::High));
+        self.chunks(data);
+        self.cs.push(PinTransaction::set(PinState::High));
+        self
+    }
+
+    /// Data sent byte by byte, each one in its own transfer
+    pub fn data_bytewise<I>(&mut self, data: I) -> &mut Self
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        for byte in data {
+            self.data(&[byte]);
+        }
+        self
+    }
+
+    /// Data sent through
+    /// [`DisplayInterface::data_inverted`](crate::interface::DisplayInterface::data_inverted), `data`
+    /// is given before the inversion
+    pub fn data_inverted(&mut self, data: &[u8]) -> &mut Self {
+        let max_chunk_size = self.max_chunk_size.unwrap_or(DEFAULT_MAX_CHUNK_SIZE);
+        self.cs.push(PinTransaction::set(PinState::Low));
+        self.dc.push(PinTransaction::set(PinState::High));
+        for chunk in data.chunks(INVERT_CHUNK_SIZE.min(max_chunk_size)) {
+            let inverted = chunk.iter().map(|b| !b).collect();
+            self.spi.push(SpiTransaction::write(inverted));
+        }
+        self.cs.push(PinTransaction::set(PinState::High));
+        self
+    }
+
+    /// The same data byte repeated `repetitions` times, with CS low for all of them
+    pub fn data_x_times(&mut self, value: u8, repetitions: u32) -> &mut Self {
+        self.cs.push(PinTransaction::set(PinState::Low));
+        self.dc.push(PinTransaction::set(PinState::High));
+        for _ in 0..repetitions {
+            self.spi.push(SpiTransaction::write(std::vec![value]));
+        }
+        self.cs.push(PinTransaction::set(PinState::High));
+        self
+    }
+
+    /// A command followed by reading back `response`
+    pub fn cmd_read(&mut self, command: u8, response: &[u8]) -> &mut Self {
+        self.cs.push(PinTransaction::set(PinState::Low));
+        self.dc.push(PinTransaction::set(PinState::Low));
+        self.spi.push(SpiTransaction::write(std::vec![command]));
+        self.dc.push(PinTransaction::set(PinState::High));
+        self.spi.push(SpiTransaction::transfer(
+            std::vec![0; response.len()],
+            response.to_vec(),
+        ));
+        self.cs.push(PinTransaction::set(PinState::High));
+        self
+    }
+
+    /// A command followed by reading back `skipped` single bytes and then `response`
+    pub fn cmd_read_skip(&mut self, command: u8, skipped: &[u8], response: &[u8]) -> &mut Self {
+        self.cs.push(PinTransaction::set(PinState::Low));
+        self.dc.push(PinTransaction::set(PinState::Low));
+        self.spi.push(SpiTransaction::write(std::vec![command]));
+        self.dc.push(PinTransaction::set(PinState::High));
+        for &byte in skipped {
+            self.spi
+                .push(SpiTransaction::transfer(std::vec![0], std::vec![byte]));
+        }
+        self.spi.push(SpiTransaction::transfer(
+            std::vec![0; response.len()],
+            response.to_vec(),
+        ));
+        self.cs.push(PinTransaction::set(PinState::High));
+        self
+    }
+
+    /// A check of the BUSY pin which finds the display idle
+    ///
+    /// `is_busy_low` is the polarity of the BUSY pin of the driver.
+    pub fn busy_idle(&mut self, is_busy_low: bool) -> &mut Self {
+        let idle = if is_busy_low {
+            PinState::High
+        } else {
+            PinState::Low
+        };
+        self.busy.push(PinTransaction::get(idle));
+        self
+    }
+
+    /// A check of the BUSY pin which finds the display still busy
+    pub fn busy(&mut self, is_busy_low: bool) -> &mut Self {
+        let busy = if is_busy_low {
+            PinState::Low
+        } else {
+            PinState::High
+        };
+        self.busy.push(PinTransaction::get(busy));
+        self
+    }
+
+    /// Creates the mocks expecting exactly this traffic
+    pub fn build(&self) -> Mocks {
+        Mocks {
+            spi: SpiMock::new(&self.spi),
+            cs: PinMock::new(&self.cs),
+            busy: PinMock::new(&self.busy),
+            dc: PinMock::new(&self.dc),
+            rst: PinMock::new(&self.rst),
+            delay: MockNoop::new(),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> &mut Self {
+        self.cs.push(PinTransaction::set(PinState::Low));
+        self.chunks(data);
+        self.cs.push(PinTransaction::set(PinState::High));
+        self
+    }
+
+    fn chunks(&mut self, data: &[u8]) {
+        let max_chunk_size = self.max_chunk_size.unwrap_or(DEFAULT_MAX_CHUNK_SIZE);
+        for chunk in data.chunks(max_chunk_size) {
+            self.spi.push(SpiTransaction::write(chunk.to_vec()));
+        }
+    }
+}
+
+/// The mocked peripherals of a driver
+///
+/// The pin mocks share their expectations between clones, so hand clones to the driver and call
+/// [`Mocks::done`] at the end of the test.
+pub struct Mocks {
+    /// SPI
+    pub spi: SpiMock,
+    /// Chip select pin
+    pub cs: PinMock,
+    /// Busy pin
+    pub busy: PinMock,
+    /// Data/Command pin
+    pub dc: PinMock,
+    /// Reset pin
+    pub rst: PinMock,
+    /// Delay, doesn't actually wait
+    pub delay: MockNoop,
+}
+
+impl Mocks {
+    /// Asserts that all expected traffic happened
+    pub fn done(&mut self) {
+        self.spi.done();
+        self.cs.done();
+        self.busy.done();
+        self.dc.done();
+        self.rst.done();
+    }
+}
+
+/// Traffic seen by a [`Recorder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The RST pin was pulled low
+    Reset,
+    /// A command byte
+    Command(u8),
+    /// Data bytes, consecutive writes are merged into one event
+    Data(Vec<u8>),
+}
+
+/// Records the logical command stream of a driver
+///
+/// Unlike [`Expectations`] this doesn't care how the traffic is split into SPI transfers or
+/// how often the pins are toggled, only about the resulting stream of commands and data. Hand
+/// the peripherals created by [`Recorder::spi`], [`Recorder::dc`] and [`Recorder::rst`] to the
+/// driver together with a [`NoopPin`] as CS and an [`IdleBusyPin`].
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    events: Rc<RefCell<Vec<Event>>>,
+    dc_high: Rc<Cell<bool>>,
+}
+
+impl Recorder {
+    /// Creates a new recorder without any events
+    pub fn new() -> Self {
+        Recorder::default()
+    }
+
+    /// SPI recording everything written to it
+    pub fn spi(&self) -> RecordingSpi {
+        RecordingSpi(self.clone())
+    }
+
+    /// Data/Command pin deciding if written bytes are commands or data
+    pub fn dc(&self) -> RecordingDc {
+        RecordingDc(self.clone())
+    }
+
+    /// Reset pin recording resets
+    pub fn rst(&self) -> RecordingRst {
+        RecordingRst(self.clone())
+    }
+
+    /// All events recorded so far
+    pub fn events(&self) -> Vec<Event> {
+        self.events.borrow().clone()
+    }
+
+    /// Returns and forgets all events recorded so far
+    pub fn take_events(&self) -> Vec<Event> {
+        self.events.replace(Vec::new())
+    }
+
+    fn push_bytes(&self, bytes: &[u8]) {
+        let mut events = self.events.borrow_mut();
+        if self.dc_high.get() {
+            match events.last_mut() {
+                Some(Event::Data(data)) => data.extend_from_slice(bytes),
+                _ => events.push(Event::Data(bytes.to_vec())),
+            }
+        } else {
+            events.extend(bytes.iter().map(|&b| Event::Command(b)));
+        }
+    }
+}
+
+/// SPI of a [`Recorder`]
+#[derive(Debug, Clone)]
+pub struct RecordingSpi(Recorder);
+
+impl Write<u8> for RecordingSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.push_bytes(words);
+        Ok(())
+    }
+}
+
+impl Transfer<u8> for RecordingSpi {
+    type Error = Infallible;
+
+    /// Records the sent bytes and reads back zeros
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.0.push_bytes(words);
+        for w in words.iter_mut() {
+            *w = 0;
+        }
+        Ok(words)
+    }
+}
+
+/// Data/Command pin of a [`Recorder`]
+#[derive(Debug, Clone)]
+pub struct RecordingDc(Recorder);
+
+impl OutputPin for RecordingDc {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.dc_high.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.dc_high.set(true);
+        Ok(())
+    }
+}
+
+/// Reset pin of a [`Recorder`]
+#[derive(Debug, Clone)]
+pub struct RecordingRst(Recorder);
+
+impl OutputPin for RecordingRst {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.events.borrow_mut().push(Event::Reset);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Output pin ignoring everything, e.g. for CS
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopPin;
+
+impl OutputPin for NoopPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Busy pin of a display which is never busy
+///
+/// Reports neither high nor low, so it is idle for both busy polarities used by the drivers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IdleBusyPin;
+
+impl InputPin for IdleBusyPin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
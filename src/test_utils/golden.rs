@@ -0,0 +1,228 @@
+//! Golden command streams of all drivers
+//!
+//! Every driver runs a canonical init, full frame update, refresh and sleep against a
+//! [`Recorder`]. The resulting stream is compared to the checked in file in `golden/`, so changes
+//! to the traffic sent to the hardware can't slip through unnoticed.
+//!
+//! The files were checked command by command against the sequences of the Waveshare reference
+//! code (`RaspberryPi_JetsonNano/c/lib/e-Paper` and `Arduino` of waveshare/e-Paper), not only
+//! recorded from the drivers. The references and the known differences, kept from the drivers:
+//!
+//! | File | Reference | Differences |
+//! |------|-----------|-------------|
+//! | `epd1in54` | `EPD_1in54.c` | 200 gate lines (0xc8) instead of `HEIGHT - 1`, deep sleep with 0x00 instead of 0x01 |
+//! | `epd1in54b` | `EPD_1in54b.c` | the resolution is sent again before each frame |
+//! | `epd1in54c` | `EPD_1in54c.c` | |
+//! | `epd2in13_v2` | `EPD_2in13_V2.c` | data entry mode 0x03 with the RAM window from the top |
+//! | `epd2in13bc` | `EPD_2in13bc.c` | |
+//! | `epd2in7b`, `epd2in7b_partial_and_lut` | `EPD_2in7b.c`, `epd2in7b.cpp` | |
+//! | `epd2in9` | `EPD_2in9.c` | deep sleep with 0x00 instead of 0x01 |
+//! | `epd2in9_v2` | `EPD_2in9_V2.c` | the waveform of the OTP instead of a LUT sent by the host |
+//! | `epd2in9bc` | `EPD_2in9bc.c` | |
+//! | `epd4in2` | `epd4in2.cpp` | |
+//! | `epd5in65f` | `EPD_5in65f.c` | |
+//! | `epd7in5` | `EPD_7in5.c` | |
+//! | `epd7in5_hd` | `EPD_7in5_HD.c` | |
+//! | `epd7in5_v2` | `EPD_7in5_V2.c` | |
+//!
+//! Run the tests with `UPDATE_GOLDEN=1` to regenerate the files after an intended change, and
+//! check the new traffic against the reference before committing it. The tri color streams
+//! clear the chromatic plane as the references do, see the `clear_frame` cases of
+//! `epd2in7b_partial_and_lut`.
+
+use super::*;
+use crate::traits::WaveshareDisplay;
+use std::fmt::Write as _;
+use std::string::String;
+
+/// Formats the events one per line, runs of 4 or more equal bytes are written as `byte*count`
+fn format_events(out: &mut String, events: &[Event]) {
+    for event in events {
+        match event {
+            Event::Reset => out.push_str("reset\n"),
+            Event::Command(c) => writeln!(out, "cmd {:02x}", c).unwrap(),
+            Event::Data(data) => {
+                out.push_str("data");
+                let mut rest = &data[..];
+                while let Some(&byte) = rest.first() {
+                    let run = rest.iter().take_while(|&&b| b == byte).count();
+                    if run >= 4 {
+                        write!(out, " {:02x}*{}", byte, run).unwrap();
+                    } else {
+                        for _ in 0..run {
+                            write!(out, " {:02x}", byte).unwrap();
+                        }
+                    }
+                    rest = &rest[run..];
+                }
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// A frame with distinct black, striped and white thirds
+fn canonical_frame(len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|i| match i * 3 / len {
+            0 => 0x00,
+            1 => 0xaa,
+            _ => 0xff,
+        })
+        .collect()
+}
+
+fn record<EPD>(buffer_len: usize) -> String
+where
+    EPD: WaveshareDisplay<RecordingSpi, NoopPin, IdleBusyPin, RecordingDc, RecordingRst, MockNoop>,
+{
+    let recorder = Recorder::new();
+    let mut spi = recorder.spi();
+    let mut delay = MockNoop::new();
+    let mut out = String::new();
+
+    let mut epd = EPD::new(
+        &mut spi,
+        NoopPin,
+        IdleBusyPin,
+        recorder.dc(),
+        recorder.rst(),
+        &mut delay,
+    )
+    .unwrap();
+    out.push_str("# new\n");
+    format_events(&mut out, &recorder.take_events());
+
+    let buffer = canonical_frame(buffer_len);
+    epd.update_frame(&mut spi, &buffer, &mut delay).unwrap();
+    out.push_str("# update_frame\n");
+    format_events(&mut out, &recorder.take_events());
+
+    epd.display_frame(&mut spi, &mut delay).unwrap();
+    out.push_str("# display_frame\n");
+    format_events(&mut out, &recorder.take_events());
+
+    epd.sleep(&mut spi, &mut delay).unwrap();
+    out.push_str("# sleep\n");
+    format_events(&mut out, &recorder.take_events());
+
+    out
+}
+
+fn check_golden(name: &str, actual: &str) {
+    let path = std::format!(
+        "{}/src/test_utils/golden/{}.txt",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("missing {}, run with UPDATE_GOLDEN=1", path));
+    for (line, (e, a)) in expected.lines().zip(actual.lines()).enumerate() {
+        assert_eq!(e, a, "{} differs in line {}", name, line + 1);
+    }
+    assert_eq!(
+        expected.lines().count(),
+        actual.lines().count(),
+        "{} differs in length",
+        name
+    );
+}
+
+fn mono(width: u32, height: u32) -> usize {
+    crate::buffer_len(width as usize, height as usize)
+}
+
+fn oct(width: u32, height: u32) -> usize {
+    width as usize * height as usize / 2
+}
+
+macro_rules! golden_test {
+    ($module:ident :: $epd:ident, $buffer_len:ident) => {
+        #[test]
+        fn $module() {
+            use crate::$module::{$epd, HEIGHT, WIDTH};
+            type Epd =
+                $epd<RecordingSpi, NoopPin, IdleBusyPin, RecordingDc, RecordingRst, MockNoop>;
+
+            check_golden(
+                stringify!($module),
+                &record::<Epd>($buffer_len(WIDTH, HEIGHT)),
+            );
+        }
+    };
+}
+
+// The type a drivers send a different LUT with `type_a_alternative_faster_lut`
+#[cfg(not(feature = "type_a_alternative_faster_lut"))]
+golden_test!(epd1in54::Epd1in54, mono);
+golden_test!(epd1in54b::Epd1in54b, mono);
+golden_test!(epd1in54c::Epd1in54c, mono);
+golden_test!(epd2in13_v2::Epd2in13, mono);
+golden_test!(epd2in13bc::Epd2in13bc, mono);
+golden_test!(epd2in7b::Epd2in7b, mono);
+#[cfg(not(feature = "type_a_alternative_faster_lut"))]
+golden_test!(epd2in9::Epd2in9, mono);
+golden_test!(epd2in9_v2::Epd2in9, mono);
+golden_test!(epd2in9bc::Epd2in9bc, mono);
+golden_test!(epd4in2::Epd4in2, mono);
+golden_test!(epd5in65f::Epd5in65f, oct);
+golden_test!(epd7in5::Epd7in5, mono);
+golden_test!(epd7in5_hd::Epd7in5, mono);
+golden_test!(epd7in5_v2::Epd7in5, mono);
+
+/// The remaining operations of `Epd2in7b`, the exact traffic was checked against the
+/// Waveshare reference code
+///
+/// A cleared frame has an empty red plane (0x00 in the inverted format of the controller)
+/// whatever the background color, like `EPD_2IN7B_Clear`.
+#[test]
+fn epd2in7b_partial_and_lut() {
+    use crate::epd2in7b::{Epd2in7b, HEIGHT, WIDTH};
+    use crate::region::Region;
+    use crate::traits::PartialWindow;
+    use crate::traits::RefreshLut;
+
+    let recorder = Recorder::new();
+    let mut spi = recorder.spi();
+    let mut delay = MockNoop::new();
+    let mut out = String::new();
+
+    let mut epd = Epd2in7b::new(
+        &mut spi,
+        NoopPin,
+        IdleBusyPin,
+        recorder.dc(),
+        recorder.rst(),
+        &mut delay,
+    )
+    .unwrap();
+    recorder.take_events();
+
+    // 16x4 window at (8, 10), black and white halves
+    let buffer = [0x00, 0xff, 0x00, 0xff, 0x0f, 0xf0, 0x0f, 0xf0];
+    let window = PartialWindow::new(Region::new(8, 10, 16, 4), WIDTH, HEIGHT).unwrap();
+    epd.update_partial_window(&mut spi, &buffer, window)
+        .unwrap();
+    out.push_str("# update_partial_window\n");
+    format_events(&mut out, &recorder.take_events());
+
+    epd.set_lut(&mut spi, Some(RefreshLut::Full)).unwrap();
+    out.push_str("# set_lut\n");
+    format_events(&mut out, &recorder.take_events());
+
+    epd.clear_frame(&mut spi, &mut delay).unwrap();
+    out.push_str("# clear_frame\n");
+    format_events(&mut out, &recorder.take_events());
+
+    epd.set_background_color(crate::color::Color::Black);
+    epd.clear_frame(&mut spi, &mut delay).unwrap();
+    out.push_str("# clear_frame black\n");
+    format_events(&mut out, &recorder.take_events());
+
+    check_golden("epd2in7b_partial_and_lut", &out);
+}
@@ -0,0 +1,573 @@
+//! A simulated panel decoding the command stream of a driver
+//!
+//! Instead of comparing raw bytes, feed the [`Event`]s of a [`Recorder`] into a [`VirtualPanel`]
+//! and check what the panel would actually show after the last refresh.
+
+use super::{std, Event, Recorder};
+use crate::color::Color;
+use std::vec::Vec;
+
+/// Controller family of a [`VirtualPanel`]
+///
+/// Only the commands needed to reconstruct a black/white image are interpreted, everything else
+/// (power settings, LUTs, ...) is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controller {
+    /// UC8176 and relatives (e.g. 4.2"): the image is written with `0x13` (Data Start
+    /// Transmission 2), partial windows are set with `0x90` between `0x91` and `0x92` and `0x12`
+    /// refreshes the display
+    Uc81xx,
+    /// SSD16xx family (e.g. 1.54", 2.9"): the image is written to the B/W RAM with `0x24`, the
+    /// RAM window with `0x44`/`0x45`, the address counter with `0x4E`/`0x4F` and `0x20`
+    /// refreshes the display
+    ///
+    /// Only the default data entry mode (x and y increment, x first) is supported.
+    Ssd16xx,
+    /// IL91874 of the 2.7" b/w/red panel: the inverted b/w image is written with `0x10` (Data
+    /// Start Transmission 1), partial windows with `0x14` followed by position and size, and
+    /// `0x12` refreshes the display
+    ///
+    /// The red layer is written with `0x13` and `0x15` and only shown while the panel setting
+    /// `0x00` selects the tri-color mode, see [`VirtualPanel::chromatic`].
+    Il91874,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Window {
+    /// In bytes
+    x_start: u32,
+    /// In bytes, inclusive
+    x_end: u32,
+    y_start: u32,
+    /// Inclusive
+    y_end: u32,
+}
+
+/// Reconstructs the displayed image from the traffic of a driver
+///
+/// ```rust
+/// use epd_waveshare::{epd4in2::*, prelude::*};
+/// use epd_waveshare::test_utils::{
+///     Controller, IdleBusyPin, MockNoop, NoopPin, Recorder, VirtualPanel,
+/// };
+///
+/// let recorder = Recorder::new();
+/// let mut spi = recorder.spi();
+/// let mut delay = MockNoop::new();
+/// let mut epd = Epd4in2::new(
+///     &mut spi,
+///     NoopPin,
+///     IdleBusyPin,
+///     recorder.dc(),
+///     recorder.rst(),
+///     &mut delay,
+/// )
+/// .unwrap();
+/// epd.clear_frame(&mut spi, &mut delay).unwrap();
+/// epd.display_frame(&mut spi, &mut delay).unwrap();
+///
+/// let mut panel = VirtualPanel::new(Controller::Uc81xx, WIDTH, HEIGHT);
+/// panel.feed(&recorder.events());
+/// assert_eq!(panel.refreshes(), 1);
+/// assert_eq!(panel.pixel(0, 0), Color::White);
+/// ```
+#[derive(Debug, Clone)]
+pub struct VirtualPanel {
+    controller: Controller,
+    width: u32,
+    height: u32,
+    ram: Vec<u8>,
+    shown: Vec<u8>,
+    /// Set bits are chromatic
+    chromatic_ram: Vec<u8>,
+    chromatic_shown: Vec<u8>,
+    /// The panel setting selects the tri-color mode
+    tri_color: bool,
+    /// The data of the current command goes to the chromatic RAM
+    writes_chromatic: bool,
+    refreshes: usize,
+    command: Option<u8>,
+    params: Vec<u8>,
+    window: Window,
+    partial: bool,
+    x: u32,
+    y: u32,
+}
+
+impl VirtualPanel {
+    /// Creates a white panel of `width` x `height` pixels
+    pub fn new(controller: Controller, width: u32, height: u32) -> Self {
+        let len = crate::buffer_len(width as usize, height as usize);
+        let mut panel = VirtualPanel {
+            controller,
+            width,
+            height,
+            ram: std::vec![Color::White.get_byte_value(); len],
+            shown: std::vec![Color::White.get_byte_value(); len],
+            chromatic_ram: std::vec![0; len],
+            chromatic_shown: std::vec![0; len],
+            tri_color: true,
+            writes_chromatic: false,
+            refreshes: 0,
+            command: None,
+            params: Vec::new(),
+            window: Window {
+                x_start: 0,
+                x_end: 0,
+                y_start: 0,
+                y_end: 0,
+            },
+            partial: false,
+            x: 0,
+            y: 0,
+        };
+        panel.reset();
+        panel
+    }
+
+    /// Creates a panel and feeds it everything recorded so far
+    pub fn from_recorder(
+        controller: Controller,
+        width: u32,
+        height: u32,
+        recorder: &Recorder,
+    ) -> Self {
+        let mut panel = VirtualPanel::new(controller, width, height);
+        panel.feed(&recorder.events());
+        panel
+    }
+
+    /// Interprets the events in order
+    pub fn feed(&mut self, events: &[Event]) {
+        for event in events {
+            match event {
+                Event::Reset => self.reset(),
+                Event::Command(command) => self.command(*command),
+                Event::Data(data) => {
+                    for &byte in data {
+                        self.data(byte);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Number of refreshes seen so far
+    pub fn refreshes(&self) -> usize {
+        self.refreshes
+    }
+
+    /// Color of a pixel as shown after the last refresh
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        Self::get(&self.shown, self.width, x, y)
+    }
+
+    /// Color of a pixel in the RAM of the controller, which might not be shown yet
+    pub fn ram_pixel(&self, x: u32, y: u32) -> Color {
+        Self::get(&self.ram, self.width, x, y)
+    }
+
+    /// Shows the pixel in the chromatic color (red) after the last refresh
+    ///
+    /// Only the [`Controller::Il91874`] has a chromatic layer.
+    pub fn chromatic(&self, x: u32, y: u32) -> bool {
+        Self::get(&self.chromatic_shown, self.width, x, y) == Color::White
+    }
+
+    /// The shown image, packed like the buffers of the [`graphics`](crate::graphics) module
+    /// without rotation
+    pub fn image(&self) -> &[u8] {
+        &self.shown
+    }
+
+    fn get(buffer: &[u8], width: u32, x: u32, y: u32) -> Color {
+        let index = (y * width.div_ceil(8) + x / 8) as usize;
+        let bit = 0x80 >> (x % 8);
+        if buffer[index] & bit == 0 {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    fn reset(&mut self) {
+        self.command = None;
+        self.partial = false;
+        self.window = self.full_window();
+        self.x = 0;
+        self.y = 0;
+    }
+
+    fn full_window(&self) -> Window {
+        Window {
+            x_start: 0,
+            x_end: self.width.div_ceil(8) - 1,
+            y_start: 0,
+            y_end: self.height - 1,
+        }
+    }
+
+    fn command(&mut self, command: u8) {
+        self.command = Some(command);
+        self.params.clear();
+        self.writes_chromatic =
+            self.controller == Controller::Il91874 && (command == 0x13 || command == 0x15);
+
+        match (self.controller, command) {
+            (Controller::Uc81xx, 0x13) => {
+                if !self.partial {
+                    self.window = self.full_window();
+                }
+                self.x = self.window.x_start;
+                self.y = self.window.y_start;
+            }
+            (Controller::Il91874, 0x10) | (Controller::Il91874, 0x13) => {
+                self.window = self.full_window();
+                self.x = self.window.x_start;
+                self.y = self.window.y_start;
+            }
+            (Controller::Uc81xx, 0x91) => self.partial = true,
+            (Controller::Uc81xx, 0x92) => self.partial = false,
+            (Controller::Uc81xx, 0x12)
+            | (Controller::Ssd16xx, 0x20)
+            | (Controller::Il91874, 0x12) => self.refresh(),
+            _ => {}
+        }
+    }
+
+    fn data(&mut self, byte: u8) {
+        let command = match self.command {
+            Some(command) => command,
+            None => panic!("data {:#04x} without a command", byte),
+        };
+
+        match (self.controller, command) {
+            (Controller::Uc81xx, 0x13) | (Controller::Ssd16xx, 0x24) => self.write_ram(byte),
+            (Controller::Uc81xx, 0x90) => {
+                self.params.push(byte);
+                // Hi and lo byte of x start, x end, y start and y end, followed by the scan mode
+                if let [xs_h, xs_l, xe_h, xe_l, ys_h, ys_l, ye_h, ye_l, _] = self.params[..] {
+                    self.window = Window {
+                        x_start: u32::from_be_bytes([0, 0, xs_h, xs_l]) / 8,
+                        x_end: u32::from_be_bytes([0, 0, xe_h, xe_l]) / 8,
+                        y_start: u32::from_be_bytes([0, 0, ys_h, ys_l]),
+                        y_end: u32::from_be_bytes([0, 0, ye_h, ye_l]),
+                    };
+                }
+            }
+            (Controller::Il91874, 0x00) => self.tri_color = byte & 0x10 == 0,
+            (Controller::Il91874, 0x10) => self.write_ram(!byte),
+            (Controller::Il91874, 0x13) => self.write_ram(byte),
+            // Hi and lo byte of x, y, width and height, followed by the image
+            (Controller::Il91874, 0x14) | (Controller::Il91874, 0x15) if self.params.len() < 8 => {
+                self.params.push(byte);
+                if let [x_h, x_l, y_h, y_l, w_h, w_l, h_h, h_l] = self.params[..] {
+                    let x = u32::from_be_bytes([0, 0, x_h, x_l]);
+                    let y = u32::from_be_bytes([0, 0, y_h, y_l]);
+                    let width = u32::from_be_bytes([0, 0, w_h, w_l]);
+                    let height = u32::from_be_bytes([0, 0, h_h, h_l]);
+                    self.window = Window {
+                        x_start: x / 8,
+                        x_end: (x + width) / 8 - 1,
+                        y_start: y,
+                        y_end: y + height - 1,
+                    };
+                    self.x = self.window.x_start;
+                    self.y = self.window.y_start;
+                }
+            }
+            (Controller::Il91874, 0x14) => self.write_ram(!byte),
+            (Controller::Il91874, 0x15) => self.write_ram(byte),
+            (Controller::Ssd16xx, 0x11) => {
+                assert_eq!(
+                    byte & 0x07,
+                    0x03,
+                    "unsupported data entry mode {:#04x}",
+                    byte
+                )
+            }
+            (Controller::Ssd16xx, 0x44) => {
+                self.params.push(byte);
+                if let [start, end] = self.params[..] {
+                    self.window.x_start = start.into();
+                    self.window.x_end = end.into();
+                }
+            }
+            (Controller::Ssd16xx, 0x45) => {
+                self.params.push(byte);
+                if let [s_l, s_h, e_l, e_h] = self.params[..] {
+                    self.window.y_start = u32::from_le_bytes([s_l, s_h, 0, 0]);
+                    self.window.y_end = u32::from_le_bytes([e_l, e_h, 0, 0]);
+                }
+            }
+            (Controller::Ssd16xx, 0x4E) => self.x = byte.into(),
+            (Controller::Ssd16xx, 0x4F) => {
+                self.params.push(byte);
+                if let [l, h] = self.params[..] {
+                    self.y = u32::from_le_bytes([l, h, 0, 0]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn write_ram(&mut self, byte: u8) {
+        let bytes_per_row = self.width.div_ceil(8);
+        assert!(
+            self.x < bytes_per_row && self.y < self.height,
+            "ram write at byte {} of row {} is outside of the panel",
+            self.x,
+            self.y
+        );
+        let ram = if self.writes_chromatic {
+            &mut self.chromatic_ram
+        } else {
+            &mut self.ram
+        };
+        ram[(self.y * bytes_per_row + self.x) as usize] = byte;
+
+        if self.x < self.window.x_end {
+            self.x += 1;
+        } else {
+            self.x = self.window.x_start;
+            self.y = if self.y < self.window.y_end {
+                self.y + 1
+            } else {
+                self.window.y_start
+            };
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.shown.copy_from_slice(&self.ram);
+        if self.tri_color {
+            self.chromatic_shown.copy_from_slice(&self.chromatic_ram);
+        } else {
+            self.chromatic_shown.fill(0);
+        }
+        self.refreshes += 1;
+    }
+}
+
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+    use crate::graphics::{Display, DisplayRotation};
+    use crate::region::Region;
+    use crate::test_utils::RecordingSpi;
+    use crate::test_utils::{IdleBusyPin, MockNoop, NoopPin, RecordingDc, RecordingRst};
+    use crate::traits::{PartialWindow, WaveshareDisplay};
+    use embedded_graphics::{
+        pixelcolor::BinaryColor,
+        prelude::*,
+        primitives::{PrimitiveStyle, Rectangle},
+    };
+
+    type Epd4in2 = crate::epd4in2::Epd4in2<
+        RecordingSpi,
+        NoopPin,
+        IdleBusyPin,
+        RecordingDc,
+        RecordingRst,
+        MockNoop,
+    >;
+    type Epd2in9 = crate::epd2in9::Epd2in9<
+        RecordingSpi,
+        NoopPin,
+        IdleBusyPin,
+        RecordingDc,
+        RecordingRst,
+        MockNoop,
+    >;
+
+    fn assert_shows<D: Display>(panel: &VirtualPanel, display: &D) {
+        assert_eq!(panel.image(), display.buffer());
+    }
+
+    #[test]
+    fn epd4in2_full_frame() {
+        use crate::epd4in2::{Display4in2, HEIGHT, WIDTH};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        let mut display = Display4in2::default();
+        Rectangle::new(Point::new(10, 20), Size::new(30, 40))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(&mut display)
+            .unwrap();
+        epd.update_frame(&mut spi, display.buffer(), &mut delay)
+            .unwrap();
+
+        let mut panel = VirtualPanel::from_recorder(Controller::Uc81xx, WIDTH, HEIGHT, &recorder);
+        assert_eq!(panel.refreshes(), 0);
+        assert_eq!(panel.pixel(10, 20), Color::White);
+        assert_eq!(panel.ram_pixel(10, 20), Color::Black);
+
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        panel.feed(&recorder.take_events()[..]);
+        assert_eq!(panel.refreshes(), 1);
+        assert_eq!(panel.pixel(9, 20), Color::White);
+        assert_eq!(panel.pixel(10, 20), Color::Black);
+        assert_eq!(panel.pixel(39, 59), Color::Black);
+        assert_eq!(panel.pixel(40, 59), Color::White);
+        assert_shows(&panel, &display);
+    }
+
+    #[test]
+    fn epd4in2_rotated_frame() {
+        use crate::epd4in2::{Display4in2, HEIGHT, WIDTH};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        let mut display = Display4in2::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        Pixel(Point::new(0, 0), BinaryColor::On)
+            .draw(&mut display)
+            .unwrap();
+        epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)
+            .unwrap();
+
+        let panel = VirtualPanel::from_recorder(Controller::Uc81xx, WIDTH, HEIGHT, &recorder);
+        // Rotated by 90 degrees clockwise the origin is in the top right corner
+        assert_eq!(panel.pixel(WIDTH - 1, 0), Color::Black);
+        assert_eq!(panel.pixel(0, 0), Color::White);
+    }
+
+    #[test]
+    fn epd4in2_partial_frame() {
+        use crate::epd4in2::{HEIGHT, WIDTH};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        // 16 x 2 pixels, black on the left half
+        let window = PartialWindow::new(Region::new(16, 8, 16, 2), WIDTH, HEIGHT).unwrap();
+        epd.update_partial_window(&mut spi, &[0x00, 0xff, 0x00, 0xff], window)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+
+        let panel = VirtualPanel::from_recorder(Controller::Uc81xx, WIDTH, HEIGHT, &recorder);
+        for y in 8..10 {
+            assert_eq!(panel.pixel(15, y), Color::White);
+            assert_eq!(panel.pixel(16, y), Color::Black);
+            assert_eq!(panel.pixel(23, y), Color::Black);
+            assert_eq!(panel.pixel(24, y), Color::White);
+        }
+        assert_eq!(panel.pixel(16, 7), Color::White);
+        assert_eq!(panel.pixel(16, 10), Color::White);
+    }
+
+    #[test]
+    fn epd2in9_full_frame() {
+        use crate::epd2in9::{Display2in9, HEIGHT, WIDTH};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        let mut display = Display2in9::default();
+        Rectangle::new(Point::new(3, 100), Size::new(50, 7))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(&mut display)
+            .unwrap();
+        epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)
+            .unwrap();
+
+        let panel = VirtualPanel::from_recorder(Controller::Ssd16xx, WIDTH, HEIGHT, &recorder);
+        assert_eq!(panel.refreshes(), 1);
+        assert_eq!(panel.pixel(3, 100), Color::Black);
+        assert_eq!(panel.pixel(2, 100), Color::White);
+        assert_shows(&panel, &display);
+    }
+
+    #[test]
+    fn epd2in7b_frames() {
+        use crate::epd2in7b::{Display2in7b, Epd2in7b, HEIGHT, WIDTH};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        let mut display = Display2in7b::default();
+        Rectangle::new(Point::new(17, 30), Size::new(20, 5))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(&mut display)
+            .unwrap();
+        epd.update_frame(&mut spi, display.buffer(), &mut delay)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+
+        let mut panel = VirtualPanel::from_recorder(Controller::Il91874, WIDTH, HEIGHT, &recorder);
+        assert_eq!(panel.refreshes(), 1);
+        assert_eq!(panel.pixel(16, 30), Color::White);
+        assert_eq!(panel.pixel(17, 30), Color::Black);
+        assert_shows(&panel, &display);
+
+        // 16 x 2 pixels, black on the right half
+        recorder.take_events();
+        let window = PartialWindow::new(Region::new(64, 100, 16, 2), WIDTH, HEIGHT).unwrap();
+        epd.update_partial_window(&mut spi, &[0xff, 0x00, 0xff, 0x00], window)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        panel.feed(&recorder.take_events());
+        for y in 100..102 {
+            assert_eq!(panel.pixel(71, y), Color::White);
+            assert_eq!(panel.pixel(72, y), Color::Black);
+            assert_eq!(panel.pixel(79, y), Color::Black);
+            assert_eq!(panel.pixel(80, y), Color::White);
+        }
+        assert_eq!(panel.pixel(72, 102), Color::White);
+        assert_eq!(panel.pixel(17, 30), Color::Black);
+    }
+}
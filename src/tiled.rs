@@ -0,0 +1,295 @@
+//! Several panels combined into one large display
+//!
+//! A [`TiledDisplay`] arranges the buffers of several physical panels (video wall style) and
+//! exposes them as a single [`DrawTarget`]. Each panel keeps its own driver instance, so it can
+//! have its own pins. [`TiledDisplay::flush`] only updates the panels that were drawn to since
+//! the last flush.
+//!
+//! ```rust
+//! use embedded_graphics::{
+//!     pixelcolor::BinaryColor,
+//!     prelude::*,
+//!     primitives::{Line, PrimitiveStyle},
+//! };
+//! use epd_waveshare::{epd4in2::Display4in2, tiled::{Tile, TiledDisplay}};
+//!
+//! // Two 4.2" panels next to each other
+//! let mut tiles = [
+//!     Tile::new(Display4in2::default(), Point::new(0, 0)),
+//!     Tile::new(Display4in2::default(), Point::new(400, 0)),
+//! ];
+//! let mut wall = TiledDisplay::new(&mut tiles);
+//! assert_eq!(wall.size(), Size::new(800, 300));
+//!
+//! // Crosses the border of the panels
+//! let _ = Line::new(Point::new(300, 150), Point::new(500, 150))
+//!     .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+//!     .draw(&mut wall);
+//! ```
+
+use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::error::DriverError;
+use crate::graphics::Display;
+use crate::traits::WaveshareDisplay;
+
+/// One panel of a [`TiledDisplay`]
+pub struct Tile<D> {
+    display: D,
+    offset: Point,
+    dirty: bool,
+}
+
+impl<D> Tile<D>
+where
+    D: Display,
+{
+    /// A panel with its top left corner at `offset` of the combined display
+    ///
+    /// The size of the tile is the (rotated) size of `display`.
+    pub fn new(display: D, offset: Point) -> Self {
+        Tile {
+            display,
+            offset,
+            dirty: true,
+        }
+    }
+
+    /// The buffer of this panel
+    pub fn display(&self) -> &D {
+        &self.display
+    }
+
+    /// The buffer of this panel, drawing to it directly marks it as changed
+    pub fn display_mut(&mut self) -> &mut D {
+        self.dirty = true;
+        &mut self.display
+    }
+
+    /// Position of the top left corner in the combined display
+    pub fn offset(&self) -> Point {
+        self.offset
+    }
+
+    /// If the panel changed since the last flush
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(self.offset, self.display.bounding_box().size)
+    }
+}
+
+/// Several panels combined into one [`DrawTarget`]
+///
+/// Pixels not covered by any tile are ignored, tiles shouldn't overlap.
+pub struct TiledDisplay<'a, D> {
+    tiles: &'a mut [Tile<D>],
+}
+
+impl<'a, D> TiledDisplay<'a, D>
+where
+    D: Display,
+{
+    /// Combines the tiles into one display
+    pub fn new(tiles: &'a mut [Tile<D>]) -> Self {
+        TiledDisplay { tiles }
+    }
+
+    /// All tiles
+    pub fn tiles(&self) -> &[Tile<D>] {
+        self.tiles
+    }
+
+    /// All tiles, accessing them doesn't change their dirty state
+    pub fn tiles_mut(&mut self) -> &mut [Tile<D>] {
+        self.tiles
+    }
+
+    /// Clears all tiles
+    pub fn clear_buffer(&mut self, background_color: crate::color::Color) {
+        for tile in self.tiles.iter_mut() {
+            tile.display_mut().clear_buffer(background_color);
+        }
+    }
+
+    /// Updates and refreshes all panels which changed since the last flush
+    ///
+    /// `epds` are the drivers of the tiles in the same order. All panels share the SPI bus, each
+    /// driver uses its own CS pin.
+    pub fn flush<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        epds: &mut [EPD],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        assert_eq!(self.tiles.len(), epds.len(), "one driver per tile needed");
+        for (tile, epd) in self.tiles.iter_mut().zip(epds.iter_mut()) {
+            if tile.dirty {
+                epd.update_and_display_frame(spi, tile.display.buffer(), delay)?;
+                tile.dirty = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, D> DrawTarget for TiledDisplay<'a, D>
+where
+    D: Display,
+{
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let Some(tile) = self
+                .tiles
+                .iter_mut()
+                .find(|tile| tile.bounding_box().contains(point))
+            {
+                let local = point - tile.offset;
+                tile.display_mut()
+                    .draw_iter(core::iter::once(Pixel(local, color)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, D> OriginDimensions for TiledDisplay<'a, D>
+where
+    D: Display,
+{
+    /// The bounding box of all tiles, starting at the origin
+    fn size(&self) -> Size {
+        self.tiles
+            .iter()
+            .map(|tile| tile.bounding_box().bottom_right())
+            .fold(Size::zero(), |size, corner| match corner {
+                Some(c) => Size::new(
+                    size.width.max(c.x as u32 + 1),
+                    size.height.max(c.y as u32 + 1),
+                ),
+                None => size,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::graphics::VarDisplay;
+    use crate::test_utils::{
+        Event, IdleBusyPin, MockNoop, NoopPin, Recorder, RecordingDc, RecordingRst, RecordingSpi,
+    };
+    use embedded_graphics::primitives::{Line, Primitive, PrimitiveStyle};
+
+    type Epd = crate::epd4in2::Epd4in2<
+        RecordingSpi,
+        NoopPin,
+        IdleBusyPin,
+        RecordingDc,
+        RecordingRst,
+        MockNoop,
+    >;
+
+    #[test]
+    fn draw_across_tiles() {
+        let mut left = [Color::White.get_byte_value(); 16 * 4 / 8];
+        let mut right = [Color::White.get_byte_value(); 16 * 4 / 8];
+        let mut tiles = [
+            Tile::new(VarDisplay::new(16, 4, &mut left), Point::new(0, 0)),
+            Tile::new(VarDisplay::new(16, 4, &mut right), Point::new(16, 0)),
+        ];
+        let mut wall = TiledDisplay::new(&mut tiles);
+        assert_eq!(wall.size(), Size::new(32, 4));
+
+        Line::new(Point::new(8, 1), Point::new(23, 1))
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(&mut wall)
+            .unwrap();
+        // Outside of all tiles
+        Pixel(Point::new(40, 1), BinaryColor::On)
+            .draw(&mut wall)
+            .unwrap();
+
+        assert_eq!(
+            wall.tiles()[0].display().buffer(),
+            &[0xff, 0xff, 0xff, 0x00, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(
+            wall.tiles()[1].display().buffer(),
+            &[0xff, 0xff, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+    }
+
+    #[test]
+    fn flush_only_dirty_tiles() {
+        use crate::epd4in2::{Display4in2, WIDTH};
+
+        // Both panels share the recorder, refreshes are counted by the display refresh command
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut new_epd = || {
+            Epd::new(
+                &mut spi,
+                NoopPin,
+                IdleBusyPin,
+                recorder.dc(),
+                recorder.rst(),
+                &mut delay,
+            )
+            .unwrap()
+        };
+        let mut epds = [new_epd(), new_epd()];
+        let refreshes = || {
+            recorder
+                .take_events()
+                .iter()
+                .filter(|&e| *e == Event::Command(0x12))
+                .count()
+        };
+        refreshes();
+
+        let mut tiles = [
+            Tile::new(Display4in2::default(), Point::new(0, 0)),
+            Tile::new(Display4in2::default(), Point::new(WIDTH as i32, 0)),
+        ];
+        let mut wall = TiledDisplay::new(&mut tiles);
+        assert!(wall.tiles().iter().all(Tile::is_dirty));
+        wall.flush(&mut spi, &mut delay, &mut epds).unwrap();
+        assert_eq!(refreshes(), 2);
+        assert!(!wall.tiles().iter().any(Tile::is_dirty));
+
+        Pixel(Point::new(WIDTH as i32 + 10, 10), BinaryColor::On)
+            .draw(&mut wall)
+            .unwrap();
+        assert!(!wall.tiles()[0].is_dirty());
+        assert!(wall.tiles()[1].is_dirty());
+        wall.flush(&mut spi, &mut delay, &mut epds).unwrap();
+        assert_eq!(refreshes(), 1);
+
+        wall.flush(&mut spi, &mut delay, &mut epds).unwrap();
+        assert_eq!(refreshes(), 0);
+    }
+}
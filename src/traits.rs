@@ -1,51 +1,151 @@
+use core::convert::Infallible;
 use core::marker::Sized;
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::*,
 };
 
+use core::time::Duration;
+
+use crate::color::Color;
+use crate::error::{DriverError, Error};
+#[cfg(feature = "graphics")]
+use crate::graphics::{
+    packing::{copy_window, find_region},
+    DisplayRotation,
+};
+use crate::interface::{DelayMsExt, TrafficFn};
+use crate::region::Region;
+
 /// All commands need to have this trait which gives the address of the command
 /// which needs to be send via SPI with activated CommandsPin (Data/Command Pin in CommandMode)
-pub(crate) trait Command {
+pub trait Command {
+    /// The command byte sent to the controller
     fn address(self) -> u8;
 }
 
+/// Raw command bytes, e.g. for undocumented registers
+impl Command for u8 {
+    fn address(self) -> u8 {
+        self
+    }
+}
+
 /// Seperates the different LUT for the Display Refresh process
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RefreshLut {
     /// The "normal" full Lookuptable for the Refresh-Sequence
+    #[default]
     Full,
     /// The quick LUT where not the full refresh sequence is followed.
     /// This might lead to some
     Quick,
 }
 
-impl Default for RefreshLut {
-    fn default() -> Self {
-        RefreshLut::Full
+/// What [`WaveshareDisplay::sleep`] does, for drivers that support both
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SleepMode {
+    /// Deep sleep, the lowest current but RAM and registers are lost and waking up runs the
+    /// whole init again
+    #[default]
+    Deep,
+    /// Only powering off, RAM and registers survive and waking up just powers on again
+    Standby,
+}
+
+/// A partial window the controllers can address: `x` and `width` are multiples of 8 and the
+/// window lies within the display
+///
+/// Taken by all partial updates, e.g.
+/// [`update_partial_window`](WaveshareDisplay::update_partial_window), so the drivers never get
+/// a window that would shift the image.
+///
+/// Only serialized, as deserializing couldn't check the bounds of the display. Deserialize the
+/// [`Region`] and pass it to [`new`](Self::new) instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PartialWindow(Region);
+
+impl PartialWindow {
+    /// The window of `region`, `None` if it isn't aligned, empty or exceeds the display
+    pub fn new(region: Region, display_width: u32, display_height: u32) -> Option<Self> {
+        if region.aligned(display_width, display_height) == region && region.area() > 0 {
+            Some(PartialWindow(region))
+        } else {
+            None
+        }
+    }
+
+    /// The smallest window containing the part of `region` on the display, `None` if that's empty
+    pub fn enclosing(region: Region, display_width: u32, display_height: u32) -> Option<Self> {
+        let region = region.aligned(display_width, display_height);
+        (region.area() > 0).then_some(PartialWindow(region))
+    }
+
+    /// The region of the window
+    pub fn region(&self) -> Region {
+        self.0
     }
+
+    /// Size of the buffer for the window in bytes
+    pub fn buffer_len(&self) -> usize {
+        (self.0.width / 8 * self.0.height) as usize
+    }
+}
+
+/// Waveform tables of the controllers with a VCOM and four pixel transition LUTs
+///
+/// Loaded with `set_custom_lut` of [`Epd2in7b`](crate::epd2in7b::Epd2in7b) and
+/// [`Epd4in2`](crate::epd4in2::Epd4in2) instead of the built-in tables, e.g. with waveforms
+/// tuned against the ghosting of a panel batch. Each group of six bytes is one phase: the
+/// voltage levels, four frame counts and the repeat count, see the datasheet of the controller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LutSet {
+    /// VCOM levels, register 0x20
+    pub vcom: [u8; 44],
+    /// White to white transitions, register 0x21
+    pub ww: [u8; 42],
+    /// Black to white transitions, register 0x22
+    pub bw: [u8; 42],
+    /// White to black transitions, register 0x23
+    pub wb: [u8; 42],
+    /// Black to black transitions, register 0x24
+    pub bb: [u8; 42],
 }
 
-pub(crate) trait InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+/// Init sequence of a driver, implemented next to [`WaveshareDisplay`]
+///
+/// Out-of-tree drivers implement it like the drivers of the crate and call it from
+/// [`new`](WaveshareDisplay::new) and [`wake_up`](WaveshareDisplay::wake_up).
+pub trait InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     /// This initialises the EPD and powers it up
     ///
     /// This function is already called from
     ///  - [new()](WaveshareDisplay::new())
-    ///  - [`wake_up`]
+    ///  - [`wake_up`](WaveshareDisplay::wake_up)
     ///
     ///
-    /// This function calls [reset](WaveshareDisplay::reset),
+    /// This function calls [reset](crate::interface::DisplayInterface::reset),
     /// so you don't need to call reset your self when trying to wake your device up
     /// after setting it to sleep.
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 }
 
 /// Functions to interact with three color panels
@@ -57,7 +157,7 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     /// Transmit data to the SRAM of the EPD
     ///
@@ -67,19 +167,92 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Update only the black/white data of the display.
     ///
     /// This must be finished by calling `update_chromatic_frame`.
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error>;
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Update only the chromatic data of the display.
     ///
     /// This should be preceded by a call to `update_achromatic_frame`.
     /// This data takes precedence over the black/white data.
-    fn update_chromatic_frame(&mut self, spi: &mut SPI, chromatic: &[u8])
-        -> Result<(), SPI::Error>;
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+}
+
+/// Typical durations of a display in ms
+///
+/// Taken from the datasheets and the Waveshare wiki. The real durations depend on the temperature
+/// and the [`Profile`](crate::config::Profile), add some margin for timeouts.
+///
+/// ```rust
+/// use epd_waveshare::epd4in2;
+///
+/// let busy_timeout_ms = epd4in2::TIMING.full_refresh_ms * 2;
+/// assert_eq!(busy_timeout_ms, 8000);
+/// assert!(epd4in2::TIMING.partial_refresh_ms.is_some());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timing {
+    /// A full refresh with the default LUT
+    pub full_refresh_ms: u32,
+    /// A partial or quick refresh, `None` if the driver doesn't support them
+    pub partial_refresh_ms: Option<u32>,
+    /// Waking up from deep sleep, i.e. the reset and init sequence
+    pub wake_ms: u32,
+}
+
+impl Timing {
+    /// Typical duration of a refresh at `temperature` in °C
+    ///
+    /// The waveforms get slower in the cold: the durations are at room temperature, below 10°C
+    /// they take 1.5 times as long, below 0°C twice as long and above 40°C three quarters of the
+    /// time. Partial refreshes of drivers without them take as long as a full refresh.
+    pub fn refresh_ms(&self, mode: RefreshMode, temperature: i8) -> u32 {
+        let base = match mode {
+            RefreshMode::Full => self.full_refresh_ms,
+            RefreshMode::Partial { .. } => self.partial_refresh_ms.unwrap_or(self.full_refresh_ms),
+        };
+        base * crate::lut::frame_percent(temperature) / 100
+    }
+}
+
+/// Kinds of refreshes for estimates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshMode {
+    /// The whole frame with the full LUT
+    Full,
+    /// A window with the partial/quick LUT, the size in pixels
+    Partial {
+        /// Width of the window
+        width: u32,
+        /// Height of the window
+        height: u32,
+    },
+}
+
+/// Errors of [`WaveshareDisplay::new_checked`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewCheckedError<E> {
+    /// The SPI clock is faster than the controller supports
+    SpiTooFast {
+        /// The configured SPI clock in Hz
+        spi_hz: u32,
+        /// [`WaveshareDisplay::MAX_SPI_HZ`] of the driver
+        max_spi_hz: u32,
+    },
+    /// The init sequence failed, over SPI or one of the pins
+    Spi(E),
 }
 
 /// All the functions to interact with the EPDs
@@ -90,7 +263,7 @@ where
 ///
 ///```rust, no_run
 ///# use embedded_hal_mock::*;
-///# fn main() -> Result<(), MockError> {
+///# fn main() -> Result<(), epd_waveshare::error::DriverError<spi::Mock, pin::Mock, pin::Mock, pin::Mock, pin::Mock>> {
 ///use embedded_graphics::{
 ///    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///};
@@ -133,10 +306,14 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     /// The Color Type used by the Display
     type DisplayColor;
+    /// Maximum SPI clock of the controller in Hz, faster clocks corrupt the frames
+    const MAX_SPI_HZ: u32;
+    /// Typical durations of the display, e.g. for power budgets and timeouts
+    const TIMING: Timing;
     /// Creates a new driver from a SPI peripheral, CS Pin, Busy InputPin, DC
     ///
     /// This already initialises the device.
@@ -147,19 +324,53 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error>
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>>
     where
         Self: Sized;
 
+    /// Like [`new`](WaveshareDisplay::new), but checks the SPI clock `spi_hz` first
+    ///
+    /// Fails without touching the display if it's faster than
+    /// [`MAX_SPI_HZ`](WaveshareDisplay::MAX_SPI_HZ).
+    #[allow(clippy::type_complexity)]
+    fn new_checked(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        spi_hz: u32,
+    ) -> Result<Self, NewCheckedError<DriverError<SPI, CS, BUSY, DC, RST>>>
+    where
+        Self: Sized,
+    {
+        if spi_hz > Self::MAX_SPI_HZ {
+            return Err(NewCheckedError::SpiTooFast {
+                spi_hz,
+                max_spi_hz: Self::MAX_SPI_HZ,
+            });
+        }
+        Self::new(spi, cs, busy, dc, rst, delay).map_err(NewCheckedError::Spi)
+    }
+
     /// Let the device enter deep-sleep mode to save power.
     ///
     /// The deep sleep mode returns to standby with a hardware reset.
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Wakes the device up from sleep
     ///
     /// Also reintialises the device if necessary.
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Sets the backgroundcolor for various commands like [clear_frame](WaveshareDisplay::clear_frame)
     fn set_background_color(&mut self, color: Self::DisplayColor);
@@ -179,13 +390,27 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Transmits partial data to the SRAM of the EPD
+    ///
+    /// `buffer` needs [`PartialWindow::buffer_len`] bytes.
+    fn update_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Transmits partial data to the SRAM of the EPD
     ///
     /// (x,y) is the top left corner
     ///
     /// BUFFER needs to be of size: width / 8 * height !
+    ///
+    /// Fails with [`Error::Unsupported`] if the window isn't a [`PartialWindow`], instead of
+    /// shifting the image.
+    #[deprecated(note = "use `update_partial_window` with a `PartialWindow`")]
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -194,12 +419,78 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        let region = Region::new(x, y, width, height);
+        let window =
+            PartialWindow::new(region, self.width(), self.height()).ok_or(Error::Unsupported)?;
+        self.update_partial_window(spi, buffer, window)
+    }
+
+    /// Updates `region` of `frame` given in the logical coordinates of a buffer drawn with
+    /// `rotation`, e.g. the whole buffer of a rotated [`Display`](crate::graphics::Display)
+    ///
+    /// The region is converted to the unrotated coordinates of the controller, grown to whole
+    /// bytes and copied from `frame` into `window` first. Returns the updated unrotated
+    /// [`PartialWindow`], `None` if `region` isn't on the display. Fails with
+    /// [`Error::BufferTooSmall`] before sending anything if `window` can't hold the window.
+    #[cfg(feature = "graphics")]
+    fn update_rotated_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        frame: &[u8],
+        region: Region,
+        rotation: DisplayRotation,
+        window: &mut [u8],
+    ) -> Result<Option<PartialWindow>, DriverError<SPI, CS, BUSY, DC, RST>> {
+        let (width, height) = (self.width(), self.height());
+        let (logical_width, logical_height) = match rotation {
+            DisplayRotation::Rotate0
+            | DisplayRotation::Rotate180
+            | DisplayRotation::FlipHorizontal
+            | DisplayRotation::FlipVertical => (width, height),
+            DisplayRotation::Rotate90
+            | DisplayRotation::Rotate270
+            | DisplayRotation::Rotate90FlipHorizontal
+            | DisplayRotation::Rotate270FlipHorizontal => (height, width),
+        };
+        let region = Region::new(
+            region.x,
+            region.y,
+            region.width.min(logical_width.saturating_sub(region.x)),
+            region.height.min(logical_height.saturating_sub(region.y)),
+        );
+        if region.area() == 0 {
+            return Ok(None);
+        }
+        let native = find_region(region, width, height, rotation);
+        let Some(partial) = PartialWindow::enclosing(native, width, height) else {
+            return Ok(None);
+        };
+        let Some(window) = window.get_mut(..partial.buffer_len()) else {
+            return Err(Error::BufferTooSmall);
+        };
+        let native = partial.region();
+        copy_window(
+            frame,
+            width,
+            native.x,
+            native.y,
+            native.width,
+            native.height,
+            window,
+        );
+        self.update_partial_window(spi, window, partial)?;
+        Ok(Some(partial))
+    }
 
     /// Displays the frame data from SRAM
     ///
     /// This function waits until the device isn`t busy anymore
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Provide a combined update&display and save some time (skipping a busy check in between)
     fn update_and_display_frame(
@@ -207,12 +498,85 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Transmits several partial windows, see
+    /// [`update_partial_window`](WaveshareDisplay::update_partial_window)
+    ///
+    /// Followed by a single [`display_frame`](WaveshareDisplay::display_frame), all windows are
+    /// refreshed together, e.g. separated fields for the time, temperature and battery cost one
+    /// refresh instead of three.
+    fn update_partial_frames(
+        &mut self,
+        spi: &mut SPI,
+        windows: &[(PartialWindow, &[u8])],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        for &(window, buffer) in windows {
+            self.update_partial_window(spi, buffer, window)?;
+        }
+        Ok(())
+    }
+
+    /// Wakes the device up, shows `buffer` and puts it back into deep sleep
+    ///
+    /// The usual cycle of battery powered devices. The device is only put to sleep after the
+    /// refresh finished. `lut` selects the LUT of the refresh, `None` keeps the current one
+    /// (drivers without [`set_lut`](WaveshareDisplay::set_lut) support need `None`).
+    fn one_shot_update(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+        lut: Option<RefreshLut>,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wake_up(spi, delay)?;
+        if lut.is_some() {
+            self.set_lut(spi, lut)?;
+        }
+        self.update_and_display_frame(spi, buffer, delay)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.sleep(spi, delay)
+    }
+
+    /// Flashes the panel black and white `cycles` times, to erase ghosts burned in by a long
+    /// static image
+    ///
+    /// Like the clear loops of the Waveshare demos, the panel is white afterwards. `lut` selects
+    /// the LUT of the flashes like for [`one_shot_update`](WaveshareDisplay::one_shot_update),
+    /// ideally [`RefreshLut::Full`] since quick refreshes don't remove ghosts.
+    fn deghost(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        cycles: u8,
+        lut: Option<RefreshLut>,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        Self::DisplayColor: From<Color> + Clone,
+    {
+        if lut.is_some() {
+            self.set_lut(spi, lut)?;
+        }
+        let background = self.background_color().clone();
+        for _ in 0..cycles {
+            for color in [Color::Black, Color::White] {
+                self.set_background_color(color.into());
+                self.clear_frame(spi, delay)?;
+                self.display_frame(spi, delay)?;
+            }
+        }
+        self.set_background_color(background);
+        self.wait_until_idle_with_timeout(delay)
+    }
 
     /// Clears the frame buffer on the EPD with the declared background color
     ///
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Trait for using various Waveforms from different LUTs
     /// E.g. for partial refreshes
@@ -226,7 +590,7 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Checks if the display is busy transmitting data
     ///
@@ -234,6 +598,374 @@ where
     /// but in the case you send data and commands directly you might need to check
     /// if the device is still busy
     fn is_busy(&self) -> bool;
+
+    /// Calls `busy_yield` between two polls of BUSY while waiting for the display
+    ///
+    /// See [`DisplayInterface::set_busy_yield`](crate::interface::DisplayInterface::set_busy_yield).
+    fn set_busy_yield(&mut self, busy_yield: Option<fn()>);
+
+    /// Waits until the display is idle, polling BUSY once per millisecond
+    ///
+    /// Gives up with [`Error::BusyTimeout`] after twice the full refresh of
+    /// [`TIMING`](WaveshareDisplay::TIMING), or the timeout set with e.g.
+    /// [`Epd2in7b::set_busy_timeout`](crate::epd2in7b::Epd2in7b::set_busy_timeout). The drivers
+    /// of the crate call the `busy_yield` between the polls and report a failed read of BUSY as
+    /// [`Error::Pin`].
+    fn wait_until_idle_with_timeout(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.wait_until_idle_or_cancel(delay, || false)
+    }
+
+    /// Splits writes to the SPI into chunks of at most `max_chunk_size` bytes
+    ///
+    /// See [`DisplayInterface::set_max_chunk_size`](crate::interface::DisplayInterface::set_max_chunk_size).
+    fn set_max_chunk_size(&mut self, max_chunk_size: usize);
+
+    /// Calls `traffic_hook` for every command, data transfer and reset, it can veto them
+    ///
+    /// The drivers take a plain function like for the busy yield. The
+    /// [`DisplayInterface`](crate::interface::DisplayInterface) of an own driver can own any
+    /// [`TrafficHook`](crate::interface::TrafficHook), e.g. a closure, see
+    /// [`DisplayInterface::set_traffic_hook`](crate::interface::DisplayInterface::set_traffic_hook).
+    fn set_traffic_hook(&mut self, traffic_hook: Option<TrafficFn>);
+
+    /// Sends a raw command byte, e.g. to experiment with undocumented registers of the controller
+    ///
+    /// The driver doesn't know what the command does, so its idea of the state of the controller
+    /// (LUT, sleep, ...) might be wrong afterwards.
+    fn send_command_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Sends raw data bytes belonging to the last [`send_command_raw`](WaveshareDisplay::send_command_raw)
+    fn send_data_raw(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Adapts the waveforms to the temperature in °C, `None` for room temperature
+    ///
+    /// Drivers with waveforms in registers ([`Epd2in7b`](crate::epd2in7b::Epd2in7b),
+    /// [`Epd4in2`](crate::epd4in2::Epd4in2)) stretch their frame counts like
+    /// [`Timing::refresh_ms`] from the next [`set_lut`](WaveshareDisplay::set_lut) or init on,
+    /// as recommended below 0°C and above 40°C. The others leave it to the controller. The
+    /// temperature can come from a sensor nearby or [`ReadBack::read_temperature`]:
+    ///
+    /// ```ignore
+    /// let temperature = epd.read_temperature(&mut spi)?;
+    /// epd.set_temperature(Some(temperature));
+    /// epd.set_lut(&mut spi, None)?;
+    /// ```
+    fn set_temperature(&mut self, temperature: Option<i8>) {
+        let _ = temperature;
+    }
+
+    /// Estimated duration of transmitting and refreshing a frame at `temperature` in °C
+    ///
+    /// The sum of the transfer of the b/w buffer at `spi_hz` and of
+    /// [`Timing::refresh_ms`]. The temperature can be read with [`ReadBack::read_temperature`]
+    /// or taken from a sensor nearby.
+    fn estimate_refresh(&self, mode: RefreshMode, temperature: i8, spi_hz: u32) -> Duration {
+        let bytes = match mode {
+            RefreshMode::Full => crate::buffer_len(self.width() as usize, self.height() as usize),
+            RefreshMode::Partial { width, height } => {
+                crate::buffer_len(width as usize, height as usize)
+            }
+        };
+        let transfer_us = bytes as u64 * 8 * 1_000_000 / u64::from(spi_hz.max(1));
+        Duration::from_micros(transfer_us)
+            + Duration::from_millis(Self::TIMING.refresh_ms(mode, temperature).into())
+    }
+
+    /// Waits until the display is idle, unless `should_cancel` returns true first
+    ///
+    /// Like [`wait_until_idle_with_timeout`](WaveshareDisplay::wait_until_idle_with_timeout),
+    /// `should_cancel` is called before every millisecond of waiting, e.g. to check a shutdown
+    /// flag or a pending event, and fails the wait with [`Error::Cancelled`]. Together with
+    /// [`InterruptRefresh::start_display_frame`] a long refresh can be abandoned. The controller
+    /// finishes the refresh on its own, reset it with [`wake_up`](WaveshareDisplay::wake_up) or
+    /// switch it off before sending anything else.
+    fn wait_until_idle_or_cancel<F>(
+        &mut self,
+        delay: &mut DELAY,
+        mut should_cancel: F,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        F: FnMut() -> bool,
+    {
+        let mut waited_ms = 0;
+        while self.is_busy() {
+            if waited_ms >= 2 * Self::TIMING.full_refresh_ms {
+                return Err(Error::BusyTimeout);
+            }
+            if should_cancel() {
+                return Err(Error::Cancelled);
+            }
+            delay.delay_ms(1);
+            waited_ms += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Full frame updates sent in chunks, so the whole buffer never has to be in RAM
+///
+/// Call [`begin_frame`](FrameStream::begin_frame), then
+/// [`write_frame_chunk`](FrameStream::write_frame_chunk) until exactly one frame buffer was sent
+/// and finally [`end_frame`](FrameStream::end_frame). The result is the same as
+/// [`WaveshareDisplay::update_frame`] with the concatenated chunks.
+///
+/// Implemented by the b/w and tri color drivers with a buffer of one bit per pixel. Not by
+/// [`Epd2in13`](crate::epd2in13_v2::Epd2in13), which sends the frame twice for full refreshes,
+/// [`Epd5in65f`](crate::epd5in65f::Epd5in65f) with its four bits per pixel and
+/// [`Epd7in5` v2](crate::epd7in5_v2::Epd7in5), which needs the delay to wait before a frame.
+pub trait FrameStream<SPI, CS, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    /// Starts the transmission of a full frame
+    fn begin_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Transmits the next part of the frame buffer
+    fn write_frame_chunk(
+        &mut self,
+        spi: &mut SPI,
+        chunk: &[u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Finishes the transmission started with [`begin_frame`](FrameStream::begin_frame)
+    fn end_frame(&mut self, spi: &mut SPI) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Transmits a full frame generated byte by byte, e.g. rendered on the fly on targets without
+    /// the RAM for a frame buffer
+    ///
+    /// The bytes are packed like the frame buffer and sent in chunks of a small buffer on the
+    /// stack.
+    fn update_frame_from_iter<I>(
+        &mut self,
+        spi: &mut SPI,
+        bytes: I,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        self.begin_frame(spi)?;
+        let mut chunk = [0u8; 64];
+        let mut len = 0;
+        for byte in bytes {
+            chunk[len] = byte;
+            len += 1;
+            if len == chunk.len() {
+                self.write_frame_chunk(spi, &chunk)?;
+                len = 0;
+            }
+        }
+        if len > 0 {
+            self.write_frame_chunk(spi, &chunk[..len])?;
+        }
+        self.end_frame(spi)
+    }
+}
+
+/// Full frame updates written to the SPI by the application, e.g. with DMA
+///
+/// The driver can't change the bytes on the way then, so
+/// [`prepare_frame`](DmaFrame::prepare_frame) converts the frame buffer in place into the bytes
+/// the controller expects (inversion etc.) beforehand. Then call
+/// [`begin_frame_transfer`](DmaFrame::begin_frame_transfer), write the prepared buffer as one
+/// contiguous slice and finish with [`finish_frame_transfer`](DmaFrame::finish_frame_transfer):
+///
+/// ```rust,ignore
+/// epd.prepare_frame(&mut buffer);
+/// epd.begin_frame_transfer(&mut spi)?;
+/// spi_dma.write(&buffer).wait();
+/// epd.finish_frame_transfer(&mut spi)?;
+/// epd.display_frame(&mut spi, &mut delay)?;
+/// ```
+pub trait DmaFrame<SPI, CS, BUSY, DC, RST, DELAY>:
+    FrameStream<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    /// Converts a frame buffer in the format of [`WaveshareDisplay::update_frame`] in place into
+    /// the bytes sent to the controller
+    fn prepare_frame(&self, buffer: &mut [u8]);
+
+    /// Starts the transmission of a full frame and leaves the display selected for data
+    fn begin_frame_transfer(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Deselects the display and finishes the transmission started with
+    /// [`begin_frame_transfer`](DmaFrame::begin_frame_transfer)
+    fn finish_frame_transfer(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+}
+
+/// Refreshes without polling BUSY, for applications notified by a BUSY interrupt
+///
+/// Start the refresh with [`start_display_frame`](InterruptRefresh::start_display_frame) and
+/// sleep (e.g. in WFI) until the interrupt of the BUSY pin reports the end of the refresh. Then
+/// acknowledge it with [`on_busy_deasserted`](InterruptRefresh::on_busy_deasserted) before using
+/// the driver again, or poll it with [`poll_display_frame`](InterruptRefresh::poll_display_frame)
+/// in between other work. Other methods of the driver still poll BUSY if they're called earlier.
+pub trait InterruptRefresh<SPI, CS, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    /// Starts the refresh of the transmitted frame and returns without waiting for it
+    fn start_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Transmits `buffer` and starts its refresh without waiting for it
+    fn start_update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.start_display_frame(spi, delay)
+    }
+
+    /// Acknowledges the end of the refresh reported by the BUSY interrupt
+    fn on_busy_deasserted(&mut self);
+
+    /// If a started refresh wasn't acknowledged yet (or seen finished by polling BUSY)
+    fn is_refreshing(&self) -> bool;
+
+    /// Polls BUSY for the end of a started refresh, for cooperative schedulers without interrupts
+    ///
+    /// Returns `WouldBlock` as long as the refresh runs and acknowledges its end like
+    /// [`on_busy_deasserted`](InterruptRefresh::on_busy_deasserted). `nb::block!` turns it into a
+    /// blocking wait.
+    fn poll_display_frame(&mut self) -> nb::Result<(), Infallible> {
+        if self.is_refreshing() && self.is_busy() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.on_busy_deasserted();
+        Ok(())
+    }
+}
+
+/// Flags of the status register, see [`ReadBack::read_status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Status {
+    /// A command or refresh is running (BUSY_N low)
+    pub busy: bool,
+    /// The power off sequence finished (POF)
+    pub power_off: bool,
+    /// The power on sequence finished, the booster and the voltages are up (PON)
+    pub power_on: bool,
+    /// The data of the last data transmission was received completely (DATA)
+    pub data_received: bool,
+    /// The I2C master of the external temperature sensor is busy (I2C_BUSY)
+    pub i2c_busy: bool,
+    /// The external temperature sensor didn't acknowledge (I2C_ERR)
+    pub i2c_error: bool,
+    /// The partial mode is active (PTL)
+    pub partial: bool,
+}
+
+impl From<u8> for Status {
+    /// Flags PTL, I2C_ERR, I2C_BUSY, DATA, PON, POF and BUSY_N in bits 6 to 0
+    fn from(flags: u8) -> Self {
+        let bit = |n: u8| flags & (1 << n) != 0;
+        Status {
+            busy: !bit(0),
+            power_off: bit(1),
+            power_on: bit(2),
+            data_received: bit(3),
+            i2c_busy: bit(4),
+            i2c_error: bit(5),
+            partial: bit(6),
+        }
+    }
+}
+
+/// Reading back the state of the controller
+///
+/// Needs a readable data line, see [Requirements: SPI](crate#spi).
+pub trait ReadBack<SPI, CS, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    /// Reads the status flags of the controller, e.g. to check if the booster is actually on
+    fn read_status(&mut self, spi: &mut SPI)
+        -> Result<Status, DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Reads the temperature of the internal sensor in degrees Celsius
+    fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<i8, DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Reads the VCOM value the controller is currently using
+    fn read_vcom(&mut self, spi: &mut SPI) -> Result<u8, DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Reads the OTP from address 0 into `buf`, e.g. to dump the factory waveforms and the
+    /// revision information of the panel
+    ///
+    /// The size and layout of the OTP depend on the controller, see its datasheet.
+    fn read_otp(
+        &mut self,
+        spi: &mut SPI,
+        buf: &mut [u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Checks the low power detection of the controller, `true` if VDD is below its threshold
+    fn is_supply_low(&mut self, spi: &mut SPI)
+        -> Result<bool, DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// [`display_frame`](WaveshareDisplay::display_frame), but fails with
+    /// [`Error::LowSupply`] instead of starting the refresh if [`is_supply_low`](Self::is_supply_low)
+    ///
+    /// A refresh on a sagging battery can stop half way and leave the panel half drawn.
+    fn display_frame_guarded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>> {
+        if self.is_supply_low(spi)? {
+            return Err(Error::LowSupply);
+        }
+        self.display_frame(spi, delay)
+    }
 }
 
 /// Allows quick refresh support for displays that support it; lets you send both
@@ -247,7 +979,7 @@ where
 /// Example:
 ///```rust, no_run
 ///# use embedded_hal_mock::*;
-///# fn main() -> Result<(), MockError> {
+///# fn main() -> Result<(), epd_waveshare::error::DriverError<spi::Mock, pin::Mock, pin::Mock, pin::Mock, pin::Mock>> {
 ///# use embedded_graphics::{
 ///#   pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///# };
@@ -265,18 +997,21 @@ where
 ///#
 ///# // Setup EPD
 ///# let mut epd = Epd4in2::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
-///let (x, y, frame_width, frame_height) = (20, 40, 80,80);
+///# use epd_waveshare::region::Region;
+///let (x, y, frame_width, frame_height) = (16, 40, 80, 80);
+///let window = PartialWindow::new(Region::new(x, y, frame_width, frame_height), WIDTH, HEIGHT)
+///    .unwrap();
 ///
 ///let mut buffer = [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 80 / 8 * 80];
 ///let mut display = VarDisplay::new(frame_width, frame_height, &mut buffer);
 ///
-///epd.update_partial_old_frame(&mut spi, display.buffer(), x, y, frame_width, frame_height)
+///epd.update_partial_old_frame(&mut spi, display.buffer(), window)
 ///  .ok();
 ///
 ///display.clear_buffer(Color::White);
 ///// Execute drawing commands here.
 ///
-///epd.update_partial_new_frame(&mut spi, display.buffer(), x, y, frame_width, frame_height)
+///epd.update_partial_new_frame(&mut spi, display.buffer(), window)
 ///  .ok();
 ///# Ok(())
 ///# }
@@ -288,7 +1023,7 @@ where
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
-    DELAY: DelayMs<u8>,
+    DELAY: DelayUs<u32>,
 {
     /// Updates the old frame.
     fn update_old_frame(
@@ -296,7 +1031,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Updates the new frame.
     fn update_new_frame(
@@ -304,10 +1039,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Displays the new frame
-    fn display_new_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Updates and displays the new frame.
     fn update_and_display_new_frame(
@@ -315,38 +1054,490 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Full refresh of `buffer` which also becomes the old frame, the base of the following
+    /// quick refreshes
+    ///
+    /// Otherwise the old frame of the first quick refresh after a full refresh is whatever the
+    /// full refresh left in the RAM, which shows as artifacts. Refreshes with the current LUT,
+    /// select the full LUT first and the quick one afterwards.
+    fn update_and_display_full_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        Self: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        WaveshareDisplay::update_and_display_frame(self, spi, buffer, delay)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.update_old_frame(spi, buffer, delay)
+    }
 
     /// Updates the old frame for a portion of the display.
     fn update_partial_old_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error>;
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Updates the new frame for a portion of the display.
     fn update_partial_new_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error>;
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
 
     /// Clears the partial frame buffer on the EPD with the declared background color
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
     fn clear_partial_frame(
         &mut self,
         spi: &mut SPI,
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error>;
+        window: PartialWindow,
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>;
+
+    /// Creates a new driver that starts with quick refreshes on top of `current`
+    ///
+    /// `current` is the frame the panel still shows, e.g. from before a deep sleep or a power
+    /// cycle. It's written as the old frame and the quick refresh LUT is selected without any
+    /// refresh, so the first [`update_and_display_new_frame`] only changes the pixels that differ
+    /// instead of flashing the whole display black and white.
+    ///
+    /// If the panel doesn't show `current` exactly, its content ghosts until the next full
+    /// refresh.
+    ///
+    /// [`update_and_display_new_frame`]: QuickRefresh::update_and_display_new_frame
+    fn new_quick(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        current: &[u8],
+    ) -> Result<Self, DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        Self: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY> + Sized,
+    {
+        let mut epd = Self::new(spi, cs, busy, dc, rst, delay)?;
+        epd.set_lut(spi, Some(RefreshLut::Quick))?;
+        epd.update_old_frame(spi, current, delay)?;
+        Ok(epd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+    use crate::test_utils::{Event, IdleBusyPin, MockNoop, NoopPin, Recorder};
+
+    #[test]
+    fn one_shot_update() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        recorder.take_events();
+
+        let buffer = [0x00; WIDTH as usize / 8 * HEIGHT as usize];
+        epd.one_shot_update(&mut spi, &buffer, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        let events = recorder.take_events();
+        let position = |event: Event| events.iter().position(|e| *e == event).unwrap();
+
+        // Reset, init with the full LUT, quick LUT, frame, refresh and deep sleep in this order
+        assert_eq!(events[0], Event::Reset);
+        let luts: std::vec::Vec<usize> = (0..events.len())
+            .filter(|&i| events[i] == Event::Command(0x20))
+            .collect();
+        assert_eq!(luts.len(), 2);
+        assert_ne!(events[luts[0] + 1], events[luts[1] + 1]);
+        assert!(luts[1] < position(Event::Command(0x13)));
+        assert!(position(Event::Command(0x13)) < position(Event::Command(0x12)));
+        assert_eq!(
+            events[events.len() - 2..],
+            [Event::Command(0x07), Event::Data(std::vec![0xa5])]
+        );
+    }
+
+    #[test]
+    fn partial_frames_one_refresh() {
+        use crate::color::Color;
+        use crate::test_utils::{Controller, VirtualPanel};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+
+        let black = [Color::Black.get_byte_value(); 2 * 4];
+        let windows = [
+            (Region::new(8, 0, 16, 4), &black[..]),
+            (Region::new(200, 100, 16, 4), &black[..]),
+            (Region::new(136, 296, 16, 4), &black[..]),
+        ]
+        .map(|(region, buffer)| (PartialWindow::new(region, WIDTH, HEIGHT).unwrap(), buffer));
+        epd.update_partial_frames(&mut spi, &windows).unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+
+        let panel = VirtualPanel::from_recorder(Controller::Uc81xx, WIDTH, HEIGHT, &recorder);
+        assert_eq!(panel.refreshes(), 1);
+        for (window, _) in &windows {
+            let region = window.region();
+            assert_eq!(
+                panel.pixel(region.x, region.y),
+                Color::Black,
+                "{:?}",
+                region
+            );
+            assert_eq!(
+                panel.pixel(region.x + 15, region.y + 3),
+                Color::Black,
+                "{:?}",
+                region
+            );
+        }
+        assert_eq!(panel.pixel(100, 0), Color::White);
+    }
+
+    #[test]
+    fn partial_window() {
+        let region = Region::new(8, 10, 16, 20);
+        let window = PartialWindow::new(region, 400, 300).unwrap();
+        assert_eq!(window.region(), region);
+        assert_eq!(window.buffer_len(), 2 * 20);
+
+        // Unaligned, beyond the display or empty
+        assert_eq!(PartialWindow::new(Region::new(5, 0, 16, 8), 400, 300), None);
+        assert_eq!(PartialWindow::new(Region::new(0, 0, 12, 8), 400, 300), None);
+        assert_eq!(
+            PartialWindow::new(Region::new(392, 0, 16, 8), 400, 300),
+            None
+        );
+        assert_eq!(PartialWindow::new(Region::new(0, 0, 8, 0), 400, 300), None);
+
+        let window = PartialWindow::enclosing(Region::new(5, 0, 12, 8), 400, 300).unwrap();
+        assert_eq!(window.region(), Region::new(0, 0, 24, 8));
+        assert_eq!(
+            PartialWindow::enclosing(Region::new(400, 0, 8, 8), 400, 300),
+            None
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn update_partial_frame_needs_a_window() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+
+        let buffer = [0xaa; 3 * 8];
+        let window = PartialWindow::enclosing(Region::new(5, 0, 12, 8), WIDTH, HEIGHT).unwrap();
+        epd.update_partial_window(&mut spi, &buffer, window)
+            .unwrap();
+        let direct = recorder.take_events();
+        epd.update_partial_frame(&mut spi, &buffer, 0, 0, 24, 8)
+            .unwrap();
+        assert_eq!(recorder.take_events(), direct);
+
+        // Masking x to 0 would shift the image
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &buffer, 5, 0, 24, 8),
+            Err(Error::Unsupported)
+        );
+        assert_eq!(recorder.take_events(), []);
+    }
+
+    #[test]
+    fn update_frame_from_iter() {
+        use crate::epd2in7b::{Epd2in7b, HEIGHT, WIDTH};
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        // A striped frame, generated row by row
+        let row_len = WIDTH as usize / 8;
+        let buffer: std::vec::Vec<u8> = (0..HEIGHT as usize * row_len)
+            .map(|i| {
+                if (i / row_len).is_multiple_of(2) {
+                    0x00
+                } else {
+                    0xff
+                }
+            })
+            .collect();
+        recorder.take_events();
+        epd.update_frame(&mut spi, &buffer, &mut delay).unwrap();
+        let expected = recorder.take_events();
+        epd.update_frame_from_iter(&mut spi, buffer.iter().copied())
+            .unwrap();
+        assert_eq!(recorder.take_events(), expected);
+    }
+
+    #[test]
+    fn full_frame_becomes_old_frame() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+
+        let buffer = [0x0f; WIDTH as usize / 8 * HEIGHT as usize];
+        epd.update_and_display_full_frame(&mut spi, &buffer, &mut delay)
+            .unwrap();
+        let events = recorder.take_events();
+        assert_eq!(
+            events[events.len() - 3..],
+            [
+                Event::Command(0x12),
+                Event::Command(0x10),
+                Event::Data(buffer.to_vec()),
+            ]
+        );
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn update_rotated_partial_frame() {
+        use crate::graphics::DisplayRotation;
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+
+        let frame: std::vec::Vec<u8> = (0..WIDTH / 8 * HEIGHT).map(|i| i as u8).collect();
+        let mut window = [0; 64];
+        // Logical x 2..22 at y 8..12 is unrotated x 388..392 at y 2..22
+        let partial = epd
+            .update_rotated_partial_frame(
+                &mut spi,
+                &frame,
+                Region::new(2, 8, 20, 4),
+                DisplayRotation::Rotate90,
+                &mut window,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(partial.region(), Region::new(384, 2, 8, 20));
+        let rotated = recorder.take_events();
+
+        let bytes: std::vec::Vec<u8> = (2..22).map(|y| frame[y * 50 + 48]).collect();
+        epd.update_partial_window(&mut spi, &bytes, partial)
+            .unwrap();
+        assert_eq!(recorder.take_events(), rotated);
+
+        // Off the display
+        let off = epd.update_rotated_partial_frame(
+            &mut spi,
+            &frame,
+            Region::new(300, 0, 8, 8),
+            DisplayRotation::Rotate90,
+            &mut window,
+        );
+        assert_eq!(off, Ok(None));
+
+        let small = epd.update_rotated_partial_frame(
+            &mut spi,
+            &frame,
+            Region::new(2, 8, 20, 4),
+            DisplayRotation::Rotate90,
+            &mut window[..19],
+        );
+        assert_eq!(small, Err(Error::BufferTooSmall));
+        assert!(recorder.take_events().is_empty());
+    }
+
+    #[test]
+    fn deghost() {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        epd.set_background_color(Color::Black);
+        recorder.take_events();
+
+        epd.deghost(&mut spi, &mut delay, 2, Some(RefreshLut::Full))
+            .unwrap();
+        let events = recorder.take_events();
+        let refresh = Event::Command(crate::epd4in2::command::Command::DisplayRefresh as u8);
+        let refreshes: std::vec::Vec<_> = events
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| **e == refresh)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(refreshes.len(), 4);
+        // The new frame written right before each refresh
+        let frame = |i: usize| match &events[i - 1] {
+            Event::Data(data) => data[0],
+            e => panic!("{:?}", e),
+        };
+        let colors: std::vec::Vec<_> = refreshes.iter().map(|&i| frame(i)).collect();
+        assert_eq!(colors, [0x00, 0xff, 0x00, 0xff]);
+        assert_eq!(epd.background_color(), &Color::Black);
+    }
+
+    #[test]
+    fn refresh_estimates() {
+        use crate::epd7in5;
+        use core::time::Duration;
+
+        let timing = crate::epd4in2::TIMING;
+        assert_eq!(timing.refresh_ms(RefreshMode::Full, 20), 4000);
+        assert_eq!(timing.refresh_ms(RefreshMode::Full, 5), 6000);
+        assert_eq!(timing.refresh_ms(RefreshMode::Full, -5), 8000);
+        assert_eq!(timing.refresh_ms(RefreshMode::Full, 45), 3000);
+        let partial = RefreshMode::Partial {
+            width: 80,
+            height: 100,
+        };
+        assert_eq!(timing.refresh_ms(partial, 20), 800);
+        // No partial refreshes on the 7.5"
+        assert_eq!(epd7in5::TIMING.refresh_ms(partial, 20), 6000);
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut MockNoop::new(),
+        )
+        .unwrap();
+        // 15000 bytes at 1MHz take 120ms
+        assert_eq!(
+            epd.estimate_refresh(RefreshMode::Full, 20, 1_000_000),
+            Duration::from_millis(4120)
+        );
+        // 1000 bytes
+        assert_eq!(
+            epd.estimate_refresh(partial, 20, 1_000_000),
+            Duration::from_millis(808)
+        );
+    }
+
+    #[test]
+    fn quick_boot_without_refresh() {
+        use crate::color::Color;
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let current = [Color::Black.get_byte_value(); (WIDTH / 8 * HEIGHT) as usize];
+        let mut epd = Epd4in2::new_quick(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+            &current,
+        )
+        .unwrap();
+        let events = recorder.take_events();
+        let position = |event: &Event| events.iter().rposition(|e| e == event).unwrap();
+
+        // Quick LUT last, then the old frame, never a refresh
+        assert!(!events.contains(&Event::Command(0x12)));
+        let first_lut = events
+            .iter()
+            .position(|e| *e == Event::Command(0x20))
+            .unwrap();
+        assert_ne!(
+            events[first_lut + 1],
+            events[position(&Event::Command(0x20)) + 1]
+        );
+        assert_eq!(
+            events[position(&Event::Command(0x10)) + 1],
+            Event::Data(current.to_vec())
+        );
+        assert!(position(&Event::Command(0x20)) < position(&Event::Command(0x10)));
+
+        epd.update_and_display_new_frame(&mut spi, &current, &mut delay)
+            .unwrap();
+        let events = recorder.take_events();
+        assert_eq!(
+            events,
+            [
+                Event::Command(0x13),
+                Event::Data(current.to_vec()),
+                Event::Command(0x12),
+                Event::Command(0x10),
+                Event::Data(current.to_vec()),
+            ]
+        );
+    }
 }
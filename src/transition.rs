@@ -0,0 +1,357 @@
+//! Animated transitions between two frames built from partial refreshes
+//!
+//! Instead of flashing the whole panel with a full refresh, a [`Transition`] reveals the next
+//! frame in a sequence of partial refreshes, e.g. for menu navigation. This works best on panels
+//! with fast partial refreshes. Put the driver into its partial/quick refresh mode first (e.g.
+//! `Epd2in13::set_refresh(.., RefreshLut::Quick)`).
+//!
+//! ```rust
+//! use epd_waveshare::scheduler::Region;
+//! use epd_waveshare::transition::{Direction, Transition};
+//!
+//! let wipe = Transition::Wipe {
+//!     direction: Direction::LeftToRight,
+//!     steps: 4,
+//! };
+//! let mut regions = wipe.regions(128, 64);
+//! assert_eq!(regions.next(), Some(Region::new(0, 0, 32, 64)));
+//! assert_eq!(regions.next(), Some(Region::new(32, 0, 32, 64)));
+//! assert_eq!(regions.count(), 2);
+//! ```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::error::{DriverError, Error};
+use crate::graphics::packing::copy_window;
+use crate::region::Region;
+use crate::traits::{PartialWindow, WaveshareDisplay};
+
+/// Direction of a [`Transition::Wipe`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Reveal from the left to the right edge
+    LeftToRight,
+    /// Reveal from the right to the left edge
+    RightToLeft,
+    /// Reveal from the top to the bottom edge
+    TopToBottom,
+    /// Reveal from the bottom to the top edge
+    BottomToTop,
+}
+
+/// How the next frame is revealed
+///
+/// All positions are in unrotated display coordinates, horizontal steps are aligned to whole
+/// bytes of the buffer. If the width isn't a multiple of 8, the last regions include the padding
+/// bits at the end of the rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Strips in `direction`, one per step
+    Wipe {
+        /// Direction of the wipe
+        direction: Direction,
+        /// Number of strips
+        steps: u32,
+    },
+    /// A band growing from the vertical center line to both edges
+    Split {
+        /// Number of steps until the band covers the display
+        steps: u32,
+    },
+    /// A grid of blocks revealed one by one, row by row
+    Blocks {
+        /// Number of blocks per row
+        columns: u32,
+        /// Number of rows
+        rows: u32,
+    },
+}
+
+impl Transition {
+    /// The regions to refresh, in order
+    ///
+    /// Together they cover the whole display.
+    pub fn regions(&self, width: u32, height: u32) -> Regions {
+        let steps = match *self {
+            Transition::Wipe { steps, .. } | Transition::Split { steps } => steps.max(1),
+            Transition::Blocks { columns, rows } => columns.max(1) * rows.max(1),
+        };
+        Regions {
+            transition: *self,
+            width,
+            height,
+            step: 0,
+            steps,
+        }
+    }
+
+    /// Shows `frame` with this transition
+    ///
+    /// Every step copies its region of `frame` (the whole buffer of the display) into `window`,
+    /// which has to be large enough for the largest region, updates it and does a refresh. A
+    /// smaller `window` fails with [`Error::BufferTooSmall`] before the first step.
+    pub fn play<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        frame: &[u8],
+        window: &mut [u8],
+    ) -> Result<(), DriverError<SPI, CS, BUSY, DC, RST>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let (width, height) = (epd.width(), epd.height());
+        // Checked up front, a transition stopped half way would leave a mix of both frames
+        let needed = self
+            .regions(width, height)
+            .filter_map(|region| PartialWindow::new(region, width, height))
+            .map(|partial| partial.buffer_len())
+            .max()
+            .unwrap_or(0);
+        if window.len() < needed {
+            return Err(Error::BufferTooSmall);
+        }
+        for region in self.regions(width, height) {
+            // The regions are aligned and on the display
+            let partial = PartialWindow::new(region, width, height).ok_or(Error::Unsupported)?;
+            let window = &mut window[..partial.buffer_len()];
+            copy_window(
+                frame,
+                width,
+                region.x,
+                region.y,
+                region.width,
+                region.height,
+                window,
+            );
+            epd.update_partial_window(spi, window, partial)?;
+            epd.display_frame(spi, delay)?;
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the regions of a [`Transition`]
+#[derive(Debug, Clone)]
+pub struct Regions {
+    transition: Transition,
+    width: u32,
+    height: u32,
+    step: u32,
+    steps: u32,
+}
+
+impl Regions {
+    /// Start of the `step`th of `steps` parts of `len`, aligned to `align`
+    ///
+    /// The end of the last part is always `len`.
+    fn split(len: u32, step: u32, steps: u32, align: u32) -> u32 {
+        if step >= steps {
+            return len;
+        }
+        let pos = (u64::from(len) * u64::from(step) / u64::from(steps)) as u32;
+        pos / align * align
+    }
+
+    fn region(&self, step: u32) -> Region {
+        // Including the padding bits at the end of the rows
+        let w = self.width.div_ceil(8) * 8;
+        let (h, steps) = (self.height, self.steps);
+        match self.transition {
+            Transition::Wipe { direction, .. } => {
+                let (start, end) = match direction {
+                    Direction::LeftToRight | Direction::TopToBottom => (step, step + 1),
+                    Direction::RightToLeft | Direction::BottomToTop => {
+                        (steps - step - 1, steps - step)
+                    }
+                };
+                match direction {
+                    Direction::LeftToRight | Direction::RightToLeft => {
+                        let x = Self::split(w, start, steps, 8);
+                        Region::new(x, 0, Self::split(w, end, steps, 8) - x, h)
+                    }
+                    Direction::TopToBottom | Direction::BottomToTop => {
+                        let y = Self::split(h, start, steps, 1);
+                        Region::new(0, y, w, Self::split(h, end, steps, 1) - y)
+                    }
+                }
+            }
+            Transition::Split { .. } => {
+                // Half of the band on each side of the center
+                let half = Self::split(w / 2, step + 1, steps, 8);
+                let center = w / 2 / 8 * 8;
+                let x = if step + 1 == steps {
+                    0
+                } else {
+                    center.saturating_sub(half)
+                };
+                let right = if step + 1 == steps {
+                    w
+                } else {
+                    (center + half).min(w)
+                };
+                Region::new(x, 0, right - x, h)
+            }
+            Transition::Blocks { columns, rows } => {
+                let (columns, rows) = (columns.max(1), rows.max(1));
+                let (column, row) = (step % columns, step / columns);
+                let x = Self::split(w, column, columns, 8);
+                let y = Self::split(h, row, rows, 1);
+                let right = Self::split(w, column + 1, columns, 8);
+                Region::new(x, y, right - x, Self::split(h, row + 1, rows, 1) - y)
+            }
+        }
+    }
+}
+
+impl Iterator for Regions {
+    type Item = Region;
+
+    fn next(&mut self) -> Option<Region> {
+        while self.step < self.steps {
+            let region = self.region(self.step);
+            self.step += 1;
+            // Skip the steps which are empty after the alignment
+            if region.area() > 0 {
+                return Some(region);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::vec::Vec;
+
+    /// Checks that every pixel is covered by at least one region
+    fn covers(transition: Transition, width: u32, height: u32) {
+        let regions: Vec<Region> = transition.regions(width, height).collect();
+        for y in 0..height {
+            for x in 0..width {
+                assert!(
+                    regions
+                        .iter()
+                        .any(|r| x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height),
+                    "{:?} doesn't cover ({}, {})",
+                    transition,
+                    x,
+                    y
+                );
+            }
+        }
+        for r in regions {
+            assert_eq!(r.x % 8, 0);
+            assert_eq!(r.width % 8, 0);
+            assert!(r.x + r.width <= width.div_ceil(8) * 8 && r.y + r.height <= height);
+        }
+    }
+
+    #[test]
+    fn transitions_cover_the_display() {
+        for &(width, height) in &[(128, 64), (122, 250), (400, 300)] {
+            for &direction in &[
+                Direction::LeftToRight,
+                Direction::RightToLeft,
+                Direction::TopToBottom,
+                Direction::BottomToTop,
+            ] {
+                for &steps in &[1, 3, 8, 50] {
+                    covers(Transition::Wipe { direction, steps }, width, height);
+                }
+            }
+            for &steps in &[1, 3, 8, 50] {
+                covers(Transition::Split { steps }, width, height);
+            }
+            covers(
+                Transition::Blocks {
+                    columns: 3,
+                    rows: 2,
+                },
+                width,
+                height,
+            );
+            covers(
+                Transition::Blocks {
+                    columns: 0,
+                    rows: 0,
+                },
+                width,
+                height,
+            );
+        }
+    }
+
+    #[test]
+    fn wipe_right_to_left() {
+        let wipe = Transition::Wipe {
+            direction: Direction::RightToLeft,
+            steps: 2,
+        };
+        let regions: Vec<Region> = wipe.regions(128, 10).collect();
+        assert_eq!(
+            regions,
+            [Region::new(64, 0, 64, 10), Region::new(0, 0, 64, 10)]
+        );
+    }
+
+    #[test]
+    fn split_grows_from_the_center() {
+        let regions: Vec<Region> = Transition::Split { steps: 2 }.regions(128, 10).collect();
+        assert_eq!(
+            regions,
+            [Region::new(32, 0, 64, 10), Region::new(0, 0, 128, 10)]
+        );
+    }
+
+    #[test]
+    fn play_reveals_frame() {
+        use crate::color::Color;
+        use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+        use crate::test_utils::{
+            Controller, IdleBusyPin, MockNoop, NoopPin, Recorder, VirtualPanel,
+        };
+
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+
+        let frame = [Color::Black.get_byte_value(); WIDTH as usize / 8 * HEIGHT as usize];
+        let mut window = [0; WIDTH as usize / 8 * HEIGHT as usize / 2];
+        let transition = Transition::Blocks {
+            columns: 2,
+            rows: 2,
+        };
+        let small = transition.play(&mut epd, &mut spi, &mut delay, &frame, &mut window[..1]);
+        assert_eq!(small, Err(crate::error::Error::BufferTooSmall));
+        transition
+            .play(&mut epd, &mut spi, &mut delay, &frame, &mut window)
+            .unwrap();
+
+        let panel = VirtualPanel::from_recorder(Controller::Uc81xx, WIDTH, HEIGHT, &recorder);
+        assert_eq!(panel.refreshes(), 4);
+        assert_eq!(panel.image(), &frame[..]);
+    }
+}
@@ -0,0 +1,203 @@
+//! Frames uploaded from async streams
+//!
+//! Enabled with the `async` feature. [`update_frame_from_stream`] takes the frame from a
+//! [`Stream`] of byte chunks (e.g. a network download or DMA reads from a flash) and passes
+//! every chunk to a [`FrameStream`] driver as soon as it arrives, so a frame rendered elsewhere
+//! never has to be buffered as a whole. The SPI transfers themselves are still blocking.
+//!
+//! ```rust,ignore
+//! // Chunks of the frame as they arrive from the network
+//! let chunks = socket.frames();
+//! update_frame_from_stream(&mut epd, &mut spi, chunks).await?;
+//! epd.display_frame(&mut spi, &mut delay)?;
+//! ```
+
+use core::future::poll_fn;
+use core::pin::Pin;
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+use futures_core::Stream;
+
+use crate::error::DriverError;
+use crate::traits::FrameStream;
+
+/// Errors of [`update_frame_from_stream`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadError<E> {
+    /// Sending to the display failed, over SPI or one of its pins
+    Spi(E),
+    /// The stream didn't contain exactly one frame buffer
+    ///
+    /// `received` is the number of bytes received until the mismatch was detected.
+    Length {
+        /// Received bytes
+        received: usize,
+    },
+}
+
+/// Transmits the full frame from the chunks of `stream`
+///
+/// The chunks can have any size, together they have to be exactly one frame buffer of the
+/// display. If the stream ends early or contains more data, the transmission is finished with
+/// what was received and [`UploadError::Length`] is returned.
+pub async fn update_frame_from_stream<SPI, CS, BUSY, DC, RST, DELAY, EPD, S, B>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    stream: S,
+) -> Result<(), UploadError<DriverError<SPI, CS, BUSY, DC, RST>>>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayUs<u32>,
+    EPD: FrameStream<SPI, CS, BUSY, DC, RST, DELAY>,
+    S: Stream<Item = B>,
+    B: AsRef<[u8]>,
+{
+    let len = crate::buffer_len(epd.width() as usize, epd.height() as usize);
+    let mut stream = core::pin::pin!(stream);
+
+    epd.begin_frame(spi).map_err(UploadError::Spi)?;
+    let mut received = 0;
+    while let Some(chunk) = next(stream.as_mut()).await {
+        let chunk = chunk.as_ref();
+        let n = chunk.len().min(len - received);
+        epd.write_frame_chunk(spi, &chunk[..n])
+            .map_err(UploadError::Spi)?;
+        received += chunk.len();
+        if received > len {
+            break;
+        }
+    }
+    epd.end_frame(spi).map_err(UploadError::Spi)?;
+
+    if received == len {
+        Ok(())
+    } else {
+        Err(UploadError::Length { received })
+    }
+}
+
+/// The next item of `stream`
+async fn next<S: Stream>(mut stream: Pin<&mut S>) -> Option<S::Item> {
+    poll_fn(|cx| stream.as_mut().poll_next(cx)).await
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+    use crate::test_utils::{
+        IdleBusyPin, MockNoop, NoopPin, Recorder, RecordingDc, RecordingRst, RecordingSpi,
+    };
+    use crate::traits::WaveshareDisplay;
+    use core::future::Future;
+    use core::task::{Context, Poll, Waker};
+    use std::vec::Vec;
+
+    const LEN: usize = WIDTH as usize / 8 * HEIGHT as usize;
+
+    type Epd = Epd4in2<RecordingSpi, NoopPin, IdleBusyPin, RecordingDc, RecordingRst, MockNoop>;
+
+    /// Yields its chunks, every other poll is pending
+    struct Chunks {
+        chunks: Vec<Vec<u8>>,
+        pending: bool,
+    }
+
+    impl Stream for Chunks {
+        type Item = Vec<u8>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if self.chunks.is_empty() {
+                Poll::Ready(None)
+            } else {
+                Poll::Ready(Some(self.chunks.remove(0)))
+            }
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = core::pin::pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    fn setup() -> (Recorder, RecordingSpi, Epd) {
+        let recorder = Recorder::new();
+        let mut spi = recorder.spi();
+        let mut delay = MockNoop::new();
+        let epd = Epd::new(
+            &mut spi,
+            NoopPin,
+            IdleBusyPin,
+            recorder.dc(),
+            recorder.rst(),
+            &mut delay,
+        )
+        .unwrap();
+        recorder.take_events();
+        (recorder, spi, epd)
+    }
+
+    /// Splits `frame` into chunks of varying sizes
+    fn chunks(frame: &[u8]) -> Chunks {
+        let mut chunks = Vec::new();
+        let mut rest = frame;
+        let mut size = 1;
+        while !rest.is_empty() {
+            let (chunk, tail) = rest.split_at(size.min(rest.len()));
+            chunks.push(chunk.to_vec());
+            rest = tail;
+            size = size * 3 % 1000 + 1;
+        }
+        Chunks {
+            chunks,
+            pending: false,
+        }
+    }
+
+    #[test]
+    fn same_as_update_frame() {
+        let (recorder, mut spi, mut epd) = setup();
+        let frame: Vec<u8> = (0..LEN).map(|i| (i * 13) as u8).collect();
+
+        epd.update_frame(&mut spi, &frame, &mut MockNoop::new())
+            .unwrap();
+        let expected = recorder.take_events();
+
+        block_on(update_frame_from_stream(&mut epd, &mut spi, chunks(&frame))).unwrap();
+        assert_eq!(recorder.take_events(), expected);
+    }
+
+    #[test]
+    fn wrong_length() {
+        let (_recorder, mut spi, mut epd) = setup();
+        let frame = [0xaa; LEN + 10];
+
+        let result = block_on(update_frame_from_stream(
+            &mut epd,
+            &mut spi,
+            chunks(&frame[..LEN - 1]),
+        ));
+        assert_eq!(result, Err(UploadError::Length { received: LEN - 1 }));
+
+        let result = block_on(update_frame_from_stream(&mut epd, &mut spi, chunks(&frame)));
+        assert!(matches!(result, Err(UploadError::Length { received }) if received > LEN));
+    }
+}